@@ -0,0 +1,278 @@
+//! Двоичный кеш разобранных диаграмм.
+//!
+//! Файл кеша начинается с заголовка: сигнатура, версия формата кеша, версия крейта
+//! и отметка исходного файла (размер и время изменения). Если хоть что-то из этого
+//! не совпадает, кеш считается устаревшим и исходный файл разбирается заново.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufReader, BufWriter, Cursor, Read, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::format::{FileFormat, detect_format};
+use crate::parser_vdx::{self, VdxError};
+use crate::parser_vsd;
+use crate::parser_vsdx::read_vsdx::{self, CellName, Diagram, VsdxError};
+
+const MAGIC: &[u8; 8] = b"RVPCACHE";
+
+/// Версия формата кеша; увеличивается при любом изменении моделей
+pub const CACHE_VERSION: u32 = 10;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Cache encoding error: {0}")]
+    Encode(#[from] bincode::Error),
+    #[error("Not a diagram cache file")]
+    BadMagic,
+    #[error("Stale cache: {0}")]
+    Stale(String),
+    #[error("VSDX error: {0}")]
+    Vsdx(#[from] VsdxError),
+    #[error("VSD error: {0}")]
+    Vsd(String),
+    #[error("VDX error: {0}")]
+    Vdx(#[from] VdxError),
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// Отметка исходного файла, по которой проверяется актуальность кеша
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceStamp {
+    pub len: u64,
+    pub modified_secs: u64,
+    pub modified_nanos: u32,
+}
+
+impl SourceStamp {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let meta = fs::metadata(path)?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            len: meta.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+/// Поля модели, которые не попадают в JSON, но нужны писателю и рендеру
+#[derive(Deserialize)]
+struct Payload {
+    diagram: Diagram,
+    media: HashMap<String, Vec<u8>>,
+    package: BTreeMap<String, Vec<u8>>,
+    page_parts: Vec<Option<String>>,
+    master_parts: Vec<Option<String>>,
+}
+
+/// [`Payload`] для записи: поля берутся из диаграммы по ссылке, без копирования модели
+#[derive(Serialize)]
+struct PayloadRef<'a> {
+    diagram: &'a Diagram,
+    media: &'a HashMap<String, Vec<u8>>,
+    package: &'a BTreeMap<String, Vec<u8>>,
+    page_parts: Vec<Option<&'a str>>,
+    master_parts: Vec<Option<&'a str>>,
+}
+
+fn write_str<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_str<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+    if len > 256 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "version string is too long",
+        ));
+    }
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Записывает диаграмму в кеш
+pub fn write<W: Write>(
+    mut writer: W,
+    diagram: &Diagram,
+    source: SourceStamp,
+) -> Result<(), CacheError> {
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(CACHE_VERSION)?;
+    write_str(&mut writer, env!("CARGO_PKG_VERSION"))?;
+    writer.write_u64::<LittleEndian>(source.len)?;
+    writer.write_u64::<LittleEndian>(source.modified_secs)?;
+    writer.write_u32::<LittleEndian>(source.modified_nanos)?;
+
+    let payload = PayloadRef {
+        diagram,
+        media: &diagram.media,
+        package: &diagram.package,
+        page_parts: diagram.pages.iter().map(|p| p.part.as_deref()).collect(),
+        master_parts: diagram.masters.iter().map(|m| m.part.as_deref()).collect(),
+    };
+    bincode::serialize_into(writer, &payload)?;
+    Ok(())
+}
+
+/// Читает диаграмму из кеша. Если задана `expected`, отметка исходного файла должна совпасть
+pub fn read<R: Read>(mut reader: R, expected: Option<SourceStamp>) -> Result<Diagram, CacheError> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(CacheError::BadMagic);
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != CACHE_VERSION {
+        return Err(CacheError::Stale(format!(
+            "cache format {} (expected {})",
+            version, CACHE_VERSION
+        )));
+    }
+    let crate_version = read_str(&mut reader)?;
+    if crate_version != env!("CARGO_PKG_VERSION") {
+        return Err(CacheError::Stale(format!(
+            "written by version {}",
+            crate_version
+        )));
+    }
+    let stamp = SourceStamp {
+        len: reader.read_u64::<LittleEndian>()?,
+        modified_secs: reader.read_u64::<LittleEndian>()?,
+        modified_nanos: reader.read_u32::<LittleEndian>()?,
+    };
+    if let Some(expected) = expected
+        && expected != stamp
+    {
+        return Err(CacheError::Stale("source file changed".to_string()));
+    }
+
+    let payload: Payload = CellName::scoped(|| bincode::deserialize_from(reader))?;
+    let mut diagram = payload.diagram;
+    diagram.media = payload.media;
+    diagram.package = payload.package;
+    for (page, part) in diagram.pages.iter_mut().zip(payload.page_parts) {
+        page.part = part;
+    }
+    for (master, part) in diagram.masters.iter_mut().zip(payload.master_parts) {
+        master.part = part;
+    }
+    Ok(diagram)
+}
+
+pub fn save(diagram: &Diagram, source: SourceStamp, path: &Path) -> Result<(), CacheError> {
+    let file = fs::File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer, diagram, source)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load(path: &Path, expected: Option<SourceStamp>) -> Result<Diagram, CacheError> {
+    let file = fs::File::open(path)?;
+    read(BufReader::new(file), expected)
+}
+
+fn parse_source(source: &Path) -> Result<Diagram, CacheError> {
+    let data = fs::read(source)?;
+    match detect_format(&data) {
+        Some(format) if format.is_package() => Ok(read_vsdx::parse(Cursor::new(data))?),
+        Some(FileFormat::Vsd) => {
+            parser_vsd::parse_data(&data).map_err(|e| CacheError::Vsd(e.to_string()))
+        }
+        Some(FileFormat::Vdx) => Ok(parser_vdx::parse_data(&data)?),
+        Some(format) => Err(CacheError::UnsupportedFormat(format.to_string())),
+        None => Err(CacheError::UnsupportedFormat(source.display().to_string())),
+    }
+}
+
+/// Берёт диаграмму из кеша, если он актуален, иначе разбирает исходный файл и обновляет кеш
+pub fn open_cached(source: &Path, cache_path: &Path) -> Result<Diagram, CacheError> {
+    let stamp = SourceStamp::of(source)?;
+    match load(cache_path, Some(stamp)) {
+        Ok(diagram) => return Ok(diagram),
+        Err(CacheError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => log::info!("Cache {:?} is not used: {}", cache_path, e),
+    }
+
+    let diagram = parse_source(source)?;
+    if let Err(e) = save(&diagram, stamp, cache_path) {
+        log::warn!("Unable to write cache {:?}: {}", cache_path, e);
+    }
+    Ok(diagram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::write_vsdx::tests::{round_trip, sample_page};
+
+    fn sample() -> Diagram {
+        round_trip(&Diagram {
+            pages: vec![sample_page(&["cached"])],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let diagram = sample();
+        let stamp = SourceStamp {
+            len: 10,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write(&mut buf, &diagram, stamp).unwrap();
+
+        let read = read(Cursor::new(&buf), Some(stamp)).unwrap();
+        assert_eq!(read.pages, diagram.pages);
+        assert!(!read.package.is_empty());
+        assert_eq!(read.package, diagram.package);
+        assert_eq!(read.pages[0].part, diagram.pages[0].part);
+        assert_eq!(read.rels, diagram.rels);
+    }
+
+    #[test]
+    fn test_stale_cache_is_rejected() {
+        let diagram = sample();
+        let mut buf = Vec::new();
+        write(&mut buf, &diagram, SourceStamp::default()).unwrap();
+
+        let changed = SourceStamp {
+            len: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            read(Cursor::new(&buf), Some(changed)),
+            Err(CacheError::Stale(_))
+        ));
+
+        buf[8] = buf[8].wrapping_add(1);
+        assert!(matches!(
+            read(Cursor::new(&buf), None),
+            Err(CacheError::Stale(_))
+        ));
+        assert!(matches!(
+            read(Cursor::new(b"not a cache".as_slice()), None),
+            Err(CacheError::BadMagic)
+        ));
+    }
+}
@@ -0,0 +1,444 @@
+//! Извлечение встроенных объектов: листов Excel, документов Word, вложенных диаграмм Visio
+//! и изображений вместе с фигурой-владельцем.
+//!
+//! Объект OLE хранится как Compound Document. Документы Office 2007+ лежат внутри него в
+//! потоке `Package`, произвольные файлы - в `\x01Ole10Native`; ProgID берётся из `\x01CompObj`.
+//! В VSDX документы Office могут лежать в `visio/embeddings/` и без обёртки OLE.
+
+use std::io::{Cursor, Read};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use super::all_shapes;
+use crate::format::detect_format;
+use crate::parser_vsdx::read_vsdx::{Diagram, Shape};
+
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Приложение, к которому относится объект
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Excel,
+    Word,
+    PowerPoint,
+    Visio,
+    /// Произвольный файл, упакованный Packager
+    Package,
+    Image,
+    Other,
+}
+
+impl ObjectKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ObjectKind::Excel => "excel",
+            ObjectKind::Word => "word",
+            ObjectKind::PowerPoint => "powerpoint",
+            ObjectKind::Visio => "visio",
+            ObjectKind::Package => "package",
+            ObjectKind::Image => "image",
+            ObjectKind::Other => "other",
+        }
+    }
+}
+
+/// Встроенный объект или изображение фигуры
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedObject {
+    /// ID страницы-владельца; `None` для фигур мастера
+    pub page: Option<String>,
+    /// ID мастера-владельца; `None` для фигур страницы
+    pub master: Option<String>,
+    pub shape: String,
+    pub kind: ObjectKind,
+    pub prog_id: Option<String>,
+    /// Часть с исходными данными, например `visio/embeddings/oleObject1.bin`
+    pub part: String,
+    /// Имя файла для извлечённых данных
+    pub file_name: String,
+    /// Содержимое без обёртки OLE, если её удалось снять
+    pub data: Vec<u8>,
+    pub show_as_icon: bool,
+    /// Изображение предпросмотра (чаще всего EMF или WMF)
+    pub preview: Option<String>,
+}
+
+/// Все встроенные объекты и изображения: сначала страниц, затем мастеров
+pub fn embedded_objects(diagram: &Diagram) -> Vec<EmbeddedObject> {
+    let mut result = Vec::new();
+    for page in &diagram.pages {
+        for shape in all_shapes(&page.shapes) {
+            if let Some(mut object) = shape_object(diagram, shape, &format!("page{}", page.id)) {
+                object.page = Some(page.id.clone());
+                result.push(object);
+            }
+        }
+    }
+    for master in &diagram.masters {
+        for shape in all_shapes(&master.shapes) {
+            if let Some(mut object) = shape_object(diagram, shape, &format!("master{}", master.id))
+            {
+                object.master = Some(master.id.clone());
+                result.push(object);
+            }
+        }
+    }
+    result
+}
+
+fn shape_object(diagram: &Diagram, shape: &Shape, owner: &str) -> Option<EmbeddedObject> {
+    let foreign = shape.foreign.as_ref()?;
+    let base_name = format!("{}-shape{}", owner, shape.id);
+
+    if let Some(part) = &foreign.object_target {
+        let Some(data) = diagram.media.get(part) else {
+            log::warn!("shape {} refers to missing object {}", shape.id, part);
+            return None;
+        };
+        let payload = unwrap(data);
+        let file_name = match &payload.file_name {
+            Some(name) => format!("{}-{}", base_name, name),
+            None => format!("{}.{}", base_name, payload.extension),
+        };
+        return Some(EmbeddedObject {
+            page: None,
+            master: None,
+            shape: shape.id.clone(),
+            kind: payload.kind,
+            prog_id: payload.prog_id,
+            part: part.clone(),
+            file_name,
+            data: payload.data,
+            show_as_icon: foreign.show_as_icon,
+            preview: foreign.target.clone(),
+        });
+    }
+
+    // Без объекта остаётся изображение; предпросмотр объекта без данных не извлекается
+    if foreign.foreign_type == "Object" {
+        return None;
+    }
+    let part = foreign.target.as_ref()?;
+    let data = diagram.media.get(part)?;
+    let extension = part.rsplit_once('.').map_or("bin", |(_, ext)| ext);
+    Some(EmbeddedObject {
+        page: None,
+        master: None,
+        shape: shape.id.clone(),
+        kind: ObjectKind::Image,
+        prog_id: None,
+        part: part.clone(),
+        file_name: format!("{}.{}", base_name, extension),
+        data: data.clone(),
+        show_as_icon: false,
+        preview: None,
+    })
+}
+
+struct Payload {
+    kind: ObjectKind,
+    prog_id: Option<String>,
+    extension: &'static str,
+    file_name: Option<String>,
+    data: Vec<u8>,
+}
+
+/// Снимает обёртку OLE и определяет тип содержимого
+fn unwrap(data: &[u8]) -> Payload {
+    if data.starts_with(ZIP_MAGIC) {
+        let (kind, extension) = package_kind(data);
+        return Payload {
+            kind,
+            prog_id: default_prog_id(kind, extension).map(str::to_string),
+            extension,
+            file_name: None,
+            data: data.to_vec(),
+        };
+    }
+    if !data.starts_with(OLE_MAGIC) {
+        return other(data);
+    }
+    let Ok(mut cf) = cfb::CompoundFile::open(Cursor::new(data)) else {
+        return other(data);
+    };
+
+    let mut read_stream = |name: &str| {
+        let mut buf = Vec::new();
+        cf.open_stream(name).ok()?.read_to_end(&mut buf).ok()?;
+        Some(buf)
+    };
+    let prog_id = read_stream("\u{1}CompObj").and_then(|data| comp_obj_prog_id(&data));
+
+    let mut payload = if let Some(package) = read_stream("Package") {
+        let (kind, extension) = package_kind(&package);
+        Payload {
+            kind,
+            prog_id: None,
+            extension,
+            file_name: None,
+            data: package,
+        }
+    } else if let Some((file_name, native)) =
+        read_stream("\u{1}Ole10Native").and_then(|data| ole10_native(&data))
+    {
+        Payload {
+            kind: ObjectKind::Package,
+            prog_id: None,
+            extension: "bin",
+            file_name,
+            data: native,
+        }
+    } else {
+        let (kind, extension) = [
+            ("Workbook", ObjectKind::Excel, "xls"),
+            ("Book", ObjectKind::Excel, "xls"),
+            ("WordDocument", ObjectKind::Word, "doc"),
+            ("PowerPoint Document", ObjectKind::PowerPoint, "ppt"),
+            ("VisioDocument", ObjectKind::Visio, "vsd"),
+        ]
+        .into_iter()
+        .find(|(stream, _, _)| cf.is_stream(stream))
+        .map_or((ObjectKind::Other, "bin"), |(_, kind, ext)| (kind, ext));
+        Payload {
+            kind,
+            prog_id: None,
+            extension,
+            file_name: None,
+            data: data.to_vec(),
+        }
+    };
+
+    if payload.kind == ObjectKind::Other
+        && let Some(kind) = prog_id.as_deref().and_then(kind_from_prog_id)
+    {
+        payload.kind = kind;
+    }
+    payload.prog_id =
+        prog_id.or_else(|| default_prog_id(payload.kind, payload.extension).map(str::to_string));
+    payload
+}
+
+fn other(data: &[u8]) -> Payload {
+    Payload {
+        kind: ObjectKind::Other,
+        prog_id: None,
+        extension: "bin",
+        file_name: None,
+        data: data.to_vec(),
+    }
+}
+
+/// Тип пакета OPC по главной части
+fn package_kind(data: &[u8]) -> (ObjectKind, &'static str) {
+    if let Some(format) = detect_format(data).filter(|f| f.is_package()) {
+        return (ObjectKind::Visio, format.extension());
+    }
+    let Ok(archive) = zip::ZipArchive::new(Cursor::new(data)) else {
+        return (ObjectKind::Other, "bin");
+    };
+    let has = |name: &str| archive.file_names().any(|n| n == name);
+    if has("xl/workbook.xml") {
+        (ObjectKind::Excel, "xlsx")
+    } else if has("word/document.xml") {
+        (ObjectKind::Word, "docx")
+    } else if has("ppt/presentation.xml") {
+        (ObjectKind::PowerPoint, "pptx")
+    } else {
+        (ObjectKind::Package, "zip")
+    }
+}
+
+fn kind_from_prog_id(prog_id: &str) -> Option<ObjectKind> {
+    let application = prog_id.split('.').next()?;
+    let kind = match application {
+        "Excel" => ObjectKind::Excel,
+        "Word" => ObjectKind::Word,
+        "PowerPoint" => ObjectKind::PowerPoint,
+        "Visio" => ObjectKind::Visio,
+        "Package" => ObjectKind::Package,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// ProgID, который Office записывает для объектов данного типа
+fn default_prog_id(kind: ObjectKind, extension: &str) -> Option<&'static str> {
+    let prog_id = match (kind, extension) {
+        (ObjectKind::Excel, "xls") => "Excel.Sheet.8",
+        (ObjectKind::Excel, _) => "Excel.Sheet.12",
+        (ObjectKind::Word, "doc") => "Word.Document.8",
+        (ObjectKind::Word, _) => "Word.Document.12",
+        (ObjectKind::PowerPoint, "ppt") => "PowerPoint.Show.8",
+        (ObjectKind::PowerPoint, _) => "PowerPoint.Show.12",
+        (ObjectKind::Visio, "vsd") => "Visio.Drawing.11",
+        (ObjectKind::Visio, _) => "Visio.Drawing.15",
+        (ObjectKind::Package, _) => "Package",
+        _ => return None,
+    };
+    Some(prog_id)
+}
+
+/// ProgID из потока `\x01CompObj` (MS-OLEDS 2.3.8)
+fn comp_obj_prog_id(data: &[u8]) -> Option<String> {
+    let mut input = Cursor::new(data);
+    input.set_position(28);
+    skip_ansi_string(&mut input)?;
+    // Формат буфера обмена: маркер со стандартным форматом или строка
+    match input.read_u32::<LittleEndian>().ok()? {
+        0xFFFF_FFFF | 0xFFFF_FFFE => {
+            input.read_u32::<LittleEndian>().ok()?;
+        }
+        len => input.set_position(input.position() + len as u64),
+    }
+    let prog_id = read_ansi_string(&mut input)?;
+    (!prog_id.is_empty()).then_some(prog_id)
+}
+
+fn skip_ansi_string(input: &mut Cursor<&[u8]>) -> Option<()> {
+    let len = input.read_u32::<LittleEndian>().ok()?;
+    input.set_position(input.position() + len as u64);
+    Some(())
+}
+
+fn read_ansi_string(input: &mut Cursor<&[u8]>) -> Option<String> {
+    let len = input.read_u32::<LittleEndian>().ok()? as usize;
+    let start = input.position() as usize;
+    let bytes = input.get_ref().get(start..start.checked_add(len)?)?;
+    input.set_position((start + len) as u64);
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Some(String::from_utf8_lossy(&bytes[..end]).trim().to_string())
+}
+
+/// Файл из потока `\x01Ole10Native`: размер, метка, исходный путь, временный путь и данные
+fn ole10_native(data: &[u8]) -> Option<(Option<String>, Vec<u8>)> {
+    let mut input = Cursor::new(data);
+    input.read_u32::<LittleEndian>().ok()?;
+    input.read_u16::<LittleEndian>().ok()?;
+    let label = read_c_string(&mut input)?;
+    read_c_string(&mut input)?;
+    input.read_u32::<LittleEndian>().ok()?;
+    input.read_u32::<LittleEndian>().ok()?;
+    read_c_string(&mut input)?;
+    let size = input.read_u32::<LittleEndian>().ok()? as usize;
+    let start = input.position() as usize;
+    let contents = data.get(start..start.checked_add(size)?)?.to_vec();
+
+    // Метка может содержать путь; для имени файла нужен только последний компонент
+    let file_name = label
+        .rsplit(['/', '\\'])
+        .next()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .map(str::to_string);
+    Some((file_name, contents))
+}
+
+fn read_c_string(input: &mut Cursor<&[u8]>) -> Option<String> {
+    let start = input.position() as usize;
+    let rest = input.get_ref().get(start..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    input.set_position((start + len + 1) as u64);
+    Some(String::from_utf8_lossy(&rest[..len]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::{self, ForeignData, Page};
+    use crate::parser_vsdx::write_vsdx;
+    use std::io::Write;
+
+    fn ole_object(prog_id: &str, streams: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut comp_obj = vec![0u8; 28];
+        for text in ["Microsoft Excel Worksheet", "Biff8", prog_id] {
+            comp_obj.extend(((text.len() + 1) as u32).to_le_bytes());
+            comp_obj.extend(text.as_bytes());
+            comp_obj.push(0);
+        }
+        let mut cf = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        cf.create_stream("\u{1}CompObj")
+            .unwrap()
+            .write_all(&comp_obj)
+            .unwrap();
+        for (name, data) in streams {
+            cf.create_stream(name).unwrap().write_all(data).unwrap();
+        }
+        cf.flush().unwrap();
+        cf.into_inner().into_inner()
+    }
+
+    fn xlsx() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("xl/workbook.xml", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"<workbook/>").unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_unwrap_ole() {
+        let payload = unwrap(&ole_object("Excel.Sheet.12", &[("Package", &xlsx())]));
+        assert_eq!(payload.kind, ObjectKind::Excel);
+        assert_eq!(payload.prog_id.as_deref(), Some("Excel.Sheet.12"));
+        assert_eq!(payload.extension, "xlsx");
+        assert!(payload.data.starts_with(ZIP_MAGIC));
+
+        let mut native = vec![0u8; 4];
+        native.extend(2u16.to_le_bytes());
+        native.extend(b"C:\\docs\\notes.txt\0C:\\docs\\notes.txt\0");
+        native.extend([0u8; 8]);
+        native.extend(b"C:\\tmp\\notes.txt\0");
+        native.extend(5u32.to_le_bytes());
+        native.extend(b"hello");
+        let payload = unwrap(&ole_object("Package", &[("\u{1}Ole10Native", &native)]));
+        assert_eq!(payload.kind, ObjectKind::Package);
+        assert_eq!(payload.file_name.as_deref(), Some("notes.txt"));
+        assert_eq!(payload.data, b"hello");
+    }
+
+    #[test]
+    fn test_vsdx_embedding() {
+        let mut diagram = Diagram::default();
+        diagram
+            .media
+            .insert("visio/media/image1.emf".to_string(), b"preview".to_vec());
+        diagram.media.insert(
+            "visio/embeddings/Microsoft_Excel_Worksheet.xlsx".to_string(),
+            xlsx(),
+        );
+        diagram.pages.push(Page {
+            id: "0".to_string(),
+            name: "Page-1".to_string(),
+            shapes: vec![Shape {
+                id: "7".to_string(),
+                shape_type: "Foreign".to_string(),
+                foreign: Some(ForeignData {
+                    foreign_type: "EnhMetaFile".to_string(),
+                    target: Some("visio/media/image1.emf".to_string()),
+                    object_target: Some(
+                        "visio/embeddings/Microsoft_Excel_Worksheet.xlsx".to_string(),
+                    ),
+                    show_as_icon: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        // Связи с объектом и предпросмотром переживают запись и чтение пакета
+        let bytes = write_vsdx::to_bytes(&diagram).unwrap();
+        let read = read_vsdx::parse(Cursor::new(bytes)).unwrap();
+        let objects = embedded_objects(&read);
+        assert_eq!(objects.len(), 1);
+        let object = &objects[0];
+        assert_eq!(object.page.as_deref(), Some("0"));
+        assert_eq!(object.shape, "7");
+        assert_eq!(object.kind, ObjectKind::Excel);
+        assert_eq!(object.prog_id.as_deref(), Some("Excel.Sheet.12"));
+        assert_eq!(object.file_name, "page0-shape7.xlsx");
+        assert!(object.show_as_icon);
+        assert_eq!(object.preview.as_deref(), Some("visio/media/image1.emf"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Граф соединений страницы в формате GraphML: фигуры — узлы, соединители — рёбра.
+
+use std::fmt::Write;
+
+use crate::export::{all_shapes, connections};
+use crate::parser_vsdx::read_vsdx::Page;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn page_to_graphml(page: &Page) -> String {
+    let links = connections(page);
+    let mut out = String::new();
+    out.push_str(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="name" for="node" attr.name="name" attr.type="string"/>
+  <key id="text" for="node" attr.name="text" attr.type="string"/>
+  <key id="master" for="node" attr.name="master" attr.type="string"/>
+  <key id="connector" for="edge" attr.name="connector" attr.type="string"/>
+"#,
+    );
+    let _ = writeln!(
+        out,
+        r#"  <graph id="{}" edgedefault="directed">"#,
+        escape(&page.name)
+    );
+
+    for shape in all_shapes(&page.shapes) {
+        if links.iter().any(|l| l.connector == shape.id) {
+            continue;
+        }
+        let _ = writeln!(out, r#"    <node id="{}">"#, escape(&shape.id));
+        if !shape.name.is_empty() {
+            let _ = writeln!(
+                out,
+                r#"      <data key="name">{}</data>"#,
+                escape(&shape.name)
+            );
+        }
+        if let Some(text) = shape.text.as_deref().filter(|t| !t.is_empty()) {
+            let _ = writeln!(out, r#"      <data key="text">{}</data>"#, escape(text));
+        }
+        if let Some(master) = &shape.master {
+            let _ = writeln!(out, r#"      <data key="master">{}</data>"#, escape(master));
+        }
+        out.push_str("    </node>\n");
+    }
+
+    for link in &links {
+        let (Some(from), Some(to)) = (&link.from, &link.to) else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            r#"    <edge id="e{0}" source="{1}" target="{2}"><data key="connector">{0}</data></edge>"#,
+            escape(&link.connector),
+            escape(from),
+            escape(to)
+        );
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::{Connect, Shape};
+
+    #[test]
+    fn test_connector_becomes_edge() {
+        let shape = |id: &str| Shape {
+            id: id.to_string(),
+            ..Default::default()
+        };
+        let glue = |cell: &str, to: &str| Connect {
+            from_sheet: "3".to_string(),
+            from_cell: Some(cell.to_string()),
+            to_sheet: to.to_string(),
+            ..Default::default()
+        };
+        let page = Page {
+            name: "P".to_string(),
+            shapes: vec![shape("1"), shape("2"), shape("3")],
+            connects: vec![glue("EndX", "2"), glue("BeginX", "1")],
+            ..Default::default()
+        };
+        let xml = page_to_graphml(&page);
+        assert!(xml.contains(r#"<node id="1">"#));
+        assert!(!xml.contains(r#"<node id="3">"#));
+        assert!(xml.contains(r#"<edge id="e3" source="1" target="2">"#));
+    }
+}
@@ -0,0 +1,129 @@
+//! Страницы диаграммы одним документом HTML: рисунок SVG со ссылками и список ссылок.
+//!
+//! Каждая страница - элемент `section` с якорем `page-N`, поэтому ссылки на другие
+//! страницы документа работают внутри одного файла.
+
+use std::fmt::Write;
+
+use crate::export::hyperlinks::{LinkTarget, page_anchor, page_links};
+use crate::parser_vsdx::read_vsdx::Diagram;
+use crate::render::svg::{escape, scene_to_svg};
+use crate::render::visio::page_scene;
+
+/// Документ HTML со страницами `pages` (индексы с нуля)
+pub fn diagram_to_html(diagram: &Diagram, pages: &[usize]) -> String {
+    let title = diagram
+        .document_info
+        .title
+        .clone()
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "Diagram".to_string());
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+</head>
+<body>"#,
+        escape(&title)
+    );
+
+    let page_href = |index: usize| format!("#{}", page_anchor(index));
+    for &index in pages {
+        let Some(page) = diagram.pages.get(index) else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            r#"<section id="{}">
+<h2>{}</h2>"#,
+            page_anchor(index),
+            escape(&page.name)
+        );
+
+        let svg = scene_to_svg(&page_scene(diagram, page));
+        // Объявление XML внутри HTML не нужно
+        let svg = match svg.split_once("?>\n") {
+            Some((_, rest)) if svg.starts_with("<?xml") => rest,
+            _ => svg.as_str(),
+        };
+        out.push_str(svg);
+
+        let links = page_links(diagram, page);
+        if !links.is_empty() {
+            out.push_str("<ul class=\"links\">\n");
+            for link in &links {
+                let text = [
+                    &link.hyperlink.description,
+                    &link.hyperlink.address,
+                    &link.hyperlink.sub_address,
+                ]
+                .into_iter()
+                .find(|t| !t.is_empty())
+                .cloned()
+                .unwrap_or_default();
+                let source = match &link.shape_id {
+                    Some(id) if link.shape_name.is_empty() => format!("Shape {id}"),
+                    Some(_) => link.shape_name.clone(),
+                    None => "Page".to_string(),
+                };
+                match &link.target {
+                    Some(target) => {
+                        let new_window = match target {
+                            LinkTarget::Url(_) if link.hyperlink.new_window => {
+                                r#" target="_blank""#
+                            }
+                            _ => "",
+                        };
+                        let _ = writeln!(
+                            out,
+                            r#"<li><a href="{}"{}>{}</a> ({})</li>"#,
+                            escape(&target.href(&page_href)),
+                            new_window,
+                            escape(&text),
+                            escape(&source)
+                        );
+                    }
+                    None => {
+                        let _ = writeln!(out, "<li>{} ({})</li>", escape(&text), escape(&source));
+                    }
+                }
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::hyperlinks::tests::linked_diagram;
+
+    #[test]
+    fn test_diagram_to_html() {
+        let html = diagram_to_html(&linked_diagram(), &[0, 1]);
+        assert!(html.contains(r#"<section id="page-1">"#));
+        assert!(html.contains(r#"<section id="page-2">"#));
+        assert!(!html.contains("<?xml"));
+        assert!(html.contains(
+            r#"<li><a href="https://wiki.example.com/runbook" target="_blank">Runbook</a> (Server)</li>"#
+        ));
+        assert!(html.contains(r##"<li><a href="#page-2">Details</a> (Server)</li>"##));
+        assert!(
+            html.contains(
+                r#"<li><a href="https://example.com" target="_blank">Home</a> (Page)</li>"#
+            )
+        );
+        // Щелчок по фигуре открывает первую ссылку: рамка 2x1 дюйма в пунктах
+        assert!(html.contains(r#"<a href="https://wiki.example.com/runbook" target="_blank">"#));
+        assert!(html.contains(r#"<path d="M72 108L216 108L216 36L72 36Z" fill="transparent""#));
+    }
+}
@@ -0,0 +1,226 @@
+//! Гиперссылки страниц: куда ведут и какие фигуры их несут.
+//!
+//! Ссылка с `Address` ведёт наружу (URL или файл), ссылка только с `SubAddress` - на
+//! страницу этого же документа. В SVG и HTML страница `N` (с единицы) получает якорь
+//! `page-N`, см. [`page_anchor`].
+
+use crate::export::all_shapes;
+use crate::parser_vsdx::read_vsdx::{Diagram, Hyperlink, Page};
+use crate::parser_vsdx::shapes::apply_masters;
+
+/// Куда ведёт гиперссылка
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    /// Внешний адрес вместе с `SubAddress` после `#`
+    Url(String),
+    /// Страница документа по индексу с нуля и, возможно, фигура на ней
+    Page { index: usize, shape: Option<String> },
+}
+
+impl LinkTarget {
+    /// Адрес для `href`; `page_href` переводит индекс страницы в адрес её файла или якоря
+    pub fn href(&self, page_href: &dyn Fn(usize) -> String) -> String {
+        match self {
+            LinkTarget::Url(url) => url.clone(),
+            LinkTarget::Page { index, .. } => page_href(*index),
+        }
+    }
+}
+
+/// Гиперссылка страницы или фигуры на ней
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLink {
+    /// ID фигуры; `None` у ссылки листа страницы
+    pub shape_id: Option<String>,
+    pub shape_name: String,
+    pub hyperlink: Hyperlink,
+    /// `None`, если адрес пуст или страница не найдена
+    pub target: Option<LinkTarget>,
+}
+
+/// Якорь страницы `index` (с нуля) в SVG и HTML
+pub fn page_anchor(index: usize) -> String {
+    format!("page-{}", index + 1)
+}
+
+/// Цель ссылки; страница ищется по `Name` или `NameU` без учёта регистра
+pub fn resolve(diagram: &Diagram, link: &Hyperlink) -> Option<LinkTarget> {
+    let address = link.address.trim();
+    let sub_address = link.sub_address.trim();
+    if !address.is_empty() {
+        return Some(LinkTarget::Url(if sub_address.is_empty() {
+            address.to_string()
+        } else {
+            format!("{address}#{sub_address}")
+        }));
+    }
+    if sub_address.is_empty() {
+        return None;
+    }
+
+    let (page_name, shape) = match sub_address.split_once('/') {
+        Some((page, shape)) => (page, Some(shape.to_string())),
+        None => (sub_address, None),
+    };
+    let page_name = page_name.to_lowercase();
+    let index = diagram.pages.iter().position(|p| {
+        std::iter::once(&p.name)
+            .chain(&p.local_name)
+            .any(|name| name.to_lowercase() == page_name)
+    })?;
+    Some(LinkTarget::Page { index, shape })
+}
+
+/// Видимые ссылки листа страницы и её фигур, включая унаследованные от мастеров
+pub fn page_links(diagram: &Diagram, page: &Page) -> Vec<PageLink> {
+    let mut links: Vec<PageLink> = page
+        .hyperlinks()
+        .into_iter()
+        .map(|hyperlink| PageLink {
+            shape_id: None,
+            shape_name: page.name.clone(),
+            target: resolve(diagram, &hyperlink),
+            hyperlink,
+        })
+        .collect();
+
+    let mut shapes = page.shapes.clone();
+    apply_masters(&mut shapes, &diagram.masters);
+    for shape in all_shapes(&shapes) {
+        for hyperlink in shape.hyperlinks() {
+            links.push(PageLink {
+                shape_id: Some(shape.id.clone()),
+                shape_name: shape.name.clone(),
+                target: resolve(diagram, &hyperlink),
+                hyperlink,
+            });
+        }
+    }
+    links.retain(|l| !l.hyperlink.invisible);
+    links
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::{Cell, Row, Section, Shape};
+
+    pub(crate) fn hyperlink_section(links: &[(&str, &str, &str)]) -> Section {
+        let cell = |value: &str| Cell {
+            value: value.to_string(),
+            ..Default::default()
+        };
+        Section {
+            name: "Hyperlink".to_string(),
+            rows: links
+                .iter()
+                .map(|(description, address, sub_address)| Row {
+                    cells: [
+                        ("Description".into(), cell(description)),
+                        ("Address".into(), cell(address)),
+                        ("SubAddress".into(), cell(sub_address)),
+                        ("NewWindow".into(), cell("1")),
+                    ]
+                    .into(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Две страницы: на первой фигура со ссылкой на справочник и на вторую страницу
+    pub(crate) fn linked_diagram() -> Diagram {
+        let mut shape = Shape {
+            id: "1".to_string(),
+            name: "Server".to_string(),
+            sections: vec![hyperlink_section(&[
+                ("Runbook", "https://wiki.example.com/runbook", ""),
+                ("Details", "", "Page-2/Sheet.3"),
+            ])],
+            ..Default::default()
+        };
+        for (name, value) in [
+            ("PinX", 2.0),
+            ("PinY", 1.0),
+            ("Width", 2.0),
+            ("Height", 1.0),
+            ("LocPinX", 1.0),
+            ("LocPinY", 0.5),
+        ] {
+            shape.set_cell(name, value);
+        }
+        Diagram {
+            pages: vec![
+                Page {
+                    id: "0".to_string(),
+                    name: "Page-1".to_string(),
+                    width: 4.0,
+                    height: 2.0,
+                    shapes: vec![shape],
+                    sections: vec![hyperlink_section(&[("Home", "https://example.com", "")])],
+                    ..Default::default()
+                },
+                Page {
+                    id: "4".to_string(),
+                    name: "Page-2".to_string(),
+                    width: 4.0,
+                    height: 2.0,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_page_links() {
+        let diagram = linked_diagram();
+        let links = page_links(&diagram, &diagram.pages[0]);
+        assert_eq!(links.len(), 3);
+
+        assert_eq!(links[0].shape_id, None);
+        assert_eq!(
+            links[0].target,
+            Some(LinkTarget::Url("https://example.com".to_string()))
+        );
+        assert_eq!(links[1].shape_name, "Server");
+        assert!(links[1].hyperlink.new_window);
+        assert_eq!(
+            links[2].target,
+            Some(LinkTarget::Page {
+                index: 1,
+                shape: Some("Sheet.3".to_string())
+            })
+        );
+        let href = links[2]
+            .target
+            .as_ref()
+            .unwrap()
+            .href(&|i| format!("#{}", page_anchor(i)));
+        assert_eq!(href, "#page-2");
+
+        let missing = Hyperlink {
+            sub_address: "Page-9".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(resolve(&diagram, &missing), None);
+    }
+
+    #[test]
+    fn test_resolve_localized_page_name() {
+        let mut diagram = linked_diagram();
+        diagram.pages[1].local_name = Some("Страница-2".to_string());
+        let link = |sub_address: &str| Hyperlink {
+            sub_address: sub_address.to_string(),
+            ..Default::default()
+        };
+        let page_2 = Some(LinkTarget::Page {
+            index: 1,
+            shape: None,
+        });
+        assert_eq!(resolve(&diagram, &link("СТРАНИЦА-2")), page_2);
+        assert_eq!(resolve(&diagram, &link("страница-2")), page_2);
+        assert_eq!(resolve(&diagram, &link("page-2")), page_2);
+    }
+}
@@ -0,0 +1,62 @@
+//! Экспорт диаграмм в сторонние форматы.
+
+pub mod embedded;
+pub mod graphml;
+pub mod html;
+pub mod hyperlinks;
+
+use crate::parser_vsdx::read_vsdx::{Page, Shape};
+
+/// Соединитель страницы и фигуры, к которым приклеены его концы
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    /// ID фигуры-соединителя
+    pub connector: String,
+    /// Фигура у начала (`BeginX`)
+    pub from: Option<String>,
+    /// Фигура у конца (`EndX`)
+    pub to: Option<String>,
+}
+
+/// Собирает соединения страницы из её `Connects` в порядке первого появления соединителя
+pub fn connections(page: &Page) -> Vec<Connection> {
+    let mut result: Vec<Connection> = Vec::new();
+    for connect in &page.connects {
+        let end = match connect.from_cell.as_deref() {
+            Some("BeginX") => true,
+            Some("EndX") => false,
+            _ => continue,
+        };
+        let index = match result
+            .iter()
+            .position(|c| c.connector == connect.from_sheet)
+        {
+            Some(index) => index,
+            None => {
+                result.push(Connection {
+                    connector: connect.from_sheet.clone(),
+                    from: None,
+                    to: None,
+                });
+                result.len() - 1
+            }
+        };
+        let target = Some(connect.to_sheet.clone());
+        if end {
+            result[index].from = target;
+        } else {
+            result[index].to = target;
+        }
+    }
+    result
+}
+
+/// Все фигуры страницы, включая вложенные в группы, в порядке документа
+pub fn all_shapes(shapes: &[Shape]) -> Vec<&Shape> {
+    let mut result = Vec::new();
+    for shape in shapes {
+        result.push(shape);
+        result.extend(all_shapes(&shape.shapes));
+    }
+    result
+}
@@ -0,0 +1,641 @@
+//! Отображаемый текст полей по кодам `field_formats`.
+//!
+//! Поле текста (`<fld>` в VSDX и VDX, `VSD_TEXT_FIELD` в VSD) ссылается на строку секции
+//! `Field`: ячейка `Value` хранит значение с единицами, ячейка `Format` - код формата,
+//! обычно формулой `FIELDPICTURE(n)`. Числа выводятся через [`Measurement`], даты и время -
+//! по шаблонам [`Locale`]: Visio показывает их в языке текста фигуры (`LangID`).
+
+use std::time::UNIX_EPOCH;
+
+use crate::measurement::{Measurement, Unit, fixed};
+use crate::parser_vsd::utils::{civil_from_days, ole_date_to_system_time};
+use crate::parser_vsd::vsd_constants::field_formats::*;
+use crate::parser_vsdx::read_vsdx::{Cell, Row, Shape};
+
+/// Неразрывный пробел: разделитель групп разрядов во французском и русском
+const NBSP: char = '\u{A0}';
+
+/// Языковые настройки вывода чисел, дат и денег
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Locale {
+    /// Тег языка, как в ячейке `LangID` VSDX
+    pub tag: &'static str,
+    /// Код языка Windows (LCID), как в `LangID` VSD и VDX
+    pub lcid: u32,
+    pub decimal_separator: char,
+    pub group_separator: char,
+    /// Шаблоны в обозначениях .NET: `yyyy`, `MMMM`, `dd`, `H`, `hh`, `mm`, `ss`, `tt`
+    pub short_date: &'static str,
+    pub long_date: &'static str,
+    /// Длинная дата без дня недели
+    pub month_day_year: &'static str,
+    pub short_time: &'static str,
+    pub long_time: &'static str,
+    pub am: &'static str,
+    pub pm: &'static str,
+    pub months: [&'static str; 12],
+    pub short_months: [&'static str; 12],
+    /// Начиная с воскресенья
+    pub days: [&'static str; 7],
+    pub currency: &'static str,
+    pub currency_first: bool,
+}
+
+pub const EN_US: Locale = Locale {
+    tag: "en-US",
+    lcid: 1033,
+    decimal_separator: '.',
+    group_separator: ',',
+    short_date: "M/d/yyyy",
+    long_date: "dddd, MMMM d, yyyy",
+    month_day_year: "MMMM d, yyyy",
+    short_time: "h:mm tt",
+    long_time: "h:mm:ss tt",
+    am: "AM",
+    pm: "PM",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    days: [
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+    ],
+    currency: "$",
+    currency_first: true,
+};
+
+pub const EN_GB: Locale = Locale {
+    tag: "en-GB",
+    lcid: 2057,
+    short_date: "dd/MM/yyyy",
+    long_date: "dddd, d MMMM yyyy",
+    month_day_year: "d MMMM yyyy",
+    short_time: "HH:mm",
+    long_time: "HH:mm:ss",
+    am: "am",
+    pm: "pm",
+    currency: "£",
+    ..EN_US
+};
+
+pub const DE_DE: Locale = Locale {
+    tag: "de-DE",
+    lcid: 1031,
+    decimal_separator: ',',
+    group_separator: '.',
+    short_date: "dd.MM.yyyy",
+    long_date: "dddd, d. MMMM yyyy",
+    month_day_year: "d. MMMM yyyy",
+    short_time: "HH:mm",
+    long_time: "HH:mm:ss",
+    am: "",
+    pm: "",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    days: [
+        "Sonntag",
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+    ],
+    currency: "€",
+    currency_first: false,
+};
+
+pub const FR_FR: Locale = Locale {
+    tag: "fr-FR",
+    lcid: 1036,
+    group_separator: NBSP,
+    short_date: "dd/MM/yyyy",
+    long_date: "dddd d MMMM yyyy",
+    month_day_year: "d MMMM yyyy",
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    short_months: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.",
+        "déc.",
+    ],
+    days: [
+        "dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi",
+    ],
+    ..DE_DE
+};
+
+pub const RU_RU: Locale = Locale {
+    tag: "ru-RU",
+    lcid: 1049,
+    group_separator: NBSP,
+    short_date: "dd.MM.yyyy",
+    long_date: "dddd, d MMMM yyyy г.",
+    month_day_year: "d MMMM yyyy г.",
+    short_time: "H:mm",
+    long_time: "H:mm:ss",
+    // В датах месяц стоит после числа, поэтому в родительном падеже
+    months: [
+        "января",
+        "февраля",
+        "марта",
+        "апреля",
+        "мая",
+        "июня",
+        "июля",
+        "августа",
+        "сентября",
+        "октября",
+        "ноября",
+        "декабря",
+    ],
+    short_months: [
+        "янв.",
+        "февр.",
+        "мар.",
+        "апр.",
+        "мая",
+        "июн.",
+        "июл.",
+        "авг.",
+        "сент.",
+        "окт.",
+        "нояб.",
+        "дек.",
+    ],
+    days: [
+        "воскресенье",
+        "понедельник",
+        "вторник",
+        "среда",
+        "четверг",
+        "пятница",
+        "суббота",
+    ],
+    currency: "₽",
+    ..DE_DE
+};
+
+/// Поддерживаемые языки; остальные выводятся как `en-US`
+pub const LOCALES: [Locale; 5] = [EN_US, EN_GB, DE_DE, FR_FR, RU_RU];
+
+impl Default for Locale {
+    fn default() -> Self {
+        EN_US
+    }
+}
+
+impl Locale {
+    /// Язык по тегу (`ru-RU`); при неизвестной области - первый с тем же языком
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let tag = tag.trim().replace('_', "-");
+        let language = tag.split('-').next().unwrap_or_default();
+        LOCALES
+            .iter()
+            .find(|l| l.tag.eq_ignore_ascii_case(&tag))
+            .or_else(|| {
+                LOCALES.iter().find(|l| {
+                    l.tag
+                        .split('-')
+                        .next()
+                        .is_some_and(|lang| lang.eq_ignore_ascii_case(language))
+                })
+            })
+            .copied()
+    }
+
+    pub fn from_lcid(lcid: u32) -> Option<Locale> {
+        LOCALES.iter().find(|l| l.lcid == lcid).copied()
+    }
+
+    /// Язык текста фигуры по `LangID` первой строки секции `Character`
+    pub fn for_shape(shape: &Shape) -> Locale {
+        shape
+            .sections_named("Character")
+            .iter()
+            .flat_map(|s| s.rows.iter())
+            .find_map(|row| row.cells.get("LangID"))
+            .and_then(|lang| match lang.value.trim().parse::<u32>() {
+                Ok(lcid) => Locale::from_lcid(lcid),
+                Err(_) => Locale::from_tag(&lang.value),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Число, записанное с точкой, с десятичным разделителем языка
+    pub fn number(&self, text: &str) -> String {
+        text.replace('.', &self.decimal_separator.to_string())
+    }
+
+    /// Денежная сумма с разделителями групп и знаком валюты
+    pub fn currency(&self, value: f64, places: usize) -> String {
+        let text = fixed(value.abs(), places);
+        let (whole, fraction) = match text.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (text.as_str(), None),
+        };
+        let mut amount = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                amount.push(self.group_separator);
+            }
+            amount.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            amount.push(self.decimal_separator);
+            amount.push_str(fraction);
+        }
+
+        let sign = if value < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            "-"
+        } else {
+            ""
+        };
+        if self.currency_first {
+            format!("{sign}{}{amount}", self.currency)
+        } else {
+            format!("{sign}{amount}{NBSP}{}", self.currency)
+        }
+    }
+
+    /// Дата и время OLE (дни от 1899-12-30) по шаблону
+    pub fn format_date(&self, ole_date: f64, pattern: &str) -> Option<String> {
+        let time = ole_date_to_system_time(ole_date)?;
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs_f64().round() as i64,
+            Err(e) => -(e.duration().as_secs_f64().round() as i64),
+        };
+        let days = seconds.div_euclid(86_400);
+        let rest = seconds.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+        let (hour, minute, second) = (rest / 3600, rest % 3600 / 60, rest % 60);
+        // 1970-01-01 - четверг
+        let weekday = (days + 4).rem_euclid(7) as usize;
+        let hour12 = match hour % 12 {
+            0 => 12,
+            h => h,
+        };
+
+        let mut out = String::new();
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let mut n = 1;
+            while i + n < chars.len() && chars[i + n] == c {
+                n += 1;
+            }
+            match (c, n) {
+                ('y', 1..=2) => out.push_str(&format!("{:02}", year.rem_euclid(100))),
+                ('y', _) => out.push_str(&format!("{year:04}")),
+                ('M', 1) => out.push_str(&month.to_string()),
+                ('M', 2) => out.push_str(&format!("{month:02}")),
+                ('M', 3) => out.push_str(self.short_months[month as usize - 1]),
+                ('M', _) => out.push_str(self.months[month as usize - 1]),
+                ('d', 1) => out.push_str(&day.to_string()),
+                ('d', 2) => out.push_str(&format!("{day:02}")),
+                ('d', 3) => out.extend(self.days[weekday].chars().take(3)),
+                ('d', _) => out.push_str(self.days[weekday]),
+                ('H', 1) => out.push_str(&hour.to_string()),
+                ('H', _) => out.push_str(&format!("{hour:02}")),
+                ('h', 1) => out.push_str(&hour12.to_string()),
+                ('h', _) => out.push_str(&format!("{hour12:02}")),
+                ('m', 1) => out.push_str(&minute.to_string()),
+                ('m', _) => out.push_str(&format!("{minute:02}")),
+                ('s', 1) => out.push_str(&second.to_string()),
+                ('s', _) => out.push_str(&format!("{second:02}")),
+                ('t', _) => out.push_str(if hour < 12 { self.am } else { self.pm }),
+                _ => out.extend(std::iter::repeat_n(c, n)),
+            }
+            i += n;
+        }
+        Some(out.trim().to_string())
+    }
+
+    /// Шаблон даты или времени для кода формата; `None` - формат не календарный
+    #[allow(non_upper_case_globals)]
+    fn date_pattern(&self, format: u16) -> Option<String> {
+        let pattern = match format {
+            VSD_FIELD_FORMAT_DateShort | VSD_FIELD_FORMAT_MsoDateShort => self.short_date,
+            VSD_FIELD_FORMAT_DateLong | VSD_FIELD_FORMAT_MsoDateLongDay => self.long_date,
+            VSD_FIELD_FORMAT_MsoDateLong | VSD_FIELD_FORMAT_MsoDateEnglish => self.month_day_year,
+            VSD_FIELD_FORMAT_DateMDYY | VSD_FIELD_FORMAT_MsoDateShortAlt => "M/d/yy",
+            VSD_FIELD_FORMAT_DateMMDDYY => "MM/dd/yy",
+            VSD_FIELD_FORMAT_DateMMMDYYYY => "MMM d, yyyy",
+            VSD_FIELD_FORMAT_DateMMMMDYYYY => "MMMM d, yyyy",
+            VSD_FIELD_FORMAT_DateDMYY => "d/M/yy",
+            VSD_FIELD_FORMAT_DateDDMMYY | VSD_FIELD_FORMAT_MsoDateShortSlash => "dd/MM/yy",
+            VSD_FIELD_FORMAT_DateDMMMYYYY => "d MMM yyyy",
+            VSD_FIELD_FORMAT_DateDMMMMYYYY => "d MMMM yyyy",
+            VSD_FIELD_FORMAT_Dateyyyymd => "yyyy/M/d",
+            VSD_FIELD_FORMAT_Dateyymmdd => "yy/MM/dd",
+            VSD_FIELD_FORMAT_Dateyyyy_m_d => "yyyy-M-d",
+            VSD_FIELD_FORMAT_Dateyy_mm_dd => "yy-MM-dd",
+            VSD_FIELD_FORMAT_MsoDateISO => "yyyy-MM-dd",
+            VSD_FIELD_FORMAT_MsoDateShortMon | VSD_FIELD_FORMAT_MsoDateShortAbb => "d-MMM-yy",
+            VSD_FIELD_FORMAT_MsoDateMonthYr => "MMMM yy",
+            VSD_FIELD_FORMAT_MsoDateMon_Yr => "MMM-yy",
+            VSD_FIELD_FORMAT_TimeGen => self.long_time,
+            VSD_FIELD_FORMAT_TimeHMM => "h:mm",
+            VSD_FIELD_FORMAT_TimeHHMM => "hh:mm",
+            VSD_FIELD_FORMAT_TimeHMM24 => "H:mm",
+            VSD_FIELD_FORMAT_TimeHHMM24 | VSD_FIELD_FORMAT_MsoTime24 => "HH:mm",
+            VSD_FIELD_FORMAT_TimeHMMAMPM | VSD_FIELD_FORMAT_MsoTimePM => "h:mm tt",
+            VSD_FIELD_FORMAT_TimeHHMMAMPM => "hh:mm tt",
+            VSD_FIELD_FORMAT_MsoTimeSecPM => "h:mm:ss tt",
+            VSD_FIELD_FORMAT_MsoTimeSec24 => "HH:mm:ss",
+            VSD_FIELD_FORMAT_MsoTimeDatePM => return Some(format!("{} h:mm tt", self.short_date)),
+            VSD_FIELD_FORMAT_MsoTimeDateSecPM => {
+                return Some(format!("{} h:mm:ss tt", self.short_date));
+            }
+            // Восточноазиатские календари выводятся в григорианском с местным шаблоном
+            VSD_FIELD_FORMAT_TimeAMPMhmm_J
+            | VSD_FIELD_FORMAT_TimeAMPMhmm_C..=VSD_FIELD_FORMAT_TimeHHMMAMPM_E
+            | VSD_FIELD_FORMAT_TimeAMPMhmm_S
+            | VSD_FIELD_FORMAT_TimeAMPMhhmm_S => self.short_time,
+            VSD_FIELD_FORMAT_DateTWNfYYYYMMDDD_C..=VSD_FIELD_FORMAT_Dateyyyymd_K
+            | VSD_FIELD_FORMAT_Dateyyyymd_S..=VSD_FIELD_FORMAT_Datewwyyyymd_S
+            | VSD_FIELD_FORMAT_MsoFEExtra1..=VSD_FIELD_FORMAT_MsoFEExtra5 => self.short_date,
+            _ => return None,
+        };
+        Some(pattern.to_string())
+    }
+}
+
+/// Код формата из ячейки `Format`: `FIELDPICTURE(n)` в формуле или число в значении
+pub fn format_code(format: &Cell) -> Option<u16> {
+    let picture = format.formula.as_deref().and_then(|f| {
+        let args = f.trim().strip_prefix("FIELDPICTURE(")?.strip_suffix(')')?;
+        args.trim().parse::<u16>().ok()
+    });
+    picture.or_else(|| format.value.trim().parse::<u16>().ok())
+}
+
+/// Текст значения поля так, как его показывает Visio
+#[allow(non_upper_case_globals)]
+pub fn format_value(value: &Cell, format: u16, locale: &Locale) -> String {
+    let unit = value
+        .unit
+        .as_deref()
+        .and_then(Unit::from_code)
+        .unwrap_or(Unit::Number);
+
+    let text = || value.value.clone();
+    match format {
+        VSD_FIELD_FORMAT_StrNormal => return text(),
+        VSD_FIELD_FORMAT_StrLower => return text().to_lowercase(),
+        VSD_FIELD_FORMAT_StrUpper => return text().to_uppercase(),
+        _ => {}
+    }
+    if matches!(unit, Unit::String | Unit::Guid | Unit::Color) {
+        return text();
+    }
+    let Some(number) = value.number() else {
+        return text();
+    };
+
+    if let Some(pattern) = locale.date_pattern(format) {
+        return locale.format_date(number, &pattern).unwrap_or_else(text);
+    }
+    if unit == Unit::Date {
+        // Числовой формат у даты: Visio показывает короткую дату, а время - если оно есть
+        let pattern = if number.fract() == 0.0 {
+            locale.short_date.to_string()
+        } else {
+            format!("{} {}", locale.short_date, locale.long_time)
+        };
+        return locale.format_date(number, &pattern).unwrap_or_else(text);
+    }
+    Measurement::from_internal(number, unit).format_in(format, locale)
+}
+
+/// Текст поля по строке секции `Field`
+pub fn field_text(row: &Row, locale: &Locale) -> String {
+    let Some(value) = row.cells.get("Value") else {
+        return String::new();
+    };
+    let format = row
+        .cells
+        .get("Format")
+        .and_then(format_code)
+        .unwrap_or(VSD_FIELD_FORMAT_NumGenDefUnits);
+    format_value(value, format, locale)
+}
+
+/// Тексты полей фигуры в порядке строк секции `Field` вместе с их `IX`
+pub fn field_texts(shape: &Shape) -> Vec<(Option<String>, String)> {
+    let locale = Locale::for_shape(shape);
+    shape
+        .sections_named("Field")
+        .iter()
+        .flat_map(|s| s.rows.iter())
+        .filter(|row| !row.deleted)
+        .map(|row| (row.index.clone(), field_text(row, &locale)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::Section;
+
+    fn cell(value: &str, unit: Option<&str>) -> Cell {
+        Cell {
+            value: value.to_string(),
+            unit: unit.map(str::to_string),
+            formula: None,
+        }
+    }
+
+    /// 2024-03-05 14:07:09
+    const DATE: f64 = 45_356.0 + (14.0 * 3600.0 + 7.0 * 60.0 + 9.0) / 86_400.0;
+
+    #[test]
+    fn test_dates() {
+        let date = cell(&DATE.to_string(), Some("DATE"));
+        let us = EN_US;
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_DateShort, &us),
+            "3/5/2024"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_DateLong, &us),
+            "Tuesday, March 5, 2024"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_DateMMMDYYYY, &us),
+            "Mar 5, 2024"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_MsoDateISO, &us),
+            "2024-03-05"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_TimeGen, &us),
+            "2:07:09 PM"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_TimeHHMM24, &us),
+            "14:07"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_MsoTimeDatePM, &us),
+            "3/5/2024 2:07 PM"
+        );
+
+        let ru = Locale::from_tag("ru-RU").unwrap();
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_DateShort, &ru),
+            "05.03.2024"
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_DateLong, &ru),
+            "вторник, 5 марта 2024 г."
+        );
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_TimeGen, &ru),
+            "14:07:09"
+        );
+        let de = Locale::from_lcid(1031).unwrap();
+        assert_eq!(
+            format_value(&date, VSD_FIELD_FORMAT_TimeHMMAMPM, &de),
+            "2:07"
+        );
+        // Числовой формат у даты
+        assert_eq!(format_value(&date, 0, &us), "3/5/2024 2:07:09 PM");
+    }
+
+    #[test]
+    fn test_numbers_and_strings() {
+        let de = Locale::from_tag("de").unwrap();
+        let length = cell("1.5", Some("MM"));
+        assert_eq!(
+            format_value(&length, VSD_FIELD_FORMAT_2PlDefUnits, &EN_US),
+            "38.10 mm"
+        );
+        assert_eq!(
+            format_value(&length, VSD_FIELD_FORMAT_2PlDefUnits, &de),
+            "38,10 mm"
+        );
+        let hours = cell("0.0625", Some("EH"));
+        assert_eq!(
+            format_value(&hours, VSD_FIELD_FORMAT_1PlDefUnits, &EN_US),
+            "1.5 eh."
+        );
+
+        let money = cell("-1234567.5", Some("CUR"));
+        assert_eq!(
+            format_value(&money, VSD_FIELD_FORMAT_NumGenDefUnits, &EN_US),
+            "-$1,234,567.50"
+        );
+        assert_eq!(
+            format_value(&money, VSD_FIELD_FORMAT_0PlDefUnits, &RU_RU),
+            "-1\u{A0}234\u{A0}568\u{A0}₽"
+        );
+        assert_eq!(
+            format_value(&money, VSD_FIELD_FORMAT_2PlNoUnits, &EN_US),
+            "-1234567.50"
+        );
+
+        let text = cell("Visio", Some("STR"));
+        assert_eq!(
+            format_value(&text, VSD_FIELD_FORMAT_StrUpper, &EN_US),
+            "VISIO"
+        );
+        assert_eq!(
+            format_value(&text, VSD_FIELD_FORMAT_DateShort, &EN_US),
+            "Visio"
+        );
+    }
+
+    #[test]
+    fn test_field_texts() {
+        let mut format = cell("esc(0.00 u)", None);
+        format.formula = Some("FIELDPICTURE(7)".to_string());
+        let shape = Shape {
+            sections: vec![
+                Section {
+                    name: "Character".to_string(),
+                    rows: vec![Row {
+                        cells: [("LangID".into(), cell("fr-FR", None))].into(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                Section {
+                    name: "Field".to_string(),
+                    rows: vec![
+                        Row {
+                            index: Some("0".to_string()),
+                            cells: [
+                                ("Value".into(), cell("2", Some("IN"))),
+                                ("Format".into(), format),
+                            ]
+                            .into(),
+                            ..Default::default()
+                        },
+                        Row {
+                            index: Some("1".to_string()),
+                            cells: [("Value".into(), cell("Page-1", Some("STR")))].into(),
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            field_texts(&shape),
+            vec![
+                (Some("0".to_string()), "2,00 in.".to_string()),
+                (Some("1".to_string()), "Page-1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Определение формата файла по содержимому.
+//!
+//! Расширение файла ничего не гарантирует: сигнатура OLE одна и та же у .vsd, .doc и .xls,
+//! а ZIP лежит внутри любого документа Office. Поэтому формат определяется по
+//! внутренней структуре: потоку `VisioDocument`, типу содержимого главной части пакета,
+//! корневому элементу XML или заголовку метафайла.
+
+use std::{
+    fmt, fs,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use xml::reader::{EventReader, XmlEvent};
+
+/// Сигнатура OLE Compound Document
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+/// Сигнатура локального заголовка ZIP
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+/// Сигнатура ` EMF` в заголовке EMR_HEADER
+const EMF_SIGNATURE: u32 = 0x464D_4520;
+/// Сигнатура размещаемого (placeable) WMF
+const WMF_PLACEABLE_MAGIC: u32 = 0x9AC6_CDD7;
+/// Пространство имён Visio 2003 XML (VDX, VSX, VTX)
+const VDX_NAMESPACE: &str = "http://schemas.microsoft.com/visio/2003/core";
+/// Сколько байт с начала файла хватает для сигнатур и корня XML
+const HEAD_LEN: u64 = 4096;
+
+/// Форматы файлов, которые умеет различать [`detect_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// Двоичный документ Visio (OLE с потоком `VisioDocument`): .vsd, а также .vss и .vst,
+    /// которые по содержимому не отличаются от документа
+    Vsd,
+    /// Пакет OPC: документ
+    Vsdx,
+    /// Пакет OPC: документ с макросами
+    Vsdm,
+    /// Пакет OPC: набор элементов
+    Vssx,
+    /// Пакет OPC: набор элементов с макросами
+    Vssm,
+    /// Пакет OPC: шаблон
+    Vstx,
+    /// Пакет OPC: шаблон с макросами
+    Vstm,
+    /// Visio 2003 XML: документ, набор элементов или шаблон
+    Vdx,
+    /// Enhanced Metafile
+    Emf,
+    /// Windows Metafile
+    Wmf,
+}
+
+impl FileFormat {
+    /// Привычное расширение файла
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Vsd => "vsd",
+            FileFormat::Vsdx => "vsdx",
+            FileFormat::Vsdm => "vsdm",
+            FileFormat::Vssx => "vssx",
+            FileFormat::Vssm => "vssm",
+            FileFormat::Vstx => "vstx",
+            FileFormat::Vstm => "vstm",
+            FileFormat::Vdx => "vdx",
+            FileFormat::Emf => "emf",
+            FileFormat::Wmf => "wmf",
+        }
+    }
+
+    /// Пакет OPC, который разбирается парсером VSDX
+    pub fn is_package(self) -> bool {
+        matches!(
+            self,
+            FileFormat::Vsdx
+                | FileFormat::Vsdm
+                | FileFormat::Vssx
+                | FileFormat::Vssm
+                | FileFormat::Vstx
+                | FileFormat::Vstm
+        )
+    }
+
+    pub fn is_macro_enabled(self) -> bool {
+        matches!(self, FileFormat::Vsdm | FileFormat::Vssm | FileFormat::Vstm)
+    }
+
+    pub fn is_stencil(self) -> bool {
+        matches!(self, FileFormat::Vssx | FileFormat::Vssm)
+    }
+
+    pub fn is_template(self) -> bool {
+        matches!(self, FileFormat::Vstx | FileFormat::Vstm)
+    }
+
+    /// Формат пакета по типу содержимого части `/visio/document.xml`
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        let format = match content_type {
+            "application/vnd.ms-visio.drawing.main+xml" => FileFormat::Vsdx,
+            "application/vnd.ms-visio.drawing.macroEnabled.main+xml" => FileFormat::Vsdm,
+            "application/vnd.ms-visio.stencil.main+xml" => FileFormat::Vssx,
+            "application/vnd.ms-visio.stencil.macroEnabled.main+xml" => FileFormat::Vssm,
+            "application/vnd.ms-visio.template.main+xml" => FileFormat::Vstx,
+            "application/vnd.ms-visio.template.macroEnabled.main+xml" => FileFormat::Vstm,
+            _ => return None,
+        };
+        Some(format)
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.extension().to_uppercase())
+    }
+}
+
+/// Определяет формат по содержимому файла; `None`, если формат не поддерживается
+pub fn detect_format(data: &[u8]) -> Option<FileFormat> {
+    detect_reader(Cursor::new(data)).ok().flatten()
+}
+
+/// Определяет формат файла, не читая его целиком
+pub fn detect_file_format(path: &Path) -> io::Result<Option<FileFormat>> {
+    detect_reader(BufReader::new(fs::File::open(path)?))
+}
+
+/// Определяет формат по источнику с произвольным доступом. Читаются только начало
+/// файла и служебные структуры: каталог OLE или центральный каталог ZIP и `[Content_Types].xml`
+pub fn detect_reader<R: Read + Seek>(mut reader: R) -> io::Result<Option<FileFormat>> {
+    let mut head = Vec::new();
+    reader.by_ref().take(HEAD_LEN).read_to_end(&mut head)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    if head.starts_with(OLE_MAGIC) {
+        return Ok(detect_ole(reader));
+    }
+    if head.starts_with(ZIP_MAGIC) {
+        return Ok(detect_package(reader));
+    }
+    if is_emf(&head) {
+        return Ok(Some(FileFormat::Emf));
+    }
+    if is_wmf(&head) {
+        return Ok(Some(FileFormat::Wmf));
+    }
+    if is_vdx(&head) {
+        return Ok(Some(FileFormat::Vdx));
+    }
+    Ok(None)
+}
+
+fn detect_ole<R: Read + Seek>(reader: R) -> Option<FileFormat> {
+    let cf = cfb::CompoundFile::open(reader).ok()?;
+    cf.is_stream("VisioDocument").then_some(FileFormat::Vsd)
+}
+
+fn detect_package<R: Read + Seek>(reader: R) -> Option<FileFormat> {
+    let mut archive = zip::ZipArchive::new(reader).ok()?;
+    archive.index_for_name("visio/document.xml")?;
+
+    let content_types = archive.by_name("[Content_Types].xml").ok()?;
+    document_content_type(content_types).and_then(|t| FileFormat::from_content_type(&t))
+}
+
+/// Тип содержимого из `<Override PartName="/visio/document.xml" ContentType="..."/>`;
+/// имена частей OPC сравниваются без учёта регистра
+fn document_content_type<R: Read>(content_types: R) -> Option<String> {
+    for event in EventReader::new(content_types) {
+        let XmlEvent::StartElement {
+            name, attributes, ..
+        } = event.ok()?
+        else {
+            continue;
+        };
+        if name.local_name != "Override" {
+            continue;
+        }
+        let attr = |key: &str| {
+            attributes
+                .iter()
+                .find(|a| a.name.local_name == key)
+                .map(|a| a.value.as_str())
+        };
+        if attr("PartName").is_some_and(|part| part.eq_ignore_ascii_case("/visio/document.xml")) {
+            return attr("ContentType").map(str::to_string);
+        }
+    }
+    None
+}
+
+fn is_emf(data: &[u8]) -> bool {
+    // Первая запись - EMR_HEADER (тип 1), сигнатура по смещению 40
+    data.len() >= 44 && le_u32(data, 0) == 1 && le_u32(data, 40) == EMF_SIGNATURE
+}
+
+fn is_wmf(data: &[u8]) -> bool {
+    if data.len() >= 4 && le_u32(data, 0) == WMF_PLACEABLE_MAGIC {
+        return true;
+    }
+    // Заголовок META_HEADER: тип 1 (память) или 2 (диск), размер 9 слов, версия 0x100 или 0x300
+    data.len() >= 18
+        && matches!(le_u16(data, 0), 1 | 2)
+        && le_u16(data, 2) == 9
+        && matches!(le_u16(data, 4), 0x0100 | 0x0300)
+}
+
+fn is_vdx(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(4096)];
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start();
+    text.starts_with('<') && text.contains("<VisioDocument") && text.contains(VDX_NAMESPACE)
+}
+
+fn le_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::Diagram;
+    use crate::parser_vsdx::write_vsdx;
+    use std::io::Write;
+
+    fn ole_with_stream(name: &str) -> Vec<u8> {
+        let mut cf = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        cf.create_stream(name).unwrap().write_all(b"data").unwrap();
+        cf.flush().unwrap();
+        cf.into_inner().into_inner()
+    }
+
+    #[test]
+    fn test_ole_needs_visio_stream() {
+        assert_eq!(
+            detect_format(&ole_with_stream("VisioDocument")),
+            Some(FileFormat::Vsd)
+        );
+        assert_eq!(detect_format(&ole_with_stream("WordDocument")), None);
+    }
+
+    #[test]
+    fn test_package() {
+        let bytes = write_vsdx::to_bytes(&Diagram::default()).unwrap();
+        assert_eq!(detect_format(&bytes), Some(FileFormat::Vsdx));
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(
+            "word/document.xml",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        let docx = zip.finish().unwrap().into_inner();
+        assert_eq!(detect_format(&docx), None);
+    }
+
+    #[test]
+    fn test_content_types() {
+        let xml = r#"<Types><Default Extension="xml" ContentType="application/xml"/><Override PartName="/visio/document.xml" ContentType="application/vnd.ms-visio.stencil.macroEnabled.main+xml"/></Types>"#;
+        assert_eq!(
+            document_content_type(xml.as_bytes()).and_then(|t| FileFormat::from_content_type(&t)),
+            Some(FileFormat::Vssm)
+        );
+
+        // Атрибуты в другом порядке, одинарные кавычки, другой регистр имени части
+        let xml = r#"<?xml version="1.0"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <!-- <Override PartName="/visio/document.xml" ContentType="bogus"/> -->
+  <Override ContentType='application/vnd.ms-visio.template.main+xml'
+            PartName='/Visio/Document.xml'/>
+</Types>"#;
+        assert_eq!(
+            document_content_type(xml.as_bytes()).and_then(|t| FileFormat::from_content_type(&t)),
+            Some(FileFormat::Vstx)
+        );
+        assert_eq!(document_content_type(&b"<Types><Override"[..]), None);
+    }
+
+    /// Считает прочитанные байты
+    struct Counting<R> {
+        inner: R,
+        read: u64,
+    }
+
+    impl<R: Read> Read for Counting<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n as u64;
+            Ok(n)
+        }
+    }
+
+    impl<R: Seek> Seek for Counting<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_package_detection_skips_part_data() {
+        let mut diagram = Diagram::default();
+        // Несжимаемые данные, чтобы размер архива определялся ими
+        let mut seed = 1u32;
+        let media: Vec<u8> = (0..1 << 20)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 24) as u8
+            })
+            .collect();
+        diagram
+            .media
+            .insert("visio/media/image1.bin".to_string(), media);
+        let bytes = write_vsdx::to_bytes(&diagram).unwrap();
+        assert!(bytes.len() > 1 << 20);
+
+        let mut reader = Counting {
+            inner: Cursor::new(&bytes),
+            read: 0,
+        };
+        assert_eq!(detect_reader(&mut reader).unwrap(), Some(FileFormat::Vsdx));
+        assert!(reader.read < 64 * 1024, "read {} bytes", reader.read);
+    }
+
+    #[test]
+    fn test_metafiles_and_xml() {
+        let mut emf = vec![0u8; 88];
+        emf[0] = 1;
+        emf[40..44].copy_from_slice(b" EMF");
+        assert_eq!(detect_format(&emf), Some(FileFormat::Emf));
+
+        let wmf = [0xD7, 0xCD, 0xC6, 0x9A, 0, 0];
+        assert_eq!(detect_format(&wmf), Some(FileFormat::Wmf));
+
+        let vdx = br#"<?xml version="1.0"?><VisioDocument xmlns="http://schemas.microsoft.com/visio/2003/core">"#;
+        assert_eq!(detect_format(vdx), Some(FileFormat::Vdx));
+        assert_eq!(detect_format(b"<html></html>"), None);
+    }
+}
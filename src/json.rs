@@ -0,0 +1,176 @@
+//! Детерминированный JSON и JSON Schema выходных данных.
+//!
+//! Словари моделей хранятся в `HashMap`, поэтому при сериализации ключи сортируются,
+//! и один и тот же файл всегда даёт один и тот же JSON. Схемы генерируются из типов
+//! Rust и версионируются через [`SCHEMA_VERSION`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::{JsonSchema, Schema, generate::SchemaSettings};
+use serde::{Serialize, Serializer};
+
+use crate::parser_emf::read_emf::TreeModel;
+use crate::parser_vsdx::read_vsdx::{Diagram, Element};
+
+/// Версия схемы; меняется при любом несовместимом изменении формата JSON
+pub const SCHEMA_VERSION: &str = "1.0.0";
+
+/// Сериализует словарь с ключами в порядке возрастания
+pub fn sorted_map<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// То же для словаря словарей: упорядочены ключи обоих уровней
+pub fn sorted_nested_map<S, K, K2, V>(
+    map: &HashMap<K, HashMap<K2, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    K2: Ord + Serialize,
+    V: Serialize,
+{
+    map.iter()
+        .map(|(k, inner)| (k, inner.iter().collect::<BTreeMap<_, _>>()))
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
+/// Элементы части пакета в порядке документа (по `inner_id`)
+pub fn ordered_elements(elements: &HashMap<i64, Element>) -> BTreeMap<i64, &Element> {
+    elements.iter().map(|(id, el)| (*id, el)).collect()
+}
+
+fn schema_for<T: JsonSchema>(name: &str) -> Schema {
+    let mut schema = SchemaSettings::draft2020_12()
+        .into_generator()
+        .into_root_schema_for::<T>();
+    schema.insert(
+        "$id".to_string(),
+        format!("urn:rusty_visio_parser:schema:{}:{}", name, SCHEMA_VERSION).into(),
+    );
+    schema.insert("version".to_string(), SCHEMA_VERSION.into());
+    schema
+}
+
+/// Схема `<file>_Diagram.json`
+pub fn diagram_schema() -> Schema {
+    schema_for::<Diagram>("diagram")
+}
+
+/// Схема файлов `jsons/<part>.json`: элементы части по `inner_id`
+pub fn elements_schema() -> Schema {
+    schema_for::<BTreeMap<i64, Element>>("elements")
+}
+
+/// Схема дерева записей EMF
+pub fn emf_schema() -> Schema {
+    schema_for::<TreeModel>("emf")
+}
+
+/// Все схемы по именам файлов, под которыми они лежат в каталоге `schema/`
+pub fn schemas() -> Vec<(String, Schema)> {
+    vec![
+        (
+            format!("diagram-{}.schema.json", SCHEMA_VERSION),
+            diagram_schema(),
+        ),
+        (
+            format!("elements-{}.schema.json", SCHEMA_VERSION),
+            elements_schema(),
+        ),
+        (format!("emf-{}.schema.json", SCHEMA_VERSION), emf_schema()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::{Cell, ForeignData, Page, Shape};
+    use std::path::Path;
+
+    #[test]
+    fn test_cells_are_sorted() {
+        let mut page = Page::default();
+        for name in ["Zeta", "Alpha", "PinX", "Beta"] {
+            page.cells.insert(name.into(), Cell::default());
+        }
+        let json = serde_json::to_string(&page).unwrap();
+        let alpha = json.find("Alpha").unwrap();
+        let beta = json.find("Beta").unwrap();
+        let pin = json.find("PinX").unwrap();
+        let zeta = json.find("Zeta").unwrap();
+        assert!(alpha < beta && beta < pin && pin < zeta);
+    }
+
+    #[test]
+    fn test_diagram_json_round_trip() {
+        let mut page = Page {
+            name: "Page-1".to_string(),
+            ..Default::default()
+        };
+        page.cells.insert("PageWidth".into(), Cell::default());
+        let diagram = Diagram {
+            pages: vec![page],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&diagram).unwrap();
+        let read: Diagram = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.pages, diagram.pages);
+    }
+
+    /// Поля, добавленные после выпуска схемы 1.0.0, необязательны: JSON без них читается
+    #[test]
+    fn test_reads_json_without_later_fields() {
+        let mut shape = Shape {
+            foreign: Some(ForeignData::default()),
+            ..Default::default()
+        };
+        shape.text = Some("A".to_string());
+        let diagram = Diagram {
+            pages: vec![Page {
+                shapes: vec![shape],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut json = serde_json::to_value(&diagram).unwrap();
+        for key in ["document_info", "comments", "fonts", "colors"] {
+            json.as_object_mut().unwrap().remove(key).unwrap();
+        }
+        let shape = &mut json["pages"][0]["shapes"][0];
+        shape.as_object_mut().unwrap().remove("text_runs").unwrap();
+        shape["foreign"]
+            .as_object_mut()
+            .unwrap()
+            .remove("show_as_icon")
+            .unwrap();
+
+        let read: Diagram = serde_json::from_value(json).unwrap();
+        assert_eq!(read.pages, diagram.pages);
+        assert!(read.comments.is_empty());
+    }
+
+    /// Схемы в `schema/` должны совпадать с типами; после изменения моделей
+    /// файлы обновляются запуском теста с `UPDATE_SCHEMA=1`
+    #[test]
+    fn test_committed_schemas_are_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+        for (name, schema) in schemas() {
+            let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+            let path = dir.join(&name);
+            if std::env::var_os("UPDATE_SCHEMA").is_some() {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, &generated).unwrap();
+            }
+            let committed = std::fs::read_to_string(&path).unwrap_or_default();
+            assert_eq!(committed, generated, "schema/{} is out of date", name);
+        }
+    }
+}
@@ -1,3 +1,11 @@
+pub mod cache;
+pub mod export;
+pub mod field_format;
+pub mod format;
+pub mod json;
+pub mod measurement;
 pub mod parser_emf;
+pub mod parser_vdx;
 pub mod parser_vsd;
-pub mod parser_vsdx;
\ No newline at end of file
+pub mod parser_vsdx;
+pub mod render;
@@ -1,40 +1,941 @@
-use rusty_visio_parser::parser_emf;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Cursor, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use thiserror::Error;
+
+use rusty_visio_parser::export::embedded::embedded_objects;
+use rusty_visio_parser::export::{all_shapes, graphml, html, hyperlinks};
+use rusty_visio_parser::format::{FileFormat, detect_format};
+use rusty_visio_parser::parser_emf::read_emf::{self, TreeModel};
+use rusty_visio_parser::parser_vdx;
 use rusty_visio_parser::parser_vsd;
-use rusty_visio_parser::parser_vsdx;
+use rusty_visio_parser::parser_vsdx::read_vsdx::{self, Diagram, DocumentInfo, Page, Shape};
+use rusty_visio_parser::render::{self, RenderError, RenderOptions};
 
-// pub mod utils;
+/// Коды завершения по классам ошибок
+const EXIT_DIFFERENT: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_IO: u8 = 3;
+const EXIT_PARSE: u8 = 4;
+const EXIT_RENDER: u8 = 5;
+const EXIT_UNSUPPORTED: u8 = 6;
 
-fn main() {
-    std::process::exit(job());
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Unable to parse {0}: {1}")]
+    Parse(PathBuf, String),
+    #[error("Unsupported file format: {0}")]
+    Unsupported(String),
+    #[error("Render error: {0}")]
+    Render(#[from] RenderError),
+    #[error("{0}")]
+    Usage(String),
 }
 
-fn job() -> i32 {
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() < 3 {
-        println!("need more args");
-        return 1;
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Io(_) => EXIT_IO,
+            CliError::Parse(..) => EXIT_PARSE,
+            CliError::Unsupported(_) => EXIT_UNSUPPORTED,
+            CliError::Render(_) => EXIT_RENDER,
+            CliError::Usage(_) => EXIT_USAGE,
+        }
     }
-    let fname = std::path::Path::new(&*args[1]);
-    let out_dir = std::path::Path::new(&*args[2]);
+}
 
-    let extention = (&*args[1]).to_lowercase();
+/// Разбор и преобразование файлов Visio (VSD, VSDX) и EMF
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Подробный вывод в stderr (-v, -vv, -vvv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Только ошибки
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    #[command(subcommand)]
+    command: Command,
+}
 
-    if (&extention).ends_with(String::from(".vsd").as_str()) {
-        parser_vsd::read_file(fname);
-        return 0;
+#[derive(Subcommand)]
+enum Command {
+    /// Краткие сведения о файле: формат, страницы, мастера, вложения
+    Info { file: PathBuf },
+    /// Полная модель в JSON
+    Dump {
+        file: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Экспорт страниц в SVG, PNG, JSON, GraphML или HTML
+    Export {
+        file: PathBuf,
+        #[arg(short, long, value_enum)]
+        format: ExportFormat,
+        #[command(flatten)]
+        pages: PageArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Разрешение PNG
+        #[arg(long, default_value_t = 96.0)]
+        dpi: f32,
+        /// Выводить мастера страницами вместо страниц документа (предпросмотр набора элементов)
+        #[arg(long)]
+        masters: bool,
+    },
+    /// Текст фигур
+    Text {
+        file: PathBuf,
+        #[command(flatten)]
+        pages: PageArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Комментарии рецензентов, по строке на комментарий: страница/фигура, автор, дата, текст
+    Comments {
+        file: PathBuf,
+        /// Показывать и решённые обсуждения
+        #[arg(long)]
+        all: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Извлекает вложенные изображения и другие медиафайлы
+    Media {
+        file: PathBuf,
+        /// Каталог для файлов
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Извлекает встроенные объекты OLE и изображения фигур, снимая обёртку OLE
+    Embedded {
+        file: PathBuf,
+        /// Каталог для файлов
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Дерево указателей и чанков двоичного VSD для исследования формата
+    Inspect {
+        file: PathBuf,
+        /// JSON вместо текстового дерева
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Проверяет ссылки модели: мастера, соединения, вложения, уникальность ID
+    Validate { file: PathBuf },
+    /// Сравнивает две диаграммы по страницам и фигурам
+    Diff { old: PathBuf, new: PathBuf },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    Svg,
+    Png,
+    Json,
+    Graphml,
+    /// Все выбранные страницы одним документом со ссылками
+    Html,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Svg => "svg",
+            ExportFormat::Png => "png",
+            ExportFormat::Json => "json",
+            ExportFormat::Graphml => "graphml",
+            ExportFormat::Html => "html",
+        }
     }
+}
+
+#[derive(Args)]
+struct PageArgs {
+    /// Страницы с единицы: `2`, `1,3-5`; по умолчанию все
+    #[arg(short, long, value_name = "PAGES")]
+    pages: Option<String>,
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Файл результата; для нескольких страниц `{page}` заменяется номером страницы
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Писать результат в stdout
+    #[arg(long, conflicts_with = "output")]
+    stdout: bool,
+}
+
+/// Разобранный входной файл
+enum Input {
+    Diagram(FileFormat, Box<Diagram>),
+    Emf(Vec<u8>),
+}
+
+/// Читает файл; в ошибке ввода-вывода указывается путь
+fn read_input(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
 
-    if extention.ends_with(String::from(".vsdx").as_str()) {
-        parser_vsdx::read_vsdx::read_file(fname, out_dir);
-        return 0;
+fn load(path: &Path) -> Result<Input, CliError> {
+    let data = read_input(path)?;
+    let parse_error = |e: &dyn std::fmt::Display| CliError::Parse(path.to_owned(), e.to_string());
+    match detect_format(&data) {
+        Some(format) if format.is_package() => read_vsdx::parse(Cursor::new(data))
+            .map(|d| Input::Diagram(format, Box::new(d)))
+            .map_err(|e| parse_error(&e)),
+        Some(FileFormat::Vsd) => parser_vsd::parse_data(&data)
+            .map(|d| Input::Diagram(FileFormat::Vsd, Box::new(d)))
+            .map_err(|e| parse_error(&e)),
+        Some(FileFormat::Vdx) => parser_vdx::parse_data(&data)
+            .map(|d| Input::Diagram(FileFormat::Vdx, Box::new(d)))
+            .map_err(|e| parse_error(&e)),
+        Some(FileFormat::Emf) => Ok(Input::Emf(data)),
+        Some(format) => Err(CliError::Unsupported(format.to_string())),
+        None => Err(CliError::Unsupported(path.display().to_string())),
     }
+}
+
+fn load_diagram(path: &Path) -> Result<Diagram, CliError> {
+    match load(path)? {
+        Input::Diagram(_, diagram) => Ok(*diagram),
+        Input::Emf(_) => Err(CliError::Unsupported(format!(
+            "{} has no pages",
+            path.display()
+        ))),
+    }
+}
+
+fn parse_emf(path: &Path, data: &[u8]) -> Result<TreeModel, CliError> {
+    read_emf::parse_data(data).map_err(|e| CliError::Parse(path.to_owned(), e.to_string()))
+}
+
+/// Номера страниц (с нуля) из выражения вида `1,3-5`
+fn select_pages(spec: Option<&str>, count: usize) -> Result<Vec<usize>, CliError> {
+    let Some(spec) = spec else {
+        return Ok((0..count).collect());
+    };
+    let bad = || CliError::Usage(format!("Invalid page selection '{}'", spec));
+    let mut pages = BTreeSet::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (a.trim(), b.trim()),
+            None => (part, part),
+        };
+        let start: usize = start.parse().map_err(|_| bad())?;
+        let end: usize = end.parse().map_err(|_| bad())?;
+        if start == 0 || end < start {
+            return Err(bad());
+        }
+        for page in start..=end {
+            if page > count {
+                return Err(CliError::Usage(format!(
+                    "Page {} is out of range (document has {})",
+                    page, count
+                )));
+            }
+            pages.insert(page - 1);
+        }
+    }
+    Ok(pages.into_iter().collect())
+}
+
+/// Путь результата для страницы, когда страниц несколько
+fn page_path(output: &Path, page: usize, multiple: bool) -> PathBuf {
+    let text = output.to_string_lossy();
+    if text.contains("{page}") {
+        return PathBuf::from(text.replace("{page}", &(page + 1).to_string()));
+    }
+    if !multiple {
+        return output.to_owned();
+    }
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match output.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, page + 1, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, page + 1),
+    };
+    output.with_file_name(name)
+}
 
-    if extention.ends_with(String::from(".emf").as_str()) {
-        parser_emf::read_emf::read_file(fname, out_dir);
-        return 0;
+fn write_output(output: &OutputArgs, path: Option<PathBuf>, data: &[u8]) -> Result<(), CliError> {
+    match path {
+        Some(path) if !output.stdout => {
+            fs::write(&path, data)?;
+            log::info!("Written {}", path.display());
+        }
+        _ => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(data)?;
+            stdout.flush()?;
+        }
     }
+    Ok(())
+}
 
-    println!("Unsupported file format");
+fn to_json<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, CliError> {
+    let mut json = serde_json::to_vec_pretty(value).map_err(io::Error::from)?;
+    json.push(b'\n');
+    Ok(json)
+}
 
-    1
+fn write_pages(out: &mut impl Write, diagram: &Diagram) -> io::Result<()> {
+    writeln!(out, "Pages: {}", diagram.pages.len())?;
+    for (i, page) in diagram.pages.iter().enumerate() {
+        writeln!(
+            out,
+            "  {}. {} ({} x {} in, {} shapes, {} connects{})",
+            i + 1,
+            page.name,
+            page.width,
+            page.height,
+            all_shapes(&page.shapes).len(),
+            page.connects.len(),
+            if page.background { ", background" } else { "" }
+        )?;
+    }
+    Ok(())
+}
+
+/// Мастера; для наборов элементов подробно, как основное содержимое
+fn write_masters(out: &mut impl Write, diagram: &Diagram, detailed: bool) -> io::Result<()> {
+    writeln!(out, "Masters: {}", diagram.masters.len())?;
+    for master in &diagram.masters {
+        if !detailed {
+            writeln!(out, "  {} {}", master.id, master.name)?;
+            continue;
+        }
+        writeln!(
+            out,
+            "  {} {} ({} shapes{})",
+            master.id,
+            master.name,
+            all_shapes(&master.shapes).len(),
+            if master.icon.is_some() { ", icon" } else { "" }
+        )?;
+        if let Some(prompt) = master.prompt.as_deref().filter(|p| !p.is_empty()) {
+            writeln!(out, "     {}", prompt)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_document_info(out: &mut impl Write, info: &DocumentInfo) -> io::Result<()> {
+    let fields = [
+        ("Title", &info.title),
+        ("Subject", &info.subject),
+        ("Author", &info.author),
+        ("Manager", &info.manager),
+        ("Company", &info.company),
+        ("Category", &info.category),
+        ("Keywords", &info.keywords),
+        ("Description", &info.description),
+        ("Created", &info.created),
+        ("Modified", &info.modified),
+        ("Last modified by", &info.last_modified_by),
+        ("Template", &info.template),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            writeln!(out, "{}: {}", label, value)?;
+        }
+    }
+    for (name, value) in &info.custom {
+        writeln!(out, "Property {}: {}", name, value)?;
+    }
+    Ok(())
+}
+
+fn info(file: &Path) -> Result<u8, CliError> {
+    let input = load(file)?;
+    let mut out = io::stdout().lock();
+    writeln!(out, "File: {}", file.display())?;
+    match input {
+        Input::Diagram(format, diagram) => {
+            writeln!(out, "Format: {}", format)?;
+            write_document_info(&mut out, &diagram.document_info)?;
+            // Двоичный набор элементов (.vss) отличается от документа только отсутствием страниц
+            if format.is_stencil() || (diagram.pages.is_empty() && !diagram.masters.is_empty()) {
+                write_masters(&mut out, &diagram, true)?;
+                write_pages(&mut out, &diagram)?;
+            } else {
+                write_pages(&mut out, &diagram)?;
+                write_masters(&mut out, &diagram, false)?;
+            }
+            writeln!(out, "Media: {}", diagram.media.len())?;
+            if let Some(vba) = &diagram.vba_project {
+                writeln!(
+                    out,
+                    "VBA project: {} ({} streams)",
+                    vba.part,
+                    vba.streams.len()
+                )?;
+                for stream in &vba.streams {
+                    writeln!(out, "  {} {} bytes", stream.path, stream.size)?;
+                }
+            }
+        }
+        Input::Emf(data) => {
+            writeln!(out, "Format: EMF")?;
+            writeln!(out, "Size: {} bytes", data.len())?;
+            writeln!(out, "Fields: {}", parse_emf(file, &data)?.len())?;
+        }
+    }
+    Ok(0)
+}
+
+fn dump(file: &Path, output: &OutputArgs) -> Result<u8, CliError> {
+    let json = match load(file)? {
+        Input::Diagram(_, diagram) => to_json(&diagram)?,
+        Input::Emf(data) => to_json(&parse_emf(file, &data)?)?,
+    };
+    write_output(output, output.output.clone(), &json)?;
+    Ok(0)
+}
+
+fn default_output(file: &Path, format: ExportFormat) -> PathBuf {
+    let stem = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "out".to_string());
+    PathBuf::from(format!("{}.{}", stem, format.extension()))
+}
+
+fn export(
+    file: &Path,
+    format: ExportFormat,
+    pages: &PageArgs,
+    output: &OutputArgs,
+    dpi: f32,
+    masters: bool,
+) -> Result<u8, CliError> {
+    let options = RenderOptions {
+        dpi,
+        ..Default::default()
+    };
+    // PNG в терминал не выводится, если об этом не попросили явно
+    let target = match (&output.output, format) {
+        (Some(path), _) => Some(path.clone()),
+        (None, ExportFormat::Png) if !output.stdout => Some(default_output(file, format)),
+        (None, _) => None,
+    };
+
+    let mut diagram = match load(file)? {
+        Input::Diagram(_, diagram) => diagram,
+        Input::Emf(data) => {
+            let bytes = match format {
+                ExportFormat::Svg => render::render_emf_svg(&data)?.into_bytes(),
+                ExportFormat::Png => render::render_emf(&data, &options)?,
+                ExportFormat::Json => to_json(&parse_emf(file, &data)?)?,
+                ExportFormat::Graphml => {
+                    return Err(CliError::Unsupported("GraphML export of EMF".to_string()));
+                }
+                ExportFormat::Html => {
+                    return Err(CliError::Unsupported("HTML export of EMF".to_string()));
+                }
+            };
+            write_output(output, target, &bytes)?;
+            return Ok(0);
+        }
+    };
+
+    if masters || diagram.pages.is_empty() {
+        log::info!("Exporting {} masters as pages", diagram.masters.len());
+        diagram.pages.clear();
+        parser_vsd::extract_stencils(&mut diagram);
+    }
+
+    let selected = select_pages(pages.pages.as_deref(), diagram.pages.len())?;
+    if format == ExportFormat::Json {
+        let mut subset = diagram.clone();
+        subset.pages = selected.iter().map(|&i| diagram.pages[i].clone()).collect();
+        write_output(output, target, &to_json(&subset)?)?;
+        return Ok(0);
+    }
+    if format == ExportFormat::Html {
+        let html = html::diagram_to_html(&diagram, &selected);
+        write_output(output, target, html.as_bytes())?;
+        return Ok(0);
+    }
+
+    let multiple = selected.len() > 1;
+    // Ссылка на выгружаемую страницу ведёт в её файл, на остальные - на якорь
+    let page_href = |index: usize| match &target {
+        Some(path) if selected.contains(&index) => page_path(path, index, multiple)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        _ => format!("#{}", hyperlinks::page_anchor(index)),
+    };
+    for &index in &selected {
+        let bytes = match format {
+            ExportFormat::Svg => {
+                render::render_page_svg_linked(&diagram, index, &page_href)?.into_bytes()
+            }
+            ExportFormat::Png => render::render_page(&diagram, index, &options)?,
+            ExportFormat::Graphml => graphml::page_to_graphml(&diagram.pages[index]).into_bytes(),
+            ExportFormat::Json | ExportFormat::Html => unreachable!(),
+        };
+        let path = target.as_deref().map(|p| page_path(p, index, multiple));
+        write_output(output, path, &bytes)?;
+    }
+    Ok(0)
+}
+
+fn push_text(out: &mut String, title: &str, shapes: &[Shape]) {
+    out.push_str(&format!("## {}\n", title));
+    for shape in all_shapes(shapes) {
+        if let Some(text) = shape
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        {
+            out.push_str(&format!("[{}] {}\n", shape.id, text));
+        }
+    }
+}
+
+fn text(file: &Path, pages: &PageArgs, output: &OutputArgs) -> Result<u8, CliError> {
+    let diagram = load_diagram(file)?;
+    let mut out = String::new();
+    // У набора элементов страниц обычно нет, текст берётся из мастеров
+    if diagram.pages.is_empty() {
+        for master in &diagram.masters {
+            push_text(&mut out, &master.name, &master.shapes);
+        }
+    }
+    for index in select_pages(pages.pages.as_deref(), diagram.pages.len())? {
+        let page = &diagram.pages[index];
+        push_text(&mut out, &page.name, &page.shapes);
+    }
+    write_output(output, output.output.clone(), out.as_bytes())?;
+    Ok(0)
+}
+
+fn comments(file: &Path, all: bool, output: &OutputArgs) -> Result<u8, CliError> {
+    let diagram = load_diagram(file)?;
+    let mut out = String::new();
+    for comment in diagram.comments.iter().filter(|c| all || !c.done) {
+        let page = comment.page.as_deref().map(|id| {
+            diagram
+                .pages
+                .iter()
+                .find(|p| p.id == id)
+                .map_or(id, |p| p.name.as_str())
+        });
+        let target = match (page, &comment.shape) {
+            (Some(page), Some(shape)) => format!("{}/{}", page, shape),
+            (Some(page), None) => page.to_string(),
+            (None, _) => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}{}\n",
+            target,
+            comment.author,
+            comment.date.as_deref().unwrap_or("-"),
+            comment.text.replace(['\r', '\n'], " "),
+            if comment.done { "\t(done)" } else { "" }
+        ));
+    }
+    write_output(output, output.output.clone(), out.as_bytes())?;
+    Ok(0)
+}
+
+/// Пишет объекты в каталог и выводит по строке на объект: путь, тип, ProgID и владельца
+fn embedded(file: &Path, out_dir: &Path) -> Result<u8, CliError> {
+    let diagram = load_diagram(file)?;
+    let objects = embedded_objects(&diagram);
+    if objects.is_empty() {
+        log::warn!("{} has no embedded objects", file.display());
+        return Ok(0);
+    }
+    fs::create_dir_all(out_dir)?;
+    for object in objects {
+        let path = out_dir.join(&object.file_name);
+        fs::write(&path, &object.data)?;
+        let owner = match (&object.page, &object.master) {
+            (Some(page), _) => format!("page {}", page),
+            (None, Some(master)) => format!("master {}", master),
+            (None, None) => String::new(),
+        };
+        println!(
+            "{}\t{}\t{}\t{} shape {}",
+            path.display(),
+            object.kind.as_str(),
+            object.prog_id.as_deref().unwrap_or("-"),
+            owner,
+            object.shape
+        );
+    }
+    Ok(0)
+}
+
+fn inspect(file: &Path, json: bool, output: &OutputArgs) -> Result<u8, CliError> {
+    let data = read_input(file)?;
+    match detect_format(&data) {
+        Some(FileFormat::Vsd) => {}
+        Some(format) => return Err(CliError::Unsupported(format.to_string())),
+        None => return Err(CliError::Unsupported(file.display().to_string())),
+    }
+    let inspection = parser_vsd::inspect::inspect_file_data(&data)
+        .map_err(|e| CliError::Parse(file.to_owned(), e.to_string()))?;
+    let text = if json {
+        to_json(&inspection)?
+    } else {
+        inspection.to_string().into_bytes()
+    };
+    write_output(output, output.output.clone(), &text)?;
+    Ok(0)
+}
+
+fn media(file: &Path, out_dir: &Path) -> Result<u8, CliError> {
+    let diagram = load_diagram(file)?;
+    if diagram.media.is_empty() {
+        log::warn!("{} has no embedded media", file.display());
+        return Ok(0);
+    }
+    fs::create_dir_all(out_dir)?;
+    let names: BTreeMap<&String, &Vec<u8>> = diagram.media.iter().collect();
+    for (name, data) in names {
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        let path = out_dir.join(file_name);
+        fs::write(&path, data)?;
+        println!("{}", path.display());
+    }
+    Ok(0)
+}
+
+/// Ошибки ссылок внутри модели
+fn validation_issues(diagram: &Diagram) -> Vec<String> {
+    let mut issues = Vec::new();
+    let masters: BTreeSet<&str> = diagram.masters.iter().map(|m| m.id.as_str()).collect();
+
+    for page in &diagram.pages {
+        let shapes = all_shapes(&page.shapes);
+        let mut ids = BTreeSet::new();
+        for shape in &shapes {
+            if !ids.insert(shape.id.as_str()) {
+                issues.push(format!("{}: duplicate shape ID {}", page.name, shape.id));
+            }
+            if let Some(master) = &shape.master
+                && !masters.contains(master.as_str())
+            {
+                issues.push(format!(
+                    "{}: shape {} refers to missing master {}",
+                    page.name, shape.id, master
+                ));
+            }
+            if let Some(foreign) = &shape.foreign {
+                for target in [&foreign.target, &foreign.object_target]
+                    .into_iter()
+                    .flatten()
+                    .filter(|target| !diagram.media.contains_key(*target))
+                {
+                    issues.push(format!(
+                        "{}: shape {} refers to missing media {}",
+                        page.name, shape.id, target
+                    ));
+                }
+            }
+        }
+        for connect in &page.connects {
+            for sheet in [&connect.from_sheet, &connect.to_sheet] {
+                if !ids.contains(sheet.as_str()) {
+                    issues.push(format!(
+                        "{}: connect refers to missing shape {}",
+                        page.name, sheet
+                    ));
+                }
+            }
+        }
+        if let Some(back) = &page.background_page
+            && !diagram.pages.iter().any(|p| &p.id == back)
+        {
+            issues.push(format!("{}: missing background page {}", page.name, back));
+        }
+    }
+    issues
+}
+
+fn validate(file: &Path) -> Result<u8, CliError> {
+    let issues = match load(file)? {
+        Input::Diagram(_, diagram) => validation_issues(&diagram),
+        Input::Emf(data) => {
+            parse_emf(file, &data)?;
+            vec![]
+        }
+    };
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    if issues.is_empty() {
+        println!("OK");
+        Ok(0)
+    } else {
+        Ok(EXIT_DIFFERENT)
+    }
+}
+
+fn shape_changes(page: &str, old: &Shape, new: &Shape, out: &mut Vec<String>) {
+    let names: BTreeSet<&str> = old
+        .cells
+        .keys()
+        .chain(new.cells.keys())
+        .map(|n| n.as_str())
+        .collect();
+    for name in names {
+        let before = old.cells.get(name).map(|c| c.value.as_str());
+        let after = new.cells.get(name).map(|c| c.value.as_str());
+        if before != after {
+            out.push(format!(
+                "~ {} shape {}: {} {} -> {}",
+                page,
+                new.id,
+                name,
+                before.unwrap_or("(none)"),
+                after.unwrap_or("(none)")
+            ));
+        }
+    }
+    if old.text != new.text {
+        out.push(format!(
+            "~ {} shape {}: text {:?} -> {:?}",
+            page,
+            new.id,
+            old.text.as_deref().unwrap_or(""),
+            new.text.as_deref().unwrap_or("")
+        ));
+    }
+    if old.sections != new.sections {
+        out.push(format!("~ {} shape {}: sections changed", page, new.id));
+    }
+}
+
+/// Построчный список отличий; страницы сопоставляются по имени, фигуры по ID
+fn diagram_diff(old: &Diagram, new: &Diagram) -> Vec<String> {
+    let mut out = Vec::new();
+    let find = |diagram: &'_ Diagram, name: &str| -> Option<Page> {
+        diagram.pages.iter().find(|p| p.name == name).cloned()
+    };
+    for page in &old.pages {
+        if find(new, &page.name).is_none() {
+            out.push(format!("- page {}", page.name));
+        }
+    }
+    for page in &new.pages {
+        let Some(old_page) = find(old, &page.name) else {
+            out.push(format!("+ page {}", page.name));
+            continue;
+        };
+        let old_shapes = all_shapes(&old_page.shapes);
+        let new_shapes = all_shapes(&page.shapes);
+        for shape in &old_shapes {
+            if !new_shapes.iter().any(|s| s.id == shape.id) {
+                out.push(format!("- {} shape {} {}", page.name, shape.id, shape.name));
+            }
+        }
+        for shape in &new_shapes {
+            match old_shapes.iter().find(|s| s.id == shape.id) {
+                Some(old_shape) => shape_changes(&page.name, old_shape, shape, &mut out),
+                None => out.push(format!("+ {} shape {} {}", page.name, shape.id, shape.name)),
+            }
+        }
+        if old_page.connects != page.connects {
+            out.push(format!("~ {}: connects changed", page.name));
+        }
+    }
+    out
+}
+
+fn diff(old: &Path, new: &Path) -> Result<u8, CliError> {
+    let changes = diagram_diff(&load_diagram(old)?, &load_diagram(new)?);
+    for line in &changes {
+        println!("{}", line);
+    }
+    Ok(if changes.is_empty() {
+        0
+    } else {
+        EXIT_DIFFERENT
+    })
+}
+
+/// Журнал в stderr с уровнем из флагов `-v`/`-q`
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{}: {}",
+                record.level().as_str().to_lowercase(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn init_logger(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, 2) => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+fn run(cli: &Cli) -> Result<u8, CliError> {
+    match &cli.command {
+        Command::Info { file } => info(file),
+        Command::Dump { file, output } => dump(file, output),
+        Command::Export {
+            file,
+            format,
+            pages,
+            output,
+            dpi,
+            masters,
+        } => export(file, *format, pages, output, *dpi, *masters),
+        Command::Text {
+            file,
+            pages,
+            output,
+        } => text(file, pages, output),
+        Command::Comments { file, all, output } => comments(file, *all, output),
+        Command::Media { file, output } => media(file, output),
+        Command::Embedded { file, output } => embedded(file, output),
+        Command::Inspect { file, json, output } => inspect(file, *json, output),
+        Command::Validate { file } => validate(file),
+        Command::Diff { old, new } => diff(old, new),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_logger(cli.verbose, cli.quiet);
+    match run(&cli) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_select_pages() {
+        assert_eq!(select_pages(None, 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(select_pages(Some("3,1-2"), 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(select_pages(Some("2"), 3).unwrap(), vec![1]);
+        assert!(matches!(
+            select_pages(Some("4"), 3),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            select_pages(Some("0"), 3),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            select_pages(Some("x"), 3),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_page_path() {
+        let out = Path::new("out/page.svg");
+        assert_eq!(page_path(out, 0, false), PathBuf::from("out/page.svg"));
+        assert_eq!(page_path(out, 1, true), PathBuf::from("out/page-2.svg"));
+        assert_eq!(
+            page_path(Path::new("p{page}.png"), 2, true),
+            PathBuf::from("p3.png")
+        );
+    }
+
+    #[test]
+    fn test_truncated_part_is_parse_error() {
+        let mut diagram = Diagram::default();
+        let mut page = Page {
+            name: "P".to_string(),
+            ..Default::default()
+        };
+        page.add_shape(Shape::default());
+        diagram.pages.push(page);
+        let package = rusty_visio_parser::parser_vsdx::write_vsdx::to_bytes(&diagram).unwrap();
+
+        // Тот же пакет, но страница обрезана посередине элемента
+        let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            if file.name() == "visio/pages/page1.xml" {
+                data.truncate(data.len() / 2);
+            }
+            zip.start_file(file.name(), zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(&data).unwrap();
+        }
+        let data = zip.finish().unwrap().into_inner();
+
+        let path = std::env::temp_dir().join(format!("truncated-{}.vsdx", std::process::id()));
+        fs::write(&path, data).unwrap();
+        let cli = Cli::parse_from(["rusty_visio_parser", "info", path.to_str().unwrap()]);
+        let result = run(&cli);
+        let _ = fs::remove_file(&path);
+        assert_eq!(result.unwrap_err().exit_code(), EXIT_PARSE);
+    }
+
+    #[test]
+    fn test_diagram_diff() {
+        let mut old = Diagram::default();
+        let mut page = Page {
+            name: "P".to_string(),
+            ..Default::default()
+        };
+        let mut shape = Shape::default();
+        shape.set_cell("PinX", 1);
+        page.add_shape(shape);
+        old.pages.push(page);
+
+        let mut new = old.clone();
+        assert!(diagram_diff(&old, &new).is_empty());
+
+        new.pages[0].shapes[0].set_cell("PinX", 2);
+        new.pages[0].add_shape(Shape::default());
+        let changes = diagram_diff(&old, &new);
+        assert!(changes.iter().any(|c| c.contains("PinX 1 -> 2")));
+        assert!(changes.iter().any(|c| c.starts_with("+ P shape 2")));
+    }
 }
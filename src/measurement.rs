@@ -0,0 +1,495 @@
+//! Величины с единицами измерения.
+//!
+//! Значение ячейки Visio хранит во внутренних единицах: длины - в дюймах, углы - в
+//! радианах, длительности - в днях. Единица ячейки (атрибут `U` в VSDX и VDX, байт типа
+//! перед значением в VSD) задаёт только то, в чём величину показывать. [`Measurement`]
+//! переводит значение между единицами одного рода и выводит его по числовым кодам
+//! `field_formats`, как Visio показывает значение в поле или в окне ShapeSheet.
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::field_format::Locale;
+use crate::parser_vsd::vsd_constants::{cell_types, field_formats::*};
+
+/// Миллиметров в дюйме
+const MM_PER_INCH: f64 = 25.4;
+/// Пункт Дидо в миллиметрах
+const DIDOT_MM: f64 = 0.376_065;
+
+/// Род величины: переводить можно только между единицами одного рода
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// Безразмерное число и проценты
+    Ratio,
+    Length,
+    Angle,
+    Duration,
+    Area,
+    /// Даты, деньги, строки, GUID и цвета: не переводятся
+    Other,
+}
+
+/// Единица измерения Visio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Безразмерное число, у ячейки нет `U`
+    Number,
+    Percent,
+    Inches,
+    InchFractions,
+    Feet,
+    FeetAndInches,
+    Yards,
+    Miles,
+    MileFractions,
+    NauticalMiles,
+    Millimeters,
+    Centimeters,
+    Meters,
+    Kilometers,
+    Points,
+    Picas,
+    PicasAndPoints,
+    Didots,
+    Ciceros,
+    CicerosAndDidots,
+    /// Единицы страницы и документа; без масштаба чертежа совпадают с дюймами
+    PageUnits,
+    DrawingUnits,
+    /// Единицы текста по умолчанию - пункты
+    TypeUnits,
+    Degrees,
+    DegreeMinuteSecond,
+    Radians,
+    AngleMinutes,
+    AngleSeconds,
+    /// Единицы угла по умолчанию - градусы
+    AngleUnits,
+    ElapsedWeeks,
+    ElapsedDays,
+    ElapsedHours,
+    ElapsedMinutes,
+    ElapsedSeconds,
+    /// Единицы длительности по умолчанию - дни
+    DurationUnits,
+    Acres,
+    Hectares,
+    Date,
+    Currency,
+    String,
+    Guid,
+    Color,
+}
+
+/// Обозначения единиц в атрибуте `U`
+const UNIT_CODES: &[(Unit, &str)] = &[
+    (Unit::Percent, "%"),
+    (Unit::Inches, "IN"),
+    (Unit::InchFractions, "IN_F"),
+    (Unit::Feet, "FT"),
+    (Unit::FeetAndInches, "F_I"),
+    (Unit::Yards, "YD"),
+    (Unit::Miles, "MI"),
+    (Unit::MileFractions, "MI_F"),
+    (Unit::NauticalMiles, "NM"),
+    (Unit::Millimeters, "MM"),
+    (Unit::Centimeters, "CM"),
+    (Unit::Meters, "M"),
+    (Unit::Kilometers, "KM"),
+    (Unit::Points, "PT"),
+    (Unit::Picas, "P"),
+    (Unit::PicasAndPoints, "P_PT"),
+    (Unit::Didots, "D"),
+    (Unit::Ciceros, "C"),
+    (Unit::CicerosAndDidots, "C_D"),
+    (Unit::PageUnits, "PU"),
+    (Unit::DrawingUnits, "DU"),
+    (Unit::TypeUnits, "TU"),
+    (Unit::Degrees, "DEG"),
+    (Unit::DegreeMinuteSecond, "D_M_S"),
+    (Unit::Radians, "RAD"),
+    (Unit::AngleMinutes, "MIN"),
+    (Unit::AngleSeconds, "SEC"),
+    (Unit::AngleUnits, "AU"),
+    (Unit::ElapsedWeeks, "EW"),
+    (Unit::ElapsedDays, "ED"),
+    (Unit::ElapsedHours, "EH"),
+    (Unit::ElapsedMinutes, "EM"),
+    (Unit::ElapsedSeconds, "ES"),
+    (Unit::DurationUnits, "DUR"),
+    (Unit::Acres, "AC"),
+    (Unit::Hectares, "HA"),
+    (Unit::Date, "DATE"),
+    (Unit::Currency, "CUR"),
+    (Unit::String, "STR"),
+    (Unit::Guid, "GUID"),
+    (Unit::Color, "COLOR"),
+];
+
+impl Unit {
+    /// Единица по атрибуту `U`; пустой атрибут - безразмерное число
+    pub fn from_code(code: &str) -> Option<Unit> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Some(Unit::Number);
+        }
+        UNIT_CODES
+            .iter()
+            .find(|(_, c)| c.eq_ignore_ascii_case(code))
+            .map(|(unit, _)| *unit)
+    }
+
+    /// Единица по байту типа значения VSD
+    pub fn from_cell_type(cell_type: u8) -> Unit {
+        cell_types::unit_name(cell_type)
+            .and_then(Unit::from_code)
+            .unwrap_or(Unit::Number)
+    }
+
+    /// Обозначение для атрибута `U`; у безразмерного числа его нет
+    pub fn code(self) -> Option<&'static str> {
+        UNIT_CODES
+            .iter()
+            .find(|(unit, _)| *unit == self)
+            .map(|(_, code)| *code)
+    }
+
+    /// Сокращение, которое Visio пишет после числа
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Number | Unit::Date | Unit::String | Unit::Guid | Unit::Color => "",
+            Unit::Percent => "%",
+            Unit::Inches | Unit::InchFractions | Unit::PageUnits | Unit::DrawingUnits => "in.",
+            Unit::Feet | Unit::FeetAndInches => "ft.",
+            Unit::Yards => "yd.",
+            Unit::Miles | Unit::MileFractions => "mi.",
+            Unit::NauticalMiles => "nm.",
+            Unit::Millimeters => "mm",
+            Unit::Centimeters => "cm",
+            Unit::Meters => "m",
+            Unit::Kilometers => "km",
+            Unit::Points | Unit::TypeUnits => "pt",
+            Unit::Picas | Unit::PicasAndPoints => "p",
+            Unit::Didots => "d",
+            Unit::Ciceros | Unit::CicerosAndDidots => "c",
+            Unit::Degrees | Unit::DegreeMinuteSecond | Unit::AngleUnits => "deg",
+            Unit::Radians => "rad",
+            Unit::AngleMinutes => "'",
+            Unit::AngleSeconds => "\"",
+            Unit::ElapsedWeeks => "ew.",
+            Unit::ElapsedDays | Unit::DurationUnits => "ed.",
+            Unit::ElapsedHours => "eh.",
+            Unit::ElapsedMinutes => "em.",
+            Unit::ElapsedSeconds => "es.",
+            Unit::Acres => "acres",
+            Unit::Hectares => "ha",
+            Unit::Currency => "¤",
+        }
+    }
+
+    pub fn dimension(self) -> Dimension {
+        self.base().0
+    }
+
+    /// Род величины и число внутренних единиц (дюймов, радиан, дней, кв. дюймов) в одной этой
+    fn base(self) -> (Dimension, f64) {
+        match self {
+            Unit::Number => (Dimension::Ratio, 1.0),
+            Unit::Percent => (Dimension::Ratio, 0.01),
+            Unit::Inches | Unit::InchFractions | Unit::PageUnits | Unit::DrawingUnits => {
+                (Dimension::Length, 1.0)
+            }
+            Unit::Feet | Unit::FeetAndInches => (Dimension::Length, 12.0),
+            Unit::Yards => (Dimension::Length, 36.0),
+            Unit::Miles | Unit::MileFractions => (Dimension::Length, 63_360.0),
+            Unit::NauticalMiles => (Dimension::Length, 1_852_000.0 / MM_PER_INCH),
+            Unit::Millimeters => (Dimension::Length, 1.0 / MM_PER_INCH),
+            Unit::Centimeters => (Dimension::Length, 10.0 / MM_PER_INCH),
+            Unit::Meters => (Dimension::Length, 1_000.0 / MM_PER_INCH),
+            Unit::Kilometers => (Dimension::Length, 1_000_000.0 / MM_PER_INCH),
+            Unit::Points | Unit::TypeUnits => (Dimension::Length, 1.0 / 72.0),
+            Unit::Picas | Unit::PicasAndPoints => (Dimension::Length, 1.0 / 6.0),
+            Unit::Didots => (Dimension::Length, DIDOT_MM / MM_PER_INCH),
+            Unit::Ciceros | Unit::CicerosAndDidots => {
+                (Dimension::Length, 12.0 * DIDOT_MM / MM_PER_INCH)
+            }
+            Unit::Degrees | Unit::DegreeMinuteSecond | Unit::AngleUnits => {
+                (Dimension::Angle, PI / 180.0)
+            }
+            Unit::Radians => (Dimension::Angle, 1.0),
+            Unit::AngleMinutes => (Dimension::Angle, PI / 10_800.0),
+            Unit::AngleSeconds => (Dimension::Angle, PI / 648_000.0),
+            Unit::ElapsedWeeks => (Dimension::Duration, 7.0),
+            Unit::ElapsedDays | Unit::DurationUnits => (Dimension::Duration, 1.0),
+            Unit::ElapsedHours => (Dimension::Duration, 1.0 / 24.0),
+            Unit::ElapsedMinutes => (Dimension::Duration, 1.0 / 1_440.0),
+            Unit::ElapsedSeconds => (Dimension::Duration, 1.0 / 86_400.0),
+            Unit::Acres => (Dimension::Area, 6_272_640.0),
+            Unit::Hectares => (Dimension::Area, 1.0e10 / (MM_PER_INCH * MM_PER_INCH)),
+            Unit::Date | Unit::Currency | Unit::String | Unit::Guid | Unit::Color => {
+                (Dimension::Other, 1.0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code().unwrap_or(""))
+    }
+}
+
+/// Величина `value`, выраженная в единицах `unit`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Measurement {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Величина из значения ячейки во внутренних единицах, показываемая в `unit`
+    pub fn from_internal(internal: f64, unit: Unit) -> Self {
+        Self::new(internal / unit.base().1, unit)
+    }
+
+    /// Значение во внутренних единицах, как его хранит ячейка
+    pub fn internal(&self) -> f64 {
+        self.value * self.unit.base().1
+    }
+
+    /// Та же величина в других единицах; `None`, если род единиц разный
+    pub fn convert(&self, unit: Unit) -> Option<Measurement> {
+        let (from, _) = self.unit.base();
+        let (to, _) = unit.base();
+        if from != to || from == Dimension::Other {
+            return (self.unit == unit).then_some(*self);
+        }
+        Some(Measurement::from_internal(self.internal(), unit))
+    }
+
+    /// Текст величины по коду формата поля. Нечисловые коды (даты, время, строки)
+    /// выводятся как `VSD_FIELD_FORMAT_NumGenDefUnits`
+    pub fn format(&self, format: u16) -> String {
+        self.format_in(format, &Locale::default())
+    }
+
+    /// То же, что [`Measurement::format`], с десятичным разделителем и валютой `locale`
+    #[allow(non_upper_case_globals)]
+    pub fn format_in(&self, format: u16, locale: &Locale) -> String {
+        let (places, units) = match format {
+            VSD_FIELD_FORMAT_NumGenNoUnits => (None, false),
+            VSD_FIELD_FORMAT_0PlNoUnits => (Some(0), false),
+            VSD_FIELD_FORMAT_1PlNoUnits => (Some(1), false),
+            VSD_FIELD_FORMAT_2PlNoUnits => (Some(2), false),
+            VSD_FIELD_FORMAT_3PlNoUnits => (Some(3), false),
+            VSD_FIELD_FORMAT_0PlDefUnits => (Some(0), true),
+            VSD_FIELD_FORMAT_1PlDefUnits => (Some(1), true),
+            VSD_FIELD_FORMAT_2PlDefUnits => (Some(2), true),
+            VSD_FIELD_FORMAT_3PlDefUnits => (Some(3), true),
+            VSD_FIELD_FORMAT_FeetAndInches => return self.feet_and_inches(0, locale),
+            VSD_FIELD_FORMAT_FeetAndInches1Pl => return self.feet_and_inches(1, locale),
+            VSD_FIELD_FORMAT_FeetAndInches2Pl => return self.feet_and_inches(2, locale),
+            VSD_FIELD_FORMAT_Radians => return self.angle(Unit::Radians, locale),
+            VSD_FIELD_FORMAT_Degrees => return self.angle(Unit::Degrees, locale),
+            VSD_FIELD_FORMAT_Fraction1PlNoUnits => return fraction(self.value, 10),
+            VSD_FIELD_FORMAT_Fraction2PlNoUnits => return fraction(self.value, 100),
+            VSD_FIELD_FORMAT_Fraction1PlDefUnits => {
+                return self.with_symbol(fraction(self.value, 10));
+            }
+            VSD_FIELD_FORMAT_Fraction2PlDefUnits => {
+                return self.with_symbol(fraction(self.value, 100));
+            }
+            _ => (None, true),
+        };
+
+        if units && self.unit == Unit::Currency {
+            return locale.currency(self.value, places.unwrap_or(2));
+        }
+        let number = match places {
+            Some(places) => fixed(self.value, places),
+            None => general_number(self.value),
+        };
+        let number = locale.number(&number);
+        if units {
+            self.with_symbol(number)
+        } else {
+            number
+        }
+    }
+
+    fn with_symbol(&self, number: String) -> String {
+        match self.unit.symbol() {
+            "" => number,
+            symbol @ ("%" | "'" | "\"") => format!("{number}{symbol}"),
+            symbol => format!("{number} {symbol}"),
+        }
+    }
+
+    /// Футы и дюймы; безразмерное число считается дюймами
+    fn feet_and_inches(&self, places: usize, locale: &Locale) -> String {
+        let inches = match self.unit.dimension() {
+            Dimension::Length => self.internal(),
+            _ => self.value,
+        };
+        let sign = if inches < 0.0 { "-" } else { "" };
+        let scale = 10f64.powi(places as i32);
+        let total = (inches.abs() * scale).round() / scale;
+        let feet = (total / 12.0).floor();
+        let rest = locale.number(&fixed(total - feet * 12.0, places));
+        format!("{sign}{feet} ft. {rest} in.")
+    }
+
+    fn angle(&self, unit: Unit, locale: &Locale) -> String {
+        let angle = match self.unit.dimension() {
+            Dimension::Angle => self.convert(unit).unwrap_or(*self),
+            _ => Measurement::new(self.value, unit),
+        };
+        angle.with_symbol(locale.number(&general_number(angle.value)))
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(VSD_FIELD_FORMAT_NumGenDefUnits))
+    }
+}
+
+/// Число без лишних нулей, не больше четырёх знаков после запятой
+fn general_number(value: f64) -> String {
+    let text = fixed(value, 4);
+    let text = if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &text
+    };
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+pub(crate) fn fixed(value: f64, places: usize) -> String {
+    let text = format!("{value:.places$}");
+    // -0.00 выглядит как ошибка
+    if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+        text[1..].to_string()
+    } else {
+        text
+    }
+}
+
+/// Смешанная дробь с наилучшим знаменателем не больше `max_denominator`
+fn fraction(value: f64, max_denominator: u64) -> String {
+    let sign = if value < 0.0 { "-" } else { "" };
+    let value = value.abs();
+    let mut whole = value.trunc() as u64;
+    let rest = value.fract();
+
+    let (mut numerator, mut denominator) = (0u64, 1u64);
+    let mut best = rest;
+    for d in 2..=max_denominator {
+        let n = (rest * d as f64).round() as u64;
+        let error = (rest - n as f64 / d as f64).abs();
+        if error + f64::EPSILON < best {
+            (numerator, denominator, best) = (n, d, error);
+        }
+    }
+    if numerator == denominator {
+        whole += 1;
+        numerator = 0;
+    }
+    match (whole, numerator) {
+        (_, 0) => format!("{sign}{whole}"),
+        (0, _) => format!("{sign}{numerator}/{denominator}"),
+        _ => format!("{sign}{whole} {numerator}/{denominator}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        assert_eq!(Unit::from_code("MM"), Some(Unit::Millimeters));
+        assert_eq!(Unit::from_code(""), Some(Unit::Number));
+        assert_eq!(Unit::from_code("XYZ"), None);
+        assert_eq!(
+            Unit::from_cell_type(cell_types::CELL_TYPE_Degrees),
+            Unit::Degrees
+        );
+        assert_eq!(
+            Unit::from_cell_type(cell_types::CELL_TYPE_Number),
+            Unit::Number
+        );
+        assert_eq!(Unit::Number.code(), None);
+        for (unit, code) in UNIT_CODES {
+            assert_eq!(Unit::from_code(code), Some(*unit));
+        }
+    }
+
+    #[test]
+    fn test_conversion() {
+        let inch = Measurement::new(1.0, Unit::Inches);
+        let mm = inch.convert(Unit::Millimeters).unwrap();
+        assert!((mm.value - 25.4).abs() < 1e-9);
+        assert!((inch.convert(Unit::Points).unwrap().value - 72.0).abs() < 1e-9);
+        assert!((Measurement::new(2.0, Unit::Feet).internal() - 24.0).abs() < 1e-9);
+        assert!(inch.convert(Unit::Degrees).is_none());
+
+        let angle = Measurement::from_internal(PI / 4.0, Unit::Degrees);
+        assert!((angle.value - 45.0).abs() < 1e-9);
+        let hours = Measurement::new(1.5, Unit::ElapsedDays).convert(Unit::ElapsedHours);
+        assert!((hours.unwrap().value - 36.0).abs() < 1e-9);
+        assert_eq!(
+            Measurement::new(0.25, Unit::Number).convert(Unit::Percent),
+            Some(Measurement::new(25.0, Unit::Percent))
+        );
+        assert!(
+            Measurement::new(1.0, Unit::Date)
+                .convert(Unit::Currency)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let m = Measurement::new(12.3456789, Unit::Millimeters);
+        assert_eq!(m.format(VSD_FIELD_FORMAT_NumGenNoUnits), "12.3457");
+        assert_eq!(m.format(VSD_FIELD_FORMAT_NumGenDefUnits), "12.3457 mm");
+        assert_eq!(m.format(VSD_FIELD_FORMAT_0PlNoUnits), "12");
+        assert_eq!(m.format(VSD_FIELD_FORMAT_2PlDefUnits), "12.35 mm");
+        assert_eq!(m.to_string(), "12.3457 mm");
+
+        let length = Measurement::new(18.5, Unit::Inches);
+        assert_eq!(length.format(VSD_FIELD_FORMAT_FeetAndInches), "1 ft. 7 in.");
+        assert_eq!(
+            length.format(VSD_FIELD_FORMAT_FeetAndInches1Pl),
+            "1 ft. 6.5 in."
+        );
+        assert_eq!(
+            length.format(VSD_FIELD_FORMAT_Fraction1PlDefUnits),
+            "18 1/2 in."
+        );
+        assert_eq!(
+            Measurement::new(-0.3333, Unit::Number).format(VSD_FIELD_FORMAT_Fraction1PlNoUnits),
+            "-1/3"
+        );
+
+        let angle = Measurement::from_internal(PI / 2.0, Unit::Degrees);
+        assert_eq!(angle.format(VSD_FIELD_FORMAT_Degrees), "90 deg");
+        assert_eq!(angle.format(VSD_FIELD_FORMAT_Radians), "1.5708 rad");
+        assert_eq!(
+            Measurement::new(50.0, Unit::Percent).format(VSD_FIELD_FORMAT_0PlDefUnits),
+            "50%"
+        );
+        assert_eq!(Measurement::new(-0.0001, Unit::Number).format(0), "-0.0001");
+        assert_eq!(Measurement::new(-0.00001, Unit::Number).format(0), "0");
+    }
+}
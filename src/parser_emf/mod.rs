@@ -1,8 +1,9 @@
 pub mod read_emf {
     use byteorder::{LittleEndian, ReadBytesExt};
-    use encoding_rs::{UTF_8, UTF_16LE};
+    use encoding_rs::UTF_16LE;
     use lazy_static::lazy_static;
-    use serde::Serialize;
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
     use serde_json::to_string_pretty;
     use std::collections::HashMap;
     use std::fs::{self, File};
@@ -10,42 +11,49 @@ pub mod read_emf {
     use std::path::Path;
     use thiserror::Error;
 
-    pub fn read_file(file_path: &Path, out_dir: &Path) -> bool {
+    /// Разбирает записи EMF в дерево полей
+    pub fn parse_data(buf: &[u8]) -> Result<TreeModel, EmfParseError> {
         let mut parser = EmfParser::new();
+        let mut cursor = Cursor::new(buf);
+
+        let mut records_count = 0;
+        while cursor.position() + 8 <= buf.len() as u64 {
+            let start_pos = cursor.position();
+            let record_type = cursor.read_u32::<LittleEndian>()?;
+            let record_size = cursor.read_u32::<LittleEndian>()? as u64;
+            if record_size < 8 || start_pos + record_size > buf.len() as u64 {
+                return Err(EmfParseError::BufferTooSmall);
+            }
+
+            let record = &buf[start_pos as usize..(start_pos + record_size) as usize];
+            if let Err(e) = parser.parse_record(record_type, record) {
+                log::debug!("EMF record at {}: {}", start_pos, e);
+            }
+
+            cursor.set_position(start_pos + record_size);
+            records_count += 1;
+        }
+        log::debug!("records_count {}", records_count);
+
+        Ok(parser.model)
+    }
+
+    pub fn read_file(file_path: &Path, out_dir: &Path) -> bool {
         match File::open(file_path) {
             Ok(file) => {
                 let mut reader: BufReader<File> = BufReader::new(file);
                 let mut buf = Vec::new();
                 let _ = reader.read_to_end(&mut buf);
 
-                let mut cursor = Cursor::new(&buf);
-
-                // let header = EmfHeader::parse(&mut cursor)?;
-
-                let mut records_count = 0;
-                while cursor.position() < buf.len() as u64 {
-                    let start_pos = cursor.position();
-                    let record_type = cursor
-                        .read_u32::<LittleEndian>()
-                        .map_err(|e| e.to_string())
-                        .unwrap();
-
-                    let record_size = cursor
-                        .read_u32::<LittleEndian>()
-                        .map_err(|e| e.to_string())
-                        .unwrap();
-
-                    // println!("record_type {} record_size {}", record_type, record_size);
-
-                    let _ = parser.parse_record(record_type, &buf);
-
-                    cursor.set_position(start_pos + record_size as u64);
-                    records_count += 1;
-                }
-
-                println!("records_count {} ", records_count);
+                let model = match parse_data(&buf) {
+                    Ok(model) => model,
+                    Err(e) => {
+                        println!("EMF parse error: {}", e);
+                        return false;
+                    }
+                };
 
-                let json_str = match to_string_pretty(&parser.model) {
+                let json_str = match to_string_pretty(&model) {
                     Ok(res) => res,
                     Err(_) => {
                         print!("Err json");
@@ -78,7 +86,7 @@ pub mod read_emf {
                     json_str,
                 )
                 .expect("Unable to write file");
-                return true;
+                true
             }
             Err(err) => {
                 eprintln!(
@@ -86,7 +94,7 @@ pub mod read_emf {
                     file_path.file_name().unwrap().to_owned(),
                     err
                 );
-                return false;
+                false
             }
         }
     }
@@ -113,6 +121,12 @@ pub mod read_emf {
         model: TreeModel,
     }
 
+    impl Default for EmfParser {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl EmfParser {
         pub fn new() -> Self {
             EmfParser {
@@ -880,7 +894,7 @@ pub mod read_emf {
                     .expect("Failed to read comment ID");
 
                 // Look up comment type description
-                let comment_desc = GC_IDS.get(&comment_id).map(|s| *s).unwrap_or("unknown");
+                let comment_desc = GC_IDS.get(&comment_id).copied().unwrap_or("unknown");
 
                 self.add_iter(
                     "PubComment ID",
@@ -1267,21 +1281,31 @@ pub mod read_emf {
         Cursor::new(data).read_u32::<LittleEndian>().unwrap()
     }
 
-    fn read_f32(data: &[u8]) -> f32 {
-        Cursor::new(data).read_f32::<LittleEndian>().unwrap()
-    }
-
     // Tree model implementation
-    #[derive(Serialize, Debug, Clone)]
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
     pub struct TreeModel {
         items: Vec<TreeItem>,
     }
 
+    impl Default for TreeModel {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
     impl TreeModel {
         pub fn new() -> Self {
             TreeModel { items: Vec::new() }
         }
 
+        pub fn len(&self) -> usize {
+            self.items.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.items.is_empty()
+        }
+
         pub fn add_item(
             &mut self,
             name: &str,
@@ -1300,7 +1324,7 @@ pub mod read_emf {
         }
     }
 
-    #[derive(Debug, Serialize, Clone)]
+    #[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
     struct TreeItem {
         name: String,
         value: String,
@@ -1310,6 +1334,8 @@ pub mod read_emf {
     }
 
     // Record type mapping
+    type RecordParser = fn(&mut EmfParser, usize, &[u8]);
+
     lazy_static! {
             static ref GC_IDS: HashMap<u32, &'static str> = {
         let mut m = HashMap::new();
@@ -1322,14 +1348,14 @@ pub mod read_emf {
         m
     };
 
-    static ref GCFUNC_IDS: HashMap<u32, fn(&mut EmfParser,size: usize, &[u8])> = {
-        let mut m: HashMap<u32, fn(&mut EmfParser,size: usize, &[u8])> = HashMap::new();
+    static ref GCFUNC_IDS: HashMap<u32, RecordParser> = {
+        let mut m: HashMap<u32, RecordParser> = HashMap::new();
         m.insert(2, EmfParser::gc_begin_group);
         m.insert(3, EmfParser::gc_end_group);
         m
     };
-        static ref EMR_IDS: HashMap<u32, fn(&mut EmfParser,size: usize, &[u8])> = {
-            let mut m: HashMap<u32, fn(&mut EmfParser,size: usize, &[u8])> = HashMap::new();
+        static ref EMR_IDS: HashMap<u32, RecordParser> = {
+            let mut m: HashMap<u32, RecordParser> = HashMap::new();
                 // Basic records
         m.insert(0x01, EmfParser::parse_header);
         m.insert(0x02, EmfParser::polybezier);
@@ -0,0 +1,525 @@
+//! Разбор Visio 2003 XML (.vdx, .vsx, .vtx) в общую модель `Diagram`.
+//!
+//! Это один XML-файл с корнем `VisioDocument`. В отличие от VSDX ячейки записаны не как
+//! `<Cell N="PinX" V="1"/>`, а дочерними элементами секций: `<XForm><PinX>1</PinX></XForm>`.
+//! Одиночные секции (XForm, Line, Fill…) раскладываются в ячейки фигуры, как в VSDX,
+//! а многострочные (Char, Prop, Geom…) становятся секциями со строками.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use thiserror::Error;
+
+use crate::field_format::field_texts;
+use crate::parser_vsdx::comments::annotation_comments;
+use crate::parser_vsdx::get_metadata::{self, Element};
+use crate::parser_vsdx::read_vsdx::{
+    Cell, CellName, Diagram, DocumentInfo, ForeignData, Master, Page, Row, Section, Shape,
+};
+use crate::parser_vsdx::shapes::{
+    MASTER_ATTRS, PAGE_ATTRS, characters_by_parent, collect_text, connect_from_attrs,
+    element_local_name, element_name, foreign_from_attrs, master_icon, other_attrs,
+    shape_from_attrs, sorted_by_name,
+};
+
+#[derive(Debug, Error)]
+pub enum VdxError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid VDX document: {0}")]
+    Format(String),
+}
+
+/// Элементы VDX, каждый из которых - строка многострочной секции, и имена этих секций в VSDX
+const ROW_SECTIONS: [(&str, &str); 15] = [
+    ("Char", "Character"),
+    ("Para", "Paragraph"),
+    ("Prop", "Property"),
+    ("User", "User"),
+    ("Connection", "Connection"),
+    ("Control", "Control"),
+    ("Scratch", "Scratch"),
+    ("Hyperlink", "Hyperlink"),
+    ("Act", "Actions"),
+    ("Layer", "Layer"),
+    ("Field", "Field"),
+    ("SmartTagDef", "SmartTag"),
+    ("ActionTag", "ActionTag"),
+    ("Reviewer", "Reviewer"),
+    ("Annotation", "Annotation"),
+];
+
+/// Дочерние элементы фигуры, которые не являются секциями ShapeSheet
+const NON_SECTIONS: [&str; 6] = ["Shapes", "Text", "ForeignData", "Data1", "Data2", "Data3"];
+
+pub fn parse_file(file_path: &Path) -> Result<Diagram, VdxError> {
+    parse(fs::File::open(file_path)?)
+}
+
+/// Разбирает VDX, уже прочитанный в память
+pub fn parse_data(data: &[u8]) -> Result<Diagram, VdxError> {
+    parse(Cursor::new(data))
+}
+
+pub fn parse<R: Read>(reader: R) -> Result<Diagram, VdxError> {
+    CellName::scoped(|| parse_document(reader))
+}
+
+fn parse_document<R: Read>(reader: R) -> Result<Diagram, VdxError> {
+    let mut scratch = Diagram::default();
+    let elements = get_metadata::encoding(reader, "document.vdx", &mut scratch)
+        .map_err(|e| VdxError::Format(e.to_string()))?;
+    let root = sorted_by_name(&elements, "VisioDocument")
+        .into_iter()
+        .find(|el| el.parent == 0)
+        .ok_or_else(|| VdxError::Format("no VisioDocument root element".to_string()))?;
+
+    let mut doc = VdxDocument {
+        elements: &elements,
+        chars: characters_by_parent(&elements),
+        media: HashMap::new(),
+    };
+    let mut diagram = Diagram::default();
+    let mut reviewers = Vec::new();
+
+    for child in doc.children(root) {
+        match child.name.as_str() {
+            "StyleSheets" => {
+                diagram.style_sheets = doc
+                    .children_named(child, "StyleSheet")
+                    .map(|el| doc.shape(el))
+                    .collect();
+            }
+            "Masters" => {
+                diagram.masters = doc
+                    .children_named(child, "Master")
+                    .map(|el| doc.master(el))
+                    .collect();
+            }
+            "DocumentProperties" => diagram.document_info = doc.document_info(child),
+            "DocumentSheet" => {
+                let sheet = doc.shape(child);
+                reviewers.extend(
+                    sheet
+                        .sections
+                        .into_iter()
+                        .filter(|s| s.name == "Reviewer")
+                        .flat_map(|s| s.rows),
+                );
+            }
+            "Pages" => {
+                diagram.pages = doc
+                    .children_named(child, "Page")
+                    .map(|el| doc.page(el))
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    diagram.comments = annotation_comments(&reviewers, &diagram.pages);
+    diagram.media = doc.media;
+    Ok(diagram)
+}
+
+struct VdxDocument<'a> {
+    elements: &'a HashMap<i64, Element>,
+    chars: HashMap<i64, Vec<i64>>,
+    /// Данные `ForeignData`, которые в VDX хранятся прямо в документе
+    media: HashMap<String, Vec<u8>>,
+}
+
+impl<'a> VdxDocument<'a> {
+    fn children(&self, el: &Element) -> impl Iterator<Item = &'a Element> + use<'a> {
+        let elements = self.elements;
+        el.children
+            .clone()
+            .into_iter()
+            .filter_map(|id| elements.get(&id))
+    }
+
+    fn children_named(
+        &self,
+        el: &Element,
+        name: &'static str,
+    ) -> impl Iterator<Item = &'a Element> + use<'a> {
+        self.children(el).filter(move |c| c.name == name)
+    }
+
+    fn text(&self, el: &Element) -> String {
+        self.chars
+            .get(&el.inner_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.elements.get(id)?.attrs.get("Text"))
+            .map(|s| s.as_str())
+            .collect()
+    }
+
+    fn is_leaf(&self, el: &Element) -> bool {
+        el.children.is_empty()
+    }
+
+    /// Ячейка из элемента вида `<PinX Unit="MM" F="Width*0.5">1.5</PinX>`
+    fn cell(&self, el: &Element) -> Cell {
+        Cell {
+            value: self.text(el).trim().to_string(),
+            unit: el.attrs.get("Unit").cloned(),
+            formula: el.attrs.get("F").cloned(),
+        }
+    }
+
+    fn leaf_cells(&self, el: &Element) -> HashMap<CellName, Cell> {
+        self.children(el)
+            .filter(|c| self.is_leaf(c))
+            .map(|c| (CellName::from(&c.name), self.cell(c)))
+            .collect()
+    }
+
+    /// Свойства из `<DocumentProperties>`; даты записаны как `2024-03-01T12:30:00`
+    fn document_info(&self, el: &Element) -> DocumentInfo {
+        let mut info = DocumentInfo::default();
+        for child in self.children(el) {
+            let field = match child.name.as_str() {
+                "Title" => &mut info.title,
+                "Subject" => &mut info.subject,
+                "Creator" => &mut info.author,
+                "Manager" => &mut info.manager,
+                "Company" => &mut info.company,
+                "Category" => &mut info.category,
+                "Keywords" => &mut info.keywords,
+                "Desc" => &mut info.description,
+                "Template" => &mut info.template,
+                "TimeCreated" => &mut info.created,
+                "TimeSaved" => &mut info.modified,
+                "CustomProps" => {
+                    for prop in self.children_named(child, "CustomProp") {
+                        if let Some(name) = prop.attrs.get("Name") {
+                            info.custom
+                                .insert(name.clone(), self.text(prop).trim().to_string());
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let value = self.text(child).trim().to_string();
+            *field = (!value.is_empty()).then_some(value);
+        }
+        info
+    }
+
+    fn row(&self, el: &Element) -> Row {
+        Row {
+            index: el.attrs.get("IX").cloned(),
+            name: el.attrs.get("NameU").or(el.attrs.get("Name")).cloned(),
+            row_type: None,
+            deleted: el.attrs.get("Del").is_some_and(|d| d == "1"),
+            cells: self.leaf_cells(el),
+        }
+    }
+
+    /// Ячейки и секции листа: фигуры, листа стилей или `PageSheet`
+    fn sheet(
+        &self,
+        el: &Element,
+        cells: &mut HashMap<CellName, Cell>,
+        sections: &mut Vec<Section>,
+    ) {
+        for child in self.children(el) {
+            if NON_SECTIONS.contains(&child.name.as_str()) || self.is_leaf(child) {
+                continue;
+            }
+            if let Some((_, name)) = ROW_SECTIONS.iter().find(|(tag, _)| *tag == child.name) {
+                let position = match sections.iter().position(|s| s.name == *name) {
+                    Some(position) => position,
+                    None => {
+                        sections.push(Section {
+                            name: name.to_string(),
+                            ..Default::default()
+                        });
+                        sections.len() - 1
+                    }
+                };
+                sections[position].rows.push(self.row(child));
+            } else if self.children(child).any(|c| !self.is_leaf(c)) {
+                // Секция со строками разных типов, например `Geom` с `MoveTo`/`LineTo`
+                let name = match child.name.as_str() {
+                    "Geom" => "Geometry",
+                    other => other,
+                };
+                sections.push(Section {
+                    name: name.to_string(),
+                    index: child.attrs.get("IX").cloned(),
+                    cells: self.leaf_cells(child),
+                    rows: self
+                        .children(child)
+                        .filter(|c| !self.is_leaf(c))
+                        .map(|c| Row {
+                            row_type: Some(c.name.clone()),
+                            ..self.row(c)
+                        })
+                        .collect(),
+                });
+            } else {
+                cells.extend(self.leaf_cells(child));
+            }
+        }
+    }
+
+    fn shape(&mut self, el: &Element) -> Shape {
+        let mut shape = shape_from_attrs(&el.attrs);
+        self.sheet(el, &mut shape.cells, &mut shape.sections);
+
+        for child in self.children(el) {
+            match child.name.as_str() {
+                "Text" => {
+                    let fields = field_texts(&shape);
+                    shape.text = Some(collect_text(self.elements, &self.chars, child, &fields));
+                }
+                "ForeignData" => shape.foreign = Some(self.foreign(child)),
+                "Shapes" => {
+                    for sub in self.children_named(child, "Shape") {
+                        let sub = self.shape(sub);
+                        shape.shapes.push(sub);
+                    }
+                }
+                _ => {}
+            }
+        }
+        shape
+    }
+
+    /// Встроенные данные переносятся в `media` под именем, как в пакете VSDX
+    fn foreign(&mut self, el: &Element) -> ForeignData {
+        let foreign_type = el.attrs.get("ForeignType").cloned().unwrap_or_default();
+        let compression = el.attrs.get("CompressionType").cloned();
+        let extension = match (foreign_type.as_str(), compression.as_deref()) {
+            ("EnhMetaFile", _) => "emf",
+            ("MetaFile", _) => "wmf",
+            (_, Some("PNG")) => "png",
+            (_, Some("JPEG")) => "jpeg",
+            (_, Some("GIF")) => "gif",
+            (_, Some("TIFF")) => "tiff",
+            (_, Some("BMP")) | ("Bitmap", _) => "bmp",
+            _ => "bin",
+        };
+
+        let target = match decode_base64(&self.text(el)) {
+            Some(data) if !data.is_empty() => {
+                let name = format!("visio/media/image{}.{}", self.media.len() + 1, extension);
+                self.media.insert(name.clone(), data);
+                Some(name)
+            }
+            _ => {
+                log::warn!("ForeignData {} has no valid base64 data", el.inner_id);
+                None
+            }
+        };
+        ForeignData {
+            target,
+            ..foreign_from_attrs(&el.attrs)
+        }
+    }
+
+    fn master(&mut self, el: &Element) -> Master {
+        let mut master = Master {
+            id: el.attrs.get("ID").cloned().unwrap_or_default(),
+            name: element_name(&el.attrs),
+            prompt: el.attrs.get("Prompt").cloned(),
+            icon: master_icon(self.elements, &self.chars, el),
+            attrs: other_attrs(&el.attrs, &MASTER_ATTRS),
+            ..Default::default()
+        };
+        for shapes in self.children_named(el, "Shapes") {
+            for shape in self.children_named(shapes, "Shape") {
+                let shape = self.shape(shape);
+                master.shapes.push(shape);
+            }
+        }
+        master
+    }
+
+    fn page(&mut self, el: &Element) -> Page {
+        let mut page = Page {
+            id: el.attrs.get("ID").cloned().unwrap_or_default(),
+            name: element_name(&el.attrs),
+            local_name: element_local_name(&el.attrs),
+            background: el.attrs.get("Background").is_some_and(|v| v == "1"),
+            background_page: el.attrs.get("BackPage").cloned(),
+            attrs: other_attrs(&el.attrs, &PAGE_ATTRS),
+            ..Default::default()
+        };
+
+        for child in self.children(el) {
+            match child.name.as_str() {
+                "PageSheet" => {
+                    let (mut cells, mut sections) = (HashMap::new(), Vec::new());
+                    self.sheet(child, &mut cells, &mut sections);
+                    page.cells = cells;
+                    page.sections = sections;
+                }
+                "Shapes" => {
+                    for shape in self.children_named(child, "Shape") {
+                        let shape = self.shape(shape);
+                        page.shapes.push(shape);
+                    }
+                }
+                "Connects" => {
+                    page.connects = self
+                        .children_named(child, "Connect")
+                        .map(|c| connect_from_attrs(&c.attrs))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        page.width = page.number("PageWidth").unwrap_or(8.5);
+        page.height = page.number("PageHeight").unwrap_or(11.0);
+        page
+    }
+}
+
+/// Декодирует base64 из `ForeignData` и `Icon`; переводы строк внутри данных пропускаются
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ if byte.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::svg::base64;
+
+    fn sample() -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<VisioDocument xmlns="http://schemas.microsoft.com/visio/2003/core">
+  <DocumentProperties>
+    <Title>Network</Title><Creator>Ivan</Creator><TimeCreated>2024-03-01T12:30:00</TimeCreated>
+    <CustomProps><CustomProp Name="Project" PropType="String">Alpha</CustomProp></CustomProps>
+  </DocumentProperties>
+  <DocumentSheet><Reviewer IX="0"><Name>Anna Petrova</Name><Initials>AP</Initials><ReviewerID>1</ReviewerID></Reviewer></DocumentSheet>
+  <StyleSheets>
+    <StyleSheet ID="0" NameU="No Style"><Line><LineWeight>0.01</LineWeight></Line></StyleSheet>
+  </StyleSheets>
+  <Masters>
+    <Master ID="2" NameU="Box" Prompt="Drag me" IconSize="1">
+      <Shapes><Shape ID="5" Type="Shape"><XForm><Width>1</Width></XForm></Shape></Shapes>
+      <Icon>
+        iVBO
+        Rw==
+      </Icon>
+    </Master>
+  </Masters>
+  <Pages>
+    <Page ID="0" NameU="Page-1">
+      <PageSheet><PageProps><PageWidth Unit="MM">8.2677</PageWidth><PageHeight>11</PageHeight></PageProps>
+        <Annotation IX="0"><X>1</X><Y>2</Y><ReviewerID>1</ReviewerID><MarkerIndex>1</MarkerIndex><Date Unit="DATE">2024-03-05T10:00:00</Date><Comment>Check size</Comment></Annotation>
+      </PageSheet>
+      <Shapes>
+        <Shape ID="1" NameU="Box.1" Type="Shape" Master="2">
+          <XForm><PinX F="Width*0.5">1.5</PinX><PinY>2</PinY></XForm>
+          <Char IX="0"><Size>0.1667</Size></Char>
+          <Geom IX="0"><NoFill>0</NoFill><MoveTo IX="1"><X>0</X><Y>0</Y></MoveTo><LineTo IX="2" Del="1"><X>1</X><Y>0</Y></LineTo></Geom>
+          <Prop NameU="Cost" ID="1"><Value Unit="STR">42</Value><Label>Cost</Label></Prop>
+          <Text><cp IX="0"/>Hello <fld IX="0">world</fld></Text>
+        </Shape>
+        <Shape ID="3" Type="Foreign">
+          <ForeignData ForeignType="Bitmap" CompressionType="PNG">{}</ForeignData>
+        </Shape>
+      </Shapes>
+      <Connects><Connect FromSheet="3" FromCell="BeginX" ToSheet="1" ToCell="PinX"/></Connects>
+    </Page>
+  </Pages>
+</VisioDocument>"#,
+            base64(b"\x89PNG data")
+        )
+    }
+
+    #[test]
+    fn test_parse_vdx() {
+        let diagram = parse_data(sample().as_bytes()).unwrap();
+        let info = &diagram.document_info;
+        assert_eq!(info.title.as_deref(), Some("Network"));
+        assert_eq!(info.author.as_deref(), Some("Ivan"));
+        assert_eq!(info.created.as_deref(), Some("2024-03-01T12:30:00"));
+        assert_eq!(info.custom["Project"], "Alpha");
+        assert_eq!(diagram.style_sheets[0].number("LineWeight"), Some(0.01));
+
+        let master = &diagram.masters[0];
+        assert_eq!(master.name, "Box");
+        assert_eq!(master.prompt.as_deref(), Some("Drag me"));
+        assert_eq!(master.icon.as_deref(), Some("iVBORw=="));
+        assert_eq!(master.attrs.get("IconSize").map(String::as_str), Some("1"));
+        assert_eq!(master.shapes[0].number("Width"), Some(1.0));
+
+        let page = &diagram.pages[0];
+        assert_eq!(page.name, "Page-1");
+        assert_eq!((page.width, page.height), (8.2677, 11.0));
+        assert_eq!(page.cells["PageWidth"].unit.as_deref(), Some("MM"));
+        let width = page.cells["PageWidth"].measurement().unwrap();
+        assert_eq!(width.format(3), "210 mm");
+
+        let shape = &page.shapes[0];
+        assert_eq!(shape.master.as_deref(), Some("2"));
+        assert_eq!(shape.number("PinX"), Some(1.5));
+        assert_eq!(shape.cells["PinX"].formula.as_deref(), Some("Width*0.5"));
+        assert_eq!(shape.text.as_deref(), Some("Hello world"));
+
+        let chars = shape.sections_named("Character");
+        assert_eq!(chars[0].rows[0].number("Size"), Some(0.1667));
+        let geometry = shape.sections_named("Geometry");
+        assert_eq!(geometry[0].number("NoFill"), Some(0.0));
+        assert_eq!(geometry[0].rows[0].row_type.as_deref(), Some("MoveTo"));
+        assert!(geometry[0].rows[1].deleted);
+        let props = shape.sections_named("Property");
+        assert_eq!(props[0].rows[0].name.as_deref(), Some("Cost"));
+        assert_eq!(props[0].rows[0].cells["Value"].value, "42");
+
+        let foreign = page.shapes[1].foreign.as_ref().unwrap();
+        let target = foreign.target.as_ref().unwrap();
+        assert_eq!(target, "visio/media/image1.png");
+        assert_eq!(diagram.media[target], b"\x89PNG data");
+
+        assert_eq!(page.connects[0].from_cell.as_deref(), Some("BeginX"));
+
+        let comment = &diagram.comments[0];
+        assert_eq!(comment.author, "Anna Petrova");
+        assert_eq!(comment.text, "Check size");
+        assert_eq!(comment.date.as_deref(), Some("2024-03-05T10:00:00"));
+        assert_eq!(comment.page.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn test_not_vdx() {
+        assert!(matches!(
+            parse_data(b"<html></html>"),
+            Err(VdxError::Format(_))
+        ));
+    }
+}
@@ -0,0 +1,338 @@
+//! Структура потока `VisioDocument` для разбора незнакомых файлов.
+//!
+//! Инспектор проходит те же списки указателей, что и [`VSDParser`](super::vsdparser::VSDParser),
+//! но ничего не интерпретирует: для каждого потока записываются тип, формат, смещение,
+//! длина, заголовки чанков и начало данных. Результат выводится в JSON или деревом.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write as _};
+use std::io::{Cursor, Seek, SeekFrom};
+
+use serde::Serialize;
+
+use super::vsd_constants::object_types::{self, VSD_COLORS, VSD_TRAILER_STREAM};
+use super::vsdinternal_stream::VSDInternalStream;
+use super::vsdparser::{Pointer, get_chunk_header, read_pointer_list};
+
+/// Сколько байт данных показывать в превью
+const PREVIEW_BYTES: usize = 32;
+/// Глубже указатели не вкладываются даже в повреждённом файле
+const MAX_DEPTH: u32 = 32;
+
+/// Результат обхода документа
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Inspection {
+    /// Версия формата из заголовка (11 для Visio 2003-2010)
+    pub version: u8,
+    pub trailer: PointerNode,
+}
+
+/// Поток, на который ссылается указатель, и вложенные в него потоки
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PointerNode {
+    /// Индекс в списке указателей родителя; у трейлера `None`
+    pub index: Option<u32>,
+    pub type_id: u32,
+    /// Имя константы из `vsd_constants::object_types`
+    pub type_name: Option<&'static str>,
+    pub format: u32,
+    pub offset: u32,
+    pub length: u32,
+    pub compressed: bool,
+    /// Размер данных после распаковки
+    pub data_length: usize,
+    /// Первые байты данных в шестнадцатеричном виде
+    pub preview: String,
+    pub chunks: Vec<ChunkNode>,
+    pub children: Vec<PointerNode>,
+    /// Почему поток не удалось прочитать или обойти
+    pub error: Option<String>,
+}
+
+/// Заголовок чанка внутри потока со списком чанков
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChunkNode {
+    pub chunk_type: u32,
+    pub type_name: Option<&'static str>,
+    pub id: u32,
+    pub list: u32,
+    pub data_length: u32,
+    pub level: u16,
+    pub unknown: u8,
+    pub trailer: u32,
+    pub preview: String,
+}
+
+/// Обходит содержимое потока `VisioDocument`
+pub fn inspect(data: &[u8]) -> Result<Inspection, Box<dyn std::error::Error>> {
+    if data.len() < 0x24 + 18 || !data.starts_with(b"Visio (TM) Drawing") {
+        return Err("This is not vsd file".into());
+    }
+    let version = data[0x1A];
+
+    let mut input = Cursor::new(data);
+    input.seek(SeekFrom::Start(0x24))?;
+    let mut pointer = Pointer::new();
+    pointer.read(&mut input)?;
+    if pointer.type_name == 0 {
+        pointer.type_name = VSD_TRAILER_STREAM as u32;
+    }
+
+    let mut visited = HashSet::new();
+    let trailer = pointer_node(data, &pointer, None, 0, &mut visited);
+    Ok(Inspection { version, trailer })
+}
+
+/// Обходит поток `VisioDocument` файла VSD, уже прочитанного в память
+pub fn inspect_file_data(data: &[u8]) -> Result<Inspection, Box<dyn std::error::Error>> {
+    let mut cf = cfb::CompoundFile::open(Cursor::new(data))?;
+    let mut document = Vec::new();
+    std::io::Read::read_to_end(&mut cf.open_stream("VisioDocument")?, &mut document)?;
+    inspect(&document)
+}
+
+fn pointer_node(
+    data: &[u8],
+    pointer: &Pointer,
+    index: Option<u32>,
+    depth: u32,
+    visited: &mut HashSet<u32>,
+) -> PointerNode {
+    let compressed = pointer.format & 2 == 2;
+    let mut node = PointerNode {
+        index,
+        type_id: pointer.type_name,
+        type_name: type_name(pointer.type_name),
+        format: pointer.format,
+        offset: pointer.offset,
+        length: pointer.length,
+        compressed,
+        data_length: 0,
+        preview: String::new(),
+        chunks: Vec::new(),
+        children: Vec::new(),
+        error: None,
+    };
+
+    let mut input = Cursor::new(data);
+    let stream = input
+        .seek(SeekFrom::Start(pointer.offset as u64))
+        .and_then(|_| VSDInternalStream::new(&mut input, pointer.length as usize, compressed));
+    let mut stream = match stream {
+        Ok(stream) => stream,
+        Err(err) => {
+            node.error = Some(err.to_string());
+            return node;
+        }
+    };
+
+    // Сжатые потоки начинаются с четырёх служебных байт
+    let shift = if compressed { 4 } else { 0 };
+    let body = stream.buffer.get(shift..).unwrap_or_default();
+    node.data_length = body.len();
+    node.preview = hex_preview(body);
+
+    match pointer.format >> 4 {
+        0x5 if pointer.type_name as u8 != VSD_COLORS || index.is_none() => {
+            if depth >= MAX_DEPTH {
+                node.error = Some("pointer lists are nested too deep".to_string());
+            } else if !visited.insert(pointer.offset) {
+                node.error = Some("pointer cycle".to_string());
+            } else {
+                match read_pointer_list(&mut stream, shift as u32) {
+                    Ok(list) => {
+                        node.children = list
+                            .pointers
+                            .iter()
+                            .map(|(i, child)| {
+                                pointer_node(data, child, Some(*i), depth + 1, visited)
+                            })
+                            .collect();
+                    }
+                    Err(err) => node.error = Some(err.to_string()),
+                }
+                visited.remove(&pointer.offset);
+            }
+        }
+        0xd | 0xc | 0x8 => node.chunks = chunk_nodes(&mut stream),
+        _ => {}
+    }
+    node
+}
+
+fn chunk_nodes(stream: &mut VSDInternalStream) -> Vec<ChunkNode> {
+    let mut chunks = Vec::new();
+    while let Some(header) = get_chunk_header(stream) {
+        let start = stream.offset;
+        let end = (start + header.data_length as usize).min(stream.buffer.len());
+        chunks.push(ChunkNode {
+            chunk_type: header.chunk_type,
+            type_name: type_name(header.chunk_type),
+            id: header.id,
+            list: header.list,
+            data_length: header.data_length,
+            level: header.level,
+            unknown: header.unknown,
+            trailer: header.trailer,
+            preview: hex_preview(&stream.buffer[start..end]),
+        });
+
+        let next = start + header.data_length as usize + header.trailer as usize;
+        if stream.seek(SeekFrom::Start(next as u64)).is_err() || next >= stream.buffer.len() {
+            break;
+        }
+    }
+    chunks
+}
+
+fn type_name(type_id: u32) -> Option<&'static str> {
+    u8::try_from(type_id).ok().and_then(object_types::name)
+}
+
+fn hex_preview(data: &[u8]) -> String {
+    let mut preview = String::new();
+    for (i, byte) in data.iter().take(PREVIEW_BYTES).enumerate() {
+        if i > 0 {
+            preview.push(' ');
+        }
+        let _ = write!(preview, "{:02x}", byte);
+    }
+    if data.len() > PREVIEW_BYTES {
+        preview.push_str(" ...");
+    }
+    preview
+}
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "VSD version {}", self.version)?;
+        self.trailer.write_tree(f, 0)
+    }
+}
+
+impl PointerNode {
+    /// Строка на поток и на каждый чанк, вложенность - отступом в два пробела
+    fn write_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        let index = self
+            .index
+            .map_or("trailer".to_string(), |i| format!("#{}", i));
+        write!(
+            f,
+            "{}{} 0x{:02x} {} format 0x{:x} offset 0x{:x} length {}",
+            indent,
+            index,
+            self.type_id,
+            self.type_name.unwrap_or("?"),
+            self.format,
+            self.offset,
+            self.length
+        )?;
+        if self.compressed {
+            write!(f, " compressed -> {}", self.data_length)?;
+        }
+        if !self.preview.is_empty() {
+            write!(f, " | {}", self.preview)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " ! {}", error)?;
+        }
+        writeln!(f)?;
+
+        for chunk in &self.chunks {
+            writeln!(
+                f,
+                "{}  chunk 0x{:02x} {} id {} list {} length {} level {} trailer {} | {}",
+                indent,
+                chunk.chunk_type,
+                chunk.type_name.unwrap_or("?"),
+                chunk.id,
+                chunk.list,
+                chunk.data_length,
+                chunk.level,
+                chunk.trailer,
+                chunk.preview
+            )?;
+        }
+        for child in &self.children {
+            child.write_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::parser_vsd::lz77;
+    use crate::parser_vsd::vsd_constants::object_types::{VSD_PAGE, VSD_TEXT};
+
+    pub(crate) fn pointer(type_id: u8, offset: usize, length: usize, format: u16) -> Vec<u8> {
+        let mut out = (type_id as u32).to_le_bytes().to_vec();
+        out.extend([0; 4]);
+        out.extend((offset as u32).to_le_bytes());
+        out.extend((length as u32).to_le_bytes());
+        out.extend(format.to_le_bytes());
+        out
+    }
+
+    /// Поток со списком указателей: смещение списка, размер и число, затем указатели
+    pub(crate) fn pointer_stream(pointers: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = 8u32.to_le_bytes().to_vec();
+        out.extend(0u32.to_le_bytes());
+        out.extend((pointers.len() as u32).to_le_bytes());
+        out.extend([0; 4]);
+        for p in pointers {
+            out.extend(p);
+        }
+        out
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut data = b"Visio (TM) Drawing".to_vec();
+        data.resize(0x1A, 0);
+        data.push(11);
+        data.resize(0x24, 0);
+        let trailer_at = 0x24 + 18;
+
+        // Текст в несжатом потоке, страница - сжатый список указателей с этим текстом
+        let text_at = 0x200;
+        let text = b"\x0e\0\0\0hello".to_vec();
+        let page_at = 0x300;
+        let mut page = vec![0u8; 4];
+        page.extend(pointer_stream(&[pointer(
+            VSD_TEXT,
+            text_at,
+            text.len(),
+            0x00,
+        )]));
+        let page = lz77::compress(&page);
+        let trailer = pointer_stream(&[pointer(VSD_PAGE, page_at, page.len(), 0x52)]);
+
+        data.extend(pointer(0, trailer_at, trailer.len(), 0x50));
+        data.extend(&trailer);
+        data.resize(text_at, 0);
+        data.extend(&text);
+        data.resize(page_at, 0);
+        data.extend(&page);
+
+        let inspection = inspect(&data).unwrap();
+        assert_eq!(inspection.version, 11);
+        let trailer = &inspection.trailer;
+        assert_eq!(trailer.type_name, Some("VSD_TRAILER_STREAM"));
+        let page = &trailer.children[0];
+        assert_eq!(page.type_name, Some("VSD_PAGE"));
+        assert!(page.compressed);
+        assert_eq!(page.error, None);
+        let text = &page.children[0];
+        assert_eq!(text.index, Some(0));
+        assert_eq!(text.type_name, Some("VSD_TEXT"));
+        assert!(text.preview.starts_with("0e 00 00 00 68"));
+
+        let tree = inspection.to_string();
+        assert!(tree.contains("\n  #0 0x15 VSD_PAGE format 0x52"));
+        assert!(tree.contains("\n    #0 0x0e VSD_TEXT"));
+        assert!(serde_json::to_string(&inspection).is_ok());
+    }
+}
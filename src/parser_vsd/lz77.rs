@@ -0,0 +1,316 @@
+//! Сжатие потоков VSD: вариант LZSS с кольцевым буфером истории на 4096 байт.
+//!
+//! Данные идут группами по восемь элементов, перед группой байт флагов: установленный
+//! бит - байт как есть, сброшенный - ссылка из двух байт. Ссылка хранит 12-битную позицию
+//! в кольцевом буфере, сдвинутую на 18 (буфер кодировщика начинается с позиции 4078),
+//! и длину от 3 до 18 байт. Конец данных не помечается: поток просто заканчивается.
+//! Как и в libvisio, буфер изначально заполнен нулями, и ссылки в ещё не записанную его
+//! часть дают нулевые байты.
+
+use std::io::{self, Read};
+
+use thiserror::Error;
+
+/// Размер кольцевого буфера истории
+const WINDOW: usize = 4096;
+/// Сдвиг позиции в ссылке относительно начала буфера декодера
+const RING_SHIFT: usize = 18;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 18;
+/// Сколько кандидатов проверяет кодировщик для одной позиции
+const MAX_CHAIN: usize = 256;
+const HASH_SIZE: usize = 1 << 12;
+
+#[derive(Debug, Error)]
+pub enum Lz77Error {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    /// Поток оборвался посреди двухбайтовой ссылки
+    #[error("Truncated back-reference at input offset {offset}")]
+    TruncatedToken { offset: u64 },
+}
+
+impl From<Lz77Error> for io::Error {
+    fn from(err: Lz77Error) -> Self {
+        match err {
+            Lz77Error::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Потоковый распаковщик поверх любого `Read`
+pub struct Decompressor<R> {
+    input: R,
+    history: Box<[u8; WINDOW]>,
+    /// Число уже распакованных байт
+    position: u64,
+    /// Прочитано байт сжатых данных
+    consumed: u64,
+    flags: u8,
+    /// Оставшиеся элементы текущей группы
+    remaining: u8,
+    /// Распакованные, но ещё не отданные байты последнего элемента
+    pending: [u8; MAX_MATCH],
+    pending_start: usize,
+    pending_end: usize,
+    done: bool,
+}
+
+impl<R: Read> Decompressor<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            history: Box::new([0; WINDOW]),
+            position: 0,
+            consumed: 0,
+            flags: 0,
+            remaining: 0,
+            pending: [0; MAX_MATCH],
+            pending_start: 0,
+            pending_end: 0,
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+
+    /// Байт входа или `None` в конце потока
+    fn next_byte(&mut self) -> Result<Option<u8>, Lz77Error> {
+        let mut byte = [0u8];
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    self.consumed += 1;
+                    return Ok(Some(byte[0]));
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn emit(&mut self, byte: u8) {
+        self.history[self.position as usize % WINDOW] = byte;
+        self.pending[self.pending_end] = byte;
+        self.pending_end += 1;
+        self.position += 1;
+    }
+
+    /// Распаковывает следующий элемент в `pending`; `false` в конце потока
+    fn decode_token(&mut self) -> Result<bool, Lz77Error> {
+        self.pending_start = 0;
+        self.pending_end = 0;
+
+        if self.remaining == 0 {
+            let Some(flags) = self.next_byte()? else {
+                return Ok(false);
+            };
+            self.flags = flags;
+            self.remaining = 8;
+        }
+        let literal = self.flags & 1 != 0;
+        self.flags >>= 1;
+        self.remaining -= 1;
+
+        let Some(first) = self.next_byte()? else {
+            return Ok(false);
+        };
+        if literal {
+            self.emit(first);
+            return Ok(true);
+        }
+
+        let Some(second) = self.next_byte()? else {
+            return Err(Lz77Error::TruncatedToken {
+                offset: self.consumed - 1,
+            });
+        };
+        let length = (second as usize & 0x0F) + MIN_MATCH;
+        let encoded = ((second as usize & 0xF0) << 4) | first as usize;
+        let ring = (encoded + RING_SHIFT) % WINDOW;
+        for j in 0..length {
+            let byte = self.history[(ring + j) % WINDOW];
+            self.emit(byte);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.pending_start == self.pending_end && (self.done || !self.decode_token()?) {
+                self.done = true;
+                break;
+            }
+            let count = (self.pending_end - self.pending_start).min(buf.len() - written);
+            buf[written..written + count]
+                .copy_from_slice(&self.pending[self.pending_start..self.pending_start + count]);
+            self.pending_start += count;
+            written += count;
+        }
+        Ok(written)
+    }
+}
+
+/// Распаковывает буфер целиком
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, Lz77Error> {
+    let mut decompressor = Decompressor::new(input);
+    let mut output = Vec::with_capacity(input.len() * 2);
+    while decompressor.decode_token()? {
+        output.extend_from_slice(&decompressor.pending[..decompressor.pending_end]);
+    }
+    Ok(output)
+}
+
+/// Сжимает буфер так, что [`decompress`] восстанавливает его без потерь.
+///
+/// Совпадения ищутся жадно по цепочкам хешей трёх байт в пределах окна
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() + input.len() / 8 + 1);
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; input.len()];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= input.len() {
+            let h = hash3(&input[pos..]);
+            prev[pos] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut flags_at = 0;
+    let mut items = 8;
+    let mut pos = 0;
+    while pos < input.len() {
+        if items == 8 {
+            flags_at = output.len();
+            output.push(0);
+            items = 0;
+        }
+
+        let (mut best_len, mut best_pos) = (0, 0);
+        if pos + MIN_MATCH <= input.len() {
+            let max_len = MAX_MATCH.min(input.len() - pos);
+            let mut candidate = head[hash3(&input[pos..])];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW && chain < MAX_CHAIN {
+                let len = (0..max_len)
+                    .take_while(|&j| input[candidate + j] == input[pos + j])
+                    .count();
+                if len > best_len {
+                    (best_len, best_pos) = (len, candidate);
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            let encoded = (best_pos % WINDOW + WINDOW - RING_SHIFT) % WINDOW;
+            output.push((encoded & 0xFF) as u8);
+            output.push((((encoded >> 4) & 0xF0) | (best_len - MIN_MATCH)) as u8);
+            for p in pos..pos + best_len {
+                insert(p, &mut head, &mut prev);
+            }
+            pos += best_len;
+        } else {
+            output[flags_at] |= 1 << items;
+            output.push(input[pos]);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+        items += 1;
+    }
+    output
+}
+
+fn hash3(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 8 ^ (bytes[1] as usize) << 4 ^ bytes[2] as usize) % HASH_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift: воспроизводимые случайные данные без внешних зависимостей
+    fn random_bytes(seed: u64, len: usize, alphabet: u8) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % alphabet as u64) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_known_stream() {
+        // "abc" и ссылка на 6 байт с позиции 0 (в кольце кодировщика - 4078)
+        let data = [0b0000_0111, b'a', b'b', b'c', 0xEE, 0xF3];
+        assert_eq!(decompress(&data).unwrap(), b"abcabcabc");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            decompress(&[0b0000_0011, b'a', b'b', 0xEE]),
+            Err(Lz77Error::TruncatedToken { offset: 3 })
+        ));
+        assert!(decompress(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_initial_window_is_zero() {
+        // Поток начинается со ссылки на пять байт в нетронутую часть буфера
+        assert_eq!(decompress(&[0b0000_0000, 0x00, 0x02]).unwrap(), [0; 5]);
+        // Ссылка на третий байт назад, когда распакованы только два: первый из них - ноль
+        assert_eq!(
+            decompress(&[0b0000_0011, b'a', b'b', 0xED, 0xF0]).unwrap(),
+            b"ab\0ab"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut inputs = vec![
+            Vec::new(),
+            b"a".to_vec(),
+            vec![0; 10_000],
+            b"Visio (TM) Drawing".repeat(500),
+        ];
+        for seed in 0..64 {
+            let len = (seed as usize * 997) % 20_000;
+            let alphabet = [2, 4, 16, 255][seed as usize % 4];
+            inputs.push(random_bytes(seed, len, alphabet));
+        }
+
+        for input in inputs {
+            let compressed = compress(&input);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+
+            // Потоковое чтение маленькими порциями даёт тот же результат
+            let mut streamed = Vec::new();
+            let mut decompressor = Decompressor::new(compressed.as_slice());
+            let mut buf = [0u8; 7];
+            loop {
+                let n = decompressor.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                streamed.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(streamed, input);
+        }
+        assert!(compress(&vec![0; 10_000]).len() < 1_300);
+    }
+}
@@ -1,13 +1,19 @@
+pub mod inspect;
+pub mod lz77;
+pub mod property_set;
 pub mod utils;
 pub mod vsd_constants;
 pub mod vsdinternal_stream;
-// mod vsdparser;
-pub mod VSDParser;
+pub mod vsdparser;
 
-// use vsdparser::VSDParser;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
 
-use std::io::Read;
-use std::{fs::File, io::BufReader, path::Path};
+use crate::format::{self, FileFormat};
+use crate::parser_vsdx::read_vsdx::{Cell, CellName, Diagram, Master, Page, Shape};
+
+/// Поле вокруг фигур мастера на странице предпросмотра, в дюймах
+const STENCIL_MARGIN: f64 = 0.25;
 
 #[derive(Debug, PartialEq)]
 pub struct Coordinate {
@@ -19,7 +25,7 @@ pub fn read_file(file_path: &Path) {
     let mut cf = cfb::open_rw(file_path).unwrap();
 
     for entry in cf.read_root_storage() {
-        println!(
+        log::debug!(
             "Found stream: {} {} bytes, is_root {}, path {:?}",
             entry.name(),
             entry.len(),
@@ -29,75 +35,324 @@ pub fn read_file(file_path: &Path) {
     }
     match cf.open_stream("VisioDocument") {
         Err(err) => {
-            println!("open_stream VisioDocument err: {}", err)
+            log::warn!("open_stream VisioDocument err: {}", err)
         }
         Ok(mut stream) => {
-            println!("open_stream VisioDocument");
+            log::debug!("open_stream VisioDocument");
             let mut buf: Vec<u8> = Vec::new();
             let _ = stream.read_to_end(&mut buf);
 
             // Анализ бинарных данных
-            let doc = match parse_visio_document(&buf) {
-                Ok(doc) => doc,
-                Err(err) => {
-                    println!("Error on parse doc {}", err);
-                    {}
-                }
-            };
-
-            doc
+            match parse_visio_document(&buf) {
+                Ok(diagram) => log::info!("Pages found: {}", diagram.pages.len()),
+                Err(err) => log::warn!("Error on parse doc {}", err),
+            }
         }
     }
 }
 
-fn parse_visio_document(data: &Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
-    println!("parse_visio_document {} bytes", data.len());
+/// Разбирает бинарный документ VSD в общую модель `Diagram`.
+///
+/// Так же читаются наборы элементов (.vss) и шаблоны (.vst): у них тот же поток
+/// `VisioDocument`, а мастера попадают в `Diagram::masters`. У набора элементов
+/// обычно нет страниц, для предпросмотра мастера выводятся страницами через [`extract_stencils`]
+pub fn parse_file(file_path: &Path) -> Result<Diagram, Box<dyn std::error::Error>> {
+    parse_compound_file(cfb::open(file_path)?)
+}
+
+/// Разбирает файл VSD, уже прочитанный в память
+pub fn parse_data(data: &[u8]) -> Result<Diagram, Box<dyn std::error::Error>> {
+    parse_compound_file(cfb::CompoundFile::open(Cursor::new(data))?)
+}
+
+/// Читает поток `VisioDocument` и свойства документа из наборов свойств OLE
+fn parse_compound_file<F: Read + Seek>(
+    mut cf: cfb::CompoundFile<F>,
+) -> Result<Diagram, Box<dyn std::error::Error>> {
+    let mut buf: Vec<u8> = Vec::new();
+    cf.open_stream("VisioDocument")?.read_to_end(&mut buf)?;
+    let mut diagram = parse_visio_document(&buf)?;
+
+    let mut read_stream = |name: &str| {
+        let mut data = Vec::new();
+        cf.open_stream(name).ok()?.read_to_end(&mut data).ok()?;
+        Some(data)
+    };
+    let summary = read_stream(property_set::SUMMARY_INFORMATION);
+    let document_summary = read_stream(property_set::DOCUMENT_SUMMARY_INFORMATION);
+    diagram.document_info =
+        property_set::document_info(summary.as_deref(), document_summary.as_deref());
+    Ok(diagram)
+}
+
+pub fn parse_visio_document(data: &[u8]) -> Result<Diagram, Box<dyn std::error::Error>> {
+    log::debug!("parse_visio_document {} bytes", data.len());
+
+    if data.len() < 0x24 + 18 {
+        return Err("This is not vsd file".into());
+    }
 
     let head_string = String::from_utf8_lossy(&data[0..18]);
 
     if head_string != "Visio (TM) Drawing" {
-        panic!("This is not vsd file");
+        return Err("This is not vsd file".into());
     }
     let version = u8::from_le_bytes([data[0x1A]]);
 
-    println!("Visio format version: {}", version);
+    log::debug!("Visio format version: {}", version);
 
     if version != 11 {
-        panic!("Supported only version 11");
+        return Err(format!("Supported only version 11, got {}", version).into());
     }
 
-    let mut vsd_parser = VSDParser::VSDParser::new(data.to_owned());
+    let mut vsd_parser = vsdparser::VSDParser::new(data.to_owned());
+    CellName::scoped(move || {
+        vsd_parser
+            .parse_main()
+            .then(|| vsd_parser.into_diagram())
+            .ok_or_else(|| "Unable to read VSD trailer".into())
+    })
+}
 
-    vsd_parser.parse_main();
+/// Проверяет, что файл - двоичный документ Visio, а не любой другой файл OLE
+pub fn check_is_vsd(file_path: &Path) -> bool {
+    match format::detect_file_format(file_path) {
+        Ok(format) => format == Some(FileFormat::Vsd),
+        Err(err) => {
+            log::warn!("Unable to read {:?}: {}", file_path, err);
+            false
+        }
+    }
+}
 
-    Ok(())
+/// Добавляет по странице на каждый мастер, как `extractStencils` в libvisio.
+///
+/// Фигуры мастера сдвигаются так, чтобы их габарит с полем [`STENCIL_MARGIN`]
+/// начинался в углу страницы; ссылок на мастер у них нет, они рисуются как есть.
+pub fn extract_stencils(diagram: &mut Diagram) {
+    let pages: Vec<Page> = diagram.masters.iter().map(stencil_page).collect();
+    diagram.pages.extend(pages);
 }
 
-pub fn check_is_vsd(file_path: &Path) -> bool {
-    match File::open(file_path) {
-        Ok(file) => {
-            println!("Файл успешно открыт.");
-            let mut reader: BufReader<File> = BufReader::new(file);
-            let mut buf = vec![0; 8];
-            reader
-                .read_exact(&mut buf)
-                .expect("Не удалось прочитать заголовок файла");
-            // Магическое число OLE Compound Document
-            if &buf[..4] != b"\xD0\xCF\x11\xE0" {
-                println!("Это не vsd!");
-                return false;
-            } else {
-                println!("Похоже на формат Visio (OLE Compound Document).");
-                return true;
+fn stencil_page(master: &Master) -> Page {
+    let mut shapes = master.shapes.clone();
+    let (width, height) = match shapes_bounds(&shapes) {
+        Some((min_x, min_y, max_x, max_y)) => {
+            let (dx, dy) = (STENCIL_MARGIN - min_x, STENCIL_MARGIN - min_y);
+            for shape in shapes.iter_mut() {
+                shift_shape(shape, dx, dy);
             }
+            (
+                max_x - min_x + 2.0 * STENCIL_MARGIN,
+                max_y - min_y + 2.0 * STENCIL_MARGIN,
+            )
         }
-        Err(err) => {
-            eprintln!(
-                "Ошибка открытия файла {:?}: {}",
-                file_path.file_name().unwrap().to_owned(),
-                err
-            );
-            return false;
+        None => (1.0, 1.0),
+    };
+
+    let mut page = Page {
+        id: format!("stencil-{}", master.id),
+        name: if master.name.is_empty() {
+            format!("Master {}", master.id)
+        } else {
+            master.name.clone()
+        },
+        width,
+        height,
+        shapes,
+        ..Default::default()
+    };
+    page.cells.insert("PageWidth".into(), number_cell(width));
+    page.cells.insert("PageHeight".into(), number_cell(height));
+    page
+}
+
+fn number_cell(value: f64) -> Cell {
+    Cell {
+        value: value.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Габарит фигур верхнего уровня без учёта поворота: (min_x, min_y, max_x, max_y)
+fn shapes_bounds(shapes: &[Shape]) -> Option<(f64, f64, f64, f64)> {
+    let mut bounds: Option<(f64, f64, f64, f64)> = None;
+    let mut add = |x: f64, y: f64| {
+        let b = bounds.get_or_insert((x, y, x, y));
+        *b = (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y));
+    };
+    for shape in shapes {
+        if let (Some(pin_x), Some(pin_y)) = (shape.number("PinX"), shape.number("PinY")) {
+            let width = shape.number("Width").unwrap_or(0.0).abs();
+            let height = shape.number("Height").unwrap_or(0.0).abs();
+            let left = pin_x - shape.number("LocPinX").unwrap_or(width / 2.0);
+            let bottom = pin_y - shape.number("LocPinY").unwrap_or(height / 2.0);
+            add(left, bottom);
+            add(left + width, bottom + height);
+        }
+        for (x, y) in [("BeginX", "BeginY"), ("EndX", "EndY")] {
+            if let (Some(x), Some(y)) = (shape.number(x), shape.number(y)) {
+                add(x, y);
+            }
+        }
+    }
+    bounds
+}
+
+fn shift_shape(shape: &mut Shape, dx: f64, dy: f64) {
+    for (name, delta) in [
+        ("PinX", dx),
+        ("PinY", dy),
+        ("BeginX", dx),
+        ("BeginY", dy),
+        ("EndX", dx),
+        ("EndY", dy),
+    ] {
+        if let Some(cell) = shape.cells.get_mut(name)
+            && let Some(value) = cell.number()
+        {
+            cell.value = (value + delta).to_string();
+            // Формула ссылается на исходное положение и больше не соответствует значению
+            cell.formula = None;
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsd::inspect::tests::{pointer, pointer_stream};
+    use crate::parser_vsd::lz77;
+    use crate::parser_vsd::vsd_constants::cell_types::CELL_TYPE_Number;
+    use crate::parser_vsd::vsd_constants::object_types::{
+        VSD_SHAPE_SHAPE, VSD_STENCIL_PAGE, VSD_STENCILS, VSD_XFORM_DATA,
     };
+    use std::{fs, io::Write};
+
+    /// Чанк потока фигуры: тип, id, признак списка, длина, уровень и байт без трейлера
+    fn chunk(chunk_type: u8, id: u32, data: &[u8]) -> Vec<u8> {
+        let mut out = (chunk_type as u32).to_le_bytes().to_vec();
+        out.extend(id.to_le_bytes());
+        out.extend(0u32.to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        out.push(0x50);
+        out.extend(data);
+        out
+    }
+
+    /// Набор элементов VSS с одним мастером из одной фигуры 2x1 в точке (5, 6)
+    fn stencil_file() -> Vec<u8> {
+        let mut shape = vec![0u8; 10];
+        // Родитель, мастер и фигура мастера не заданы, стили - нулевые
+        for id in [u32::MAX, u32::MAX, u32::MAX, 0, 0, 0] {
+            shape.extend(id.to_le_bytes());
+            shape.extend([0; 4]);
+        }
+        let mut xform = Vec::new();
+        for value in [5.0, 6.0, 2.0, 1.0, 1.0, 0.5, 0.0] {
+            xform.push(CELL_TYPE_Number);
+            xform.extend(f64::to_le_bytes(value));
+        }
+        xform.extend([0, 0]);
+        let mut shape_stream = chunk(VSD_SHAPE_SHAPE, 1, &shape);
+        shape_stream.extend(chunk(VSD_XFORM_DATA, 2, &xform));
+
+        // Страница мастера сжата, как в файлах Visio; список и фигура - нет
+        let shape_at = 0x400;
+        let mut master = vec![0u8; 4];
+        master.extend(pointer_stream(&[pointer(
+            VSD_SHAPE_SHAPE,
+            shape_at,
+            shape_stream.len(),
+            0xd0,
+        )]));
+        let master = lz77::compress(&master);
+        let master_at = 0x300;
+        let stencils = pointer_stream(&[pointer(VSD_STENCIL_PAGE, master_at, master.len(), 0x52)]);
+        let stencils_at = 0x200;
+        let trailer = pointer_stream(&[pointer(VSD_STENCILS, stencils_at, stencils.len(), 0x50)]);
+
+        let mut data = b"Visio (TM) Drawing".to_vec();
+        data.resize(0x1A, 0);
+        data.push(11);
+        data.resize(0x24, 0);
+        let trailer_at = 0x24 + 18;
+        data.extend(pointer(0, trailer_at, trailer.len(), 0x50));
+        data.extend(&trailer);
+        for (at, stream) in [
+            (stencils_at, &stencils),
+            (master_at, &master),
+            (shape_at, &shape_stream),
+        ] {
+            data.resize(at, 0);
+            data.extend(stream);
+        }
+
+        let mut cf = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        cf.create_stream("VisioDocument")
+            .unwrap()
+            .write_all(&data)
+            .unwrap();
+        cf.flush().unwrap();
+        cf.into_inner().into_inner()
+    }
+
+    #[test]
+    fn test_binary_stencil() {
+        let path = std::env::temp_dir().join(format!("rvp-stencil-{}.vss", std::process::id()));
+        fs::write(&path, stencil_file()).unwrap();
+        assert!(check_is_vsd(&path));
+        let diagram = parse_file(&path);
+        fs::remove_file(&path).unwrap();
+        let mut diagram = diagram.unwrap();
+
+        assert!(diagram.pages.is_empty());
+        assert_eq!(diagram.masters.len(), 1);
+        let shape = &diagram.masters[0].shapes[0];
+        assert_eq!(shape.id, "0");
+        assert_eq!(shape.number("PinX"), Some(5.0));
+        assert_eq!(shape.number("Width"), Some(2.0));
+
+        extract_stencils(&mut diagram);
+        assert_eq!(diagram.pages.len(), 1);
+        let page = &diagram.pages[0];
+        assert_eq!((page.width, page.height), (2.5, 1.5));
+        assert_eq!(page.shapes[0].number("PinX"), Some(1.25));
+        assert_eq!(page.shapes[0].number("PinY"), Some(0.75));
+    }
+
+    #[test]
+    fn test_extract_stencils() {
+        let mut shape = Shape::default();
+        shape.set_cell("PinX", 5.0);
+        shape.set_cell("PinY", 6.0);
+        shape.set_cell("Width", 2.0);
+        shape.set_cell("Height", 1.0);
+        let mut diagram = Diagram {
+            masters: vec![
+                Master {
+                    id: "2".to_string(),
+                    name: "Box".to_string(),
+                    shapes: vec![shape],
+                    ..Default::default()
+                },
+                Master {
+                    id: "3".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        extract_stencils(&mut diagram);
+
+        assert_eq!(diagram.pages.len(), 2);
+        let page = &diagram.pages[0];
+        assert_eq!(page.name, "Box");
+        assert_eq!((page.width, page.height), (2.5, 1.5));
+        assert_eq!(page.shapes[0].number("PinX"), Some(1.25));
+        assert_eq!(page.shapes[0].number("PinY"), Some(0.75));
+        assert_eq!(diagram.pages[1].name, "Master 3");
+        assert_eq!(diagram.pages[1].width, 1.0);
+    }
 }
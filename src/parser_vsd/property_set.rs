@@ -0,0 +1,380 @@
+//! Наборы свойств OLE (`\x05SummaryInformation`, `\x05DocumentSummaryInformation`).
+//!
+//! Формат описан в MS-OLEPS: заголовок со списком секций, в каждой секции таблица
+//! "идентификатор -> смещение" и типизированные значения. Строки VT_LPSTR записаны
+//! в кодовой странице секции (свойство 1), VT_LPWSTR - в UTF-16LE.
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::time::SystemTime;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+use crate::parser_vsd::utils::{
+    VisioUtilsError, codepage_encoding, filetime_to_system_time, ole_date_to_system_time,
+    read_utf16_string, system_time_to_iso8601,
+};
+use crate::parser_vsdx::read_vsdx::DocumentInfo;
+
+pub const SUMMARY_INFORMATION: &str = "\u{5}SummaryInformation";
+pub const DOCUMENT_SUMMARY_INFORMATION: &str = "\u{5}DocumentSummaryInformation";
+
+const PID_DICTIONARY: u32 = 0;
+const PID_CODEPAGE: u32 = 1;
+const CODEPAGE_UTF16: u16 = 1200;
+
+const VT_I2: u32 = 2;
+const VT_I4: u32 = 3;
+const VT_R4: u32 = 4;
+const VT_R8: u32 = 5;
+const VT_DATE: u32 = 7;
+const VT_BSTR: u32 = 8;
+const VT_BOOL: u32 = 11;
+const VT_UI2: u32 = 18;
+const VT_UI4: u32 = 19;
+const VT_I8: u32 = 20;
+const VT_UI8: u32 = 21;
+const VT_INT: u32 = 22;
+const VT_UINT: u32 = 23;
+const VT_LPSTR: u32 = 30;
+const VT_LPWSTR: u32 = 31;
+const VT_FILETIME: u32 = 64;
+
+/// Значение свойства; векторы и прочие составные типы не разбираются
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Time(SystemTime),
+    Unsupported(u32),
+}
+
+impl PropertyValue {
+    /// Текстовое представление для `DocumentInfo`
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            PropertyValue::Str(s) => Some(s.clone()),
+            PropertyValue::Int(v) => Some(v.to_string()),
+            PropertyValue::Float(v) => Some(v.to_string()),
+            PropertyValue::Bool(v) => Some(v.to_string()),
+            PropertyValue::Time(t) => Some(system_time_to_iso8601(*t)),
+            PropertyValue::Unsupported(_) => None,
+        }
+    }
+}
+
+/// Секция набора свойств
+#[derive(Debug, Clone, Default)]
+pub struct PropertySection {
+    pub fmtid: [u8; 16],
+    pub properties: BTreeMap<u32, PropertyValue>,
+    /// Словарь имён пользовательских свойств (свойство 0)
+    pub names: BTreeMap<u32, String>,
+}
+
+/// Разбирает поток набора свойств
+pub fn parse_property_set(data: &[u8]) -> Result<Vec<PropertySection>, VisioUtilsError> {
+    let mut input = Cursor::new(data);
+    if input.read_u16::<LittleEndian>()? != 0xFFFE {
+        return Err(VisioUtilsError::InvalidFormat);
+    }
+    input.seek(SeekFrom::Start(24))?;
+    let count = input.read_u32::<LittleEndian>()?;
+
+    let mut headers = Vec::new();
+    for _ in 0..count.min(16) {
+        let mut fmtid = [0u8; 16];
+        input.read_exact(&mut fmtid)?;
+        headers.push((fmtid, input.read_u32::<LittleEndian>()? as u64));
+    }
+
+    let mut sections = Vec::new();
+    for (fmtid, offset) in headers {
+        let mut section = read_section(&mut input, offset)?;
+        section.fmtid = fmtid;
+        sections.push(section);
+    }
+    Ok(sections)
+}
+
+fn read_section(input: &mut Cursor<&[u8]>, start: u64) -> Result<PropertySection, VisioUtilsError> {
+    input.seek(SeekFrom::Start(start + 4))?;
+    let count = input.read_u32::<LittleEndian>()?;
+    let mut offsets = Vec::new();
+    for _ in 0..count.min(4096) {
+        let id = input.read_u32::<LittleEndian>()?;
+        offsets.push((id, input.read_u32::<LittleEndian>()? as u64));
+    }
+
+    // Кодовая страница нужна до чтения строк, поэтому читается первой
+    let codepage = offsets
+        .iter()
+        .find(|(id, _)| *id == PID_CODEPAGE)
+        .and_then(|(_, offset)| {
+            input.seek(SeekFrom::Start(start + offset + 4)).ok()?;
+            input.read_u16::<LittleEndian>().ok()
+        })
+        .unwrap_or(1252);
+
+    let mut section = PropertySection::default();
+    for (id, offset) in offsets {
+        input.seek(SeekFrom::Start(start + offset))?;
+        if id == PID_DICTIONARY {
+            section.names = read_dictionary(input, codepage)?;
+            continue;
+        }
+        match read_value(input, codepage) {
+            Ok(value) => {
+                section.properties.insert(id, value);
+            }
+            Err(e) => log::debug!("property {} is truncated: {}", id, e),
+        }
+    }
+    Ok(section)
+}
+
+fn read_value(input: &mut Cursor<&[u8]>, codepage: u16) -> Result<PropertyValue, VisioUtilsError> {
+    let value_type = input.read_u32::<LittleEndian>()?;
+    let value = match value_type {
+        VT_I2 => PropertyValue::Int(input.read_i16::<LittleEndian>()? as i64),
+        VT_UI2 => PropertyValue::Int(input.read_u16::<LittleEndian>()? as i64),
+        VT_I4 | VT_INT => PropertyValue::Int(input.read_i32::<LittleEndian>()? as i64),
+        VT_UI4 | VT_UINT => PropertyValue::Int(input.read_u32::<LittleEndian>()? as i64),
+        VT_I8 => PropertyValue::Int(input.read_i64::<LittleEndian>()?),
+        VT_UI8 => PropertyValue::Int(input.read_u64::<LittleEndian>()? as i64),
+        VT_R4 => PropertyValue::Float(input.read_f32::<LittleEndian>()? as f64),
+        VT_R8 => PropertyValue::Float(input.read_f64::<LittleEndian>()?),
+        VT_BOOL => PropertyValue::Bool(input.read_i16::<LittleEndian>()? != 0),
+        VT_DATE => match ole_date_to_system_time(input.read_f64::<LittleEndian>()?) {
+            Some(time) => PropertyValue::Time(time),
+            None => PropertyValue::Unsupported(value_type),
+        },
+        VT_FILETIME => match filetime_to_system_time(input.read_u64::<LittleEndian>()?) {
+            Some(time) => PropertyValue::Time(time),
+            None => PropertyValue::Unsupported(value_type),
+        },
+        VT_LPSTR | VT_BSTR => {
+            let len = input.read_u32::<LittleEndian>()? as usize;
+            if codepage == CODEPAGE_UTF16 {
+                PropertyValue::Str(trim_nul(read_utf16_string(input, len / 2)?))
+            } else {
+                PropertyValue::Str(read_codepage_string(input, len, codepage)?)
+            }
+        }
+        VT_LPWSTR => {
+            let len = input.read_u32::<LittleEndian>()? as usize;
+            PropertyValue::Str(trim_nul(read_utf16_string(input, len)?))
+        }
+        _ => PropertyValue::Unsupported(value_type),
+    };
+    Ok(value)
+}
+
+fn read_dictionary(
+    input: &mut Cursor<&[u8]>,
+    codepage: u16,
+) -> Result<BTreeMap<u32, String>, VisioUtilsError> {
+    let count = input.read_u32::<LittleEndian>()?;
+    let mut names = BTreeMap::new();
+    for _ in 0..count.min(4096) {
+        let id = input.read_u32::<LittleEndian>()?;
+        let len = input.read_u32::<LittleEndian>()? as usize;
+        let name = if codepage == CODEPAGE_UTF16 {
+            let name = trim_nul(read_utf16_string(input, len)?);
+            // Записи в UTF-16 выровнены по 4 байтам
+            if len % 2 == 1 {
+                input.seek(SeekFrom::Current(2))?;
+            }
+            name
+        } else {
+            read_codepage_string(input, len, codepage)?
+        };
+        names.insert(id, name);
+    }
+    Ok(names)
+}
+
+fn read_codepage_string(
+    input: &mut Cursor<&[u8]>,
+    len: usize,
+    codepage: u16,
+) -> Result<String, VisioUtilsError> {
+    let remaining = input
+        .get_ref()
+        .len()
+        .saturating_sub(input.position() as usize);
+    if len > remaining {
+        return Err(VisioUtilsError::InvalidFormat);
+    }
+    let mut buf = vec![0u8; len];
+    input.read_exact(&mut buf)?;
+    let (text, _, _) = codepage_encoding(codepage).decode(&buf);
+    Ok(trim_nul(text.into_owned()))
+}
+
+fn trim_nul(text: String) -> String {
+    match text.find('\0') {
+        Some(end) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+/// Заполняет `DocumentInfo` из обоих наборов свойств документа
+pub fn document_info(summary: Option<&[u8]>, document_summary: Option<&[u8]>) -> DocumentInfo {
+    let mut info = DocumentInfo::default();
+    let sections = |data: Option<&[u8]>, name: &str| match data.map(parse_property_set) {
+        Some(Ok(sections)) => sections,
+        Some(Err(e)) => {
+            log::warn!("Unable to parse {:?}: {}", name, e);
+            vec![]
+        }
+        None => vec![],
+    };
+
+    if let Some(section) = sections(summary, SUMMARY_INFORMATION).first() {
+        let text = |id: u32| section.properties.get(&id).and_then(PropertyValue::to_text);
+        info.title = text(2);
+        info.subject = text(3);
+        info.author = text(4);
+        info.keywords = text(5);
+        info.description = text(6);
+        info.template = text(7);
+        info.last_modified_by = text(8);
+        info.created = text(12);
+        info.modified = text(13);
+    }
+
+    let document_sections = sections(document_summary, DOCUMENT_SUMMARY_INFORMATION);
+    if let Some(section) = document_sections.first() {
+        let text = |id: u32| section.properties.get(&id).and_then(PropertyValue::to_text);
+        info.category = text(2);
+        info.manager = text(14);
+        info.company = text(15);
+    }
+    // Вторая секция - пользовательские свойства с именами из словаря
+    if let Some(custom) = document_sections.get(1) {
+        for (id, value) in &custom.properties {
+            if let (Some(name), Some(value)) = (custom.names.get(id), value.to_text()) {
+                info.custom.insert(name.clone(), value);
+            }
+        }
+    }
+
+    // Пустые строки не несут информации
+    for field in [
+        &mut info.title,
+        &mut info.subject,
+        &mut info.author,
+        &mut info.keywords,
+        &mut info.description,
+        &mut info.template,
+        &mut info.last_modified_by,
+        &mut info.category,
+        &mut info.manager,
+        &mut info.company,
+    ] {
+        if field.as_deref().is_some_and(|v| v.trim().is_empty()) {
+            *field = None;
+        }
+    }
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use std::io::Write;
+
+    /// Набор свойств из одной или двух секций; значения уже закодированы вместе с типом
+    fn property_set(sections: &[Vec<(u32, Vec<u8>)>]) -> Vec<u8> {
+        let mut bodies = Vec::new();
+        for props in sections {
+            let mut body = Vec::new();
+            let table_len = 8 + props.len() * 8;
+            let mut values = Vec::new();
+            let mut table = Vec::new();
+            for (id, value) in props {
+                table.write_u32::<LittleEndian>(*id).unwrap();
+                table
+                    .write_u32::<LittleEndian>((table_len + values.len()) as u32)
+                    .unwrap();
+                values.extend_from_slice(value);
+            }
+            body.write_u32::<LittleEndian>((table_len + values.len()) as u32)
+                .unwrap();
+            body.write_u32::<LittleEndian>(props.len() as u32).unwrap();
+            body.extend(table);
+            body.extend(values);
+            bodies.push(body);
+        }
+
+        let mut out = Vec::new();
+        out.write_u16::<LittleEndian>(0xFFFE).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_all(&[0; 16]).unwrap();
+        out.write_u32::<LittleEndian>(sections.len() as u32)
+            .unwrap();
+        let mut offset = 28 + 20 * sections.len();
+        for body in &bodies {
+            out.write_all(&[0; 16]).unwrap();
+            out.write_u32::<LittleEndian>(offset as u32).unwrap();
+            offset += body.len();
+        }
+        for body in bodies {
+            out.extend(body);
+        }
+        out
+    }
+
+    fn typed(value_type: u32, data: &[u8]) -> Vec<u8> {
+        let mut out = value_type.to_le_bytes().to_vec();
+        out.extend_from_slice(data);
+        while !out.len().is_multiple_of(4) {
+            out.push(0);
+        }
+        out
+    }
+
+    fn lpstr(text: &[u8]) -> Vec<u8> {
+        let mut data = ((text.len() + 1) as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(text);
+        data.push(0);
+        typed(VT_LPSTR, &data)
+    }
+
+    #[test]
+    fn test_document_info() {
+        let summary = property_set(&[vec![
+            (PID_CODEPAGE, typed(VT_I2, &1251u16.to_le_bytes())),
+            (2, lpstr(b"\xd1\xf5\xe5\xec\xe0")),
+            (4, lpstr(b"Ivan")),
+            (
+                12,
+                typed(VT_FILETIME, &133_537_698_000_000_000u64.to_le_bytes()),
+            ),
+        ]]);
+
+        let mut dictionary = 1u32.to_le_bytes().to_vec();
+        dictionary.extend(2u32.to_le_bytes());
+        dictionary.extend(8u32.to_le_bytes());
+        dictionary.extend(b"Project\0");
+        let document_summary = property_set(&[
+            vec![(15, lpstr(b"ACME"))],
+            vec![
+                (PID_DICTIONARY, dictionary),
+                (2, typed(VT_I4, &7i32.to_le_bytes())),
+            ],
+        ]);
+
+        let info = document_info(Some(&summary), Some(&document_summary));
+        assert_eq!(info.title.as_deref(), Some("Схема"));
+        assert_eq!(info.author.as_deref(), Some("Ivan"));
+        assert_eq!(info.created.as_deref(), Some("2024-03-01T12:30:00Z"));
+        assert_eq!(info.company.as_deref(), Some("ACME"));
+        assert_eq!(info.custom["Project"], "7");
+    }
+}
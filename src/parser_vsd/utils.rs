@@ -1,8 +1,9 @@
 // src/utils.rs
 
-use std::io::{self, Read, Seek, SeekFrom};
-use std::mem::size_of;
 use byteorder::{LittleEndian, ReadBytesExt};
+use encoding_rs::Encoding;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,12 +20,13 @@ pub enum VisioUtilsError {
 pub fn read_utf16_string<R: Read>(reader: &mut R, len: usize) -> Result<String, VisioUtilsError> {
     let mut buf = vec![0u8; len * 2];
     reader.read_exact(&mut buf)?;
-    
+
     String::from_utf16(
         &buf.chunks_exact(2)
             .map(|c| u16::from_le_bytes([c[0], c[1]]))
-            .collect::<Vec<_>>()
-    ).map_err(|_| VisioUtilsError::InvalidUtf16)
+            .collect::<Vec<_>>(),
+    )
+    .map_err(|_| VisioUtilsError::InvalidUtf16)
 }
 
 /// Читает 16-битное целое с проверкой
@@ -55,16 +57,88 @@ pub fn read_guid<R: Read>(reader: &mut R) -> Result<[u8; 16], VisioUtilsError> {
     Ok(guid)
 }
 
-/// Конвертирует OLE-дату в SystemTime
-pub fn ole_date_to_system_time(ole_date: f64) -> Option<std::time::SystemTime> {
-    // Реализация конвертации OLE-даты (как в оригинале)
-    unimplemented!()
+/// Дней от 1899-12-30 (ноль OLE-даты) до 1970-01-01
+const OLE_EPOCH_DAYS: i64 = 25569;
+/// Интервалов по 100 нс от 1601-01-01 (ноль FILETIME) до 1970-01-01
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Конвертирует OLE-дату (VT_DATE) в SystemTime.
+///
+/// Целая часть - дни от 1899-12-30, дробная - доля суток. Для дат раньше нуля
+/// дробная часть всё равно отсчитывается вперёд: -1.25 это 1899-12-29 06:00.
+pub fn ole_date_to_system_time(ole_date: f64) -> Option<SystemTime> {
+    if !ole_date.is_finite() {
+        return None;
+    }
+    let days = ole_date.trunc();
+    let day_fraction = (ole_date - days).abs();
+    let seconds = (days - OLE_EPOCH_DAYS as f64 + day_fraction) * SECONDS_PER_DAY;
+    // Округление до миллисекунд убирает погрешность double
+    let millis = (seconds * 1000.0).round();
+    if millis.abs() > i64::MAX as f64 {
+        return None;
+    }
+    let millis = millis as i64;
+    if millis >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_millis(millis as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_millis(millis.unsigned_abs()))
+    }
+}
+
+/// Конвертирует FILETIME (100 нс от 1601-01-01) в SystemTime; ноль означает "не задано"
+pub fn filetime_to_system_time(filetime: u64) -> Option<SystemTime> {
+    if filetime == 0 {
+        return None;
+    }
+    let ticks = filetime as i128 - FILETIME_UNIX_EPOCH as i128;
+    let duration = Duration::from_nanos((ticks.unsigned_abs() * 100).min(u64::MAX as u128) as u64);
+    if ticks >= 0 {
+        UNIX_EPOCH.checked_add(duration)
+    } else {
+        UNIX_EPOCH.checked_sub(duration)
+    }
+}
+
+/// Время в формате ISO 8601 UTC с точностью до секунды, например `2024-03-01T12:30:00Z`
+pub fn system_time_to_iso8601(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+    let days = seconds.div_euclid(86_400);
+    let rest = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// Дата по числу дней от 1970-01-01 (алгоритм Говарда Хиннанта)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
 }
 
 /// Проверяет сигнатуру файла
 pub fn check_file_signature<R: Read + Seek>(
-    reader: &mut R, 
-    signature: &[u8]
+    reader: &mut R,
+    signature: &[u8],
 ) -> Result<bool, VisioUtilsError> {
     let mut buf = vec![0u8; signature.len()];
     reader.read_exact(&mut buf)?;
@@ -72,6 +146,51 @@ pub fn check_file_signature<R: Read + Seek>(
     Ok(buf == signature)
 }
 
+/// Кодировка по номеру кодовой страницы Windows
+pub fn codepage_encoding(codepage: u16) -> &'static Encoding {
+    let label = match codepage {
+        65001 => "utf-8",
+        874 => "windows-874",
+        932 => "shift_jis",
+        936 => "gbk",
+        949 => "euc-kr",
+        950 => "big5",
+        1250..=1258 => return windows_encoding(codepage),
+        10000 => "macintosh",
+        20866 => "koi8-r",
+        21866 => "koi8-u",
+        28591 => "iso-8859-1",
+        28595 => "iso-8859-5",
+        _ => "windows-1252",
+    };
+    Encoding::for_label(label.as_bytes()).unwrap_or(encoding_rs::WINDOWS_1252)
+}
+
+fn windows_encoding(codepage: u16) -> &'static Encoding {
+    Encoding::for_label(format!("windows-{}", codepage).as_bytes())
+        .unwrap_or(encoding_rs::WINDOWS_1252)
+}
+
+/// Кодовая страница Windows для набора символов шрифта (`lfCharSet`)
+pub fn charset_codepage(charset: u8) -> u16 {
+    match charset {
+        128 => 932,
+        129 => 949,
+        134 => 936,
+        136 => 950,
+        161 => 1253,
+        162 => 1254,
+        163 => 1258,
+        177 => 1255,
+        178 => 1256,
+        186 => 1257,
+        204 => 1251,
+        222 => 874,
+        238 => 1250,
+        _ => 1252,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,6 +204,23 @@ mod tests {
         assert_eq!(s, "Hello");
     }
 
+    #[test]
+    fn test_ole_dates() {
+        let iso = |d| system_time_to_iso8601(ole_date_to_system_time(d).unwrap());
+        assert_eq!(iso(25569.0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso(0.0), "1899-12-30T00:00:00Z");
+        assert_eq!(iso(-1.25), "1899-12-29T06:00:00Z");
+        assert_eq!(iso(45352.5208333333), "2024-03-01T12:30:00Z");
+        assert_eq!(ole_date_to_system_time(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_filetime() {
+        let time = filetime_to_system_time(133_537_698_000_000_000).unwrap();
+        assert_eq!(system_time_to_iso8601(time), "2024-03-01T12:30:00Z");
+        assert_eq!(filetime_to_system_time(0), None);
+    }
+
     #[test]
     fn test_read_u32_checked() {
         let data = [0x78, 0x56, 0x34, 0x12]; // 0x12345678
@@ -92,4 +228,4 @@ mod tests {
         let val = read_u32_checked(&mut cursor).unwrap();
         assert_eq!(val, 0x12345678);
     }
-}
\ No newline at end of file
+}
@@ -126,6 +126,110 @@ pub mod object_types {
     pub const VSD_SHAPE_DATA: u8 = 0xd1;
     pub const VSD_FONTFACE: u8 = 0xd7;
     pub const VSD_FONTFACES: u8 = 0xd8;
+
+    /// Имя константы по идентификатору типа, например `VSD_PAGE` для 0x15
+    pub fn name(type_id: u8) -> Option<&'static str> {
+        let name = match type_id {
+            VSD_FOREIGN_DATA => "VSD_FOREIGN_DATA",
+            VSD_OLE_LIST => "VSD_OLE_LIST",
+            VSD_TEXT => "VSD_TEXT",
+            VSD_TRAILER_STREAM => "VSD_TRAILER_STREAM",
+            VSD_PAGE => "VSD_PAGE",
+            VSD_COLORS => "VSD_COLORS",
+            VSD_FONT_LIST => "VSD_FONT_LIST",
+            VSD_FONT_IX => "VSD_FONT_IX",
+            VSD_STYLES => "VSD_STYLES",
+            VSD_STENCILS => "VSD_STENCILS",
+            VSD_STENCIL_PAGE => "VSD_STENCIL_PAGE",
+            VSD_OLE_DATA => "VSD_OLE_DATA",
+            VSD_PAGES => "VSD_PAGES",
+            VSD_NAME_LIST => "VSD_NAME_LIST",
+            VSD_NAME => "VSD_NAME",
+            VSD_NAME_LIST2 => "VSD_NAME_LIST2",
+            VSD_NAME2 => "VSD_NAME2",
+            VSD_NAMEIDX123 => "VSD_NAMEIDX123",
+            VSD_PAGE_SHEET => "VSD_PAGE_SHEET",
+            VSD_SHAPE_GROUP => "VSD_SHAPE_GROUP",
+            VSD_SHAPE_SHAPE => "VSD_SHAPE_SHAPE",
+            VSD_SHAPE_GUIDE => "VSD_SHAPE_GUIDE",
+            VSD_SHAPE_FOREIGN => "VSD_SHAPE_FOREIGN",
+            VSD_STYLE_SHEET => "VSD_STYLE_SHEET",
+            VSD_SCRATCH_LIST => "VSD_SCRATCH_LIST",
+            VSD_SHAPE_LIST => "VSD_SHAPE_LIST",
+            VSD_FIELD_LIST => "VSD_FIELD_LIST",
+            VSD_PROP_LIST => "VSD_PROP_LIST",
+            VSD_CHAR_LIST => "VSD_CHAR_LIST",
+            VSD_PARA_LIST => "VSD_PARA_LIST",
+            VSD_TABS_DATA_LIST => "VSD_TABS_DATA_LIST",
+            VSD_GEOM_LIST => "VSD_GEOM_LIST",
+            VSD_CUST_PROPS_LIST => "VSD_CUST_PROPS_LIST",
+            VSD_ACT_ID_LIST => "VSD_ACT_ID_LIST",
+            VSD_LAYER_LIST => "VSD_LAYER_LIST",
+            VSD_CTRL_LIST => "VSD_CTRL_LIST",
+            VSD_C_PNTS_LIST => "VSD_C_PNTS_LIST",
+            VSD_CONNECT_LIST => "VSD_CONNECT_LIST",
+            VSD_HYPER_LNK_LIST => "VSD_HYPER_LNK_LIST",
+            VSD_SMART_TAG_LIST => "VSD_SMART_TAG_LIST",
+            VSD_SHAPE_ID => "VSD_SHAPE_ID",
+            VSD_EVENT => "VSD_EVENT",
+            VSD_LINE => "VSD_LINE",
+            VSD_FILL_AND_SHADOW => "VSD_FILL_AND_SHADOW",
+            VSD_TEXT_BLOCK => "VSD_TEXT_BLOCK",
+            VSD_TABS_DATA_1 => "VSD_TABS_DATA_1",
+            VSD_GEOMETRY => "VSD_GEOMETRY",
+            VSD_MOVE_TO => "VSD_MOVE_TO",
+            VSD_LINE_TO => "VSD_LINE_TO",
+            VSD_ARC_TO => "VSD_ARC_TO",
+            VSD_INFINITE_LINE => "VSD_INFINITE_LINE",
+            VSD_ELLIPSE => "VSD_ELLIPSE",
+            VSD_ELLIPTICAL_ARC_TO => "VSD_ELLIPTICAL_ARC_TO",
+            VSD_PAGE_PROPS => "VSD_PAGE_PROPS",
+            VSD_STYLE_PROPS => "VSD_STYLE_PROPS",
+            VSD_CHAR_IX => "VSD_CHAR_IX",
+            VSD_PARA_IX => "VSD_PARA_IX",
+            VSD_TABS_DATA_2 => "VSD_TABS_DATA_2",
+            VSD_TABS_DATA_3 => "VSD_TABS_DATA_3",
+            VSD_FOREIGN_DATA_TYPE => "VSD_FOREIGN_DATA_TYPE",
+            VSD_CONNECTION_POINTS => "VSD_CONNECTION_POINTS",
+            VSD_XFORM_DATA => "VSD_XFORM_DATA",
+            VSD_TEXT_XFORM => "VSD_TEXT_XFORM",
+            VSD_XFORM_1D => "VSD_XFORM_1D",
+            VSD_SCRATCH => "VSD_SCRATCH",
+            VSD_PROTECTION => "VSD_PROTECTION",
+            VSD_TEXT_FIELD => "VSD_TEXT_FIELD",
+            VSD_CONTROL_ANOTHER_TYPE => "VSD_CONTROL_ANOTHER_TYPE",
+            VSD_MISC => "VSD_MISC",
+            VSD_SPLINE_START => "VSD_SPLINE_START",
+            VSD_SPLINE_KNOT => "VSD_SPLINE_KNOT",
+            VSD_LAYER_MEMBERSHIP => "VSD_LAYER_MEMBERSHIP",
+            VSD_LAYER => "VSD_LAYER",
+            VSD_ACT_ID => "VSD_ACT_ID",
+            VSD_CONTROL => "VSD_CONTROL",
+            VSD_USER_DEFINED_CELLS => "VSD_USER_DEFINED_CELLS",
+            VSD_TABS_DATA_4 => "VSD_TABS_DATA_4",
+            VSD_CUSTOM_PROPS => "VSD_CUSTOM_PROPS",
+            VSD_RULER_GRID => "VSD_RULER_GRID",
+            VSD_CONNECTION_POINTS_ANOTHER_TYPE => "VSD_CONNECTION_POINTS_ANOTHER_TYPE",
+            VSD_DOC_PROPS => "VSD_DOC_PROPS",
+            VSD_IMAGE => "VSD_IMAGE",
+            VSD_GROUP => "VSD_GROUP",
+            VSD_LAYOUT => "VSD_LAYOUT",
+            VSD_PAGE_LAYOUT_IX => "VSD_PAGE_LAYOUT_IX",
+            VSD_POLYLINE_TO => "VSD_POLYLINE_TO",
+            VSD_NURBS_TO => "VSD_NURBS_TO",
+            VSD_HYPERLINK => "VSD_HYPERLINK",
+            VSD_REVIEWER => "VSD_REVIEWER",
+            VSD_ANNOTATION => "VSD_ANNOTATION",
+            VSD_SMART_TAG_DEF => "VSD_SMART_TAG_DEF",
+            VSD_PRINT_PROPS => "VSD_PRINT_PROPS",
+            VSD_NAMEIDX => "VSD_NAMEIDX",
+            VSD_SHAPE_DATA => "VSD_SHAPE_DATA",
+            VSD_FONTFACE => "VSD_FONTFACE",
+            VSD_FONTFACES => "VSD_FONTFACES",
+            _ => return None,
+        };
+        Some(name)
+    }
 }
 
 // ================== Типы ячеек ==================
@@ -180,6 +284,63 @@ pub mod cell_types {
     pub const CELL_TYPE_Color: u8 = 251;
     pub const CELL_TYPE_NoCast: u8 = 252;
     pub const CELL_TYPE_Invalid: u8 = 255;
+
+    /// Значение хранится номером строки в таблице имён
+    pub fn is_name_reference(cell_type: u8) -> bool {
+        matches!(
+            cell_type,
+            CELL_TYPE_String | CELL_TYPE_StringWithoutUnit | CELL_TYPE_GUID
+        )
+    }
+
+    /// Обозначение единиц в атрибуте `U` ячеек VSDX; `None` - безразмерное число
+    pub fn unit_name(cell_type: u8) -> Option<&'static str> {
+        let unit = match cell_type {
+            CELL_TYPE_Percent => "%",
+            CELL_TYPE_Acre => "AC",
+            CELL_TYPE_Hectare => "HA",
+            CELL_TYPE_Date => "DATE",
+            CELL_TYPE_DurationUnits => "DUR",
+            CELL_TYPE_ElapsedWeek => "EW",
+            CELL_TYPE_ElapsedDay => "ED",
+            CELL_TYPE_ElapsedHour => "EH",
+            CELL_TYPE_ElapsedMin => "EM",
+            CELL_TYPE_ElapsedSec => "ES",
+            CELL_TYPE_TypeUnits => "TU",
+            CELL_TYPE_PicasAndPoints => "P_PT",
+            CELL_TYPE_Points => "PT",
+            CELL_TYPE_Picas => "P",
+            CELL_TYPE_CicerosAndDidots => "C_D",
+            CELL_TYPE_Didots => "D",
+            CELL_TYPE_Ciceros => "C",
+            CELL_TYPE_PageUnits => "PU",
+            CELL_TYPE_DrawingUnits => "DU",
+            CELL_TYPE_Inches => "IN",
+            CELL_TYPE_Feet => "FT",
+            CELL_TYPE_FeetAndInches => "F_I",
+            CELL_TYPE_Miles => "MI",
+            CELL_TYPE_Centimeters => "CM",
+            CELL_TYPE_Millimeters => "MM",
+            CELL_TYPE_Meters => "M",
+            CELL_TYPE_Kilometers => "KM",
+            CELL_TYPE_InchFractions => "IN_F",
+            CELL_TYPE_MileFractions => "MI_F",
+            CELL_TYPE_Yards => "YD",
+            CELL_TYPE_NauticalMiles => "NM",
+            CELL_TYPE_AngleUnits => "AU",
+            CELL_TYPE_Degrees => "DEG",
+            CELL_TYPE_DegreeMinuteSecond => "D_M_S",
+            CELL_TYPE_Radians => "RAD",
+            CELL_TYPE_Minutes => "MIN",
+            CELL_TYPE_Sec => "SEC",
+            CELL_TYPE_GUID => "GUID",
+            CELL_TYPE_Currency => "CUR",
+            CELL_TYPE_String | CELL_TYPE_StringWithoutUnit => "STR",
+            CELL_TYPE_Color => "COLOR",
+            _ => return None,
+        };
+        Some(unit)
+    }
 }
 
 // ================== Форматы полей ==================
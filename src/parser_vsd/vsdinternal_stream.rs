@@ -1,6 +1,6 @@
-use cfb::{self, Stream};
 use std::io::{self, Read, Seek, SeekFrom};
-use std::ops::Range;
+
+use super::lz77;
 
 /// Тип для представления потока ввода, аналогичный librevenge::RVNGInputStream
 pub trait RVNGInputStream: Read + Seek {}
@@ -13,89 +13,25 @@ pub struct VSDInternalStream {
 }
 
 impl VSDInternalStream {
-    /// Создает новый VSDInternalStream из входного потока
+    /// Создает новый VSDInternalStream из входного потока.
+    ///
+    /// Читается до `size` байт; короткий поток не ошибка, а повреждённые сжатые данные -
+    /// ошибка `InvalidData` с [`Lz77Error`](super::lz77::Lz77Error) внутри
     pub fn new(input: &mut dyn RVNGInputStream, size: usize, compressed: bool) -> io::Result<Self> {
-        let mut buffer = vec![0; size];
-        let bytes_read = input.read(&mut buffer)?;
+        let mut buffer = Vec::with_capacity(size);
+        input.take(size as u64).read_to_end(&mut buffer)?;
 
-        if bytes_read < 2 {
+        if buffer.len() < 2 {
             return Ok(Self {
                 buffer: Vec::new(),
                 offset: 0,
             });
         }
 
-        if !compressed {
-            buffer.truncate(bytes_read);
-            Ok(Self { buffer, offset: 0 })
-        } else {
-            Self::decompress_buffer(&buffer[..bytes_read])
+        if compressed {
+            buffer = lz77::decompress(&buffer)?;
         }
-    }
-
-    /// Декомпрессия буфера по алгоритму VSD
-    fn decompress_buffer(input: &[u8]) -> io::Result<Self> {
-        let mut output = Vec::new();
-        let mut history = [0u8; 4096];
-        let mut pos = 0;
-        let mut offset = 0;
-
-        while offset < input.len() {
-            let flag = input[offset];
-            offset += 1;
-
-            if offset >= input.len() {
-                break;
-            }
-
-            let mut mask = 1;
-            for _ in 0..8 {
-                if offset >= input.len() {
-                    break;
-                }
-
-                if flag & mask != 0 {
-                    // Просто копируем байт
-                    history[pos % 4096] = input[offset];
-                    output.push(input[offset]);
-                    offset += 1;
-                    pos += 1;
-                } else {
-                    // Копируем из истории
-                    if offset + 1 >= input.len() {
-                        break;
-                    }
-
-                    let addr1 = input[offset] as usize;
-                    let addr2 = input[offset + 1] as usize;
-                    offset += 2;
-
-                    let length = (addr2 & 0x0F) + 3;
-                    let mut pointer = ((addr2 & 0xF0) << 4) | addr1;
-
-                    if pointer > 4078 {
-                        pointer -= 4078;
-                    } else {
-                        pointer += 18;
-                    }
-
-                    for j in 0..length {
-                        let src_pos = (pointer + j) % 4096;
-                        let value = history[src_pos];
-                        history[(pos + j) % 4096] = value;
-                        output.push(value);
-                    }
-                    pos += length;
-                }
-
-                mask <<= 1;
-            }
-        }
-
-        Ok(Self {
-            buffer: output,
-            offset: 0,
-        })
+        Ok(Self { buffer, offset: 0 })
     }
 
     /// Проверяет, достигнут ли конец потока
@@ -153,7 +89,6 @@ impl Seek for VSDInternalStream {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Cursor;
 
     struct MockInputStream {
         data: Vec<u8>,
@@ -192,7 +127,10 @@ mod tests {
     #[test]
     fn test_stream_operations() {
         let data = vec![1, 2, 3, 4, 5];
-        let mut input = Cursor::new(data.clone());
+        let mut input = MockInputStream {
+            data: data.clone(),
+            pos: 0,
+        };
         let mut stream = VSDInternalStream::new(&mut input, data.len(), false).unwrap();
 
         // Test read
@@ -210,4 +148,24 @@ mod tests {
         stream.seek(SeekFrom::End(0)).unwrap();
         assert!(stream.is_end());
     }
+
+    #[test]
+    fn test_compressed_stream() {
+        let data = b"VisioDocument stream ".repeat(40);
+        let compressed = lz77::compress(&data);
+        let mut input = MockInputStream {
+            data: compressed.clone(),
+            pos: 0,
+        };
+        let stream = VSDInternalStream::new(&mut input, compressed.len(), true).unwrap();
+        assert_eq!(stream.buffer, data);
+
+        // Ссылка оборвана после первого байта
+        let mut input = MockInputStream {
+            data: vec![0x00, b'a'],
+            pos: 0,
+        };
+        let err = VSDInternalStream::new(&mut input, 2, true).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
 }
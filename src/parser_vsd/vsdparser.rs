@@ -1,164 +1,184 @@
+use crate::parser_vsd::utils::{
+    charset_codepage, codepage_encoding, ole_date_to_system_time, read_utf16_string,
+    system_time_to_iso8601,
+};
+use crate::parser_vsd::vsd_constants::cell_types::{
+    self, CELL_TYPE_Date, CELL_TYPE_StringWithoutUnit,
+};
 use crate::parser_vsd::vsdinternal_stream::VSDInternalStream;
 
-// use crate::parser_vsd::VSDInternalStream;
-
+use crate::field_format::field_texts;
+use crate::measurement::Unit;
 use crate::parser_vsd::vsd_constants::object_types::{
-    VSD_FONTFACES, VSD_NAME_LIST2, VSD_NAMEIDX, VSD_NAMEIDX123, VSD_OLE_LIST, VSD_PAGE,
-    VSD_SHAPE_GROUP, VSD_SHAPE_SHAPE, VSD_STENCIL_PAGE, VSD_STENCILS, VSD_TRAILER_STREAM,
+    VSD_ANNOTATION, VSD_ARC_TO, VSD_CHAR_IX, VSD_COLORS, VSD_CONNECT_LIST, VSD_CONNECTION_POINTS,
+    VSD_CONNECTION_POINTS_ANOTHER_TYPE, VSD_CUSTOM_PROPS, VSD_ELLIPSE, VSD_ELLIPTICAL_ARC_TO,
+    VSD_FILL_AND_SHADOW, VSD_FONT_IX, VSD_FONTFACE, VSD_FONTFACES, VSD_FOREIGN_DATA,
+    VSD_FOREIGN_DATA_TYPE, VSD_GEOMETRY, VSD_HYPERLINK, VSD_LINE, VSD_LINE_TO, VSD_MOVE_TO,
+    VSD_NAME, VSD_NAME_LIST2, VSD_NAME2, VSD_NAMEIDX, VSD_NAMEIDX123, VSD_NURBS_TO, VSD_OLE_DATA,
+    VSD_OLE_LIST, VSD_PAGE, VSD_PAGE_PROPS, VSD_PAGE_SHEET, VSD_PARA_IX, VSD_POLYLINE_TO,
+    VSD_REVIEWER, VSD_SHAPE_DATA, VSD_SHAPE_FOREIGN, VSD_SHAPE_GROUP, VSD_SHAPE_SHAPE,
+    VSD_SPLINE_KNOT, VSD_SPLINE_START, VSD_STENCIL_PAGE, VSD_STENCILS, VSD_STYLE_SHEET, VSD_TEXT,
+    VSD_TEXT_BLOCK, VSD_TEXT_FIELD, VSD_TEXT_XFORM, VSD_USER_DEFINED_CELLS, VSD_XFORM_1D,
+    VSD_XFORM_DATA,
+};
+use crate::parser_vsdx::comments::annotation_comments;
+use crate::parser_vsdx::read_vsdx::{
+    Cell, CellName, Connect, Diagram, FontFace, ForeignData, Master, Page, Row, Section, Shape,
+    TextRun,
 };
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use std::io::Cursor;
 use std::io::{Read, Seek, SeekFrom};
 
+/// Значение "нет ссылки" для идентификаторов в бинарном формате
+const MINUS_ONE: u32 = 0xffff_ffff;
+/// Сигнатура OLE Compound Document у встроенного объекта
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+/// Место поля в тексте фигуры
+const FIELD_PLACEHOLDER: char = '\u{FFFC}';
+/// Конец постоянной части чанка поля, за ней идут блоки
+const FIELD_BLOCKS_OFFSET: u64 = 0x24;
+/// Заголовок блока поля: длина u32 вместе с заголовком, байт, тип блока, байт
+const FIELD_BLOCK_HEADER: u64 = 7;
+/// Тип блока с номером формата числового поля
+const FIELD_FORMAT_BLOCK: u8 = 2;
+/// Маркер за номером формата; без него формат не задан
+const FIELD_FORMAT_MARKER: u8 = 0x80;
+
 pub struct VSDParser {
     input: Cursor<Vec<u8>>,
+    pages: Vec<Page>,
+    stencils: Vec<Master>,
+    style_sheets: Vec<Shape>,
+    current_page: Option<Page>,
+    current_stencil: Option<Master>,
+    /// Фигуры текущей страницы или мастера вместе с идентификатором родителя
+    current_shapes: Vec<(u32, Shape)>,
+    current_shape: Option<(u32, Shape)>,
+    current_shape_id: u32,
+    is_in_page_sheet: bool,
+    /// Текущая фигура - лист стиля и по завершении уходит в `style_sheets`
+    is_in_style_sheet: bool,
+    /// Таблицы имён `NameList2` по индексу потока: идентификатор имени -> имя
+    name_lists: HashMap<u32, HashMap<u32, String>>,
+    current_name_list: u32,
+    /// Имена из `NameIdx`, ещё не привязанные к списку указателей
+    pending_names: HashMap<u32, String>,
+    /// Имена элементов для каждого обрабатываемого списка указателей: индекс -> имя
+    names_stack: Vec<HashMap<u32, String>>,
+    fonts: Vec<FontFace>,
+    /// Строки из `NameList` (значения текстовых полей и строки формата)
+    names: HashMap<u32, String>,
+    /// Длины отрезков `CharIX` и `ParaIX` текущей фигуры в символах UTF-16
+    current_chars: Vec<u32>,
+    current_paras: Vec<u32>,
+    /// Формулы `POLYLINE`/`NURBS` из чанков `ShapeData` текущей фигуры по идентификатору
+    current_shape_data: HashMap<u32, String>,
+    /// Ссылки строк геометрии на `ShapeData`: секция, строка, ячейка и идентификатор данных
+    current_data_refs: Vec<(usize, usize, &'static str, u32)>,
+    /// Уровень открытого списка соединений: более глубокие чанки - записи `Connect`
+    connect_list_level: Option<u16>,
+    /// Палитра документа из потока `Colors`
+    colours: Vec<String>,
+    /// Изображения и встроенные объекты под именами, как в пакете VSDX
+    media: HashMap<String, Vec<u8>>,
+    /// Строки секции `Reviewer` листа документа: авторы пометок `Annotation`
+    reviewers: Vec<Row>,
 }
 
 impl VSDParser {
     pub fn new(data: Vec<u8>) -> Self {
         Self {
             input: Cursor::new(data),
+            pages: Vec::new(),
+            stencils: Vec::new(),
+            style_sheets: Vec::new(),
+            current_page: None,
+            current_stencil: None,
+            current_shapes: Vec::new(),
+            current_shape: None,
+            current_shape_id: MINUS_ONE,
+            is_in_page_sheet: false,
+            is_in_style_sheet: false,
+            name_lists: HashMap::new(),
+            current_name_list: MINUS_ONE,
+            pending_names: HashMap::new(),
+            names_stack: Vec::new(),
+            fonts: Vec::new(),
+            names: HashMap::new(),
+            current_chars: Vec::new(),
+            current_paras: Vec::new(),
+            current_shape_data: HashMap::new(),
+            current_data_refs: Vec::new(),
+            connect_list_level: None,
+            colours: Vec::new(),
+            media: HashMap::new(),
+            reviewers: Vec::new(),
         }
     }
-    pub fn parse_main(&mut self) -> bool {
-        // if self.input.is_none() {
-        //     return false;
-        // }
 
+    pub fn parse_main(&mut self) -> bool {
         // Seek to trailer stream pointer
-        if let Err(_) = self.input.seek(SeekFrom::Start(0x24)) {
+        if self.input.seek(SeekFrom::Start(0x24)).is_err() {
             return false;
         }
 
-        // let trailer_pointer = match self.read_pointer(self.input) {
-        //     Ok(ptr) => ptr,
-        //     Err(_) => return false,
-        // };
-
         let mut trailer_pointer = Pointer::new();
 
-        let _ = trailer_pointer.read(&mut self.input);
+        if trailer_pointer.read(&mut self.input).is_err() {
+            return false;
+        }
 
         let compressed = (trailer_pointer.format & 2) == 2;
         let shift = if compressed { 4 } else { 0 };
 
-        if let Err(_) = self
+        if self
             .input
             .seek(SeekFrom::Start(trailer_pointer.offset as u64))
+            .is_err()
         {
             return false;
         }
 
-        let mut trailer_stream = VSDInternalStream::new(
+        let mut trailer_stream = match VSDInternalStream::new(
             &mut self.input,
-            trailer_pointer.length.try_into().unwrap(),
+            trailer_pointer.length as usize,
             compressed,
-        )
-        .unwrap();
-
-        let mut group_xforms_sequence: Vec<HashMap<u32, XForm>> = Vec::new();
-        let mut group_memberships_sequence: Vec<HashMap<u32, u32>> = Vec::new();
-        let mut document_page_shape_orders: Vec<Vec<u32>> = Vec::new();
-
-        // First pass - styles collection
-        // let mut styles_collector = VSDStylesCollector::new(
-        //     &mut group_xforms_sequence,
-        //     &mut group_memberships_sequence,
-        //     &mut document_page_shape_orders,
-        // );
-        // self.collector = Some(Box::new(styles_collector));
-
-        println!("VSDParser::parse_main 1st pass");
-        if !self.parse_document(&mut trailer_stream, shift) {
-            return false;
-        }
-
-        // self.handle_level_change(0);
-
-        // let styles = styles_collector.get_style_sheets();
-
-        // // Second pass - content collection
-        // let content_collector = VSDContentCollector::new(
-        //     self.painter,
-        //     group_xforms_sequence,
-        //     group_memberships_sequence,
-        //     document_page_shape_orders,
-        //     styles,
-        //     &self.stencils,
-        // );
-        // self.collector = Some(Box::new(content_collector));
-
-        // if self.container.is_some() {
-        //     self.parse_meta_data();
-        // }
-
-        println!("VSDParser::parse_main 2nd pass");
-        if !self.parse_document(&mut trailer_stream, shift) {
-            return false;
-        }
+        ) {
+            Ok(stream) => stream,
+            Err(_) => return false,
+        };
 
-        true
+        // В libvisio документ проходится дважды (стили, затем содержимое);
+        // модель здесь собирается за один проход
+        log::debug!("VSDParser::parse_main");
+        self.parse_document(&mut trailer_stream, shift)
     }
 
-    fn read_pointer_info<R: Read + Seek>(
-        &mut self,
-        input: &mut R,
-        shift: u32,
-        list_size: &mut u32,
-        pointer_count: &mut u32,
-    ) -> Result<(u32, u32), Box<dyn std::error::Error>> {
-        println!("readPointerInfo",);
-        println!("shift {}", shift);
-        // Переходим к позиции shift
-        let _ = input.seek(SeekFrom::Start(shift as u64))?;
-
-        // Читаем смещение
-        let offset = input.read_u32::<LittleEndian>()?;
-
-        println!("offset {}", offset);
-
-        // Переходим к позиции offset + shift - 4
-        let new_pos = (offset as u64) + (shift as u64) - 4;
-
-        let _ = input.seek(SeekFrom::Start(new_pos))?;
-
-        // Читаем размер списка и количество указателей
-        let list_size_val = match input.read_u32::<LittleEndian>() {
-            Ok(va) => va,
-            Err(_) => 0,
+    /// Собирает разобранные страницы и трафареты в общую модель `Diagram`
+    pub fn into_diagram(self) -> Diagram {
+        let mut diagram = Diagram {
+            comments: annotation_comments(&self.reviewers, &self.pages),
+            pages: self.pages,
+            masters: self.stencils,
+            style_sheets: self.style_sheets,
+            fonts: self.fonts,
+            colors: self.colours,
+            media: self.media,
+            ..Default::default()
         };
-        println!("list_size {}", list_size);
-
-        let pointer_count_val = match input.read_u32::<LittleEndian>() {
-            Ok(va) => va,
-            Err(_) => 0,
-        };
-
-        *list_size = list_size_val;
-        *pointer_count = pointer_count_val;
-
-        println!("pointer_count {}", pointer_count);
-
-        // Пропускаем 4 байта
-        let _ = input.seek(SeekFrom::Current(4));
-
-        Ok((list_size_val, pointer_count_val))
+        resolve_fonts_and_colours(&mut diagram);
+        diagram
     }
 
     pub fn parse_document(&mut self, input: &mut VSDInternalStream, shift: u32) -> bool {
         let mut visited = HashSet::new();
-        match self.handle_streams(input, shift, 0, &mut visited) {
-            Ok(_) => {
-                assert!(visited.is_empty());
-                true
-            }
-            Err(_) => {
-                assert!(visited.is_empty());
-                false
-            }
-        }
+        let res = self.handle_streams(input, shift, 0, &mut visited);
+        // Каждый указатель снимается с пути после обхода, в том числе при ошибке
+        debug_assert!(visited.is_empty());
+        res.is_ok()
     }
 
     fn handle_streams(
@@ -168,56 +188,29 @@ impl VSDParser {
         level: u32,
         visited: &mut HashSet<u32>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        println!("VSDParser::handle_streams");
-
-        let mut pointer_order = Vec::new();
-        let mut ptr_list = HashMap::new();
-        let mut font_faces = HashMap::new();
-        let mut name_list = HashMap::new();
-        let mut name_idx = HashMap::new();
-
-        // Parse out pointers to streams
-        let mut list_size = 0;
-        let mut pointer_count = 0;
-        self.read_pointer_info(input, shift, &mut list_size, &mut pointer_count)?;
-
-        let mut input_cursor: Cursor<Vec<u8>> = Cursor::new(input.buffer.to_owned());
-
-        for i in 0..pointer_count {
-            let mut ptr = Pointer::new();
-            let _ = ptr.read(&mut input_cursor);
-            if ptr.type_name == 0 {
-                continue;
-            }
+        let mut ptr_list = BTreeMap::new();
+        let mut font_faces = BTreeMap::new();
+        let mut name_list = BTreeMap::new();
+        let mut name_idx = BTreeMap::new();
 
+        let list = read_pointer_list(input, shift)?;
+        for (i, ptr) in list.pointers {
             match ptr.type_name as u8 {
                 VSD_FONTFACES => {
-                    println!("font_faces.insert");
-                    font_faces.insert(i as u32, ptr);
+                    font_faces.insert(i, ptr);
                 }
                 VSD_NAME_LIST2 => {
-                    println!("name_list.insert");
-                    name_list.insert(i as u32, ptr);
+                    name_list.insert(i, ptr);
                 }
                 VSD_NAMEIDX | VSD_NAMEIDX123 => {
-                    println!("name_idx.insert");
-                    name_idx.insert(i as u32, ptr);
+                    name_idx.insert(i, ptr);
                 }
                 _ => {
-                    // println!("ptr_list.insert");
-                    ptr_list.insert(i as u32, ptr);
+                    ptr_list.insert(i, ptr);
                 }
             }
         }
 
-        if list_size <= 1 {
-            list_size = 0;
-        }
-
-        for _ in 0..list_size {
-            pointer_order.push(input.read_u32::<byteorder::LittleEndian>());
-        }
-
         // Process the streams in specific order
         for (idx, ptr) in name_list {
             self.handle_stream(ptr, idx, level + 1, visited)?;
@@ -227,17 +220,29 @@ impl VSDParser {
             self.handle_stream(ptr, idx, level + 1, visited)?;
         }
 
+        // Индекс имён относится к элементам того же списка: страницам, мастерам, стилям, фигурам
+        let names = std::mem::take(&mut self.pending_names);
+        self.names_stack.push(names);
+        let res = self.handle_list_streams(font_faces, ptr_list, list.order, level, visited);
+        self.names_stack.pop();
+        res
+    }
+
+    fn handle_list_streams(
+        &mut self,
+        font_faces: BTreeMap<u32, Pointer>,
+        mut ptr_list: BTreeMap<u32, Pointer>,
+        order: Vec<u32>,
+        level: u32,
+        visited: &mut HashSet<u32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         for (idx, ptr) in font_faces {
             self.handle_stream(ptr, idx, level + 1, visited)?;
         }
 
-        if !pointer_order.is_empty() {
-            for j in pointer_order {
-                let j_v = &j.unwrap();
-                // println!("j_v {}", j_v);
-                if let Some(ptr) = ptr_list.remove(&j_v) {
-                    self.handle_stream(ptr, *j_v, level + 1, visited)?;
-                }
+        for j in order {
+            if let Some(ptr) = ptr_list.remove(&j) {
+                self.handle_stream(ptr, j, level + 1, visited)?;
             }
         }
 
@@ -248,6 +253,15 @@ impl VSDParser {
         Ok(())
     }
 
+    /// Имя элемента текущего списка указателей из индекса имён
+    fn element_name(&self, idx: u32) -> String {
+        self.names_stack
+            .last()
+            .and_then(|names| names.get(&idx))
+            .cloned()
+            .unwrap_or_default()
+    }
+
     fn handle_stream(
         &mut self,
         ptr: Pointer,
@@ -255,185 +269,2091 @@ impl VSDParser {
         level: u32,
         visited: &mut HashSet<u32>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        // self.header.level = level;
-        // self.header.id = idx;
-        // self.header.chunk_type = ptr.type;
-        // self.handle_level_change(level);
-
-        // let mut tmp_stencil = VSDStencil::new();
         let compressed = (ptr.format & 2) == 2;
 
-        println!(
-            "VSDParser::handle_stream {} type 0x{} compressed {}",
-            idx, ptr.type_name, compressed
+        log::debug!(
+            "VSDParser::handle_stream {} type 0x{:x} format 0x{:x} compressed {}",
+            idx,
+            ptr.type_name,
+            ptr.format,
+            compressed
         );
 
         self.input.seek(SeekFrom::Start(ptr.offset as u64))?;
-        // let mut tmp_input = VSDInternalStream::new(
-        //     self.input,
-        //     ptr.length,
-        //     compressed
-        // );
+        // Повреждённый сжатый поток пропускается, остальной документ читается дальше
+        let mut tmp_input =
+            match VSDInternalStream::new(&mut self.input, ptr.length as usize, compressed) {
+                Ok(stream) => stream,
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                    log::warn!(
+                        "stream {} type 0x{:x} is skipped: {}",
+                        idx,
+                        ptr.type_name,
+                        err
+                    );
+                    return Ok(());
+                }
+                Err(err) => return Err(err.into()),
+            };
 
-        // self.header.data_length = tmp_input.get_size();
         let shift = if compressed { 4 } else { 0 };
 
-        // // Handle different stream types
+        // Handle different stream types
+        match ptr.type_name as u8 {
+            VSD_PAGE => {
+                self.current_page = Some(Page {
+                    id: idx.to_string(),
+                    name: self.element_name(idx),
+                    background: (ptr.format & 0x1) == 0,
+                    width: 8.5,
+                    height: 11.0,
+                    ..Default::default()
+                });
+            }
+            VSD_STENCIL_PAGE => {
+                self.current_stencil = Some(Master {
+                    id: idx.to_string(),
+                    name: self.element_name(idx),
+                    ..Default::default()
+                });
+            }
+            VSD_SHAPE_GROUP | VSD_SHAPE_SHAPE | VSD_SHAPE_FOREIGN => {
+                self.current_shape_id = idx;
+            }
+            VSD_NAME_LIST2 => self.start_name_list(idx),
+            // Смещение палитры отсчитывается от начала потока вместе со служебными байтами
+            VSD_COLORS => self.read_colours(&tmp_input.buffer),
+            VSD_STENCILS | VSD_OLE_LIST => {}
+            _ => {}
+        }
+
+        // Process the stream content
+        match ptr.format >> 4 {
+            0x4 | 0x5 | 0x0 => {
+                let header = ChunkHeader {
+                    chunk_type: ptr.type_name,
+                    id: idx,
+                    data_length: (tmp_input.buffer.len() as u32).saturating_sub(shift),
+                    level: level as u16,
+                    ..Default::default()
+                };
+                let start = (shift as usize).min(tmp_input.buffer.len());
+                let data = tmp_input.buffer[start..].to_vec();
+                self.handle_chunk(&header, &data);
+
+                if (ptr.format >> 4) == 0x5
+                    && ptr.type_name as u8 != VSD_COLORS
+                    && visited.insert(ptr.offset)
+                {
+                    let res = self.handle_streams(&mut tmp_input, shift, level + 1, visited);
+                    visited.remove(&ptr.offset);
+                    res?;
+                }
+            }
+            0xd | 0xc | 0x8 => {
+                self.handle_chunks(&mut tmp_input, level + 1);
+            }
+            _ => {}
+        }
+
+        // Clean up after processing
         match ptr.type_name as u8 {
-            // VSD_STYLES => self.is_in_styles = true,
-            // VSD_PAGES => if self.extract_stencils { return Ok(()); },
             VSD_PAGE => {
-                print!("Страница")
-                // if self.extract_stencils { return Ok(()); }
-                // self.is_background_page = (ptr.format & 0x1) == 0;
-                // self.name_from_id(&mut self.current_page_name, idx, level + 1);
-                // self.collector.as_mut().unwrap().start_page(idx);
-            }
-            VSD_STENCILS => {
-                print!("VSD_STENCILS")
-                // if self.extract_stencils { return Ok(()); }
-                // if self.stencils.count() > 0 { return Ok(()); }
-                // self.is_stencil_started = true;
+                self.end_shape();
+                self.connect_list_level = None;
+                if let Some(mut page) = self.current_page.take() {
+                    page.shapes = nest_shapes(std::mem::take(&mut self.current_shapes));
+                    self.pages.push(page);
+                }
             }
             VSD_STENCIL_PAGE => {
-                print!("VSD_STENCIL_PAGE")
-                // if self.extract_stencils {
-                //     self.is_background_page = false;
-                //     self.name_from_id(&mut self.current_page_name, idx, level + 1);
-                //     self.collector.as_mut().unwrap().start_page(idx);
-                // } else {
-                //     self.current_stencil = Some(&mut tmp_stencil);
-                // }
-            }
-            // VSD_SHAPE_GROUP | VSD_SHAPE_SHAPE | VSD_SHAPE_FOREIGN => {
-            //     print!("VSD_SHAPE_GROUP | VSD_SHAPE_SHAPE | VSD_SHAPE_FOREIGN")
-            //     // self.current_shape_id = idx;
-            // },
-            VSD_OLE_LIST => {
-                print!("VSD_OLE_LIST")
-                // if self.shape.foreign.is_none() {
-                //     self.shape.foreign = Some(ForeignData::new());
-                // }
-                // self.shape.foreign.as_mut().unwrap().data_id = idx;
+                self.end_shape();
+                if let Some(mut stencil) = self.current_stencil.take() {
+                    stencil.shapes = nest_shapes(std::mem::take(&mut self.current_shapes));
+                    self.stencils.push(stencil);
+                }
+            }
+            VSD_SHAPE_GROUP | VSD_SHAPE_SHAPE | VSD_SHAPE_FOREIGN | VSD_STYLE_SHEET => {
+                self.end_shape();
             }
             _ => {}
         }
 
-        // // Process the stream content
-        // match ptr.format >> 4 {
-        //     0x4 | 0x5 | 0x0 => {
-        //         self.handle_blob(&mut tmp_input, shift, level + 1)?;
-
-        //         if (ptr.format >> 4) == 0x5 && ptr.type != VSD_COLORS {
-        //             if visited.insert(ptr.offset) {
-        //                 match self.handle_streams(&tmp_input, ptr.type, shift, level + 1, visited) {
-        //                     Ok(_) => { visited.remove(&ptr.offset); },
-        //                     Err(e) => {
-        //                         visited.remove(&ptr.offset);
-        //                         return Err(e);
-        //                     }
-        //                 }
-        //             }
-        //         }
-        //     },
-        //     0xD | 0xC | 0x8 => {
-        //         self.handle_chunks(&mut tmp_input, level + 1)?;
-        //     },
-        //     _ => {}
-        // }
-
-        // // Clean up after processing
-        // match ptr.type {
-        //     VSD_STYLES => {
-        //         self.handle_level_change(0);
-        //         self.is_in_styles = false;
-        //     },
-        //     VSD_PAGE => {
-        //         self.handle_level_change(0);
-        //         self.collector.as_mut().unwrap().end_page();
-        //     },
-        //     VSD_PAGES => {
-        //         self.handle_level_change(0);
-        //         self.collector.as_mut().unwrap().end_pages();
-        //     },
-        //     VSD_STENCILS => {
-        //         self.handle_level_change(0);
-        //         if self.extract_stencils {
-        //             self.collector.as_mut().unwrap().end_pages();
-        //         } else {
-        //             self.is_stencil_started = false;
-        //         }
-        //     },
-        //     VSD_STENCIL_PAGE => {
-        //         self.handle_level_change(0);
-        //         if self.extract_stencils {
-        //             self.collector.as_mut().unwrap().end_page();
-        //         } else if let Some(stencil) = self.current_stencil.take() {
-        //             self.stencils.add_stencil(idx, stencil);
-        //         }
-        //     },
-        //     VSD_SHAPE_GROUP | VSD_SHAPE_SHAPE | VSD_SHAPE_FOREIGN => {
-        //         if self.is_stencil_started {
-        //             self.handle_level_change(0);
-        //             if let Some(stencil) = &mut self.current_stencil {
-        //                 stencil.add_stencil_shape(self.shape.shape_id, self.shape.clone());
-        //             }
-        //         }
-        //     },
-        //     _ => {}
-        // }
+        Ok(())
+    }
+
+    fn handle_chunks(&mut self, input: &mut VSDInternalStream, level: u32) {
+        while let Some(mut header) = get_chunk_header(input) {
+            header.level += level as u16;
+            let start = input.offset;
+            let end = (start + header.data_length as usize).min(input.buffer.len());
+            let data = input.buffer[start..end].to_vec();
+
+            self.handle_chunk(&header, &data);
+
+            let next = start + header.data_length as usize + header.trailer as usize;
+            if input.seek(SeekFrom::Start(next as u64)).is_err() || next >= input.buffer.len() {
+                break;
+            }
+        }
+    }
+
+    fn handle_chunk(&mut self, header: &ChunkHeader, data: &[u8]) {
+        let mut input = Cursor::new(data);
+        if let Some(level) = self.connect_list_level {
+            if header.level > level {
+                if let Err(err) = self.read_connect(&mut input) {
+                    log::debug!("connect {} is truncated: {}", header.id, err);
+                }
+                return;
+            }
+            self.connect_list_level = None;
+        }
+
+        let res = match header.chunk_type as u8 {
+            VSD_SHAPE_GROUP | VSD_SHAPE_SHAPE | VSD_SHAPE_FOREIGN => {
+                self.read_shape(header, &mut input)
+            }
+            VSD_PAGE_SHEET => {
+                self.end_shape();
+                self.is_in_page_sheet = true;
+                Ok(())
+            }
+            VSD_STYLE_SHEET => self.read_style_sheet(header, &mut input),
+            VSD_PAGE_PROPS => self.read_page_props(&mut input),
+            VSD_XFORM_DATA => self.read_xform_data(&mut input, ""),
+            VSD_XFORM_1D => self.read_xform_1d(&mut input),
+            VSD_CONNECTION_POINTS | VSD_CONNECTION_POINTS_ANOTHER_TYPE => {
+                self.read_connection_point(&mut input)
+            }
+            VSD_CONNECT_LIST => {
+                self.connect_list_level = Some(header.level);
+                Ok(())
+            }
+            VSD_TEXT_XFORM => self.read_xform_data(&mut input, "Txt"),
+            VSD_LINE => self.read_line(&mut input),
+            VSD_FILL_AND_SHADOW => self.read_fill_and_shadow(&mut input),
+            VSD_GEOMETRY => self.read_geometry(header, &mut input),
+            VSD_MOVE_TO => self.read_geom_row(header, &mut input, "MoveTo", &["X", "Y"]),
+            VSD_LINE_TO => self.read_geom_row(header, &mut input, "LineTo", &["X", "Y"]),
+            VSD_ARC_TO => self.read_geom_row(header, &mut input, "ArcTo", &["X", "Y", "A"]),
+            VSD_ELLIPSE => self.read_geom_row(
+                header,
+                &mut input,
+                "Ellipse",
+                &["X", "Y", "A", "B", "C", "D"],
+            ),
+            VSD_ELLIPTICAL_ARC_TO => self.read_geom_row(
+                header,
+                &mut input,
+                "EllipticalArcTo",
+                &["X", "Y", "A", "B", "C", "D"],
+            ),
+            // Точки полилинии и NURBS лежат в отдельном чанке `ShapeData`
+            VSD_POLYLINE_TO => self.read_data_row(header, &mut input, "PolylineTo", "A"),
+            VSD_NURBS_TO => self.read_data_row(header, &mut input, "NURBSTo", "E"),
+            // Сплайны пока сводятся к отрезку до конечной точки
+            VSD_SPLINE_START | VSD_SPLINE_KNOT => {
+                self.read_geom_row(header, &mut input, "LineTo", &["X", "Y"])
+            }
+            VSD_SHAPE_DATA => self.read_shape_data(header, &mut input),
+            VSD_TEXT => self.read_text(&mut input, data.len()),
+            VSD_FOREIGN_DATA_TYPE => self.read_foreign_data_type(&mut input),
+            VSD_FOREIGN_DATA => {
+                self.read_foreign_data(data);
+                Ok(())
+            }
+            VSD_OLE_DATA => {
+                self.read_ole_data(data);
+                Ok(())
+            }
+            VSD_NAME_LIST2 => {
+                self.start_name_list(header.id);
+                Ok(())
+            }
+            VSD_NAME2 => self.read_name2(header, &mut input),
+            VSD_NAME => {
+                self.read_name(header, data);
+                Ok(())
+            }
+            VSD_CHAR_IX => self.read_char_ix(&mut input),
+            VSD_PARA_IX => self.read_para_ix(&mut input),
+            VSD_TEXT_BLOCK => self.read_text_block(&mut input),
+            VSD_USER_DEFINED_CELLS => self.read_user_cell(header, &mut input),
+            VSD_CUSTOM_PROPS => self.read_custom_prop(header, &mut input),
+            VSD_HYPERLINK => self.read_hyperlink(header, &mut input),
+            VSD_REVIEWER => self.read_reviewer(&mut input),
+            VSD_ANNOTATION => self.read_annotation(&mut input),
+            VSD_TEXT_FIELD => self.read_text_field(header, &mut input),
+            VSD_FONTFACE => self.read_font(header, &mut input, true),
+            VSD_FONT_IX => self.read_font(header, &mut input, false),
+            VSD_NAMEIDX => self.read_name_idx(&mut input, true),
+            VSD_NAMEIDX123 => self.read_name_idx(&mut input, false),
+            _ => Ok(()),
+        };
+
+        if let Err(err) = res {
+            log::debug!(
+                "chunk 0x{:x} id {} is truncated: {}",
+                header.chunk_type,
+                header.id,
+                err
+            );
+        }
+    }
+
+    fn end_shape(&mut self) {
+        let chars = std::mem::take(&mut self.current_chars);
+        let paras = std::mem::take(&mut self.current_paras);
+        let shape_data = std::mem::take(&mut self.current_shape_data);
+        let data_refs = std::mem::take(&mut self.current_data_refs);
+        if let Some(mut shape) = self.current_shape.take() {
+            let fields: Vec<String> = field_texts(&shape.1)
+                .into_iter()
+                .map(|(_, text)| text)
+                .collect();
+            finish_text(&mut shape.1, &chars, &paras, &fields);
+            for (section, row, name, id) in data_refs {
+                let row = shape
+                    .1
+                    .sections
+                    .get_mut(section)
+                    .and_then(|s| s.rows.get_mut(row));
+                if let (Some(row), Some(formula)) = (row, shape_data.get(&id)) {
+                    row.cells.insert(
+                        name.into(),
+                        Cell {
+                            value: formula.clone(),
+                            unit: None,
+                            formula: Some(formula.clone()),
+                        },
+                    );
+                }
+            }
+            if self.is_in_style_sheet {
+                self.style_sheets.push(shape.1);
+            } else {
+                self.current_shapes.push(shape);
+            }
+        }
+        self.is_in_style_sheet = false;
+    }
+
+    /// Лист стиля: ячейки читаются так же, как у фигуры, затем ссылки на родительские стили
+    fn read_style_sheet(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        self.end_shape();
+        self.is_in_page_sheet = false;
+        self.is_in_style_sheet = true;
+        let mut style = Shape {
+            id: header.id.to_string(),
+            name: self.element_name(header.id),
+            ..Default::default()
+        };
+        self.current_shape = Some((0, style.clone()));
+
+        input.seek(SeekFrom::Current(0x22))?;
+        let line_style = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let fill_style = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let text_style = input.read_u32::<LittleEndian>()?;
+
+        style.line_style = optional_id(line_style);
+        style.fill_style = optional_id(fill_style);
+        style.text_style = optional_id(text_style);
+        self.current_shape = Some((0, style));
+        Ok(())
+    }
+
+    /// Палитра: число цветов в байте 6, байт пропуска, затем RGBA на цвет
+    fn read_colours(&mut self, data: &[u8]) {
+        let (Some(&count), Some(entries)) = (data.get(6), data.get(8..)) else {
+            return;
+        };
+        self.colours = entries
+            .chunks_exact(4)
+            .take(count as usize)
+            .map(|c| colour_value([c[0], c[1], c[2], c[3]]))
+            .collect();
+    }
+
+    /// Шрифт: восемь байт заголовка (набор символов в третьем байте, флаги в последних
+    /// четырёх), затем имя до 32 символов - UTF-16 в `FontFace`, байтовое в `FontIX`
+    fn read_font(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+        unicode: bool,
+    ) -> std::io::Result<()> {
+        input.seek(SeekFrom::Current(2))?;
+        let charset = input.read_u8()?;
+        input.seek(SeekFrom::Current(1))?;
+        let flags = input.read_u32::<LittleEndian>()?;
+
+        let rest = &input.get_ref()[input.position() as usize..];
+        let name = if unicode {
+            let len = rest
+                .chunks_exact(2)
+                .take(32)
+                .position(|c| c == [0, 0])
+                .unwrap_or((rest.len() / 2).min(32));
+            read_utf16_string(input, len).map_err(|err| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+            })?
+        } else {
+            let bytes = &rest[..rest.len().min(32)];
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            let encoding = codepage_encoding(charset_codepage(charset));
+            encoding.decode(&bytes[..end]).0.into_owned()
+        };
+
+        let font = FontFace {
+            id: header.id.to_string(),
+            name,
+            charset,
+            flags,
+        };
+        match self.fonts.iter_mut().find(|f| f.id == font.id) {
+            Some(existing) => *existing = font,
+            None => self.fonts.push(font),
+        }
+        Ok(())
+    }
+
+    fn start_name_list(&mut self, id: u32) {
+        self.current_name_list = id;
+        self.name_lists.insert(id, HashMap::new());
+    }
+
+    /// Имя в `NameList2`: четыре байта (всегда 1), затем строка UTF-16 до нуля
+    fn read_name2(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        input.seek(SeekFrom::Current(4))?;
+        let rest = &input.get_ref()[input.position() as usize..];
+        let len = rest
+            .chunks_exact(2)
+            .position(|c| c == [0, 0])
+            .unwrap_or(rest.len() / 2);
+        let name = read_utf16_string(input, len)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+        self.name_lists
+            .entry(self.current_name_list)
+            .or_default()
+            .insert(header.id, name);
+        Ok(())
+    }
+
+    /// Индекс имён: записи (список имён, имя, элемент) и байт флагов. В `NameIdx`
+    /// поля 32-битные, в `NameIdx123` - 16-битные
+    fn read_name_idx(&mut self, input: &mut Cursor<&[u8]>, wide: bool) -> std::io::Result<()> {
+        let count = input.read_u32::<LittleEndian>()?;
+        let read_field = |input: &mut Cursor<&[u8]>| -> std::io::Result<u32> {
+            if wide {
+                input.read_u32::<LittleEndian>()
+            } else {
+                input.read_u16::<LittleEndian>().map(u32::from)
+            }
+        };
+        for _ in 0..count {
+            let list_id = read_field(input)?;
+            let name_id = read_field(input)?;
+            let element_id = read_field(input)?;
+            input.read_u8()?;
+            if let Some(name) = self
+                .name_lists
+                .get(&list_id)
+                .and_then(|names| names.get(&name_id))
+            {
+                self.pending_names.insert(element_id, name.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn read_shape(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        self.end_shape();
+        self.is_in_page_sheet = false;
+
+        let id = if self.current_shape_id != MINUS_ONE {
+            self.current_shape_id
+        } else {
+            header.id
+        };
+        self.current_shape_id = MINUS_ONE;
+
+        let mut shape = Shape {
+            id: id.to_string(),
+            name: self.element_name(id),
+            shape_type: match header.chunk_type as u8 {
+                VSD_SHAPE_GROUP => "Group",
+                VSD_SHAPE_FOREIGN => "Foreign",
+                _ => "Shape",
+            }
+            .to_string(),
+            ..Default::default()
+        };
+        self.current_shape = Some((0, shape.clone()));
+
+        input.seek(SeekFrom::Current(10))?;
+        let parent = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let master_page = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let master_shape = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let fill_style = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let line_style = input.read_u32::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(4))?;
+        let text_style = input.read_u32::<LittleEndian>()?;
+
+        shape.master = optional_id(master_page);
+        shape.master_shape = optional_id(master_shape);
+        shape.fill_style = optional_id(fill_style);
+        shape.line_style = optional_id(line_style);
+        shape.text_style = optional_id(text_style);
+
+        let parent = if parent == MINUS_ONE { 0 } else { parent };
+        self.current_shape = Some((parent, shape));
+        Ok(())
+    }
+
+    /// Ячейки текущей фигуры, либо листа страницы, если разбирается PageSheet
+    fn current_cells(&mut self) -> Option<&mut HashMap<CellName, Cell>> {
+        if self.is_in_page_sheet {
+            return self.current_page.as_mut().map(|page| &mut page.cells);
+        }
+        self.current_shape
+            .as_mut()
+            .map(|(_, shape)| &mut shape.cells)
+    }
+
+    fn set_cells(&mut self, values: Vec<(String, Cell)>) {
+        if let Some(cells) = self.current_cells() {
+            for (name, value) in values {
+                cells.insert(name.into(), value);
+            }
+        }
+    }
+
+    fn read_page_props(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let width = read_cell(input)?;
+        let height = read_cell(input)?;
+        let shadow_x = read_cell(input)?;
+        let mut shadow_y = read_cell(input)?;
+        shadow_y.value = (-shadow_y.number().unwrap_or_default()).to_string();
+
+        if let Some(page) = self.current_page.as_mut() {
+            page.width = width.number().unwrap_or_default();
+            page.height = height.number().unwrap_or_default();
+        }
+
+        self.set_cells(vec![
+            ("PageWidth".to_string(), width),
+            ("PageHeight".to_string(), height),
+            ("ShdwOffsetX".to_string(), shadow_x),
+            ("ShdwOffsetY".to_string(), shadow_y),
+        ]);
+        Ok(())
+    }
+
+    fn read_xform_data(&mut self, input: &mut Cursor<&[u8]>, prefix: &str) -> std::io::Result<()> {
+        let mut values = Vec::new();
+        for name in [
+            "PinX", "PinY", "Width", "Height", "LocPinX", "LocPinY", "Angle",
+        ] {
+            values.push((format!("{prefix}{name}"), read_cell(input)?));
+        }
+
+        if prefix.is_empty() {
+            let flip_x = input.read_u8().map(|v| v != 0).unwrap_or(false);
+            let flip_y = input.read_u8().map(|v| v != 0).unwrap_or(false);
+            values.push(("FlipX".to_string(), cell(bool_value(flip_x))));
+            values.push(("FlipY".to_string(), cell(bool_value(flip_y))));
+        }
+
+        self.set_cells(values);
+        Ok(())
+    }
+
+    /// Концы одномерной фигуры (соединителя) в координатах родителя
+    fn read_xform_1d(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let mut values = Vec::new();
+        for name in ["BeginX", "BeginY", "EndX", "EndY"] {
+            values.push((name.to_string(), read_cell(input)?));
+        }
+        self.set_cells(values);
+        Ok(())
+    }
+
+    /// Точка соединения: координаты, направление и тип (0 - входящая, 1 - исходящая, 2 - обе)
+    fn read_connection_point(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let mut cells = Vec::new();
+        for name in ["X", "Y", "DirX", "DirY"] {
+            cells.push((name, read_cell(input)?));
+        }
+        cells.push(("Type", cell(input.read_u8()?.to_string())));
+        self.push_named_row("Connection", None, cells);
+        Ok(())
+    }
+
+    /// Запись списка соединений страницы: соединитель и фигура (по 32 бита), затем
+    /// части обеих фигур (по 16 бит) в тех же кодах, что `FromPart` и `ToPart` в VSDX
+    fn read_connect(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let from_sheet = input.read_u32::<LittleEndian>()?;
+        let to_sheet = input.read_u32::<LittleEndian>()?;
+        let from_part = input.read_u16::<LittleEndian>()?;
+        let to_part = input.read_u16::<LittleEndian>()?;
+
+        if let Some(page) = self.current_page.as_mut() {
+            page.connects.push(Connect {
+                from_sheet: from_sheet.to_string(),
+                from_cell: from_part_cell(from_part),
+                from_part: Some(from_part.to_string()),
+                to_sheet: to_sheet.to_string(),
+                to_cell: to_part_cell(to_part),
+                to_part: Some(to_part.to_string()),
+            });
+        }
+        Ok(())
+    }
+
+    fn read_line(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let stroke_width = read_cell(input)?;
+        input.seek(SeekFrom::Current(1))?;
+        let color = read_colour(input)?;
+        let line_pattern = input.read_u8()?;
+        let rounding = read_cell(input)?;
+
+        let mut values = vec![
+            ("LineWeight".to_string(), stroke_width),
+            ("LineColor".to_string(), cell(colour_value(color))),
+            (
+                "LineColorTrans".to_string(),
+                cell((color[3] as f64 / 255.0).to_string()),
+            ),
+            ("LinePattern".to_string(), cell(line_pattern.to_string())),
+            ("Rounding".to_string(), rounding),
+        ];
+
+        if input.seek(SeekFrom::Current(1)).is_ok()
+            && let (Ok(begin), Ok(end), Ok(cap)) =
+                (input.read_u8(), input.read_u8(), input.read_u8())
+        {
+            values.push(("BeginArrow".to_string(), cell(begin.to_string())));
+            values.push(("EndArrow".to_string(), cell(end.to_string())));
+            values.push(("LineCap".to_string(), cell(cap.to_string())));
+        }
+
+        self.set_cells(values);
+        Ok(())
+    }
+
+    fn read_fill_and_shadow(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        input.seek(SeekFrom::Current(1))?;
+        let fg = read_colour(input)?;
+        input.seek(SeekFrom::Current(1))?;
+        let bg = read_colour(input)?;
+        let fill_pattern = input.read_u8()?;
+
+        self.set_cells(vec![
+            ("FillForegnd".to_string(), cell(colour_value(fg))),
+            (
+                "FillForegndTrans".to_string(),
+                cell((fg[3] as f64 / 255.0).to_string()),
+            ),
+            ("FillBkgnd".to_string(), cell(colour_value(bg))),
+            (
+                "FillBkgndTrans".to_string(),
+                cell((bg[3] as f64 / 255.0).to_string()),
+            ),
+            ("FillPattern".to_string(), cell(fill_pattern.to_string())),
+        ]);
+        Ok(())
+    }
+
+    fn read_geometry(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        let flags = input.read_u8().unwrap_or(0);
+
+        let Some((_, shape)) = self.current_shape.as_mut() else {
+            return Ok(());
+        };
+
+        let index = shape
+            .sections
+            .iter()
+            .filter(|s| s.name == "Geometry")
+            .count();
+        shape.sections.push(Section {
+            name: "Geometry".to_string(),
+            index: Some(index.to_string()),
+            cells: HashMap::from([
+                ("NoFill".into(), cell(bool_value(flags & 1 != 0))),
+                ("NoLine".into(), cell(bool_value(flags & 2 != 0))),
+                ("NoShow".into(), cell(bool_value(flags & 4 != 0))),
+            ]),
+            rows: vec![],
+        });
+        log::debug!("geometry {} of shape {}", header.id, shape.id);
+        Ok(())
+    }
+
+    /// Строка геометрии: каждая ячейка хранится как байт-признак и double
+    fn read_geom_row(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+        row_type: &str,
+        names: &[&str],
+    ) -> std::io::Result<()> {
+        let mut cells = HashMap::new();
+        for name in names {
+            cells.insert((*name).into(), read_cell(input)?);
+        }
+
+        let Some((_, shape)) = self.current_shape.as_mut() else {
+            return Ok(());
+        };
+        if !shape.sections.iter().any(|s| s.name == "Geometry") {
+            shape.sections.push(Section {
+                name: "Geometry".to_string(),
+                index: Some("0".to_string()),
+                ..Default::default()
+            });
+        }
+        if let Some(section) = shape
+            .sections
+            .iter_mut()
+            .rev()
+            .find(|s| s.name == "Geometry")
+        {
+            section.rows.push(Row {
+                index: Some(header.id.to_string()),
+                row_type: Some(row_type.to_string()),
+                cells,
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+
+    /// Строка полилинии или NURBS: конечная точка и, после маркера 0x8b, ссылка на `ShapeData`
+    fn read_data_row(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+        row_type: &str,
+        data_cell: &'static str,
+    ) -> std::io::Result<()> {
+        self.read_geom_row(header, input, row_type, &["X", "Y"])?;
+        if input.read_u8().ok() != Some(0x8b) {
+            return Ok(());
+        }
+        input.seek(SeekFrom::Current(3))?;
+        let data_id = input.read_u32::<LittleEndian>()?;
 
+        if let Some((_, shape)) = self.current_shape.as_ref()
+            && let Some(section) = shape.sections.iter().rposition(|s| s.name == "Geometry")
+            && let Some(row) = shape.sections[section].rows.len().checked_sub(1)
+        {
+            self.current_data_refs
+                .push((section, row, data_cell, data_id));
+        }
         Ok(())
     }
+
+    /// Данные геометрии: 0x80 - точки полилинии, 0x82 - точки, узлы и веса NURBS.
+    /// Тип координаты 0 означает долю ширины или высоты фигуры
+    fn read_shape_data(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        let data_type = input.read_u8()?;
+        input.seek(SeekFrom::Current(15))?;
+        let formula = match data_type {
+            0x80 => {
+                let x_type = input.read_u8()?;
+                let y_type = input.read_u8()?;
+                let count = input.read_u32::<LittleEndian>()?;
+                let mut args = vec![x_type.to_string(), y_type.to_string()];
+                for _ in 0..count {
+                    args.push(input.read_f64::<LittleEndian>()?.to_string());
+                    args.push(input.read_f64::<LittleEndian>()?.to_string());
+                }
+                format!("POLYLINE({})", args.join(", "))
+            }
+            0x82 => {
+                let last_knot = input.read_f64::<LittleEndian>()?;
+                let degree = input.read_u16::<LittleEndian>()?;
+                let x_type = input.read_u8()?;
+                let y_type = input.read_u8()?;
+                let count = input.read_u32::<LittleEndian>()?;
+                let mut args = vec![
+                    last_knot.to_string(),
+                    degree.to_string(),
+                    x_type.to_string(),
+                    y_type.to_string(),
+                ];
+                for _ in 0..count {
+                    for _ in 0..4 {
+                        args.push(input.read_f64::<LittleEndian>()?.to_string());
+                    }
+                }
+                format!("NURBS({})", args.join(", "))
+            }
+            _ => return Ok(()),
+        };
+        self.current_shape_data.insert(header.id, formula);
+        Ok(())
+    }
+
+    /// Тип внешних данных: 0 и 4 - метафайл, 1 - растр (формат во втором поле), 2 - объект OLE
+    fn read_foreign_data_type(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        input.seek(SeekFrom::Current(0x24))?;
+        let foreign_type = input.read_u16::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(0xb))?;
+        let format = input.read_u32::<LittleEndian>()?;
+
+        let (foreign_type, compression) = match (foreign_type, format) {
+            (1, 1) => ("Bitmap", Some("JPEG")),
+            (1, 2) => ("Bitmap", Some("GIF")),
+            (1, 3) => ("Bitmap", Some("TIFF")),
+            (1, 4) => ("Bitmap", Some("PNG")),
+            (1, _) => ("Bitmap", None),
+            (2, _) => ("Object", None),
+            _ => ("EnhMetaFile", None),
+        };
+        if let Some((_, shape)) = self.current_shape.as_mut() {
+            let foreign = shape.foreign.get_or_insert_with(ForeignData::default);
+            foreign.foreign_type = foreign_type.to_string();
+            foreign.compression = compression.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    /// Данные изображения или предпросмотра объекта OLE
+    fn read_foreign_data(&mut self, data: &[u8]) {
+        if data.starts_with(OLE_MAGIC) {
+            self.read_ole_data(data);
+            return;
+        }
+        let Some((_, shape)) = self.current_shape.as_mut() else {
+            return;
+        };
+        let foreign = shape.foreign.get_or_insert_with(|| ForeignData {
+            foreign_type: "EnhMetaFile".to_string(),
+            ..Default::default()
+        });
+
+        let (extension, data) = match (
+            foreign.foreign_type.as_str(),
+            foreign.compression.as_deref(),
+        ) {
+            ("Bitmap", Some("JPEG")) => ("jpeg", data.to_vec()),
+            ("Bitmap", Some("GIF")) => ("gif", data.to_vec()),
+            ("Bitmap", Some("TIFF")) => ("tiff", data.to_vec()),
+            ("Bitmap", Some("PNG")) => ("png", data.to_vec()),
+            ("Bitmap", _) => ("bmp", bitmap_file(data)),
+            // Метафайлы различаются по сигнатуре ` EMF` в заголовке
+            _ if data.len() > 44 && &data[40..44] == b" EMF" => ("emf", data.to_vec()),
+            _ => ("wmf", data.to_vec()),
+        };
+        if foreign.foreign_type != "Object" && foreign.foreign_type != "Bitmap" {
+            foreign.foreign_type = if extension == "emf" {
+                "EnhMetaFile"
+            } else {
+                "MetaFile"
+            }
+            .to_string();
+        }
+
+        let images = self
+            .media
+            .keys()
+            .filter(|name| name.starts_with("visio/media/"))
+            .count();
+        let name = format!("visio/media/image{}.{}", images + 1, extension);
+        foreign.target = Some(name.clone());
+        self.media.insert(name, data);
+    }
+
+    /// Хранилище встроенного объекта; длинный объект записан несколькими чанками подряд
+    fn read_ole_data(&mut self, data: &[u8]) {
+        let Some((_, shape)) = self.current_shape.as_mut() else {
+            log::debug!("OLE data outside of a shape");
+            return;
+        };
+        let foreign = shape.foreign.get_or_insert_with(|| ForeignData {
+            foreign_type: "Object".to_string(),
+            ..Default::default()
+        });
+        match &foreign.object_target {
+            Some(name) => self
+                .media
+                .entry(name.clone())
+                .or_default()
+                .extend_from_slice(data),
+            None => {
+                let objects = self
+                    .media
+                    .keys()
+                    .filter(|name| name.starts_with("visio/embeddings/"))
+                    .count();
+                let name = format!("visio/embeddings/oleObject{}.bin", objects + 1);
+                foreign.object_target = Some(name.clone());
+                self.media.insert(name, data.to_vec());
+            }
+        }
+    }
+
+    fn read_text(&mut self, input: &mut Cursor<&[u8]>, length: usize) -> std::io::Result<()> {
+        input.seek(SeekFrom::Current(8))?;
+        let mut buf = vec![0u8; length.saturating_sub(8)];
+        input.read_exact(&mut buf)?;
+
+        let text = String::from_utf16_lossy(
+            &buf.chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>(),
+        );
+
+        if let Some((_, shape)) = self.current_shape.as_mut() {
+            shape.text = Some(text.trim_end_matches('\0').to_string());
+        }
+        Ok(())
+    }
+
+    /// Строка таблицы `NameList`: весь чанк - UTF-16
+    fn read_name(&mut self, header: &ChunkHeader, data: &[u8]) {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        self.names
+            .insert(header.id, String::from_utf16_lossy(&units));
+    }
+
+    /// Добавляет строку в секцию текущей фигуры, листа стиля или листа страницы
+    fn push_row(&mut self, name: &str, values: Vec<(&str, String)>) {
+        let cells = values
+            .into_iter()
+            .map(|(name, value)| (name, cell(value)))
+            .collect();
+        self.push_named_row(name, None, cells);
+    }
+
+    fn push_named_row(&mut self, name: &str, row_name: Option<String>, cells: Vec<(&str, Cell)>) {
+        let sections = if self.is_in_page_sheet {
+            self.current_page.as_mut().map(|page| &mut page.sections)
+        } else {
+            self.current_shape
+                .as_mut()
+                .map(|(_, shape)| &mut shape.sections)
+        };
+        let Some(sections) = sections else {
+            return;
+        };
+        let section = match sections.iter().position(|s| s.name == name) {
+            Some(i) => &mut sections[i],
+            None => {
+                sections.push(Section {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                sections.last_mut().expect("section was just pushed")
+            }
+        };
+        section.rows.push(Row {
+            index: Some(section.rows.len().to_string()),
+            name: row_name,
+            cells: cells
+                .into_iter()
+                .map(|(name, cell)| (name.into(), cell))
+                .collect(),
+            ..Default::default()
+        });
+    }
+
+    /// Типизированное значение ячейки: байт типа из `cell_types` и восемь байт данных.
+    /// Строки и GUID хранятся в таблице имён, данные - номер строки; остальное - double
+    fn read_typed_value(&self, input: &mut Cursor<&[u8]>) -> std::io::Result<Cell> {
+        let cell_type = input.read_u8()?;
+        let mut data = [0u8; 8];
+        input.read_exact(&mut data)?;
+        let unit = Unit::from_cell_type(cell_type).code().map(str::to_string);
+
+        let value = if cell_types::is_name_reference(cell_type) {
+            let id = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+            self.names.get(&id).cloned().unwrap_or_default()
+        } else if cell_type == CELL_TYPE_Date {
+            ole_date_to_system_time(f64::from_le_bytes(data))
+                .map(system_time_to_iso8601)
+                .unwrap_or_default()
+        } else {
+            f64::from_le_bytes(data).to_string()
+        };
+        Ok(Cell {
+            value,
+            unit,
+            formula: None,
+        })
+    }
+
+    /// Имя строки из индекса имён списка или имя по умолчанию, как в Visio
+    fn row_name(&self, header: &ChunkHeader, section: &str) -> String {
+        let name = self.element_name(header.id);
+        if !name.is_empty() {
+            return name;
+        }
+        let count = self
+            .current_shape
+            .as_ref()
+            .and_then(|(_, shape)| shape.sections.iter().find(|s| s.name == section))
+            .map_or(0, |s| s.rows.len());
+        format!("Row_{}", count + 1)
+    }
+
+    /// Пользовательская ячейка: значение и подсказка
+    fn read_user_cell(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        let value = self.read_typed_value(input)?;
+        let prompt = self.read_typed_value(input)?;
+        let name = self.row_name(header, "User");
+        self.push_named_row(
+            "User",
+            Some(name),
+            vec![("Value", value), ("Prompt", prompt)],
+        );
+        Ok(())
+    }
+
+    /// Данные фигуры: значение, подсказка, метка, формат, ключ сортировки и флаги
+    fn read_custom_prop(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        let mut cells = Vec::new();
+        for name in ["Value", "Prompt", "Label", "Format", "SortKey"] {
+            cells.push((name, self.read_typed_value(input)?));
+        }
+        for name in ["Type", "Invisible", "Verify"] {
+            cells.push((name, cell(input.read_u8()?.to_string())));
+        }
+        cells.push((
+            "LangID",
+            cell(input.read_u32::<LittleEndian>()?.to_string()),
+        ));
+        cells.push(("Calendar", cell(input.read_u8()?.to_string())));
+
+        let name = self.row_name(header, "Property");
+        self.push_named_row("Property", Some(name), cells);
+        Ok(())
+    }
+
+    /// Гиперссылка: строки описания, адреса, подадреса, параметров и кадра, затем флаги
+    fn read_hyperlink(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        let mut cells = Vec::new();
+        for name in ["Description", "Address", "SubAddress", "ExtraInfo", "Frame"] {
+            let mut value = self.read_typed_value(input)?;
+            value.unit = None;
+            cells.push((name, value));
+        }
+        for name in ["NewWindow", "Default", "Invisible"] {
+            cells.push((name, cell(bool_value(input.read_u8()? != 0))));
+        }
+
+        let name = self.row_name(header, "Hyperlink");
+        self.push_named_row("Hyperlink", Some(name), cells);
+        Ok(())
+    }
+
+    /// Рецензент: имя и инициалы, цвет пометок, идентификатор и номер последней пометки
+    fn read_reviewer(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let mut cells: HashMap<CellName, Cell> = HashMap::new();
+        for name in ["Name", "Initials"] {
+            let mut value = self.read_typed_value(input)?;
+            value.unit = None;
+            cells.insert(name.into(), value);
+        }
+        cells.insert("Color".into(), cell(colour_value(read_colour(input)?)));
+        for name in ["ReviewerID", "CurrentIndex"] {
+            cells.insert(
+                name.into(),
+                cell(input.read_u32::<LittleEndian>()?.to_string()),
+            );
+        }
+        self.reviewers.push(Row {
+            index: Some(self.reviewers.len().to_string()),
+            cells,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Пометка рецензента на странице: положение, рецензент, номер, дата, текст и язык
+    fn read_annotation(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let x = read_cell(input)?;
+        let y = read_cell(input)?;
+        let reviewer = input.read_u32::<LittleEndian>()?;
+        let marker = input.read_u32::<LittleEndian>()?;
+        let date = self.read_typed_value(input)?;
+        let mut comment = self.read_typed_value(input)?;
+        comment.unit = None;
+        let lang = input.read_u32::<LittleEndian>()?;
+        self.push_named_row(
+            "Annotation",
+            None,
+            vec![
+                ("X", x),
+                ("Y", y),
+                ("ReviewerID", cell(reviewer.to_string())),
+                ("MarkerIndex", cell(marker.to_string())),
+                ("Date", date),
+                ("Comment", comment),
+                ("LangID", cell(lang.to_string())),
+            ],
+        );
+        Ok(())
+    }
+
+    /// Формат символов: длина отрезка, шрифт, цвет, начертание и размер
+    fn read_char_ix(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let count = input.read_u32::<LittleEndian>()?;
+        let font = input.read_u16::<LittleEndian>()?;
+        input.seek(SeekFrom::Current(1))?;
+        let colour = read_colour(input)?;
+        let style = input.read_u8()? & 0x0f;
+        let case = input.read_u8()?;
+        let pos = input.read_u8()?;
+        input.seek(SeekFrom::Current(4))?;
+        let size = input.read_f64::<LittleEndian>()?;
+        let lines = input.read_u8().unwrap_or(0);
+
+        // Флаги регистра (все прописные, каждое слово) и положения (верхний, нижний индекс)
+        let choice = |flags: u8| match flags {
+            f if f & 1 != 0 => 1,
+            f if f & 2 != 0 => 2,
+            _ => 0,
+        };
+        let (case, pos) = (choice(case), choice(pos));
+        self.current_chars.push(count);
+        self.push_row(
+            "Character",
+            vec![
+                ("Font", font.to_string()),
+                ("Color", colour_value(colour)),
+                ("ColorTrans", (colour[3] as f64 / 255.0).to_string()),
+                ("Style", style.to_string()),
+                ("Case", case.to_string()),
+                ("Pos", pos.to_string()),
+                ("Size", size.to_string()),
+                ("DblUnderline", bool_value(lines & 0x01 != 0)),
+                ("Strikethru", bool_value(lines & 0x04 != 0)),
+                ("DoubleStrikethrough", bool_value(lines & 0x20 != 0)),
+            ],
+        );
+        Ok(())
+    }
+
+    /// Формат абзаца: длина отрезка, отступы, интервалы, выравнивание и маркер
+    fn read_para_ix(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let count = input.read_u32::<LittleEndian>()?;
+        let mut cells = Vec::new();
+        for name in [
+            "IndFirst", "IndLeft", "IndRight", "SpLine", "SpBefore", "SpAfter",
+        ] {
+            cells.push((name, read_cell(input)?));
+        }
+        cells.push(("HorzAlign", cell(input.read_u8()?.to_string())));
+        cells.push(("Bullet", cell(input.read_u8()?.to_string())));
+
+        self.current_paras.push(count);
+        self.push_named_row("Paragraph", None, cells);
+        Ok(())
+    }
+
+    /// Блок текста: поля, вертикальное выравнивание и фон
+    fn read_text_block(&mut self, input: &mut Cursor<&[u8]>) -> std::io::Result<()> {
+        let mut values = Vec::new();
+        for name in ["LeftMargin", "RightMargin", "TopMargin", "BottomMargin"] {
+            values.push((name.to_string(), read_cell(input)?));
+        }
+        values.push((
+            "VerticalAlign".to_string(),
+            cell(input.read_u8()?.to_string()),
+        ));
+        let filled = input.read_u8()? != 0;
+        let colour = read_colour(input)?;
+        let background = if filled {
+            colour_value(colour)
+        } else {
+            "0".to_string()
+        };
+        values.push(("TextBkgnd".to_string(), cell(background)));
+        self.set_cells(values);
+        Ok(())
+    }
+
+    /// Поле текста. Постоянная часть чанка: 7 байт, код единиц, затем ссылка на строку
+    /// (id имени, 6 байт, id формата) или число double (дата - тоже число); до
+    /// `FIELD_BLOCKS_OFFSET` - неизвестные данные, дальше блоки с форматом числа.
+    /// Отображаемый текст поля получается из строки `Field` при завершении фигуры
+    fn read_text_field(
+        &mut self,
+        header: &ChunkHeader,
+        input: &mut Cursor<&[u8]>,
+    ) -> std::io::Result<()> {
+        input.seek(SeekFrom::Current(7))?;
+        let code = input.read_u8()?;
+        let unit = Unit::from_cell_type(code).code().map(str::to_string);
+
+        let (value, field_type, format) = if code == CELL_TYPE_StringWithoutUnit {
+            let name_id = input.read_u32::<LittleEndian>()?;
+            input.seek(SeekFrom::Current(6))?;
+            let format_id = input.read_u32::<LittleEndian>()?;
+            let text = self.names.get(&name_id).cloned().unwrap_or_default();
+            let format = cell(self.names.get(&format_id).cloned().unwrap_or_default());
+            (text, 0, format)
+        } else {
+            let number = input.read_f64::<LittleEndian>()?;
+            let format = match read_field_format(input, header) {
+                Some(code) => Cell {
+                    value: code.to_string(),
+                    unit: None,
+                    formula: Some(format!("FIELDPICTURE({code})")),
+                },
+                None => cell(String::new()),
+            };
+            let field_type = if code == CELL_TYPE_Date { 5 } else { 2 };
+            (number.to_string(), field_type, format)
+        };
+
+        let value = Cell {
+            value,
+            unit,
+            formula: None,
+        };
+        self.push_named_row(
+            "Field",
+            None,
+            vec![
+                ("Value", value),
+                ("Type", cell(field_type.to_string())),
+                ("Format", format),
+            ],
+        );
+        Ok(())
+    }
+}
+
+/// Ячейка соединителя по `FromPart`: 9 - начало, 12 - конец; 4 - управляющий маркер
+fn from_part_cell(part: u16) -> Option<String> {
+    let cell = match part {
+        9 => "BeginX",
+        12 => "EndX",
+        4 => "Controls.X1",
+        _ => return None,
+    };
+    Some(cell.to_string())
+}
+
+/// Ячейка фигуры по `ToPart`: 3 - вся фигура, 100 и дальше - точка соединения
+fn to_part_cell(part: u16) -> Option<String> {
+    match part {
+        3 => Some("PinX".to_string()),
+        100.. => Some(format!("Connections.X{}", part - 99)),
+        _ => None,
+    }
+}
+
+/// Номер формата числового поля из блоков чанка. Блоки идут до конца данных чанка
+/// с трейлером; у блока формата сразу за заголовком номер u16 и `FIELD_FORMAT_MARKER`.
+/// Повреждённый список блоков означает, что формат не задан
+fn read_field_format(input: &mut Cursor<&[u8]>, header: &ChunkHeader) -> Option<u16> {
+    let end = (input.get_ref().len() as u64).min(header.data_length as u64 + header.trailer as u64);
+    let mut pos = FIELD_BLOCKS_OFFSET;
+    while pos + FIELD_BLOCK_HEADER <= end {
+        input.seek(SeekFrom::Start(pos)).ok()?;
+        let length = input.read_u32::<LittleEndian>().ok()? as u64;
+        if length == 0 {
+            return None;
+        }
+        input.seek(SeekFrom::Current(1)).ok()?;
+        let block_type = input.read_u8().ok()?;
+        input.seek(SeekFrom::Current(1)).ok()?;
+        if block_type == FIELD_FORMAT_BLOCK {
+            let number = input.read_u16::<LittleEndian>().ok()?;
+            return (input.read_u8().ok()? == FIELD_FORMAT_MARKER).then_some(number);
+        }
+        pos += length;
+    }
+    None
+}
+
+/// Подставляет значения полей вместо заполнителей и делит текст на отрезки форматирования
+fn finish_text(shape: &mut Shape, chars: &[u32], paras: &[u32], fields: &[String]) {
+    let Some(text) = shape.text.as_ref() else {
+        return;
+    };
+    let mut fields = fields.iter();
+    let mut substitute = |part: &str| {
+        part.chars()
+            .map(|c| match c {
+                FIELD_PLACEHOLDER => fields
+                    .next()
+                    .cloned()
+                    .unwrap_or_else(|| FIELD_PLACEHOLDER.to_string()),
+                c => c.to_string(),
+            })
+            .collect::<String>()
+    };
+
+    if chars.is_empty() && paras.is_empty() {
+        shape.text = Some(substitute(text));
+        return;
+    }
+
+    // Номер отрезка для позиции: последний отрезок продолжается до конца текста
+    let row_at = |counts: &[u32], pos: usize| -> Option<u32> {
+        let mut end = 0usize;
+        for (i, &count) in counts.iter().enumerate() {
+            end += count as usize;
+            if pos < end {
+                return Some(i as u32);
+            }
+        }
+        counts.len().checked_sub(1).map(|i| i as u32)
+    };
+
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut runs: Vec<TextRun> = Vec::new();
+    let mut start = 0;
+    while start < units.len() {
+        let key = (row_at(chars, start), row_at(paras, start));
+        let mut end = start + 1;
+        while end < units.len() && (row_at(chars, end), row_at(paras, end)) == key {
+            end += 1;
+        }
+        runs.push(TextRun {
+            text: String::from_utf16_lossy(&units[start..end]),
+            character: key.0,
+            paragraph: key.1,
+        });
+        start = end;
+    }
+
+    for run in runs.iter_mut() {
+        run.text = substitute(&run.text);
+    }
+    shape.text = Some(runs.iter().map(|r| r.text.as_str()).collect());
+    shape.text_runs = runs;
+}
+
+/// Список указателей потока и порядок их обработки
+pub(crate) struct PointerList {
+    /// Указатели с ненулевым типом и их индексы в списке
+    pub pointers: Vec<(u32, Pointer)>,
+    /// Индексы в порядке обработки; пустой, если порядок не задан
+    pub order: Vec<u32>,
+}
+
+/// Читает список указателей: по смещению `shift` лежит смещение списка, перед списком -
+/// его размер и число указателей, после указателей - порядок обработки
+pub(crate) fn read_pointer_list<R: Read + Seek>(
+    input: &mut R,
+    shift: u32,
+) -> Result<PointerList, Box<dyn std::error::Error>> {
+    input.seek(SeekFrom::Start(shift as u64))?;
+    let offset = input.read_u32::<LittleEndian>()?;
+    input.seek(SeekFrom::Start(
+        (offset as u64 + shift as u64).saturating_sub(4),
+    ))?;
+
+    let mut list_size = input.read_u32::<LittleEndian>().unwrap_or(0);
+    let pointer_count = input.read_u32::<LittleEndian>().unwrap_or(0);
+    log::debug!(
+        "read_pointer_list shift {} offset {} list_size {} pointer_count {}",
+        shift,
+        offset,
+        list_size,
+        pointer_count
+    );
+    input.seek(SeekFrom::Current(4))?;
+
+    let mut list = PointerList {
+        pointers: Vec::new(),
+        order: Vec::new(),
+    };
+    for i in 0..pointer_count {
+        let mut ptr = Pointer::new();
+        if ptr.read(input).is_err() {
+            break;
+        }
+        if ptr.type_name != 0 {
+            list.pointers.push((i, ptr));
+        }
+    }
+
+    if list_size <= 1 {
+        list_size = 0;
+    }
+    for _ in 0..list_size {
+        match input.read_u32::<LittleEndian>() {
+            Ok(idx) => list.order.push(idx),
+            Err(_) => break,
+        }
+    }
+    Ok(list)
+}
+
+/// Заголовок чанка VSD 11: тип, идентификатор, признак списка, длина и уровень
+#[derive(Debug, Clone, Default)]
+pub struct ChunkHeader {
+    pub chunk_type: u32,
+    pub id: u32,
+    pub list: u32,
+    pub data_length: u32,
+    pub level: u16,
+    pub unknown: u8,
+    pub trailer: u32,
+}
+
+/// Читает заголовок очередного чанка, пропуская нулевое выравнивание
+pub fn get_chunk_header(input: &mut VSDInternalStream) -> Option<ChunkHeader> {
+    let mut tmp_char = 0u8;
+    while !input.is_end() && tmp_char == 0 {
+        tmp_char = input.read_u8().ok()?;
+    }
+    if tmp_char == 0 {
+        return None;
+    }
+    input.seek(SeekFrom::Current(-1)).ok()?;
+
+    let mut header = ChunkHeader {
+        chunk_type: input.read_u32::<LittleEndian>().ok()?,
+        id: input.read_u32::<LittleEndian>().ok()?,
+        list: input.read_u32::<LittleEndian>().ok()?,
+        ..Default::default()
+    };
+
+    // Certain chunk types seem to always have a trailer
+    if header.list != 0
+        || matches!(
+            header.chunk_type,
+            0x71 | 0x70 | 0x6b | 0x6a | 0x69 | 0x66 | 0x65 | 0x64 | 0x2c | 0x0d
+        )
+    {
+        header.trailer += 8;
+    }
+
+    header.data_length = input.read_u32::<LittleEndian>().ok()?;
+    header.level = input.read_u16::<LittleEndian>().ok()?;
+    header.unknown = input.read_u8().ok()?;
+
+    if header.list != 0
+        || (header.level == 2 && header.unknown == 0x55)
+        || (header.level == 2 && header.unknown == 0x54 && header.chunk_type == 0xaa)
+        || (header.level == 3 && header.unknown != 0x50 && header.unknown != 0x54)
+    {
+        header.trailer += 4;
+    }
+
+    const TRAILER_CHUNKS: [u32; 14] = [
+        0x64, 0x65, 0x66, 0x69, 0x6a, 0x6b, 0x6f, 0x71, 0x92, 0xa9, 0xb4, 0xb6, 0xb9, 0xc7,
+    ];
+    if TRAILER_CHUNKS.contains(&header.chunk_type) && header.trailer != 12 && header.trailer != 4 {
+        header.trailer += 4;
+    }
+
+    // Some chunks never have a trailer
+    if matches!(header.chunk_type, 0x1f | 0xc9 | 0x2d | 0xd1) {
+        header.trailer = 0;
+    }
+
+    Some(header)
 }
 
+/// Ячейки с номером шрифта или цвета из палитры получают имя шрифта и `#RRGGBB`
+fn resolve_fonts_and_colours(diagram: &mut Diagram) {
+    let fonts: HashMap<&str, &str> = diagram
+        .fonts
+        .iter()
+        .map(|f| (f.id.as_str(), f.name.as_str()))
+        .collect();
+    let colours = &diagram.colors;
+    let resolve = |cells: &mut HashMap<CellName, Cell>| {
+        for (name, cell) in cells.iter_mut() {
+            let name = name.as_ref();
+            let value = cell.value.trim();
+            if name == "Font" {
+                if let Some(font) = fonts.get(value) {
+                    cell.value = font.to_string();
+                }
+            } else if is_colour_cell(name)
+                && let Some(colour) = value.parse::<usize>().ok().and_then(|i| colours.get(i))
+            {
+                cell.value = colour.clone();
+            }
+        }
+    };
+
+    fn walk(shape: &mut Shape, resolve: &impl Fn(&mut HashMap<CellName, Cell>)) {
+        resolve(&mut shape.cells);
+        for section in shape.sections.iter_mut() {
+            resolve(&mut section.cells);
+            for row in section.rows.iter_mut() {
+                resolve(&mut row.cells);
+            }
+        }
+        for sub in shape.shapes.iter_mut() {
+            walk(sub, resolve);
+        }
+    }
+
+    let shapes = diagram
+        .pages
+        .iter_mut()
+        .flat_map(|p| p.shapes.iter_mut())
+        .chain(diagram.masters.iter_mut().flat_map(|m| m.shapes.iter_mut()))
+        .chain(diagram.style_sheets.iter_mut());
+    for shape in shapes {
+        walk(shape, &resolve);
+    }
+}
+
+fn is_colour_cell(name: &str) -> bool {
+    name.ends_with("Color")
+        || matches!(
+            name,
+            "FillForegnd" | "FillBkgnd" | "ShdwForegnd" | "ShdwBkgnd"
+        )
+}
+
+/// Раскладывает плоский список фигур по группам согласно идентификатору родителя
+fn nest_shapes(flat: Vec<(u32, Shape)>) -> Vec<Shape> {
+    let ids: HashSet<String> = flat.iter().map(|(_, s)| s.id.clone()).collect();
+    let mut children: HashMap<String, Vec<Shape>> = HashMap::new();
+    let mut roots = Vec::new();
+
+    for (parent, shape) in flat {
+        let parent = parent.to_string();
+        if parent != "0" && ids.contains(&parent) && parent != shape.id {
+            children.entry(parent).or_default().push(shape);
+        } else {
+            roots.push(shape);
+        }
+    }
+
+    fn attach(shape: &mut Shape, children: &mut HashMap<String, Vec<Shape>>) {
+        if let Some(mut subs) = children.remove(&shape.id) {
+            for sub in subs.iter_mut() {
+                attach(sub, children);
+            }
+            shape.shapes = subs;
+        }
+    }
+
+    for shape in roots.iter_mut() {
+        attach(shape, &mut children);
+    }
+    roots
+}
+
+/// Растр хранится без заголовка файла BMP: он восстанавливается по заголовку DIB
+fn bitmap_file(dib: &[u8]) -> Vec<u8> {
+    let le_u32 = |offset: usize| {
+        dib.get(offset..offset + 4)
+            .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let header_size = le_u32(0);
+    let bit_count = dib
+        .get(14..16)
+        .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]));
+    let colors = match le_u32(32) {
+        0 if bit_count <= 8 => 1u32 << bit_count,
+        0 => 0,
+        used => used,
+    };
+    let offset = colors
+        .saturating_mul(4)
+        .saturating_add(header_size)
+        .saturating_add(14);
+
+    let mut file = Vec::with_capacity(dib.len() + 14);
+    file.extend_from_slice(b"BM");
+    file.extend_from_slice(&(dib.len() as u32 + 14).to_le_bytes());
+    file.extend_from_slice(&[0; 4]);
+    file.extend_from_slice(&offset.to_le_bytes());
+    file.extend_from_slice(dib);
+    file
+}
+
+fn optional_id(value: u32) -> Option<String> {
+    if value == MINUS_ONE {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn cell(value: String) -> Cell {
+    Cell {
+        value,
+        unit: None,
+        formula: None,
+    }
+}
+
+fn bool_value(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+/// Значение ячейки: байт типа с единицами отображения и double во внутренних единицах
+fn read_cell<R: Read>(input: &mut R) -> std::io::Result<Cell> {
+    let unit = Unit::from_cell_type(input.read_u8()?);
+    let value = input.read_f64::<LittleEndian>()?;
+    Ok(Cell {
+        value: value.to_string(),
+        unit: unit.code().map(str::to_string),
+        formula: None,
+    })
+}
+
+fn read_colour<R: Read>(input: &mut R) -> std::io::Result<[u8; 4]> {
+    let mut c = [0u8; 4];
+    input.read_exact(&mut c)?;
+    Ok(c)
+}
+
+fn colour_value(c: [u8; 4]) -> String {
+    format!("#{:02X}{:02X}{:02X}", c[0], c[1], c[2])
+}
+
+/// Указатель на поток внутри `VisioDocument`
 #[derive(Debug, Clone)]
-struct Pointer {
+pub(crate) struct Pointer {
     pub type_name: u32,
     pub format: u32,
     pub offset: u32,
     pub length: u32,
-    // pub list_size: u16,
 }
 
 impl Pointer {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             type_name: 0,
             format: 0,
             offset: 0,
             length: 0,
-            // list_size: 0,
         }
     }
-    fn read(&mut self, stream: &mut Cursor<Vec<u8>>) -> Result<(), Box<dyn std::error::Error>> {
-        self.type_name = stream.read_u32::<byteorder::LittleEndian>()?;
-        let _ = stream.seek(SeekFrom::Current(4))?;
-        self.offset = stream.read_u32::<byteorder::LittleEndian>()?;
-        self.length = stream.read_u32::<byteorder::LittleEndian>()?;
-        self.format = stream.read_u32::<byteorder::LittleEndian>()?;
-        // self.list_size = stream.read_u16::<byteorder::LittleEndian>()?;
+
+    /// Указатель VSD 11: тип, 4 байта пропуска, смещение, длина и 16-битный формат
+    pub fn read<R: Read + Seek>(
+        &mut self,
+        stream: &mut R,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.type_name = stream.read_u32::<LittleEndian>()? & 0xffff;
+        stream.seek(SeekFrom::Current(4))?;
+        self.offset = stream.read_u32::<LittleEndian>()?;
+        self.length = stream.read_u32::<LittleEndian>()?;
+        self.format = stream.read_u16::<LittleEndian>()? as u32;
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct XForm {
-    pub pin_x: f64,
-    pub pin_y: f64,
-    pub height: f64,
-    pub width: f64,
-    pub pin_loc_x: f64,
-    pub pin_loc_y: f64,
-    pub angle: f64,
-    pub flip_x: bool,
-    pub flip_y: bool,
-    pub x: f64,
-    pub y: f64,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsd::inspect::tests::{pointer, pointer_stream};
+    use crate::parser_vsd::vsd_constants::cell_types::{CELL_TYPE_Millimeters, CELL_TYPE_Number};
+    use crate::parser_vsd::vsd_constants::object_types::VSD_PAGES;
 
-impl XForm {
-    pub fn new() -> Self {
-        Self::default()
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16()
+            .chain([0])
+            .flat_map(|c| c.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_read_cell_units() {
+        let mut data = vec![CELL_TYPE_Millimeters];
+        data.extend(2.0f64.to_le_bytes());
+        data.push(CELL_TYPE_Number);
+        data.extend(0.5f64.to_le_bytes());
+        let mut input = Cursor::new(data.as_slice());
+
+        let width = read_cell(&mut input).unwrap();
+        assert_eq!(width.value, "2");
+        assert_eq!(width.unit.as_deref(), Some("MM"));
+        assert!((width.measurement().unwrap().value - 50.8).abs() < 1e-9);
+
+        let ratio = read_cell(&mut input).unwrap();
+        assert_eq!(ratio.unit, None);
+        assert_eq!(ratio.measurement().map(|m| m.value), Some(0.5));
+    }
+
+    #[test]
+    fn test_names() {
+        let mut data = b"Visio (TM) Drawing".to_vec();
+        data.resize(0x24, 0);
+        let trailer_at = 0x100;
+
+        // Таблица имён с одним именем, страницы - индекс имён и страница с индексом 1
+        let name_at = 0x200;
+        let mut name = 1u32.to_le_bytes().to_vec();
+        name.extend(utf16("Схема"));
+        let name_list_at = 0x300;
+        let name_list = pointer_stream(&[pointer(VSD_NAME2, name_at, name.len(), 0x00)]);
+
+        let name_idx_at = 0x400;
+        let mut name_idx = 1u32.to_le_bytes().to_vec();
+        for field in [0u32, 0, 1] {
+            name_idx.extend(field.to_le_bytes());
+        }
+        name_idx.push(0);
+        let page_at = 0x500;
+        let page = pointer_stream(&[]);
+        let pages_at = 0x600;
+        let pages = pointer_stream(&[
+            pointer(VSD_NAMEIDX, name_idx_at, name_idx.len(), 0x00),
+            pointer(VSD_PAGE, page_at, page.len(), 0x51),
+        ]);
+
+        let trailer = pointer_stream(&[
+            pointer(VSD_NAME_LIST2, name_list_at, name_list.len(), 0x50),
+            pointer(VSD_PAGES, pages_at, pages.len(), 0x50),
+        ]);
+        data.extend(pointer(0, trailer_at, trailer.len(), 0x50));
+        for (at, part) in [
+            (trailer_at, &trailer),
+            (name_at, &name),
+            (name_list_at, &name_list),
+            (name_idx_at, &name_idx),
+            (page_at, &page),
+            (pages_at, &pages),
+        ] {
+            data.resize(at, 0);
+            data.extend(part);
+        }
+
+        let mut parser = VSDParser::new(data);
+        assert!(parser.parse_main());
+        let diagram = parser.into_diagram();
+        assert_eq!(diagram.pages.len(), 1);
+        assert_eq!(diagram.pages[0].id, "1");
+        assert_eq!(diagram.pages[0].name, "Схема");
+    }
+
+    #[test]
+    fn test_fonts_and_colours() {
+        let mut parser = VSDParser::new(Vec::new());
+
+        let mut colours = vec![0u8; 6];
+        colours.extend([2, 0]);
+        colours.extend([0, 0, 0, 0, 0x12, 0x34, 0x56, 0]);
+        parser.read_colours(&colours);
+
+        let header = |chunk_type: u8, id: u32| ChunkHeader {
+            chunk_type: chunk_type as u32,
+            id,
+            ..Default::default()
+        };
+        let mut face = vec![0, 0, 204, 0, 1, 0, 0, 0];
+        face.extend(utf16("Arial"));
+        parser.handle_chunk(&header(VSD_FONTFACE, 3), &face);
+        let mut ix = vec![0, 0, 204, 0, 0, 0, 0, 0];
+        ix.extend(b"\xcf\xf0\xe8\xec\xe5\xf0\0");
+        parser.handle_chunk(&header(VSD_FONT_IX, 4), &ix);
+
+        let mut shape = Shape::default();
+        shape.set_cell("LineColor", 1.0);
+        shape
+            .cells
+            .insert("FillForegnd".into(), cell("#FFFFFF".to_string()));
+        let mut row = Row::default();
+        row.cells.insert("Font".into(), cell("3".to_string()));
+        shape.sections.push(Section {
+            name: "Character".to_string(),
+            rows: vec![row],
+            ..Default::default()
+        });
+        parser.pages.push(Page {
+            shapes: vec![shape],
+            ..Default::default()
+        });
+
+        let diagram = parser.into_diagram();
+        assert_eq!(diagram.colors, ["#000000", "#123456"]);
+        assert_eq!(diagram.fonts.len(), 2);
+        assert_eq!(diagram.fonts[0].name, "Arial");
+        assert_eq!((diagram.fonts[0].charset, diagram.fonts[0].flags), (204, 1));
+        assert_eq!(diagram.fonts[1].name, "Пример");
+
+        let shape = &diagram.pages[0].shapes[0];
+        assert_eq!(shape.cells["LineColor"].value, "#123456");
+        assert_eq!(shape.cells["FillForegnd"].value, "#FFFFFF");
+        assert_eq!(shape.sections[0].rows[0].cells["Font"].value, "Arial");
+    }
+
+    #[test]
+    fn test_text_formatting() {
+        let mut parser = VSDParser::new(Vec::new());
+        parser.current_shape = Some((0, Shape::default()));
+        let header = |chunk_type: u8, length: usize| ChunkHeader {
+            chunk_type: chunk_type as u32,
+            data_length: length as u32,
+            ..Default::default()
+        };
+        let mut chunk = |chunk_type: u8, data: &[u8]| {
+            parser.handle_chunk(&header(chunk_type, data.len()), data);
+        };
+
+        let mut text = vec![0u8; 8];
+        text.extend(utf16("Bold \u{FFFC} kg\n"));
+        chunk(VSD_TEXT, &text);
+
+        let char_ix = |count: u32, style: u8, size: f64| {
+            let mut data = count.to_le_bytes().to_vec();
+            data.extend(3u16.to_le_bytes());
+            data.extend([0, 0xff, 0, 0, 0, style, 0, 2, 0, 0, 0, 0]);
+            data.extend(size.to_le_bytes());
+            data.push(0x04);
+            data
+        };
+        chunk(VSD_CHAR_IX, &char_ix(5, 1, 0.25));
+        chunk(VSD_CHAR_IX, &char_ix(10, 0, 1.0 / 6.0));
+
+        let mut para = 20u32.to_le_bytes().to_vec();
+        for value in [0.0, 0.5, 0.0, -1.2, 0.0, 0.0] {
+            para.push(0);
+            para.extend(f64::to_le_bytes(value));
+        }
+        para.extend([2, 0]);
+        chunk(VSD_PARA_IX, &para);
+
+        // Число 42 с форматом 1 во втором блоке
+        let mut field = vec![0u8; 7];
+        field.push(0x20);
+        field.extend(42f64.to_le_bytes());
+        field.resize(0x24, 0);
+        field.extend([7, 0, 0, 0, 0, 1, 0]);
+        field.extend([10, 0, 0, 0, 0, 2, 0, 1, 0, 0x80]);
+        chunk(VSD_TEXT_FIELD, &field);
+
+        parser.end_shape();
+        let (_, shape) = &parser.current_shapes[0];
+        assert_eq!(shape.text.as_deref(), Some("Bold 42 kg\n"));
+        let runs: Vec<_> = shape
+            .text_runs
+            .iter()
+            .map(|r| (r.text.as_str(), r.character, r.paragraph))
+            .collect();
+        assert_eq!(
+            runs,
+            [("Bold ", Some(0), Some(0)), ("42 kg\n", Some(1), Some(0))]
+        );
+
+        let rows = |name: &str| &shape.sections_named(name)[0].rows;
+        let bold = &rows("Character")[0].cells;
+        assert_eq!(bold["Font"].value, "3");
+        assert_eq!(bold["Color"].value, "#FF0000");
+        assert_eq!(bold["Style"].value, "1");
+        assert_eq!(bold["Pos"].value, "2");
+        assert_eq!(bold["Size"].value, "0.25");
+        assert_eq!(bold["Strikethru"].value, "1");
+        let para = &rows("Paragraph")[0].cells;
+        assert_eq!(para["HorzAlign"].value, "2");
+        assert_eq!(para["SpLine"].value, "-1.2");
+        let field = &rows("Field")[0].cells;
+        assert_eq!(field["Value"].value, "42");
+        assert_eq!(field["Format"].value, "1");
+        assert_eq!(field["Type"].value, "2");
+    }
+
+    #[test]
+    fn test_field_format_blocks() {
+        let format = |blocks: &[&[u8]]| {
+            let mut data = vec![0u8; FIELD_BLOCKS_OFFSET as usize];
+            for block in blocks {
+                data.extend(*block);
+            }
+            read_field_format(
+                &mut Cursor::new(data.as_slice()),
+                &ChunkHeader {
+                    data_length: data.len() as u32,
+                    ..Default::default()
+                },
+            )
+        };
+        // Длина в +0, тип блока в +5, номер в +7, маркер в +9
+        assert_eq!(format(&[&[11, 0, 0, 0, 0, 2, 0, 37, 0, 0x80, 0]]), Some(37));
+        // Перед блоком формата - блок другого типа
+        assert_eq!(
+            format(&[&[7, 0, 0, 0, 0, 1, 0], &[10, 0, 0, 0, 0, 2, 0, 4, 1, 0x80]]),
+            Some(0x104)
+        );
+        // Без маркера формат не задан
+        assert_eq!(format(&[&[11, 0, 0, 0, 0, 2, 0, 37, 0, 0, 0x80]]), None);
+        // Блок формата обрезан концом чанка
+        assert_eq!(format(&[&[11, 0, 0, 0, 0, 2, 0, 37]]), None);
+        // Нулевая длина завершает список
+        assert_eq!(
+            format(&[&[0; 7], &[11, 0, 0, 0, 0, 2, 0, 37, 0, 0x80, 0]]),
+            None
+        );
+        assert_eq!(format(&[]), None);
+    }
+
+    #[test]
+    fn test_connects() {
+        let mut parser = VSDParser::new(Vec::new());
+        parser.current_page = Some(Page::default());
+        let header = |chunk_type: u8, level: u16| ChunkHeader {
+            chunk_type: chunk_type as u32,
+            level,
+            ..Default::default()
+        };
+
+        parser.current_shape = Some((0, Shape::default()));
+        let mut point = Vec::new();
+        for value in [0.5, 1.0, 0.0, 1.0] {
+            point.push(0);
+            point.extend(f64::to_le_bytes(value));
+        }
+        point.push(0);
+        parser.handle_chunk(&header(VSD_CONNECTION_POINTS, 3), &point);
+        let mut xform = Vec::new();
+        for value in [1.0, 2.0, 4.0, 2.0] {
+            xform.push(0);
+            xform.extend(f64::to_le_bytes(value));
+        }
+        parser.handle_chunk(&header(VSD_XFORM_1D, 3), &xform);
+        let shape = parser.current_shape.as_ref().map(|(_, s)| s).unwrap();
+        assert_eq!(shape.number("EndX"), Some(4.0));
+        assert_eq!(shape.sections[0].name, "Connection");
+        assert_eq!(shape.sections[0].rows[0].cells["Y"].value, "1");
+
+        let connect = |from: u32, to: u32, from_part: u16, to_part: u16| {
+            let mut data = from.to_le_bytes().to_vec();
+            data.extend(to.to_le_bytes());
+            data.extend(from_part.to_le_bytes());
+            data.extend(to_part.to_le_bytes());
+            data
+        };
+        parser.handle_chunk(&header(VSD_CONNECT_LIST, 2), &[]);
+        parser.handle_chunk(&header(0, 3), &connect(5, 1, 9, 3));
+        parser.handle_chunk(&header(0, 3), &connect(5, 2, 12, 100));
+        // Чанк того же уровня закрывает список
+        parser.handle_chunk(&header(VSD_PAGE_SHEET, 2), &[]);
+        parser.handle_chunk(&header(0, 3), &connect(6, 1, 9, 3));
+
+        let page = parser.current_page.take().unwrap();
+        assert_eq!(page.connects.len(), 2);
+        assert_eq!(page.connects[1].to_cell.as_deref(), Some("Connections.X1"));
+        assert_eq!(
+            crate::export::connections(&page),
+            [crate::export::Connection {
+                connector: "5".to_string(),
+                from: Some("1".to_string()),
+                to: Some("2".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reviewers_and_annotations() {
+        let mut parser = VSDParser::new(Vec::new());
+        for (id, name) in [(1, "Anna Petrova"), (2, "AP"), (3, "Check size")] {
+            parser.handle_chunk(
+                &ChunkHeader {
+                    chunk_type: VSD_NAME as u32,
+                    id,
+                    ..Default::default()
+                },
+                &utf16(name),
+            );
+        }
+        let header = |chunk_type: u8| ChunkHeader {
+            chunk_type: chunk_type as u32,
+            ..Default::default()
+        };
+        let typed = |cell_type: u8, data: [u8; 8]| {
+            let mut out = vec![cell_type];
+            out.extend(data);
+            out
+        };
+        let name_ref = |id: u32| {
+            let mut data = [0u8; 8];
+            data[..4].copy_from_slice(&id.to_le_bytes());
+            typed(232, data)
+        };
+
+        // Рецензенты лежат в листе документа, вне страниц
+        let mut reviewer = name_ref(1);
+        reviewer.extend(name_ref(2));
+        reviewer.extend([0xff, 0, 0, 0]);
+        reviewer.extend(1u32.to_le_bytes());
+        reviewer.extend(1u32.to_le_bytes());
+        parser.handle_chunk(&header(VSD_REVIEWER), &reviewer);
+
+        parser.current_page = Some(Page {
+            id: "0".to_string(),
+            ..Default::default()
+        });
+        parser.handle_chunk(&header(VSD_PAGE_SHEET), &[]);
+        let mut annotation = typed(0, 1f64.to_le_bytes());
+        annotation.extend(typed(0, 2f64.to_le_bytes()));
+        annotation.extend(1u32.to_le_bytes());
+        annotation.extend(1u32.to_le_bytes());
+        annotation.extend(typed(CELL_TYPE_Date, 45356.5f64.to_le_bytes()));
+        annotation.extend(name_ref(3));
+        annotation.extend(1033u32.to_le_bytes());
+        parser.handle_chunk(&header(VSD_ANNOTATION), &annotation);
+        let page = parser.current_page.take().unwrap();
+        parser.pages.push(page);
+
+        let diagram = parser.into_diagram();
+        let annotations = &diagram.pages[0].sections[0];
+        assert_eq!(annotations.name, "Annotation");
+        assert_eq!(annotations.rows[0].cells["Y"].value, "2");
+        assert_eq!(diagram.comments.len(), 1);
+        let comment = &diagram.comments[0];
+        assert_eq!(comment.author, "Anna Petrova");
+        assert_eq!(comment.initials.as_deref(), Some("AP"));
+        assert_eq!(comment.text, "Check size");
+        assert_eq!(comment.date.as_deref(), Some("2024-03-05T12:00:00Z"));
+        assert_eq!(comment.page.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn test_shape_data_and_hyperlinks() {
+        let mut parser = VSDParser::new(Vec::new());
+        for (id, name) in [(1, "Server"), (2, "Host name"), (3, "https://example.com")] {
+            parser.handle_chunk(
+                &ChunkHeader {
+                    chunk_type: VSD_NAME as u32,
+                    id,
+                    ..Default::default()
+                },
+                &utf16(name),
+            );
+        }
+        parser.current_shape = Some((0, Shape::default()));
+        let header = |chunk_type: u8| ChunkHeader {
+            chunk_type: chunk_type as u32,
+            ..Default::default()
+        };
+        let typed = |cell_type: u8, data: [u8; 8]| {
+            let mut out = vec![cell_type];
+            out.extend(data);
+            out
+        };
+        let name_ref = |id: u32| {
+            let mut data = [0u8; 8];
+            data[..4].copy_from_slice(&id.to_le_bytes());
+            typed(232, data)
+        };
+
+        let mut prop = name_ref(1);
+        prop.extend(name_ref(0));
+        prop.extend(name_ref(2));
+        prop.extend(name_ref(0));
+        prop.extend(name_ref(0));
+        prop.extend([0, 0, 0]);
+        prop.extend(1049u32.to_le_bytes());
+        prop.push(0);
+        parser.handle_chunk(&header(VSD_CUSTOM_PROPS), &prop);
+
+        let mut user = typed(70, 25.4f64.to_le_bytes());
+        user.extend(name_ref(0));
+        parser.handle_chunk(&header(VSD_USER_DEFINED_CELLS), &user);
+        let mut date = typed(40, 45352.5f64.to_le_bytes());
+        date.extend(name_ref(0));
+        parser.handle_chunk(&header(VSD_USER_DEFINED_CELLS), &date);
+
+        let mut link = name_ref(0);
+        link.extend(name_ref(3));
+        for _ in 0..3 {
+            link.extend(name_ref(0));
+        }
+        link.extend([1, 0, 0]);
+        parser.handle_chunk(&header(VSD_HYPERLINK), &link);
+
+        // Полилиния в долях ширины и высоты, ссылка на неё из строки геометрии
+        let mut data = vec![0x80];
+        data.extend([0; 15]);
+        data.extend([0, 0]);
+        data.extend(1u32.to_le_bytes());
+        data.extend(0.5f64.to_le_bytes());
+        data.extend(1f64.to_le_bytes());
+        parser.handle_chunk(
+            &ChunkHeader {
+                chunk_type: VSD_SHAPE_DATA as u32,
+                id: 7,
+                ..Default::default()
+            },
+            &data,
+        );
+        let mut row = Vec::new();
+        for value in [1.0, 0.0] {
+            row.push(0);
+            row.extend(f64::to_le_bytes(value));
+        }
+        row.extend([0x8b, 0, 0, 0]);
+        row.extend(7u32.to_le_bytes());
+        parser.handle_chunk(&header(VSD_POLYLINE_TO), &row);
+
+        parser.end_shape();
+        let (_, shape) = &parser.current_shapes[0];
+        let rows = |name: &str| &shape.sections_named(name)[0].rows;
+
+        let prop = &rows("Property")[0];
+        assert_eq!(prop.name.as_deref(), Some("Row_1"));
+        assert_eq!(prop.cells["Value"].value, "Server");
+        assert_eq!(prop.cells["Value"].unit.as_deref(), Some("STR"));
+        assert_eq!(prop.cells["Label"].value, "Host name");
+        assert_eq!(prop.cells["LangID"].value, "1049");
+
+        let user = rows("User");
+        assert_eq!(user[0].cells["Value"].value, "25.4");
+        assert_eq!(user[0].cells["Value"].unit.as_deref(), Some("MM"));
+        assert_eq!(user[1].name.as_deref(), Some("Row_2"));
+        assert_eq!(user[1].cells["Value"].value, "2024-03-01T12:00:00Z");
+
+        let link = &rows("Hyperlink")[0].cells;
+        assert_eq!(link["Address"].value, "https://example.com");
+        assert_eq!(link["Address"].unit, None);
+        assert_eq!(link["NewWindow"].value, "1");
+
+        let geometry = &rows("Geometry")[0];
+        assert_eq!(geometry.row_type.as_deref(), Some("PolylineTo"));
+        assert_eq!(geometry.cells["A"].value, "POLYLINE(0, 0, 0.5, 1)");
     }
 }
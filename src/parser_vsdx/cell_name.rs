@@ -0,0 +1,189 @@
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+thread_local! {
+    /// Пул имён текущего разбора: `PinX`, `Width` и т.п. повторяются в каждой фигуре.
+    /// Существует только внутри [`CellName::scoped`]
+    static NAMES: RefCell<Option<HashSet<Arc<str>>>> = const { RefCell::new(None) };
+}
+
+/// Интернированное имя ячейки ShapeSheet.
+///
+/// Внутри одного разбора все копии одного имени указывают на одну строку, поэтому ключ
+/// словаря ячеек занимает два машинных слова без отдельной аллокации. Вне разбора
+/// каждое имя получает свою строку.
+#[derive(Clone, PartialOrd, Ord)]
+pub struct CellName(Arc<str>);
+
+impl CellName {
+    pub fn new(name: &str) -> Self {
+        NAMES.with(|names| {
+            let mut names = names.borrow_mut();
+            let Some(names) = names.as_mut() else {
+                return CellName(Arc::from(name));
+            };
+            if let Some(existing) = names.get(name) {
+                return CellName(existing.clone());
+            }
+            let interned: Arc<str> = Arc::from(name);
+            names.insert(interned.clone());
+            CellName(interned)
+        })
+    }
+
+    /// Выполняет `f` с пулом имён, общим для всех ячеек, созданных в этом потоке.
+    /// Пул освобождается по выходу; вложенный вызов пользуется внешним пулом
+    pub(crate) fn scoped<T>(f: impl FnOnce() -> T) -> T {
+        struct Release;
+        impl Drop for Release {
+            fn drop(&mut self) {
+                NAMES.with(|names| names.borrow_mut().take());
+            }
+        }
+
+        if NAMES.with(|names| names.borrow().is_some()) {
+            return f();
+        }
+        NAMES.with(|names| *names.borrow_mut() = Some(HashSet::new()));
+        let _release = Release;
+        f()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for CellName {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for CellName {}
+
+impl Hash for CellName {
+    // Должен совпадать с хешем `str`, чтобы работал поиск `cells.get("PinX")`
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Deref for CellName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for CellName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for CellName {
+    fn from(name: &str) -> Self {
+        CellName::new(name)
+    }
+}
+
+impl From<String> for CellName {
+    fn from(name: String) -> Self {
+        CellName::new(&name)
+    }
+}
+
+impl From<&String> for CellName {
+    fn from(name: &String) -> Self {
+        CellName::new(name)
+    }
+}
+
+impl PartialEq<str> for CellName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CellName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for CellName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for CellName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for CellName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CellName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(CellName::new(&name))
+    }
+}
+
+impl JsonSchema for CellName {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "CellName".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({ "type": "string" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_names_are_shared() {
+        let (a, b) = CellName::scoped(|| {
+            let a = CellName::from("PinX");
+            let b = CellName::scoped(|| CellName::from("PinX".to_string()));
+            (a, b)
+        });
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        NAMES.with(|names| assert!(names.borrow().is_none()));
+
+        // Вне разбора пул не заполняется
+        let c = CellName::from("PinX");
+        assert!(!Arc::ptr_eq(&a.0, &c.0));
+        assert_eq!(a, c);
+
+        let mut cells = HashMap::new();
+        cells.insert(a, 1);
+        assert_eq!(cells.get("PinX"), Some(&1));
+    }
+}
@@ -0,0 +1,307 @@
+//! Комментарии рецензентов.
+//!
+//! Visio 2013 хранит комментарии в `visio/comments.xml`: список авторов `AuthorList` и
+//! записи `CommentEntry` с привязкой к странице и фигуре. В Visio 2019+ обсуждения
+//! ведутся цепочками: записи `threadedComment` с автором из списка `person` и ссылкой
+//! `parentId` на начало цепочки. Старые форматы (VSD, VDX и VSDX, сохранённые из них)
+//! держат пометки в строках секции `Annotation` листа страницы, а рецензентов - в секции
+//! `Reviewer` листа документа.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::doc_props::element_text;
+use super::get_metadata::Element;
+use super::lossless::{XmlDocument, XmlElement};
+use super::read_vsdx::{Comment, Page, Row};
+use super::shapes::{DOCUMENT_PART, build_section, sorted_by_name};
+use crate::parser_vsd::utils::{ole_date_to_system_time, system_time_to_iso8601};
+
+pub const COMMENTS_PART: &str = "visio/comments.xml";
+
+/// Комментарии из `visio/comments.xml` и частей с цепочками обсуждений
+pub fn read_comments(package: &BTreeMap<String, Vec<u8>>) -> Vec<Comment> {
+    let parse = |part: &str, data: &[u8]| {
+        XmlDocument::parse(data)
+            .map_err(|e| log::warn!("Unable to parse {}: {}", part, e))
+            .ok()
+    };
+
+    let mut comments = Vec::new();
+    if let Some(doc) = package
+        .get(COMMENTS_PART)
+        .and_then(|data| parse(COMMENTS_PART, data))
+    {
+        comments.extend(legacy_comments(&doc.root));
+    }
+
+    // Имена частей с цепочками зависят от версии Visio, поэтому они ищутся по корню
+    let threaded: Vec<XmlDocument> = package
+        .iter()
+        .filter(|(name, _)| name.starts_with("visio/") && name.ends_with(".xml"))
+        .filter(|(name, _)| name.as_str() != COMMENTS_PART)
+        .filter(|(_, data)| contains(data, b"threadedComment") || contains(data, b"personList"))
+        .filter_map(|(name, data)| parse(name, data))
+        .collect();
+    let persons: HashMap<&str, &str> = threaded
+        .iter()
+        .filter(|doc| doc.root.name.local_name.eq_ignore_ascii_case("personList"))
+        .flat_map(|doc| doc.root.elements())
+        .filter_map(|person| Some((person.attr("id")?, person.attr("displayName")?)))
+        .collect();
+    for doc in &threaded {
+        if doc
+            .root
+            .name
+            .local_name
+            .eq_ignore_ascii_case("ThreadedComments")
+        {
+            comments.extend(threaded_comments(&doc.root, &persons));
+        }
+    }
+    comments
+}
+
+/// `CommentEntry` с автором из `AuthorEntry` по `AuthorID`
+fn legacy_comments(root: &XmlElement) -> Vec<Comment> {
+    let lists: Vec<&XmlElement> = root.elements().collect();
+    let authors: HashMap<&str, &XmlElement> = lists
+        .iter()
+        .filter(|el| el.name.local_name == "AuthorList")
+        .flat_map(|list| list.elements())
+        .filter_map(|author| Some((author.attr("ID")?, author)))
+        .collect();
+
+    lists
+        .iter()
+        .filter(|el| el.name.local_name == "CommentList")
+        .flat_map(|list| list.elements())
+        .filter(|entry| entry.name.local_name == "CommentEntry")
+        .map(|entry| {
+            let author = entry.attr("AuthorID").and_then(|id| authors.get(id));
+            Comment {
+                id: entry.attr("CommentID").map(str::to_string),
+                author: author
+                    .and_then(|a| a.attr("Name"))
+                    .unwrap_or_default()
+                    .to_string(),
+                initials: author.and_then(|a| a.attr("Initials")).map(str::to_string),
+                date: entry.attr("Date").map(str::to_string),
+                text: element_text(entry),
+                page: entry.attr("PageID").map(str::to_string),
+                shape: entry.attr("ShapeID").map(str::to_string),
+                done: entry.attr("Done") == Some("1"),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// `threadedComment` с текстом в дочернем `text`; привязка к странице и фигуре - в тех же
+/// атрибутах `PageID` и `ShapeID`, что и у `CommentEntry`
+fn threaded_comments(root: &XmlElement, persons: &HashMap<&str, &str>) -> Vec<Comment> {
+    root.elements()
+        .filter(|el| el.name.local_name == "threadedComment")
+        .map(|el| Comment {
+            id: el.attr("id").map(str::to_string),
+            parent: el.attr("parentId").map(str::to_string),
+            author: el
+                .attr("personId")
+                .and_then(|id| persons.get(id))
+                .unwrap_or(&"")
+                .to_string(),
+            date: el.attr("dT").map(str::to_string),
+            text: el
+                .elements()
+                .find(|child| child.name.local_name == "text")
+                .map(element_text)
+                .unwrap_or_default(),
+            page: el.attr("PageID").map(str::to_string),
+            shape: el.attr("ShapeID").map(str::to_string),
+            done: matches!(el.attr("done"), Some("1" | "true")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Строки секции `Reviewer` из `DocumentSheet` части `visio/document.xml`
+pub fn document_reviewers(parts: &HashMap<String, HashMap<i64, Element>>) -> Vec<Row> {
+    let Some(document) = parts.get(DOCUMENT_PART) else {
+        return Vec::new();
+    };
+    sorted_by_name(document, "DocumentSheet")
+        .into_iter()
+        .flat_map(|sheet| sheet.children.iter().filter_map(|id| document.get(id)))
+        .filter(|el| el.name == "Section" && el.attrs.get("N").is_some_and(|n| n == "Reviewer"))
+        .flat_map(|el| build_section(document, el).rows)
+        .collect()
+}
+
+/// Пометки из секций `Annotation` страниц; автор ищется среди `reviewers` по `ReviewerID`
+pub fn annotation_comments(reviewers: &[Row], pages: &[Page]) -> Vec<Comment> {
+    let value = |row: &Row, name: &str| {
+        row.cells
+            .get(name)
+            .map(|c| c.value.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let same_id = |a: &str, b: &str| match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    };
+
+    let mut comments = Vec::new();
+    for page in pages {
+        let rows = page
+            .sections
+            .iter()
+            .filter(|s| s.name == "Annotation")
+            .flat_map(|s| &s.rows)
+            .filter(|row| !row.deleted);
+        for row in rows {
+            let reviewer = value(row, "ReviewerID").and_then(|id| {
+                reviewers
+                    .iter()
+                    .filter(|r| !r.deleted)
+                    .find(|r| value(r, "ReviewerID").is_some_and(|rid| same_id(&rid, &id)))
+            });
+            comments.push(Comment {
+                id: value(row, "MarkerIndex").or_else(|| row.index.clone()),
+                author: reviewer.and_then(|r| value(r, "Name")).unwrap_or_default(),
+                initials: reviewer.and_then(|r| value(r, "Initials")),
+                date: value(row, "Date").map(|date| annotation_date(&date)),
+                text: row
+                    .cells
+                    .get("Comment")
+                    .map(|c| c.value.clone())
+                    .unwrap_or_default(),
+                page: Some(page.id.clone()),
+                ..Default::default()
+            });
+        }
+    }
+    comments
+}
+
+/// Дата пометки: число дней OLE переводится в ISO 8601, строка остаётся как есть
+fn annotation_date(value: &str) -> String {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(ole_date_to_system_time)
+        .map(system_time_to_iso8601)
+        .unwrap_or_else(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::{Cell, Section};
+
+    #[test]
+    fn test_read_comments() {
+        let mut package = BTreeMap::new();
+        package.insert(
+            COMMENTS_PART.to_string(),
+            br#"<?xml version="1.0" encoding="utf-8"?>
+<Comments xmlns="http://schemas.microsoft.com/office/visio/2012/main">
+  <AuthorList><AuthorEntry Name="Anna Petrova" Initials="AP" ID="0"/></AuthorList>
+  <CommentList>
+    <CommentEntry AuthorID="0" PageID="0" ShapeID="3" Date="2024-03-05T10:00:00" Done="0" CommentID="0">Check the &amp; label</CommentEntry>
+    <CommentEntry AuthorID="7" PageID="4" Date="2024-03-06T09:00:00" Done="1" CommentID="1">Fixed</CommentEntry>
+  </CommentList>
+</Comments>"#
+                .to_vec(),
+        );
+        package.insert(
+            "visio/persons/person.xml".to_string(),
+            br#"<personList><person displayName="Ivan" id="{P1}"/></personList>"#.to_vec(),
+        );
+        package.insert(
+            "visio/threadedComments/threadedComment1.xml".to_string(),
+            br#"<ThreadedComments>
+  <threadedComment dT="2024-03-07T08:00:00.00" personId="{P1}" id="{C1}" PageID="0" ShapeID="3"><text>Why here?</text></threadedComment>
+  <threadedComment dT="2024-03-07T09:00:00.00" personId="{P1}" id="{C2}" parentId="{C1}" done="1"><text>Moved</text></threadedComment>
+</ThreadedComments>"#
+                .to_vec(),
+        );
+
+        let comments = read_comments(&package);
+        assert_eq!(comments.len(), 4);
+        assert_eq!(
+            comments[0],
+            Comment {
+                id: Some("0".to_string()),
+                author: "Anna Petrova".to_string(),
+                initials: Some("AP".to_string()),
+                date: Some("2024-03-05T10:00:00".to_string()),
+                text: "Check the & label".to_string(),
+                page: Some("0".to_string()),
+                shape: Some("3".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(comments[1].author, "");
+        assert!(comments[1].done);
+        assert_eq!(comments[2].author, "Ivan");
+        assert_eq!(comments[2].text, "Why here?");
+        assert_eq!(comments[2].shape.as_deref(), Some("3"));
+        assert_eq!(comments[3].parent.as_deref(), Some("{C1}"));
+        assert!(comments[3].done);
+    }
+
+    #[test]
+    fn test_annotation_comments() {
+        let row = |cells: &[(&str, &str)]| Row {
+            cells: cells
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        (*name).into(),
+                        Cell {
+                            value: value.to_string(),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let reviewers = [row(&[
+            ("Name", "Anna Petrova"),
+            ("Initials", "AP"),
+            ("ReviewerID", "1"),
+        ])];
+        let page = Page {
+            id: "0".to_string(),
+            sections: vec![Section {
+                name: "Annotation".to_string(),
+                rows: vec![row(&[
+                    ("ReviewerID", "1.0"),
+                    ("MarkerIndex", "2"),
+                    ("Date", "45356.5"),
+                    ("Comment", "Align the servers"),
+                ])],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let comments = annotation_comments(&reviewers, &[page]);
+        assert_eq!(
+            comments,
+            vec![Comment {
+                id: Some("2".to_string()),
+                author: "Anna Petrova".to_string(),
+                initials: Some("AP".to_string()),
+                date: Some("2024-03-05T12:00:00Z".to_string()),
+                text: "Align the servers".to_string(),
+                page: Some("0".to_string()),
+                ..Default::default()
+            }]
+        );
+    }
+}
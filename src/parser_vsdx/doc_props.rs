@@ -0,0 +1,121 @@
+//! Свойства документа из частей `docProps/core.xml`, `app.xml` и `custom.xml`.
+
+use std::collections::BTreeMap;
+
+use super::lossless::{XmlDocument, XmlElement, XmlNode};
+use super::read_vsdx::DocumentInfo;
+
+const CORE_PART: &str = "docProps/core.xml";
+const APP_PART: &str = "docProps/app.xml";
+const CUSTOM_PART: &str = "docProps/custom.xml";
+
+/// Собирает `DocumentInfo` из частей пакета; отсутствующие части пропускаются
+pub fn read_document_info(package: &BTreeMap<String, Vec<u8>>) -> DocumentInfo {
+    let mut info = DocumentInfo::default();
+    let parse = |part: &str| {
+        let data = package.get(part)?;
+        XmlDocument::parse(data)
+            .map_err(|e| log::warn!("Unable to parse {}: {}", part, e))
+            .ok()
+    };
+
+    if let Some(core) = parse(CORE_PART) {
+        for el in core.root.elements() {
+            let value = element_text(el);
+            let field = match el.name.local_name.as_str() {
+                "title" => &mut info.title,
+                "subject" => &mut info.subject,
+                "creator" => &mut info.author,
+                "keywords" => &mut info.keywords,
+                "description" => &mut info.description,
+                "lastModifiedBy" => &mut info.last_modified_by,
+                "created" => &mut info.created,
+                "modified" => &mut info.modified,
+                "category" => &mut info.category,
+                _ => continue,
+            };
+            *field = non_empty(value);
+        }
+    }
+
+    if let Some(app) = parse(APP_PART) {
+        for el in app.root.elements() {
+            let field = match el.name.local_name.as_str() {
+                "Company" => &mut info.company,
+                "Manager" => &mut info.manager,
+                "Template" => &mut info.template,
+                _ => continue,
+            };
+            *field = non_empty(element_text(el));
+        }
+    }
+
+    if let Some(custom) = parse(CUSTOM_PART) {
+        for property in custom.root.elements() {
+            let Some(name) = property.attr("name") else {
+                continue;
+            };
+            // Значение лежит в единственном дочернем элементе вида `<vt:lpwstr>`
+            let value = property.elements().next().map(element_text);
+            info.custom
+                .insert(name.to_string(), value.unwrap_or_default());
+        }
+    }
+
+    info
+}
+
+pub(super) fn element_text(el: &XmlElement) -> String {
+    el.children
+        .iter()
+        .filter_map(|node| match node {
+            XmlNode::Text(text) | XmlNode::CData(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_props() {
+        let mut package = BTreeMap::new();
+        package.insert(
+            CORE_PART.to_string(),
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/">
+  <dc:title>Network</dc:title><dc:creator>Ivan</dc:creator><cp:keywords>lan; wan</cp:keywords>
+  <dcterms:created>2024-03-01T12:30:00Z</dcterms:created><dc:subject></dc:subject>
+</cp:coreProperties>"#
+                .to_vec(),
+        );
+        package.insert(
+            APP_PART.to_string(),
+            br#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties"><Company>ACME</Company><Template>BASIC_M.VSTX</Template></Properties>"#
+                .to_vec(),
+        );
+        package.insert(
+            CUSTOM_PART.to_string(),
+            br#"<Properties xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"><property fmtid="{D5CDD505-2E9C-101B-9397-08002B2CF9AE}" pid="2" name="Project"><vt:lpwstr>Alpha</vt:lpwstr></property></Properties>"#
+                .to_vec(),
+        );
+
+        let info = read_document_info(&package);
+        assert_eq!(info.title.as_deref(), Some("Network"));
+        assert_eq!(info.author.as_deref(), Some("Ivan"));
+        assert_eq!(info.keywords.as_deref(), Some("lan; wan"));
+        assert_eq!(info.subject, None);
+        assert_eq!(info.created.as_deref(), Some("2024-03-01T12:30:00Z"));
+        assert_eq!(info.company.as_deref(), Some("ACME"));
+        assert_eq!(info.template.as_deref(), Some("BASIC_M.VSTX"));
+        assert_eq!(info.custom["Project"], "Alpha");
+    }
+}
@@ -1,18 +1,21 @@
-use serde::Serialize;
-use std::fs::File;
-use std::io::BufReader;
-use std::{collections::HashMap, fs};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
 use xml::reader::{EventReader, XmlEvent};
-use zip::read::ZipFile;
 
 use super::helpers::attrs_to_hashmap;
 use crate::parser_vsdx::read_vsdx::Diagram;
 
-#[derive(Serialize, Debug, Clone)]
+/// Элемент XML-части в плоском представлении: дочерние элементы хранятся по `inner_id`
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Element {
     pub inner_id: i64,
     pub name: String,
+    #[serde(serialize_with = "crate::json::sorted_map")]
     pub attrs: HashMap<String, String>,
+    /// Ячейки `Cell` элемента: имя -> атрибуты ячейки
+    #[serde(serialize_with = "crate::json::sorted_nested_map")]
     pub params: HashMap<String, HashMap<String, String>>,
     pub children: Vec<i64>,
     pub parent: i64,
@@ -24,22 +27,20 @@ impl Element {
     }
 }
 
-pub fn encoding(
-    file: &mut ZipFile<'_, BufReader<File>>,
+/// Разбирает XML-часть в плоскую таблицу элементов; связи `.rels` попадают в `diagram.rels`.
+/// Ошибка XML прерывает разбор: усечённая или испорченная часть не выдаётся за целую
+pub fn encoding<R: Read>(
+    file: R,
+    fname: &str,
     diagram: &mut Diagram,
-) -> HashMap<i64, Element> {
-    let mangled_name = &file.mangled_name();
-
-    let fname = mangled_name.file_name().unwrap().to_str().unwrap();
-
+) -> Result<HashMap<i64, Element>, xml::reader::Error> {
     let mut temp_hash: HashMap<String, String> = HashMap::new();
 
-    let xmlfile: BufReader<&mut zip::read::ZipFile<'_, BufReader<fs::File>>> = BufReader::new(file);
+    let xmlfile = BufReader::new(file);
 
     let mut hash_elements: HashMap<i64, Element> = HashMap::new();
 
-    let parser: EventReader<BufReader<&mut zip::read::ZipFile<'_, BufReader<fs::File>>>> =
-        EventReader::new(xmlfile);
+    let parser = EventReader::new(xmlfile);
     let mut number_count = 0;
     let mut stack_numbers: Vec<i64> = Vec::new();
 
@@ -71,10 +72,7 @@ pub fn encoding(
 
                 number_count += 1;
 
-                let parent = match stack_numbers.last() {
-                    Some(parent) => parent,
-                    None => &0,
-                };
+                let parent = stack_numbers.last().unwrap_or(&0);
 
                 let element = Element {
                     inner_id: number_count,
@@ -85,11 +83,6 @@ pub fn encoding(
                     params: HashMap::new(),
                 };
 
-                if tag_name != "Cell" {
-                    hash_elements.insert(number_count, element.clone());
-                }
-                // println!("{}", tag_name);
-
                 hash_elements.entry(*parent).and_modify(|e: &mut Element| {
                     if tag_name == "Cell" {
                         let mut param_name = String::new();
@@ -109,18 +102,20 @@ pub fn encoding(
                     }
                 });
 
+                // Ячейки хранятся только в `params` родителя
+                if tag_name != "Cell" {
+                    hash_elements.insert(number_count, element);
+                }
+
                 stack_numbers.push(number_count);
             }
             Ok(XmlEvent::EndElement { .. }) => {
                 stack_numbers.pop();
             }
-            Ok(XmlEvent::EndDocument { .. }) => {}
+            Ok(XmlEvent::EndDocument) => {}
             Ok(XmlEvent::Characters(text)) => {
                 number_count += 1;
-                let parent = match stack_numbers.last() {
-                    Some(parent) => parent,
-                    None => &0,
-                };
+                let parent = stack_numbers.last().unwrap_or(&0);
 
                 let mut attrs = HashMap::new();
 
@@ -135,17 +130,14 @@ pub fn encoding(
                     params: HashMap::new(),
                 };
 
-                hash_elements.insert(number_count, element.to_owned());
-            }
-            Err(e) => {
-                eprintln!("Error: {e}");
-                break;
+                hash_elements.insert(number_count, element);
             }
+            Err(e) => return Err(e),
             _ => {}
         }
     }
     if fname.ends_with(".rels") {
         diagram.rels.insert(fname.to_owned(), temp_hash);
     };
-    hash_elements
+    Ok(hash_elements)
 }
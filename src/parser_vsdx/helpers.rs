@@ -4,7 +4,7 @@ use xml::attribute::OwnedAttribute;
 
 use crate::{parser_vsdx::get_metadata::Element, parser_vsdx::read_vsdx::Diagram};
 
-pub fn attrs_to_hashmap(attributes: &Vec<OwnedAttribute>) -> HashMap<String, String> {
+pub fn attrs_to_hashmap(attributes: &[OwnedAttribute]) -> HashMap<String, String> {
     let mut attrs: HashMap<String, String> = HashMap::new();
 
     for attr in attributes {
@@ -17,16 +17,20 @@ pub fn attrs_to_hashmap(attributes: &Vec<OwnedAttribute>) -> HashMap<String, Str
 }
 
 pub fn get_masters_rel(hash_elements: &HashMap<i64, Element>, diagram: &mut Diagram) {
-    for (id, el) in hash_elements {
+    for el in hash_elements.values() {
         if el.name == "Master" {
+            let Some(id) = el.attrs.get("ID") else {
+                continue;
+            };
             for attr_id in &el.children {
-                let child = hash_elements.get(&attr_id).unwrap();
+                let Some(child) = hash_elements.get(attr_id) else {
+                    continue;
+                };
 
-                if child.name == "Rel" {
-                    let rel_id = child.attrs.get("id").unwrap();
-                    diagram
-                        .masters_rel
-                        .insert(id.to_string(), rel_id.to_owned());
+                if child.name == "Rel"
+                    && let Some(rel_id) = child.attrs.get("id")
+                {
+                    diagram.masters_rel.insert(id.to_owned(), rel_id.to_owned());
                 }
             }
         }
@@ -0,0 +1,747 @@
+//! Режим точного чтения и записи XML-частей пакета.
+//!
+//! В отличие от `get_metadata::encoding`, здесь сохраняются полные имена с префиксами,
+//! объявления пространств имён и атрибуты в исходном порядке, кавычки вокруг значений,
+//! комментарии, инструкции обработки, CDATA и текстовые узлы на своих местах, так что
+//! часть можно записать обратно.
+
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read, Seek, Write},
+};
+
+use xml::{
+    ParserConfig,
+    name::OwnedName,
+    namespace::Namespace,
+    reader::{EventReader, XmlEvent},
+};
+
+use super::read_vsdx::VsdxError;
+
+/// Полное имя элемента или атрибута
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QName {
+    pub prefix: Option<String>,
+    pub local_name: String,
+    /// URI пространства имён, к которому относится имя
+    pub namespace: Option<String>,
+}
+
+impl QName {
+    fn from_owned(name: OwnedName) -> Self {
+        Self {
+            prefix: name.prefix,
+            local_name: name.local_name,
+            namespace: name.namespace,
+        }
+    }
+
+    /// Имя в том виде, как оно записано в документе: `prefix:local`
+    pub fn qualified(&self) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{}:{}", prefix, self.local_name),
+            None => self.local_name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlAttribute {
+    pub name: QName,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlElement {
+    pub name: QName,
+    /// Пространства имён, объявленные на этом элементе, в исходном порядке:
+    /// префикс (пустой — по умолчанию) -> URI. Пишутся перед атрибутами, как у Visio
+    pub namespaces: Vec<(String, String)>,
+    /// Атрибуты в исходном порядке
+    pub attributes: Vec<XmlAttribute>,
+    /// Кавычка вокруг значений атрибутов: Visio пишет свои части с `'`
+    pub quote: char,
+    pub children: Vec<XmlNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+    CData(String),
+    Comment(String),
+    ProcessingInstruction { name: String, data: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlDeclaration {
+    pub version: String,
+    pub encoding: Option<String>,
+    pub standalone: Option<bool>,
+    pub quote: char,
+    /// Пробел перед `?>`, как в `<?xml version='1.0' encoding='utf-8' ?>`
+    pub space_before_end: bool,
+}
+
+/// Документ целиком: объявление, узлы до и после корня и сам корень
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlDocument {
+    pub bom: bool,
+    pub declaration: Option<XmlDeclaration>,
+    pub prolog: Vec<XmlNode>,
+    pub root: XmlElement,
+    pub epilog: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    /// Значение атрибута по локальному имени
+    pub fn attr(&self, local_name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|a| a.name.local_name == local_name)
+            .map(|a| a.value.as_str())
+    }
+
+    /// Дочерние элементы без текста и комментариев
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|node| match node {
+            XmlNode::Element(el) => Some(el),
+            _ => None,
+        })
+    }
+}
+
+fn push_text(nodes: &mut Vec<XmlNode>, text: String) {
+    // Соседние текстовые события (например, разбитые сущностями) храним одним узлом
+    if let Some(XmlNode::Text(last)) = nodes.last_mut() {
+        last.push_str(&text);
+    } else {
+        nodes.push(XmlNode::Text(text));
+    }
+}
+
+/// То, что xml-rs не сообщает об открывающем теге: префиксы объявлений пространств имён
+/// в исходном порядке и кавычка первого атрибута
+#[derive(Debug, Default, PartialEq)]
+struct TagLayout {
+    namespaces: Vec<String>,
+    quote: Option<char>,
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+/// [`TagLayout`] всех открывающих тегов в порядке документа. Комментарии, CDATA,
+/// инструкции и закрывающие теги пропускаются; на незакрытой конструкции проход обрывается
+fn scan_tags(body: &[u8]) -> Vec<TagLayout> {
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = body[pos..].iter().position(|&b| b == b'<') {
+        pos += offset;
+        let rest = &body[pos..];
+        let skip_to = |end: &[u8]| find(rest, end).map(|at| at + end.len());
+        let len = if rest.starts_with(b"<!--") {
+            skip_to(b"-->")
+        } else if rest.starts_with(b"<![CDATA[") {
+            skip_to(b"]]>")
+        } else if rest.starts_with(b"<?") {
+            skip_to(b"?>")
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") {
+            skip_to(b">")
+        } else {
+            scan_start_tag(rest).map(|(layout, len)| {
+                tags.push(layout);
+                len
+            })
+        };
+        match len {
+            Some(len) => pos += len,
+            None => break,
+        }
+    }
+    tags
+}
+
+/// Разбирает открывающий тег с начала `tag`; возвращает раскладку и длину тега
+fn scan_start_tag(tag: &[u8]) -> Option<(TagLayout, usize)> {
+    let mut layout = TagLayout::default();
+    // Имя атрибута лежит между концом предыдущего значения и знаком `=`
+    let mut token_start = 1;
+    let mut name: &[u8] = &[];
+    let mut pos = 1;
+    while pos < tag.len() {
+        match tag[pos] {
+            b'>' => return Some((layout, pos + 1)),
+            b'=' => name = tag[token_start..pos].trim_ascii(),
+            quote @ (b'"' | b'\'') => {
+                layout.quote.get_or_insert(quote as char);
+                let end = pos + 1 + tag[pos + 1..].iter().position(|&b| b == quote)?;
+                // Первый токен - имя элемента, оно отделено пробелом от имени атрибута
+                let name = name
+                    .rsplit(|b| b.is_ascii_whitespace())
+                    .next()
+                    .unwrap_or(name);
+                if name == b"xmlns" {
+                    layout.namespaces.push(String::new());
+                } else if let Some(prefix) = name.strip_prefix(b"xmlns:") {
+                    layout
+                        .namespaces
+                        .push(String::from_utf8_lossy(prefix).into_owned());
+                }
+                pos = end;
+                token_start = end + 1;
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Объявления на элементе в порядке `layout`; если раскладка не сходится с тем, что
+/// прочитал xml-rs (например, часть не в UTF-8), - объявления, которых нет у родителя
+fn element_namespaces(
+    current: &Namespace,
+    parent: Option<&Namespace>,
+    layout: Option<&TagLayout>,
+) -> Vec<(String, String)> {
+    let declared = declared_here(current, parent);
+    match layout {
+        Some(layout)
+            if declared
+                .iter()
+                .all(|(prefix, _)| layout.namespaces.contains(prefix)) =>
+        {
+            layout
+                .namespaces
+                .iter()
+                .filter_map(|prefix| Some((prefix.clone(), current.get(prefix)?.to_owned())))
+                .collect()
+        }
+        _ => declared,
+    }
+}
+
+/// Объявления, которых нет у родителя
+fn declared_here(current: &Namespace, parent: Option<&Namespace>) -> Vec<(String, String)> {
+    current
+        .0
+        .iter()
+        .filter(|(prefix, uri)| {
+            !matches!(prefix.as_str(), "xml" | "xmlns")
+                && match parent {
+                    Some(parent) => parent.get(prefix.as_str()) != Some(uri.as_str()),
+                    None => !uri.is_empty(),
+                }
+        })
+        .map(|(prefix, uri)| (prefix.to_owned(), uri.to_owned()))
+        .collect()
+}
+
+impl XmlDocument {
+    pub fn parse(data: &[u8]) -> Result<XmlDocument, VsdxError> {
+        let bom = data.starts_with(&[0xEF, 0xBB, 0xBF]);
+        let body = &data[if bom { 3 } else { 0 }..];
+        let has_declaration = body.starts_with(b"<?xml");
+        // xml-rs подставляет кодировку по умолчанию, поэтому её наличие проверяем по исходным байтам
+        let declared_encoding = body
+            .iter()
+            .position(|&b| b == b'>')
+            .is_some_and(|end| body[..end].windows(8).any(|w| w == b"encoding"));
+        let declaration_end = has_declaration.then(|| find(body, b"?>")).flatten();
+        let declaration_quote = declaration_end
+            .and_then(|end| body[..end].iter().find(|&&b| b == b'\'' || b == b'"'))
+            .map_or('"', |&b| b as char);
+        let space_before_end =
+            declaration_end.is_some_and(|end| end > 0 && body[end - 1].is_ascii_whitespace());
+        let tags = scan_tags(body);
+        let mut tag_index = 0;
+
+        let config = ParserConfig::new()
+            .trim_whitespace(false)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(false)
+            .ignore_comments(false)
+            .coalesce_characters(false)
+            .ignore_root_level_whitespace(false);
+        let reader = EventReader::new_with_config(data, config);
+
+        let mut declaration = None;
+        let mut prolog = Vec::new();
+        let mut epilog = Vec::new();
+        let mut root = None;
+        // Открытые элементы вместе с их полным набором пространств имён
+        let mut stack: Vec<(XmlElement, Namespace)> = Vec::new();
+
+        for event in reader {
+            let event = event?;
+            let nodes = match stack.last_mut() {
+                Some((el, _)) => &mut el.children,
+                None if root.is_none() => &mut prolog,
+                None => &mut epilog,
+            };
+            match event {
+                XmlEvent::StartDocument {
+                    version,
+                    encoding,
+                    standalone,
+                } => {
+                    if has_declaration {
+                        declaration = Some(XmlDeclaration {
+                            version: version.to_string(),
+                            encoding: declared_encoding.then_some(encoding),
+                            standalone,
+                            quote: declaration_quote,
+                            space_before_end,
+                        });
+                    }
+                }
+                XmlEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                } => {
+                    let layout = tags.get(tag_index);
+                    tag_index += 1;
+                    let element = XmlElement {
+                        name: QName::from_owned(name),
+                        namespaces: element_namespaces(
+                            &namespace,
+                            stack.last().map(|(_, ns)| ns),
+                            layout,
+                        ),
+                        quote: layout.and_then(|l| l.quote).unwrap_or('"'),
+                        attributes: attributes
+                            .into_iter()
+                            .map(|a| XmlAttribute {
+                                name: QName::from_owned(a.name),
+                                value: a.value,
+                            })
+                            .collect(),
+                        children: Vec::new(),
+                    };
+                    stack.push((element, namespace));
+                }
+                XmlEvent::EndElement { .. } => {
+                    let Some((element, _)) = stack.pop() else {
+                        continue;
+                    };
+                    match stack.last_mut() {
+                        Some((parent, _)) => parent.children.push(XmlNode::Element(element)),
+                        None => root = Some(element),
+                    }
+                }
+                XmlEvent::Characters(text) | XmlEvent::Whitespace(text) => push_text(nodes, text),
+                XmlEvent::CData(text) => nodes.push(XmlNode::CData(text)),
+                XmlEvent::Comment(text) => nodes.push(XmlNode::Comment(text)),
+                XmlEvent::ProcessingInstruction { name, data } => {
+                    nodes.push(XmlNode::ProcessingInstruction { name, data })
+                }
+                XmlEvent::EndDocument => break,
+            }
+        }
+
+        let root = root.ok_or(VsdxError::Format("XML part has no root element".into()))?;
+        Ok(XmlDocument {
+            bom,
+            declaration,
+            prolog,
+            root,
+            epilog,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = String::new();
+        if let Some(decl) = &self.declaration {
+            let q = decl.quote;
+            out.push_str(&format!("<?xml version={q}{}{q}", decl.version));
+            if let Some(encoding) = &decl.encoding {
+                out.push_str(&format!(" encoding={q}{}{q}", encoding));
+            }
+            if let Some(standalone) = decl.standalone {
+                let value = if standalone { "yes" } else { "no" };
+                out.push_str(&format!(" standalone={q}{value}{q}"));
+            }
+            out.push_str(if decl.space_before_end { " ?>" } else { "?>" });
+        }
+        for node in &self.prolog {
+            write_node(&mut out, node);
+        }
+        write_element(&mut out, &self.root);
+        for node in &self.epilog {
+            write_node(&mut out, node);
+        }
+
+        let mut bytes = Vec::with_capacity(out.len() + 3);
+        if self.bom {
+            bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+        }
+        bytes.extend_from_slice(out.as_bytes());
+        bytes
+    }
+}
+
+/// Экранирует текст; для значения атрибута передаётся кавычка вокруг него
+fn escape(text: &str, quote: Option<char>) -> String {
+    let attribute = quote.is_some();
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if quote == Some('"') => out.push_str("&quot;"),
+            '\'' if quote == Some('\'') => out.push_str("&apos;"),
+            '\n' if attribute => out.push_str("&#xA;"),
+            '\r' if attribute => out.push_str("&#xD;"),
+            '\t' if attribute => out.push_str("&#x9;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn write_node(out: &mut String, node: &XmlNode) {
+    match node {
+        XmlNode::Element(el) => write_element(out, el),
+        XmlNode::Text(text) => out.push_str(&escape(text, None)),
+        XmlNode::CData(text) => {
+            out.push_str("<![CDATA[");
+            out.push_str(text);
+            out.push_str("]]>");
+        }
+        XmlNode::Comment(text) => {
+            out.push_str("<!--");
+            out.push_str(text);
+            out.push_str("-->");
+        }
+        XmlNode::ProcessingInstruction { name, data } => {
+            out.push_str("<?");
+            out.push_str(name);
+            if let Some(data) = data {
+                out.push(' ');
+                out.push_str(data);
+            }
+            out.push_str("?>");
+        }
+    }
+}
+
+fn write_element(out: &mut String, el: &XmlElement) {
+    let name = el.name.qualified();
+    out.push('<');
+    out.push_str(&name);
+    let q = el.quote;
+    for (prefix, uri) in &el.namespaces {
+        let uri = escape(uri, Some(q));
+        if prefix.is_empty() {
+            out.push_str(&format!(" xmlns={q}{uri}{q}"));
+        } else {
+            out.push_str(&format!(" xmlns:{prefix}={q}{uri}{q}"));
+        }
+    }
+    for attr in &el.attributes {
+        let value = escape(&attr.value, Some(q));
+        out.push_str(&format!(" {}={q}{value}{q}", attr.name.qualified()));
+    }
+    if el.children.is_empty() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+    for child in &el.children {
+        write_node(out, child);
+    }
+    out.push_str("</");
+    out.push_str(&name);
+    out.push('>');
+}
+
+/// Содержимое части пакета в режиме точного чтения
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartContent {
+    Xml(Box<XmlDocument>),
+    Binary(Vec<u8>),
+}
+
+/// Пакет VSDX, разобранный без потерь: все части в исходном порядке архива
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LosslessPackage {
+    pub parts: Vec<(String, PartContent)>,
+}
+
+fn is_xml_part(name: &str) -> bool {
+    name.ends_with(".xml") || name.ends_with(".rels") || name.ends_with(".vml")
+}
+
+impl LosslessPackage {
+    pub fn read<R: Read + Seek>(reader: R) -> Result<LosslessPackage, VsdxError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut parts = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.name().to_string();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+
+            let content = if is_xml_part(&name) {
+                match XmlDocument::parse(&data) {
+                    Ok(doc) => PartContent::Xml(Box::new(doc)),
+                    Err(e) => {
+                        log::warn!("Part {} is kept as binary: {}", name, e);
+                        PartContent::Binary(data)
+                    }
+                }
+            } else {
+                PartContent::Binary(data)
+            };
+            parts.push((name, content));
+        }
+        Ok(LosslessPackage { parts })
+    }
+
+    pub fn part(&self, name: &str) -> Option<&PartContent> {
+        self.parts.iter().find(|(n, _)| n == name).map(|(_, c)| c)
+    }
+
+    pub fn part_mut(&mut self, name: &str) -> Option<&mut PartContent> {
+        self.parts
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| c)
+    }
+
+    /// Байты всех частей после сериализации
+    pub fn to_parts(&self) -> BTreeMap<String, Vec<u8>> {
+        self.parts
+            .iter()
+            .map(|(name, content)| {
+                let data = match content {
+                    PartContent::Xml(doc) => doc.to_bytes(),
+                    PartContent::Binary(data) => data.clone(),
+                };
+                (name.clone(), data)
+            })
+            .collect()
+    }
+
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<W, VsdxError> {
+        let mut zip = zip::ZipWriter::new(writer);
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, content) in &self.parts {
+            zip.start_file(name.as_str(), options)?;
+            match content {
+                PartContent::Xml(doc) => zip.write_all(&doc.to_bytes())?,
+                PartContent::Binary(data) => zip.write_all(data)?,
+            }
+        }
+        Ok(zip.finish()?)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, VsdxError> {
+        Ok(self.write(Cursor::new(Vec::new()))?.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::Diagram;
+    use crate::parser_vsdx::write_vsdx::{self, tests::sample_page};
+
+    const HANDWRITTEN: &str = "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\r\n\
+        <!-- prolog comment -->\
+        <VisioDocument xmlns=\"http://schemas.microsoft.com/office/visio/2012/main\" \
+        xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+        xml:space=\"preserve\" Z=\"1\" A=\"2\">\r\n\
+        <?custom-pi some data?>\
+        <Text>before<cp IX=\"0\"/>middle &amp; <![CDATA[<raw>]]> after</Text>\
+        <r:Ext r:id=\"rId1\"/>\
+        </VisioDocument>";
+
+    #[test]
+    fn test_handwritten_part_is_byte_identical() {
+        let doc = XmlDocument::parse(HANDWRITTEN.as_bytes()).unwrap();
+        assert_eq!(doc.root.namespaces.len(), 2);
+        assert_eq!(doc.root.attributes[1].name.local_name, "Z");
+        assert_eq!(doc.root.attributes[0].name.prefix.as_deref(), Some("xml"));
+        assert_eq!(String::from_utf8(doc.to_bytes()).unwrap(), HANDWRITTEN);
+    }
+
+    #[test]
+    fn test_every_part_round_trips() {
+        let mut diagram = Diagram {
+            pages: vec![sample_page(&["multi\nline"])],
+            ..Default::default()
+        };
+        diagram.package.insert(
+            "visio/document.xml".to_string(),
+            HANDWRITTEN.as_bytes().to_vec(),
+        );
+
+        let bytes = write_vsdx::to_bytes(&diagram).unwrap();
+        let package = LosslessPackage::read(Cursor::new(bytes.clone())).unwrap();
+
+        let mut original = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let rewritten = package.to_parts();
+        assert_eq!(rewritten.len(), original.len());
+        for (name, data) in &rewritten {
+            let mut source = Vec::new();
+            original
+                .by_name(name)
+                .unwrap()
+                .read_to_end(&mut source)
+                .unwrap();
+            if data != &source {
+                // Допускается только XML-эквивалентность
+                assert_eq!(
+                    XmlDocument::parse(data).unwrap(),
+                    XmlDocument::parse(&source).unwrap(),
+                    "part {}",
+                    name
+                );
+            }
+        }
+
+        let again = LosslessPackage::read(Cursor::new(package.to_bytes().unwrap())).unwrap();
+        assert_eq!(again, package);
+    }
+
+    /// Части в том виде, как их пишут Visio и System.IO.Packaging: одинарные кавычки
+    /// и пробел перед `?>` в частях Visio, объявления пространств имён не по алфавиту,
+    /// элементы расширений, которых нет в модели
+    const VISIO_STYLE_PARTS: [(&str, &str); 8] = [
+        (
+            "[Content_Types].xml",
+            "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\r\n\
+            <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+            <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+            <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+            <Override PartName=\"/visio/document.xml\" ContentType=\"application/vnd.ms-visio.drawing.main+xml\"/>\
+            <Override PartName=\"/visio/pages/pages.xml\" ContentType=\"application/vnd.ms-visio.pages+xml\"/>\
+            <Override PartName=\"/visio/pages/page1.xml\" ContentType=\"application/vnd.ms-visio.page+xml\"/>\
+            <Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>\
+            </Types>",
+        ),
+        (
+            "_rels/.rels",
+            "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\r\n\
+            <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+            <Relationship Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" Target=\"docProps/core.xml\" Id=\"rId2\"/>\
+            <Relationship Type=\"http://schemas.microsoft.com/visio/2010/relationships/document\" Target=\"visio/document.xml\" Id=\"rId1\"/>\
+            </Relationships>",
+        ),
+        (
+            "docProps/core.xml",
+            "<?xml version='1.0' encoding='utf-8' ?>\
+            <cp:coreProperties xmlns:cp='http://schemas.openxmlformats.org/package/2006/metadata/core-properties' \
+            xmlns:dc='http://purl.org/dc/elements/1.1/' xmlns:dcterms='http://purl.org/dc/terms/' \
+            xmlns:dcmitype='http://purl.org/dc/dcmitype/' xmlns:xsi='http://www.w3.org/2001/XMLSchema-instance'>\
+            <dc:title>Office network</dc:title><dc:creator>Anna Petrova</dc:creator>\
+            <dcterms:created xsi:type='dcterms:W3CDTF'>2024-03-05T10:00:00Z</dcterms:created>\
+            </cp:coreProperties>",
+        ),
+        (
+            "visio/document.xml",
+            "<?xml version='1.0' encoding='utf-8' ?>\
+            <VisioDocument xmlns='http://schemas.microsoft.com/office/visio/2012/main' \
+            xmlns:r='http://schemas.openxmlformats.org/officeDocument/2006/relationships' xml:space='preserve'>\
+            <DocumentSettings TopPage='0' DefaultTextStyle='3' DefaultLineStyle='3' DefaultFillStyle='3' DefaultGuideStyle='4'>\
+            <GlueSettings>9</GlueSettings><SnapSettings>65847</SnapSettings><SnapExtensions>34</SnapExtensions>\
+            <SnapAngles/><DynamicGridEnabled>1</DynamicGridEnabled></DocumentSettings>\
+            <Colors><ColorEntry IX='24' RGB='#000000'/></Colors>\
+            <FaceNames><FaceName NameU='Calibri' UnicodeRanges='-469750017 -1073732485 9 0' \
+            CharSets='536871423 0' Panose='2 15 5 2 2 2 4 3 2 4' Flags='325'/></FaceNames>\
+            <EventList><EventItem ID='1' Action='1' EventCode='1' Enabled='1' Target='' TargetArgs='&apos;x&apos;'/></EventList>\
+            </VisioDocument>",
+        ),
+        (
+            "visio/_rels/document.xml.rels",
+            "<?xml version='1.0' encoding='utf-8' ?>\
+            <Relationships xmlns='http://schemas.openxmlformats.org/package/2006/relationships'>\
+            <Relationship Id='rId1' Type='http://schemas.microsoft.com/visio/2010/relationships/pages' Target='pages/pages.xml'/>\
+            </Relationships>",
+        ),
+        (
+            "visio/pages/pages.xml",
+            "<?xml version='1.0' encoding='utf-8' ?>\
+            <Pages xmlns='http://schemas.microsoft.com/office/visio/2012/main' \
+            xmlns:r='http://schemas.openxmlformats.org/officeDocument/2006/relationships' xml:space='preserve'>\
+            <Page ID='0' NameU='Page-1' Name='Page-1' ViewScale='-1' ViewCenterX='4.25' ViewCenterY='5.5'>\
+            <PageSheet LineStyle='0' FillStyle='0' TextStyle='0'><Cell N='PageWidth' V='8.5'/>\
+            <Cell N='PageHeight' V='11'/></PageSheet><Rel r:id='rId1'/></Page></Pages>",
+        ),
+        (
+            "visio/pages/_rels/pages.xml.rels",
+            "<?xml version='1.0' encoding='utf-8' ?>\
+            <Relationships xmlns='http://schemas.openxmlformats.org/package/2006/relationships'>\
+            <Relationship Id='rId1' Type='http://schemas.microsoft.com/visio/2010/relationships/page' Target='page1.xml'/>\
+            </Relationships>",
+        ),
+        (
+            "visio/pages/page1.xml",
+            "<?xml version='1.0' encoding='utf-8' ?>\
+            <PageContents xmlns='http://schemas.microsoft.com/office/visio/2012/main' \
+            xmlns:r='http://schemas.openxmlformats.org/officeDocument/2006/relationships' \
+            xmlns:mc='http://schemas.openxmlformats.org/markup-compatibility/2006' \
+            xmlns:v16='http://schemas.microsoft.com/office/visio/2016/main' mc:Ignorable='v16' xml:space='preserve'>\
+            <Shapes><Shape ID='1' Type='Shape' LineStyle='3' FillStyle='3' TextStyle='3'>\
+            <Cell N='PinX' V='4.25'/><Cell N='PinY' V='5.5'/><Cell N='Width' V='1'/><Cell N='Height' V='0.75'/>\
+            <Section N='Geometry' IX='0'><Row T='RelMoveTo' IX='1'><Cell N='X' V='0'/><Cell N='Y' V='0'/></Row></Section>\
+            <Text><cp IX='0'/>Router &amp; switch</Text>\
+            <v16:Extension xmlns:x='urn:example:extension' Kind='Unknown'><x:Data>kept</x:Data></v16:Extension>\
+            </Shape></Shapes></PageContents>",
+        ),
+    ];
+
+    #[test]
+    fn test_visio_style_package_is_byte_identical() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in VISIO_STYLE_PARTS {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let package = LosslessPackage::read(Cursor::new(bytes.clone())).unwrap();
+        let Some(PartContent::Xml(core)) = package.part("docProps/core.xml") else {
+            panic!("core.xml is not parsed");
+        };
+        let prefixes: Vec<&str> = core
+            .root
+            .namespaces
+            .iter()
+            .map(|(p, _)| p.as_str())
+            .collect();
+        assert_eq!(prefixes, ["cp", "dc", "dcterms", "dcmitype", "xsi"]);
+        assert_eq!(core.root.quote, '\'');
+
+        let rewritten = package.to_parts();
+        for (name, data) in VISIO_STYLE_PARTS {
+            assert_eq!(
+                String::from_utf8_lossy(&rewritten[name]),
+                data,
+                "part {}",
+                name
+            );
+        }
+
+        // Модель читается из того же пакета, несмотря на незнакомые элементы
+        let diagram = crate::parser_vsdx::read_vsdx::parse(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            diagram.pages[0].shapes[0].text.as_deref(),
+            Some("Router & switch")
+        );
+        assert_eq!(
+            diagram.document_info.title.as_deref(),
+            Some("Office network")
+        );
+    }
+}
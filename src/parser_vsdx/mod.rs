@@ -1,37 +1,621 @@
-mod get_metadata;
+mod cell_name;
+pub(crate) mod comments;
+mod doc_props;
+pub(crate) mod get_metadata;
 mod helpers;
+pub mod lossless;
+pub(crate) mod shapes;
+pub mod stream;
+mod vba;
+pub mod write_vsdx;
 pub mod read_vsdx {
-    use super::helpers::get_masters_rel;
-
     use serde_json::to_string_pretty;
-    use std::{collections::HashMap, fs, io::BufReader, path::Path};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        fs,
+        io::{self, BufReader, Cursor, Read, Seek},
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+    use thiserror::Error;
 
-    use serde::Serialize;
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
 
+    pub use super::cell_name::CellName;
     use super::get_metadata;
+    pub use super::get_metadata::Element;
+    use super::shapes::{DOCUMENT_PART, build_diagram};
+    pub use super::vba::{VbaProject, VbaStream};
+    use crate::json::{ordered_elements, sorted_map, sorted_nested_map};
+    use crate::measurement::{Measurement, Unit};
+
+    #[derive(Debug, Error)]
+    pub enum VsdxError {
+        #[error("IO error: {0}")]
+        Io(#[from] io::Error),
+        #[error("Zip error: {0}")]
+        Zip(#[from] zip::result::ZipError),
+        #[error("XML write error: {0}")]
+        Xml(#[from] xml::writer::Error),
+        #[error("XML read error: {0}")]
+        XmlRead(#[from] xml::reader::Error),
+        #[error("Invalid package: {0}")]
+        Format(String),
+    }
 
-    #[derive(Serialize, Debug, Clone)]
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
     pub struct Diagram {
+        #[serde(serialize_with = "sorted_nested_map")]
         pub rels: HashMap<String, HashMap<String, String>>,
         pub pages: Vec<Page>,
+        #[serde(serialize_with = "sorted_map")]
         pub masters_rel: HashMap<String, String>,
+        /// Мастера; в наборах элементов (VSSX, VSSM) это основное содержимое файла
+        pub masters: Vec<Master>,
+        /// Листы стилей из `document.xml`: у них та же структура ячеек, что и у фигур
+        pub style_sheets: Vec<Shape>,
+        /// Проект VBA документа с макросами (VSDM, VSSM, VSTM)
+        pub vba_project: Option<VbaProject>,
+        /// Свойства документа: `docProps/*.xml` в VSDX, наборы свойств OLE в VSD
+        #[serde(default)]
+        pub document_info: DocumentInfo,
+        /// Комментарии рецензентов: `visio/comments.xml` и строки `Annotation` листов страниц
+        #[serde(default)]
+        pub comments: Vec<Comment>,
+        /// Шрифты документа; ячейки `Font` бинарного формата ссылаются на них по `id`
+        #[serde(default)]
+        pub fonts: Vec<FontFace>,
+        /// Палитра документа `#RRGGBB`: индексированный цвет - номер в этом списке
+        #[serde(default)]
+        pub colors: Vec<String>,
+        /// Двоичные части по полному пути внутри пакета: изображения `visio/media/*`
+        /// и встроенные объекты `visio/embeddings/*`
+        #[serde(skip)]
+        pub media: HashMap<String, Vec<u8>>,
+        /// Исходные XML-части пакета по полному пути; писатель переносит их без изменений.
+        /// Заполняется только при разборе с `ParseOptions::keep_package`
+        #[serde(skip)]
+        pub package: BTreeMap<String, Vec<u8>>,
+    }
+
+    /// Сведения о документе. Даты - в ISO 8601 UTC
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct DocumentInfo {
+        pub title: Option<String>,
+        pub subject: Option<String>,
+        pub author: Option<String>,
+        pub manager: Option<String>,
+        pub company: Option<String>,
+        pub category: Option<String>,
+        pub keywords: Option<String>,
+        pub description: Option<String>,
+        pub last_modified_by: Option<String>,
+        pub created: Option<String>,
+        pub modified: Option<String>,
+        pub template: Option<String>,
+        /// Пользовательские свойства: имя -> значение в текстовом виде
+        pub custom: BTreeMap<String, String>,
     }
 
-    #[derive(Serialize, Debug, Clone)]
+    /// Гарнитура из таблицы шрифтов документа
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct FontFace {
+        pub id: String,
+        pub name: String,
+        /// Набор символов Windows (`lfCharSet`): 0 - ANSI, 204 - кириллица, 2 - символьный
+        pub charset: u8,
+        pub flags: u32,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
     pub struct Page {
-        shapes: Vec<Shape>,
+        pub id: String,
+        /// Универсальное имя (`NameU`), а без него - `Name`
+        pub name: String,
+        /// Локализованное имя (`Name`), если оно отличается от `name`
+        #[serde(default)]
+        pub local_name: Option<String>,
+        /// Размеры страницы в дюймах
+        pub width: f64,
+        pub height: f64,
+        pub background: bool,
+        pub background_page: Option<String>,
+        #[serde(serialize_with = "sorted_map")]
+        pub cells: HashMap<CellName, Cell>,
+        /// Секции `PageSheet` (слои, пользовательские ячейки)
+        pub sections: Vec<Section>,
+        /// Атрибуты элемента `Page`, которых нет в модели
+        pub attrs: BTreeMap<String, String>,
+        pub shapes: Vec<Shape>,
+        pub connects: Vec<Connect>,
+        /// Часть пакета с содержимым страницы, например `visio/pages/page1.xml`
+        #[serde(skip)]
+        pub part: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Master {
+        pub id: String,
+        pub name: String,
+        pub prompt: Option<String>,
+        /// Значок для окна фигур в base64, как в элементе `Icon`
+        pub icon: Option<String>,
+        /// Атрибуты элемента `Master`, которых нет в модели
+        pub attrs: BTreeMap<String, String>,
+        pub shapes: Vec<Shape>,
+        #[serde(skip)]
+        pub part: Option<String>,
+    }
+
+    /// Приклеивание ячейки одной фигуры к другой (`Connects/Connect`)
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Connect {
+        pub from_sheet: String,
+        pub from_cell: Option<String>,
+        pub from_part: Option<String>,
+        pub to_sheet: String,
+        pub to_cell: Option<String>,
+        pub to_part: Option<String>,
+    }
+
+    /// Комментарий рецензента к странице или фигуре на ней
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Comment {
+        pub id: Option<String>,
+        /// Комментарий, на который отвечает этот, в цепочках обсуждения
+        pub parent: Option<String>,
+        pub author: String,
+        pub initials: Option<String>,
+        /// Дата в ISO 8601
+        pub date: Option<String>,
+        pub text: String,
+        /// ID страницы и фигуры, к которым привязан комментарий
+        pub page: Option<String>,
+        pub shape: Option<String>,
+        /// Обсуждение помечено решённым
+        pub done: bool,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Shape {
+        pub id: String,
+        pub name: String,
+        pub shape_type: String,
+        pub master: Option<String>,
+        pub master_shape: Option<String>,
+        pub line_style: Option<String>,
+        pub fill_style: Option<String>,
+        pub text_style: Option<String>,
+        /// Атрибуты элемента `Shape`, которых нет в модели
+        pub attrs: BTreeMap<String, String>,
+        #[serde(serialize_with = "sorted_map")]
+        pub cells: HashMap<CellName, Cell>,
+        pub sections: Vec<Section>,
+        pub text: Option<String>,
+        /// Отрезки текста с общим форматированием; заполняются двоичным парсером
+        #[serde(default)]
+        pub text_runs: Vec<TextRun>,
+        pub foreign: Option<ForeignData>,
+        pub shapes: Vec<Shape>,
+    }
+
+    /// Отрезок текста фигуры с номерами строк секций `Character` и `Paragraph`
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct TextRun {
+        pub text: String,
+        pub character: Option<u32>,
+        pub paragraph: Option<u32>,
+    }
+
+    /// Значение ячейки ShapeSheet: `V` во внутренних единицах, `U` и `F` как в XML
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Cell {
+        pub value: String,
+        pub unit: Option<String>,
+        pub formula: Option<String>,
     }
 
-    #[derive(Serialize, Debug, Clone)]
-    pub struct Shape {}
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Section {
+        pub name: String,
+        pub index: Option<String>,
+        #[serde(serialize_with = "sorted_map")]
+        pub cells: HashMap<CellName, Cell>,
+        pub rows: Vec<Row>,
+    }
 
-    #[derive(Serialize, Debug, Clone)]
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct Row {
+        pub index: Option<String>,
+        pub name: Option<String>,
+        pub row_type: Option<String>,
+        pub deleted: bool,
+        #[serde(serialize_with = "sorted_map")]
+        pub cells: HashMap<CellName, Cell>,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+    pub struct ForeignData {
+        pub foreign_type: String,
+        pub compression: Option<String>,
+        pub rel_id: Option<String>,
+        /// Путь к данным внутри пакета, например `visio/media/image1.png`;
+        /// у объекта OLE это изображение предпросмотра
+        pub target: Option<String>,
+        /// Объект OLE показывается значком, а не содержимым
+        #[serde(default)]
+        pub show_as_icon: bool,
+        pub object_rel_id: Option<String>,
+        /// Встроенный объект, например `visio/embeddings/oleObject1.bin`
+        pub object_target: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Rel {
         id: String,
         type_url: String,
         target: String,
     }
 
+    impl Cell {
+        pub fn number(&self) -> Option<f64> {
+            self.value.trim().parse::<f64>().ok()
+        }
+
+        /// Значение в единицах отображения ячейки; `None`, если значение не число
+        /// или единица неизвестна
+        pub fn measurement(&self) -> Option<Measurement> {
+            let unit = Unit::from_code(self.unit.as_deref().unwrap_or(""))?;
+            Some(Measurement::from_internal(self.number()?, unit))
+        }
+    }
+
+    impl Page {
+        pub fn number(&self, name: &str) -> Option<f64> {
+            self.cells.get(name).and_then(Cell::number)
+        }
+
+        /// Гиперссылки листа страницы
+        pub fn hyperlinks(&self) -> Vec<Hyperlink> {
+            hyperlinks(self.sections.iter())
+        }
+    }
+
+    /// Строка секции `Hyperlink` фигуры или страницы
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct Hyperlink {
+        /// Имя строки, например `Row_1`
+        pub name: Option<String>,
+        pub description: String,
+        /// URL или путь к файлу; пусто у ссылки на страницу этого же документа
+        pub address: String,
+        /// Страница и фигура внутри документа: `Page-2` или `Page-2/Sheet.5`
+        pub sub_address: String,
+        pub extra_info: String,
+        pub frame: String,
+        pub new_window: bool,
+        /// Ссылка по умолчанию открывается щелчком по фигуре
+        pub default: bool,
+        pub invisible: bool,
+    }
+
+    impl Hyperlink {
+        pub fn from_row(row: &Row) -> Self {
+            let text = |name: &str| {
+                row.cells
+                    .get(name)
+                    .map(|c| c.value.clone())
+                    .unwrap_or_default()
+            };
+            let flag = |name: &str| row.number(name).is_some_and(|v| v != 0.0);
+            Hyperlink {
+                name: row.name.clone(),
+                description: text("Description"),
+                address: text("Address"),
+                sub_address: text("SubAddress"),
+                extra_info: text("ExtraInfo"),
+                frame: text("Frame"),
+                new_window: flag("NewWindow"),
+                default: flag("Default"),
+                invisible: flag("Invisible"),
+            }
+        }
+    }
+
+    fn hyperlinks<'a>(sections: impl Iterator<Item = &'a Section>) -> Vec<Hyperlink> {
+        sections
+            .filter(|s| s.name == "Hyperlink")
+            .flat_map(|s| s.rows.iter())
+            .filter(|row| !row.deleted)
+            .map(Hyperlink::from_row)
+            .collect()
+    }
+
+    impl Shape {
+        pub fn cell(&self, name: &str) -> Option<&Cell> {
+            self.cells.get(name)
+        }
+
+        pub fn number(&self, name: &str) -> Option<f64> {
+            self.cell(name).and_then(Cell::number)
+        }
+
+        /// Гиперссылки фигуры в порядке строк
+        pub fn hyperlinks(&self) -> Vec<Hyperlink> {
+            hyperlinks(self.sections.iter())
+        }
+
+        /// Ссылка, которая открывается щелчком по фигуре: с `Default`, иначе первая
+        pub fn default_hyperlink(&self) -> Option<Hyperlink> {
+            let links = self.hyperlinks();
+            let index = links.iter().position(|l| l.default).unwrap_or(0);
+            links.into_iter().nth(index)
+        }
+
+        /// Секции с именем `name`, упорядоченные по `IX`
+        pub fn sections_named(&self, name: &str) -> Vec<&Section> {
+            let mut found: Vec<&Section> =
+                self.sections.iter().filter(|s| s.name == name).collect();
+            found.sort_by_key(|s| s.index.as_deref().and_then(|i| i.parse::<u32>().ok()));
+            found
+        }
+    }
+
+    impl Section {
+        pub fn number(&self, name: &str) -> Option<f64> {
+            self.cells.get(name).and_then(Cell::number)
+        }
+    }
+
+    impl Row {
+        pub fn number(&self, name: &str) -> Option<f64> {
+            self.cells.get(name).and_then(Cell::number)
+        }
+    }
+
+    /// Настройки разбора пакета
+    #[derive(Debug, Clone)]
+    pub struct ParseOptions {
+        /// Число потоков для распаковки и разбора частей; 1 — последовательный разбор
+        pub threads: usize,
+        /// Сохранять исходные части в `Diagram::package`, чтобы `write_vsdx` мог записать
+        /// пакет обратно без потерь; без этого флага части освобождаются после разбора
+        pub keep_package: bool,
+    }
+
+    impl Default for ParseOptions {
+        fn default() -> Self {
+            Self {
+                threads: 1,
+                keep_package: false,
+            }
+        }
+    }
+
+    impl ParseOptions {
+        /// По потоку на каждое доступное ядро
+        pub fn parallel() -> Self {
+            Self {
+                threads: thread::available_parallelism().map_or(1, |n| n.get()),
+                ..Self::default()
+            }
+        }
+
+        /// Последовательный разбор с сохранением частей для записи обратно
+        pub fn editable() -> Self {
+            Self {
+                keep_package: true,
+                ..Self::default()
+            }
+        }
+    }
+
+    /// Открывает и разбирает пакет VSDX; так же читаются VSDM, VSSX, VSSM, VSTX и VSTM
+    pub fn open(fname: &Path) -> Result<Diagram, VsdxError> {
+        open_with_options(fname, &ParseOptions::default())
+    }
+
+    pub fn open_with_options(fname: &Path, options: &ParseOptions) -> Result<Diagram, VsdxError> {
+        let file = fs::File::open(fname)?;
+        parse_with_options(BufReader::new(file), options)
+    }
+
+    /// Разбирает пакет VSDX из любого источника с произвольным доступом
+    pub fn parse<R: Read + Seek>(reader: R) -> Result<Diagram, VsdxError> {
+        parse_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Разбирает пакет; при `threads > 1` части распаковываются и разбираются параллельно,
+    /// а результат собирается в порядке записей архива и совпадает с последовательным
+    pub fn parse_with_options<R: Read + Seek>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<Diagram, VsdxError> {
+        if options.threads <= 1 {
+            return load(reader, options.keep_package, |_, _| {});
+        }
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let archive = zip::ZipArchive::new(Cursor::new(bytes.as_slice()))?;
+        let parts = read_parts_parallel(&archive, options.threads)?;
+        Ok(assemble(parts, options.keep_package, |_, _| {}))
+    }
+
+    /// Распакованная и разобранная часть пакета
+    struct LoadedPart {
+        name: String,
+        fname: String,
+        data: Vec<u8>,
+        elements: Option<HashMap<i64, Element>>,
+        rels: Option<HashMap<String, String>>,
+    }
+
+    fn read_part<R: Read + Seek>(
+        archive: &mut zip::ZipArchive<R>,
+        index: usize,
+    ) -> Result<Option<LoadedPart>, VsdxError> {
+        let mut file = archive.by_index(index)?;
+        let outpath = match file.enclosed_name() {
+            Some(path) => path,
+            None => {
+                log::warn!("Entry {} has a suspicious path", file.name());
+                return Ok(None);
+            }
+        };
+
+        if file.is_dir() {
+            return Ok(None);
+        }
+
+        let name = outpath.to_string_lossy().replace('\\', "/");
+        let fname = match outpath.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_owned(),
+            None => {
+                log::warn!("Entry {} has no file name", file.name());
+                return Ok(None);
+            }
+        };
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        let mut part = LoadedPart {
+            name,
+            fname,
+            data,
+            elements: None,
+            rels: None,
+        };
+        if !is_binary_part(&part.name)
+            && (part.fname.ends_with(".xml") || part.fname.ends_with(".rels"))
+        {
+            let mut scratch = Diagram::default();
+            part.elements = Some(get_metadata::encoding(
+                Cursor::new(&part.data),
+                &part.fname,
+                &mut scratch,
+            )?);
+            part.rels = scratch.rels.remove(&part.fname);
+        }
+        Ok(Some(part))
+    }
+
+    fn read_parts_parallel(
+        archive: &zip::ZipArchive<Cursor<&[u8]>>,
+        threads: usize,
+    ) -> Result<Vec<LoadedPart>, VsdxError> {
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<(usize, Result<Option<LoadedPart>, VsdxError>)> =
+            thread::scope(|scope| {
+                let workers: Vec<_> = (0..threads.min(archive.len()))
+                    .map(|_| {
+                        scope.spawn(|| {
+                            let mut archive = archive.clone();
+                            let mut done = Vec::new();
+                            loop {
+                                let index = next.fetch_add(1, Ordering::Relaxed);
+                                if index >= archive.len() {
+                                    break;
+                                }
+                                done.push((index, read_part(&mut archive, index)));
+                            }
+                            done
+                        })
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| {
+                        worker
+                            .join()
+                            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                    })
+                    .collect()
+            });
+
+        results.sort_by_key(|(index, _)| *index);
+        let mut parts = Vec::with_capacity(results.len());
+        for (_, part) in results {
+            if let Some(part) = part? {
+                parts.push(part);
+            }
+        }
+        Ok(parts)
+    }
+
+    fn load<R: Read + Seek>(
+        reader: R,
+        keep_package: bool,
+        on_part: impl FnMut(&str, &HashMap<i64, Element>),
+    ) -> Result<Diagram, VsdxError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut parts = Vec::new();
+        for i in 0..archive.len() {
+            if let Some(part) = read_part(&mut archive, i)? {
+                parts.push(part);
+            }
+        }
+        Ok(assemble(parts, keep_package, on_part))
+    }
+
+    /// Изображения и встроенные объекты попадают в `Diagram::media`
+    fn is_binary_part(name: &str) -> bool {
+        name.starts_with("visio/media/") || name.starts_with("visio/embeddings/")
+    }
+
+    /// Части, которые читаются из `Diagram::package` уже после сборки страниц и мастеров:
+    /// свойства документа, проект VBA и комментарии
+    fn read_after_build(name: &str) -> bool {
+        !(name.starts_with("visio/pages/")
+            || name.starts_with("visio/masters/")
+            || name == DOCUMENT_PART)
+    }
+
+    /// Собирает модель из частей в порядке записей архива. Без `keep_package` в пакете
+    /// остаются только части, нужные после сборки, и они освобождаются в конце
+    fn assemble(
+        loaded: Vec<LoadedPart>,
+        keep_package: bool,
+        mut on_part: impl FnMut(&str, &HashMap<i64, Element>),
+    ) -> Diagram {
+        let mut diagram = Diagram::default();
+        let mut parts: HashMap<String, HashMap<i64, Element>> = HashMap::new();
+
+        for part in loaded {
+            if is_binary_part(&part.name) {
+                diagram.media.insert(part.name, part.data);
+                continue;
+            }
+            if let Some(rels) = part.rels {
+                diagram.rels.insert(part.fname.clone(), rels);
+            }
+            if let Some(elements) = part.elements {
+                on_part(&part.fname, &elements);
+                parts.insert(part.name.clone(), elements);
+            }
+            if keep_package || read_after_build(&part.name) {
+                diagram.package.insert(part.name, part.data);
+            }
+        }
+
+        CellName::scoped(|| build_diagram(&mut diagram, &parts));
+        diagram.vba_project = super::vba::read_project(&diagram);
+        diagram.document_info = super::doc_props::read_document_info(&diagram.package);
+        diagram.comments = super::comments::read_comments(&diagram.package);
+        diagram
+            .comments
+            .extend(super::comments::annotation_comments(
+                &super::comments::document_reviewers(&parts),
+                &diagram.pages,
+            ));
+        if !keep_package {
+            diagram.package.clear();
+        }
+        diagram
+    }
+
     pub fn read_file(fname: &Path, out_dir: &Path) {
         let file = match fs::File::open(fname) {
             Ok(res) => res,
@@ -42,85 +626,33 @@ pub mod read_vsdx {
         };
         let reader = BufReader::new(file);
 
-        let mut archive = zip::ZipArchive::new(reader).unwrap();
-        let mut diagram = Diagram {
-            pages: vec![],
-            rels: HashMap::new(),
-            masters_rel: HashMap::new(),
-        };
+        let res_folder = out_dir.join(std::path::Path::new("jsons"));
+        let _ = fs::create_dir(&res_folder);
 
-        for i in 0..archive.len() {
-            let mut file: zip::read::ZipFile<'_, BufReader<fs::File>> =
-                archive.by_index(i).unwrap();
-            let outpath = match file.enclosed_name() {
-                Some(path) => path,
-                None => {
-                    println!("Entry {} has a suspicious path", file.name());
-                    continue;
+        let diagram = load(reader, false, |part_name, hash_elements| {
+            let json_str = match to_string_pretty(&ordered_elements(hash_elements)) {
+                Ok(res) => res,
+                Err(_) => {
+                    print!("Err json");
+                    "No data".to_string()
                 }
             };
 
-            {
-                let comment = file.comment();
-                if !comment.is_empty() {
-                    println!("Entry {i} comment: {comment}");
-                }
-            }
-
-            if file.is_dir() {
-                println!(
-                    "Entry {} is a directory with name \"{}\"",
-                    i,
-                    outpath.display()
-                );
-            } else {
-                let mangled_name = &file.mangled_name();
-
-                let f_name_p = match mangled_name.file_name() {
-                    Some(name) => name,
-                    None => {
-                        println!("No name");
-                        continue;
-                    }
-                };
-
-                let fname = match f_name_p.to_str() {
-                    Some(name) => name,
-                    None => {
-                        println!("No name");
-                        continue;
-                    }
-                };
-
-                if !&fname.ends_with(".xml") && !&fname.ends_with(".xml.rels") {
-                    continue;
-                }
-
-                // if fname == "page2.xml" {
-                // println!("{:?}", &mangled_name.starts_with(base));
-                let hash_elements = get_metadata::encoding(&mut file, &mut diagram);
+            fs::write(
+                res_folder.join(std::path::Path::new(&(part_name.to_owned() + ".json"))),
+                json_str,
+            )
+            .expect("Unable to write file");
+        });
 
-                if fname == "masters.xml" {
-                    get_masters_rel(&hash_elements, &mut diagram);
-                }
-
-                let json_str = match to_string_pretty(&hash_elements) {
-                    Ok(res) => res,
-                    Err(_) => {
-                        print!("Err json");
-                        "No data".to_string()
-                    }
-                };
-                let res_folder = out_dir.join(std::path::Path::new(&("jsons")));
-                let _ = fs::create_dir(&res_folder);
-
-                fs::write(
-                    res_folder.join(std::path::Path::new(&(fname.to_owned() + ".json"))),
-                    json_str,
-                )
-                .expect("Unable to write file");
+        let diagram = match diagram {
+            Ok(diagram) => diagram,
+            Err(e) => {
+                println!("Read file Error{e}");
+                return;
             }
-        }
+        };
+
         let json_str = match to_string_pretty(&diagram) {
             Ok(res) => res,
             Err(_) => {
@@ -141,4 +673,41 @@ pub mod read_vsdx {
 
         print!("File {:?} converted!", &fname)
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::parser_vsdx::write_vsdx::{self, tests::sample_page};
+
+        #[test]
+        fn test_parallel_parse_matches_sequential() {
+            // Страниц больше, чем потоков, чтобы части разбирались вперемешку
+            let pages = (0..8)
+                .map(|i| Page {
+                    id: i.to_string(),
+                    name: format!("Page-{}", i + 1),
+                    ..sample_page(&["a", "b", "c"])
+                })
+                .collect();
+            let diagram = Diagram {
+                pages,
+                ..Default::default()
+            };
+            let bytes = write_vsdx::to_bytes(&diagram).unwrap();
+
+            let sequential =
+                parse_with_options(Cursor::new(bytes.clone()), &ParseOptions::editable()).unwrap();
+            let options = ParseOptions {
+                threads: 4,
+                ..ParseOptions::editable()
+            };
+            let parallel = parse_with_options(Cursor::new(bytes), &options).unwrap();
+
+            assert_eq!(parallel.pages.len(), 8);
+            assert_eq!(parallel.pages, sequential.pages);
+            assert_eq!(parallel.masters, sequential.masters);
+            assert_eq!(parallel.rels, sequential.rels);
+            assert_eq!(parallel.package, sequential.package);
+        }
+    }
 }
@@ -0,0 +1,694 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::field_format::field_texts;
+use crate::parser_vsdx::get_metadata::Element;
+use crate::parser_vsdx::helpers::get_masters_rel;
+use crate::parser_vsdx::read_vsdx::{
+    Cell, CellName, Connect, Diagram, ForeignData, Master, Page, Row, Section, Shape,
+};
+
+pub const MASTERS_PART: &str = "visio/masters/masters.xml";
+pub const PAGES_PART: &str = "visio/pages/pages.xml";
+pub const DOCUMENT_PART: &str = "visio/document.xml";
+
+/// Собирает мастера и страницы из разобранных частей пакета
+pub fn build_diagram(diagram: &mut Diagram, parts: &HashMap<String, HashMap<i64, Element>>) {
+    if let Some(document) = parts.get(DOCUMENT_PART) {
+        let chars = characters_by_parent(document);
+        diagram.style_sheets = sorted_by_name(document, "StyleSheet")
+            .into_iter()
+            .map(|el| build_shape(document, &chars, el))
+            .collect();
+    }
+
+    if let Some(masters) = parts.get(MASTERS_PART) {
+        get_masters_rel(masters, diagram);
+        diagram.masters = build_masters(diagram, masters);
+        for master in diagram.masters.iter_mut() {
+            let Some(part) = &master.part else {
+                continue;
+            };
+            let mut shapes = parts.get(part).map(build_shapes).unwrap_or_default();
+            resolve_foreign(&diagram.rels, part, &mut shapes);
+            master.shapes = shapes;
+        }
+    }
+
+    if let Some(pages) = parts.get(PAGES_PART) {
+        diagram.pages = build_pages(diagram, pages);
+        for page in diagram.pages.iter_mut() {
+            let Some(part) = &page.part else {
+                continue;
+            };
+            if let Some(hash_elements) = parts.get(part) {
+                page.shapes = build_shapes(hash_elements);
+                page.connects = build_connects(hash_elements);
+            }
+            resolve_foreign(&diagram.rels, part, &mut page.shapes);
+        }
+    }
+}
+
+/// Атрибуты, которые модель хранит в отдельных полях
+pub const MASTER_ATTRS: [&str; 4] = ["ID", "NameU", "Name", "Prompt"];
+pub const PAGE_ATTRS: [&str; 5] = ["ID", "NameU", "Name", "Background", "BackPage"];
+const SHAPE_ATTRS: [&str; 9] = [
+    "ID",
+    "NameU",
+    "Name",
+    "Type",
+    "Master",
+    "MasterShape",
+    "LineStyle",
+    "FillStyle",
+    "TextStyle",
+];
+
+pub fn other_attrs(attrs: &HashMap<String, String>, known: &[&str]) -> BTreeMap<String, String> {
+    attrs
+        .iter()
+        .filter(|(k, _)| !known.contains(&k.as_str()))
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect()
+}
+
+pub fn element_name(attrs: &HashMap<String, String>) -> String {
+    attrs
+        .get("NameU")
+        .or(attrs.get("Name"))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Атрибут `Name`, если он отличается от [`element_name`]
+pub fn element_local_name(attrs: &HashMap<String, String>) -> Option<String> {
+    attrs
+        .get("Name")
+        .filter(|name| attrs.get("NameU").is_some_and(|u| u != *name))
+        .cloned()
+}
+
+/// Мастера из `masters.xml` без содержимого фигур
+pub fn build_masters(diagram: &Diagram, masters: &HashMap<i64, Element>) -> Vec<Master> {
+    let chars = characters_by_parent(masters);
+    sorted_by_name(masters, "Master")
+        .into_iter()
+        .map(|el| {
+            let id = el.attrs.get("ID").cloned().unwrap_or_default();
+            let part = diagram
+                .masters_rel
+                .get(&id)
+                .and_then(|rel_id| rel_target(&diagram.rels, MASTERS_PART, rel_id));
+            Master {
+                id,
+                name: element_name(&el.attrs),
+                prompt: el.attrs.get("Prompt").cloned(),
+                icon: master_icon(masters, &chars, el),
+                attrs: other_attrs(&el.attrs, &MASTER_ATTRS),
+                shapes: vec![],
+                part,
+            }
+        })
+        .collect()
+}
+
+/// Значок мастера: содержимое элемента `Icon` в base64 без переводов строк
+pub fn master_icon(
+    masters: &HashMap<i64, Element>,
+    chars: &HashMap<i64, Vec<i64>>,
+    master: &Element,
+) -> Option<String> {
+    let icon = master
+        .children
+        .iter()
+        .find(|id| masters.get(id).is_some_and(|c| c.name == "Icon"))?;
+    let text: String = chars
+        .get(icon)?
+        .iter()
+        .filter_map(|id| masters.get(id)?.attrs.get("Text"))
+        .flat_map(|t| t.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Страницы из `pages.xml` без содержимого фигур
+pub fn build_pages(diagram: &Diagram, pages: &HashMap<i64, Element>) -> Vec<Page> {
+    let mut result = Vec::new();
+    for el in sorted_by_name(pages, "Page") {
+        let mut page = Page {
+            id: el.attrs.get("ID").cloned().unwrap_or_default(),
+            name: element_name(&el.attrs),
+            local_name: element_local_name(&el.attrs),
+            background: el
+                .attrs
+                .get("Background")
+                .map(|v| v == "1")
+                .unwrap_or(false),
+            background_page: el.attrs.get("BackPage").cloned(),
+            attrs: other_attrs(&el.attrs, &PAGE_ATTRS),
+            width: 8.5,
+            height: 11.0,
+            ..Default::default()
+        };
+
+        for child in el.children.iter().filter_map(|id| pages.get(id)) {
+            match child.name.as_str() {
+                "PageSheet" => {
+                    page.cells = cells_from_params(&child.params);
+                    page.width = page.number("PageWidth").unwrap_or(8.5);
+                    page.height = page.number("PageHeight").unwrap_or(11.0);
+                    page.sections = child
+                        .children
+                        .iter()
+                        .filter_map(|id| pages.get(id))
+                        .filter(|c| c.name == "Section")
+                        .map(|c| build_section(pages, c))
+                        .collect();
+                }
+                "Rel" => {
+                    page.part = child
+                        .attrs
+                        .get("id")
+                        .and_then(|rel_id| rel_target(&diagram.rels, PAGES_PART, rel_id));
+                }
+                _ => {}
+            }
+        }
+        result.push(page);
+    }
+    result
+}
+
+/// Связи `Connect` из части страницы
+pub fn build_connects(hash_elements: &HashMap<i64, Element>) -> Vec<Connect> {
+    sorted_by_name(hash_elements, "Connect")
+        .into_iter()
+        .map(|el| connect_from_attrs(&el.attrs))
+        .collect()
+}
+
+pub fn connect_from_attrs(attrs: &HashMap<String, String>) -> Connect {
+    Connect {
+        from_sheet: attrs.get("FromSheet").cloned().unwrap_or_default(),
+        from_cell: attrs.get("FromCell").cloned(),
+        from_part: attrs.get("FromPart").cloned(),
+        to_sheet: attrs.get("ToSheet").cloned().unwrap_or_default(),
+        to_cell: attrs.get("ToCell").cloned(),
+        to_part: attrs.get("ToPart").cloned(),
+    }
+}
+
+pub fn sorted_by_name<'a>(
+    hash_elements: &'a HashMap<i64, Element>,
+    name: &str,
+) -> Vec<&'a Element> {
+    let mut found: Vec<&Element> = hash_elements
+        .values()
+        .filter(|el| el.name == name)
+        .collect();
+    found.sort_by_key(|el| el.inner_id);
+    found
+}
+
+/// Ищет цель связи `rel_id` в `_rels/<part>.rels`
+pub fn rel_target(
+    rels: &HashMap<String, HashMap<String, String>>,
+    part: &str,
+    rel_id: &str,
+) -> Option<String> {
+    let fname = part.rsplit('/').next()?;
+    let target = rels.get(&(fname.to_owned() + ".rels"))?.get(rel_id)?;
+    Some(resolve_target(part, target))
+}
+
+pub fn foreign_from_attrs(attrs: &HashMap<String, String>) -> ForeignData {
+    ForeignData {
+        foreign_type: attrs.get("ForeignType").cloned().unwrap_or_default(),
+        compression: attrs.get("CompressionType").cloned(),
+        show_as_icon: attrs.get("ShowAsIcon").is_some_and(|v| v == "1"),
+        ..Default::default()
+    }
+}
+
+/// У объекта OLE две связи: изображение предпросмотра и сам объект
+pub fn add_foreign_rel(foreign: &mut ForeignData, id: &str) {
+    if foreign.rel_id.is_none() {
+        foreign.rel_id = Some(id.to_string());
+    } else if foreign.object_rel_id.is_none() {
+        foreign.object_rel_id = Some(id.to_string());
+    }
+}
+
+/// Встроенные объекты лежат в `visio/embeddings/`, всё остальное считается изображением
+pub fn is_embedding(target: &str) -> bool {
+    target.starts_with("visio/embeddings/")
+}
+
+pub fn resolve_foreign(
+    rels: &HashMap<String, HashMap<String, String>>,
+    part: &str,
+    shapes: &mut [Shape],
+) {
+    for shape in shapes.iter_mut() {
+        if let Some(foreign) = shape.foreign.as_mut() {
+            if let Some(rel_id) = &foreign.rel_id {
+                foreign.target = rel_target(rels, part, rel_id);
+            }
+            if let Some(rel_id) = &foreign.object_rel_id {
+                foreign.object_target = rel_target(rels, part, rel_id);
+            }
+            // Порядок элементов `Rel` не задан: объект мог оказаться первым
+            if foreign.target.as_deref().is_some_and(is_embedding)
+                && !foreign.object_target.as_deref().is_some_and(is_embedding)
+            {
+                std::mem::swap(&mut foreign.rel_id, &mut foreign.object_rel_id);
+                std::mem::swap(&mut foreign.target, &mut foreign.object_target);
+            }
+        }
+        resolve_foreign(rels, part, &mut shape.shapes);
+    }
+}
+
+/// Индекс символьных узлов по родителю, чтобы не искать текст перебором всех элементов
+pub fn characters_by_parent(hash_elements: &HashMap<i64, Element>) -> HashMap<i64, Vec<i64>> {
+    let mut chars: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (id, el) in hash_elements {
+        if el.name == "Characters" {
+            chars.entry(el.parent).or_default().push(*id);
+        }
+    }
+    for ids in chars.values_mut() {
+        ids.sort();
+    }
+    chars
+}
+
+/// Собирает фигуры верхнего уровня из разобранной части `pageN.xml` или `masterN.xml`
+pub fn build_shapes(hash_elements: &HashMap<i64, Element>) -> Vec<Shape> {
+    let chars = characters_by_parent(hash_elements);
+
+    let mut roots: Vec<&Element> = hash_elements
+        .values()
+        .filter(|el| {
+            el.name == "Shapes"
+                && hash_elements
+                    .get(&el.parent)
+                    .map(|p| p.name == "PageContents" || p.name == "MasterContents")
+                    .unwrap_or(false)
+        })
+        .collect();
+    roots.sort_by_key(|el| el.inner_id);
+
+    let mut shapes = Vec::new();
+    for root in roots {
+        for child_id in &root.children {
+            if let Some(child) = hash_elements.get(child_id)
+                && child.name == "Shape"
+            {
+                shapes.push(build_shape(hash_elements, &chars, child));
+            }
+        }
+    }
+    shapes
+}
+
+/// Фигура с полями из атрибутов элемента `Shape`, без ячеек и дочерних элементов
+pub fn shape_from_attrs(attrs: &HashMap<String, String>) -> Shape {
+    Shape {
+        id: attrs.get("ID").cloned().unwrap_or_default(),
+        name: element_name(attrs),
+        shape_type: attrs.get("Type").cloned().unwrap_or("Shape".to_string()),
+        master: attrs.get("Master").cloned(),
+        master_shape: attrs.get("MasterShape").cloned(),
+        line_style: attrs.get("LineStyle").cloned(),
+        fill_style: attrs.get("FillStyle").cloned(),
+        text_style: attrs.get("TextStyle").cloned(),
+        attrs: other_attrs(attrs, &SHAPE_ATTRS),
+        ..Default::default()
+    }
+}
+
+pub fn build_shape(
+    hash_elements: &HashMap<i64, Element>,
+    chars: &HashMap<i64, Vec<i64>>,
+    el: &Element,
+) -> Shape {
+    let mut shape = shape_from_attrs(&el.attrs);
+    shape.cells = cells_from_params(&el.params);
+
+    let mut text_el = None;
+    for child_id in &el.children {
+        let Some(child) = hash_elements.get(child_id) else {
+            continue;
+        };
+        match child.name.as_str() {
+            "Section" => shape.sections.push(build_section(hash_elements, child)),
+            "Text" => text_el = Some(child),
+            "ForeignData" => {
+                let mut foreign = foreign_from_attrs(&child.attrs);
+                for rel in child
+                    .children
+                    .iter()
+                    .filter_map(|id| hash_elements.get(id))
+                    .filter(|c| c.name == "Rel")
+                {
+                    if let Some(id) = rel.attrs.get("id") {
+                        add_foreign_rel(&mut foreign, id);
+                    }
+                }
+                shape.foreign = Some(foreign);
+            }
+            "Shapes" => {
+                for sub_id in &child.children {
+                    if let Some(sub) = hash_elements.get(sub_id)
+                        && sub.name == "Shape"
+                    {
+                        shape.shapes.push(build_shape(hash_elements, chars, sub));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Значения пустых полей берутся из секции `Field`, поэтому текст собирается после секций
+    if let Some(text_el) = text_el {
+        let fields = field_texts(&shape);
+        shape.text = Some(collect_text(hash_elements, chars, text_el, &fields));
+    }
+    shape
+}
+
+pub fn build_section(hash_elements: &HashMap<i64, Element>, el: &Element) -> Section {
+    let mut section = section_from_attrs(&el.attrs);
+    section.cells = cells_from_params(&el.params);
+
+    for row_id in &el.children {
+        let Some(row) = hash_elements.get(row_id) else {
+            continue;
+        };
+        if row.name != "Row" {
+            continue;
+        }
+        let mut result = row_from_attrs(&row.attrs);
+        result.cells = cells_from_params(&row.params);
+        section.rows.push(result);
+    }
+
+    section
+}
+
+pub fn section_from_attrs(attrs: &HashMap<String, String>) -> Section {
+    Section {
+        name: attrs.get("N").cloned().unwrap_or_default(),
+        index: attrs.get("IX").cloned(),
+        ..Default::default()
+    }
+}
+
+pub fn row_from_attrs(attrs: &HashMap<String, String>) -> Row {
+    Row {
+        index: attrs.get("IX").cloned(),
+        name: attrs.get("N").cloned(),
+        row_type: attrs.get("T").cloned(),
+        deleted: attrs.get("Del").map(|d| d == "1").unwrap_or(false),
+        ..Default::default()
+    }
+}
+
+pub fn cells_from_params(
+    params: &HashMap<String, HashMap<String, String>>,
+) -> HashMap<CellName, Cell> {
+    params
+        .iter()
+        .map(|(name, attrs)| (name.into(), cell_from_attrs(attrs)))
+        .collect()
+}
+
+pub fn cell_from_attrs(attrs: &HashMap<String, String>) -> Cell {
+    Cell {
+        value: attrs.get("V").cloned().unwrap_or_default(),
+        unit: attrs.get("U").cloned(),
+        formula: attrs.get("F").cloned(),
+    }
+}
+
+/// Склеивает текст фигуры вместе с отображаемыми значениями полей `<fld>`.
+///
+/// Пустой `<fld>` (его текст не сохранён) заменяется значением строки секции `Field`
+/// с тем же `IX` из `fields`, см. [`field_texts`]
+pub fn collect_text(
+    hash_elements: &HashMap<i64, Element>,
+    chars: &HashMap<i64, Vec<i64>>,
+    text_el: &Element,
+    fields: &[(Option<String>, String)],
+) -> String {
+    let mut ids: Vec<i64> = chars.get(&text_el.inner_id).cloned().unwrap_or_default();
+    for child_id in &text_el.children {
+        if let Some(ch) = hash_elements.get(child_id)
+            && ch.name == "fld"
+        {
+            match chars.get(child_id) {
+                Some(field_chars) if !field_chars.is_empty() => ids.extend(field_chars),
+                _ => ids.push(*child_id),
+            }
+        }
+    }
+    ids.sort();
+
+    ids.iter()
+        .filter_map(|id| hash_elements.get(id))
+        .filter_map(|el| match el.name.as_str() {
+            "fld" => field_value(fields, el.attrs.get("IX")),
+            _ => el.attrs.get("Text").map(String::as_str),
+        })
+        .collect::<String>()
+}
+
+/// Текст поля по `IX` элемента `<fld>`
+pub fn field_value<'a>(
+    fields: &'a [(Option<String>, String)],
+    ix: Option<&String>,
+) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(index, _)| index.as_ref() == ix)
+        .map(|(_, text)| text.as_str())
+}
+
+/// Наследование ячеек, секций, текста и вложенных фигур от мастера
+pub fn apply_masters(shapes: &mut [Shape], masters: &[Master]) {
+    for shape in shapes.iter_mut() {
+        let master = shape
+            .master
+            .as_ref()
+            .and_then(|id| masters.iter().find(|m| &m.id == id));
+        match master {
+            Some(master) => apply_master(shape, master),
+            None => apply_masters(&mut shape.shapes, masters),
+        }
+    }
+}
+
+fn apply_master(shape: &mut Shape, master: &Master) {
+    let base = match &shape.master_shape {
+        Some(id) => find_shape(&master.shapes, id),
+        None if master.shapes.len() == 1 => master.shapes.first(),
+        None => None,
+    };
+
+    if let Some(base) = base {
+        merge_shape(shape, base);
+        if shape.shapes.is_empty() {
+            shape.shapes = base.shapes.clone();
+        }
+    }
+
+    for child in shape.shapes.iter_mut() {
+        if child.master.is_none() && child.master_shape.is_some() {
+            apply_master(child, master);
+        }
+    }
+}
+
+fn find_shape<'a>(shapes: &'a [Shape], id: &str) -> Option<&'a Shape> {
+    for shape in shapes {
+        if shape.id == id {
+            return Some(shape);
+        }
+        if let Some(found) = find_shape(&shape.shapes, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn merge_shape(shape: &mut Shape, base: &Shape) {
+    for (name, cell) in &base.cells {
+        shape
+            .cells
+            .entry(name.to_owned())
+            .or_insert_with(|| cell.clone());
+    }
+
+    for base_section in &base.sections {
+        match shape
+            .sections
+            .iter_mut()
+            .find(|s| s.name == base_section.name && s.index == base_section.index)
+        {
+            Some(section) => merge_section(section, base_section),
+            None => shape.sections.push(base_section.clone()),
+        }
+    }
+
+    if shape.text.is_none() {
+        shape.text = base.text.clone();
+        shape.text_runs = base.text_runs.clone();
+    }
+    if shape.foreign.is_none() {
+        shape.foreign = base.foreign.clone();
+    }
+    if shape.line_style.is_none() {
+        shape.line_style = base.line_style.clone();
+    }
+    if shape.fill_style.is_none() {
+        shape.fill_style = base.fill_style.clone();
+    }
+    if shape.text_style.is_none() {
+        shape.text_style = base.text_style.clone();
+    }
+}
+
+fn merge_section(section: &mut Section, base: &Section) {
+    for (name, cell) in &base.cells {
+        section
+            .cells
+            .entry(name.to_owned())
+            .or_insert_with(|| cell.clone());
+    }
+
+    let mut rows = Vec::new();
+    for base_row in &base.rows {
+        let local = section
+            .rows
+            .iter()
+            .position(|r| r.index == base_row.index && r.name == base_row.name);
+        match local {
+            Some(pos) => {
+                let mut row = section.rows.remove(pos);
+                if row.deleted {
+                    continue;
+                }
+                for (name, cell) in &base_row.cells {
+                    row.cells
+                        .entry(name.to_owned())
+                        .or_insert_with(|| cell.clone());
+                }
+                if row.row_type.is_none() {
+                    row.row_type = base_row.row_type.clone();
+                }
+                rows.push(row);
+            }
+            None => rows.push(base_row.clone()),
+        }
+    }
+    rows.extend(section.rows.drain(..).filter(|r| !r.deleted));
+    section.rows = rows;
+}
+
+/// Разрешает относительный путь из `.rels` относительно части-источника
+pub fn resolve_target(base_part: &str, target: &str) -> String {
+    if let Some(stripped) = target.strip_prefix('/') {
+        return stripped.to_string();
+    }
+    let mut parts: Vec<&str> = base_part.split('/').collect();
+    parts.pop();
+    for piece in target.split('/') {
+        match piece {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            _ => parts.push(piece),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(v: &str) -> Cell {
+        Cell {
+            value: v.to_string(),
+            unit: None,
+            formula: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_target() {
+        assert_eq!(
+            resolve_target("visio/pages/page1.xml", "../media/image1.png"),
+            "visio/media/image1.png"
+        );
+        assert_eq!(
+            resolve_target("visio/pages/pages.xml", "page1.xml"),
+            "visio/pages/page1.xml"
+        );
+        assert_eq!(
+            resolve_target("visio/document.xml", "/docProps/core.xml"),
+            "docProps/core.xml"
+        );
+    }
+
+    #[test]
+    fn test_apply_master_inherits_missing_cells_and_rows() {
+        let mut geometry = Section {
+            name: "Geometry".to_string(),
+            index: Some("0".to_string()),
+            cells: HashMap::new(),
+            rows: vec![Row {
+                index: Some("1".to_string()),
+                name: None,
+                row_type: Some("MoveTo".to_string()),
+                deleted: false,
+                cells: HashMap::from([("X".into(), cell("0"))]),
+            }],
+        };
+        let master_shape = Shape {
+            id: "5".to_string(),
+            cells: HashMap::from([("Width".into(), cell("1")), ("Height".into(), cell("2"))]),
+            sections: vec![geometry.clone()],
+            text: Some("master".to_string()),
+            ..Default::default()
+        };
+        let master = Master {
+            id: "2".to_string(),
+            shapes: vec![master_shape],
+            ..Default::default()
+        };
+
+        geometry.rows[0].cells = HashMap::from([("Y".into(), cell("3"))]);
+        let mut shapes = vec![Shape {
+            id: "1".to_string(),
+            master: Some("2".to_string()),
+            cells: HashMap::from([("Width".into(), cell("4"))]),
+            sections: vec![geometry],
+            ..Default::default()
+        }];
+
+        apply_masters(&mut shapes, &[master]);
+
+        let shape = &shapes[0];
+        assert_eq!(shape.cells["Width"].value, "4");
+        assert_eq!(shape.cells["Height"].value, "2");
+        assert_eq!(shape.text.as_deref(), Some("master"));
+        let row = &shape.sections[0].rows[0];
+        assert_eq!(row.cells["X"].value, "0");
+        assert_eq!(row.cells["Y"].value, "3");
+    }
+}
@@ -0,0 +1,368 @@
+//! Потоковый разбор страниц VSDX для очень больших чертежей.
+//!
+//! `read_vsdx::parse` держит в памяти все элементы всех частей; здесь часть страницы
+//! читается событиями xml-rs, и в памяти находится только текущая фигура верхнего
+//! уровня вместе с вложенными фигурами группы.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufReader, Cursor, Read, Seek},
+    path::Path,
+};
+
+use xml::reader::{EventReader, XmlEvent};
+
+use super::get_metadata;
+use super::helpers::attrs_to_hashmap;
+use super::read_vsdx::{
+    CellName, Connect, Diagram, ForeignData, Page, Row, Section, Shape, VsdxError,
+};
+use super::shapes::field_value;
+use super::shapes::{
+    PAGES_PART, add_foreign_rel, build_pages, cell_from_attrs, connect_from_attrs,
+    foreign_from_attrs, resolve_foreign, row_from_attrs, section_from_attrs, shape_from_attrs,
+};
+use crate::field_format::field_texts;
+
+/// Очередной объект части страницы
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageItem {
+    /// Фигура верхнего уровня со всеми вложенными фигурами
+    Shape(Box<Shape>),
+    Connect(Connect),
+}
+
+/// Открытый элемент внутри собираемой фигуры
+enum Frame {
+    Shape(Box<Shape>),
+    Shapes,
+    Section(Section),
+    Row(Row),
+    Text(String),
+    /// Поле `<fld>`: его `IX` и длина текста фигуры до поля
+    Field(Option<String>, usize),
+    Foreign(ForeignData),
+    Skip,
+}
+
+/// Итератор по фигурам и связям одной части `pageN.xml` или `masterN.xml`
+pub struct PageReader<R: Read> {
+    events: EventReader<BufReader<R>>,
+    /// Имена открытых элементов вне текущей фигуры
+    path: Vec<String>,
+    frames: Vec<Frame>,
+    done: bool,
+}
+
+impl<R: Read> PageReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            events: EventReader::new(BufReader::new(reader)),
+            path: Vec::new(),
+            frames: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn at_top_level_shapes(&self) -> bool {
+        let n = self.path.len();
+        n >= 2
+            && self.path[n - 1] == "Shapes"
+            && matches!(self.path[n - 2].as_str(), "PageContents" | "MasterContents")
+    }
+
+    fn start(&mut self, name: &str, attrs: HashMap<String, String>) -> Option<PageItem> {
+        if self.frames.is_empty() {
+            if name == "Shape" && self.at_top_level_shapes() {
+                self.frames
+                    .push(Frame::Shape(Box::new(shape_from_attrs(&attrs))));
+                return None;
+            }
+            self.path.push(name.to_owned());
+            if name == "Connect" {
+                return Some(PageItem::Connect(connect_from_attrs(&attrs)));
+            }
+            return None;
+        }
+
+        let frame = match (name, self.frames.last_mut()) {
+            ("Cell", Some(top)) => {
+                let cells = match top {
+                    Frame::Shape(shape) => Some(&mut shape.cells),
+                    Frame::Section(section) => Some(&mut section.cells),
+                    Frame::Row(row) => Some(&mut row.cells),
+                    _ => None,
+                };
+                if let Some(cells) = cells {
+                    let cell_name = attrs.get("N").map(String::as_str).unwrap_or_default();
+                    cells.insert(CellName::from(cell_name), cell_from_attrs(&attrs));
+                }
+                Frame::Skip
+            }
+            ("Section", Some(Frame::Shape(_))) => Frame::Section(section_from_attrs(&attrs)),
+            ("Row", Some(Frame::Section(_))) => Frame::Row(row_from_attrs(&attrs)),
+            ("Text", Some(Frame::Shape(_))) => Frame::Text(String::new()),
+            ("fld", Some(Frame::Text(text))) => Frame::Field(attrs.get("IX").cloned(), text.len()),
+            ("Shapes", Some(Frame::Shape(_))) => Frame::Shapes,
+            ("Shape", Some(Frame::Shapes)) => Frame::Shape(Box::new(shape_from_attrs(&attrs))),
+            ("ForeignData", Some(Frame::Shape(_))) => Frame::Foreign(foreign_from_attrs(&attrs)),
+            ("Rel", Some(Frame::Foreign(foreign))) => {
+                if let Some(id) = attrs.get("id") {
+                    add_foreign_rel(foreign, id);
+                }
+                Frame::Skip
+            }
+            _ => Frame::Skip,
+        };
+        self.frames.push(frame);
+        None
+    }
+
+    fn characters(&mut self, text: &str) {
+        let n = self.frames.len();
+        let target = match self.frames.last() {
+            Some(Frame::Text(_)) => n - 1,
+            Some(Frame::Field(..)) => n - 2,
+            _ => return,
+        };
+        if let Some(Frame::Text(buf)) = self.frames.get_mut(target) {
+            buf.push_str(text);
+        }
+    }
+
+    fn end(&mut self) -> Option<PageItem> {
+        let Some(frame) = self.frames.pop() else {
+            self.path.pop();
+            return None;
+        };
+
+        match frame {
+            Frame::Shape(shape) => {
+                let parent = self.frames.iter_mut().rev().find_map(|f| match f {
+                    Frame::Shape(parent) => Some(parent),
+                    _ => None,
+                });
+                match parent {
+                    Some(parent) => parent.shapes.push(*shape),
+                    None => return Some(PageItem::Shape(shape)),
+                }
+            }
+            Frame::Section(section) => {
+                if let Some(Frame::Shape(shape)) = self.frames.last_mut() {
+                    shape.sections.push(section);
+                }
+            }
+            Frame::Row(row) => {
+                if let Some(Frame::Section(section)) = self.frames.last_mut() {
+                    section.rows.push(row);
+                }
+            }
+            Frame::Text(text) => {
+                if let Some(Frame::Shape(shape)) = self.frames.last_mut() {
+                    shape.text = Some(text);
+                }
+            }
+            Frame::Foreign(foreign) => {
+                if let Some(Frame::Shape(shape)) = self.frames.last_mut() {
+                    shape.foreign = Some(foreign);
+                }
+            }
+            Frame::Field(ix, start) => {
+                // Текст поля не сохранён: значение вычисляется по секции `Field` фигуры
+                let n = self.frames.len();
+                if let [.., Frame::Shape(shape), Frame::Text(text)] = &mut self.frames[..n]
+                    && text.len() == start
+                {
+                    let fields = field_texts(shape);
+                    text.push_str(field_value(&fields, ix.as_ref()).unwrap_or_default());
+                }
+            }
+            Frame::Shapes | Frame::Skip => {}
+        }
+        None
+    }
+}
+
+impl<R: Read> Iterator for PageReader<R> {
+    type Item = Result<PageItem, VsdxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let item = match self.events.next() {
+                Ok(XmlEvent::StartElement {
+                    name, attributes, ..
+                }) => self.start(&name.local_name, attrs_to_hashmap(&attributes)),
+                Ok(XmlEvent::EndElement { .. }) => self.end(),
+                Ok(XmlEvent::Characters(text)) => {
+                    self.characters(&text);
+                    None
+                }
+                Ok(XmlEvent::EndDocument) => {
+                    self.done = true;
+                    None
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            if let Some(item) = item {
+                return Some(Ok(item));
+            }
+        }
+        None
+    }
+}
+
+/// Обработчик потокового обхода документа
+pub trait Visitor {
+    /// Начало страницы; фигуры и связи страницы ещё не прочитаны
+    fn page(&mut self, _page: &Page) {}
+    /// Фигура верхнего уровня; после возврата она больше не хранится
+    fn shape(&mut self, _page: &Page, _shape: Shape) {}
+    fn connect(&mut self, _page: &Page, _connect: Connect) {}
+    fn end_page(&mut self, _page: &Page) {}
+}
+
+/// Обходит страницы пакета по одной фигуре верхнего уровня за раз.
+///
+/// Целиком разбираются только связи `.rels` и список страниц `pages.xml`.
+pub fn visit<R: Read + Seek, V: Visitor>(reader: R, visitor: &mut V) -> Result<(), VsdxError> {
+    CellName::scoped(|| visit_package(reader, visitor))
+}
+
+fn visit_package<R: Read + Seek, V: Visitor>(reader: R, visitor: &mut V) -> Result<(), VsdxError> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let mut diagram = Diagram::default();
+
+    let names: Vec<String> = archive.file_names().map(str::to_owned).collect();
+    for name in names.iter().filter(|n| n.ends_with(".rels")) {
+        let file = archive.by_name(name)?;
+        let fname = name.rsplit('/').next().unwrap_or(name);
+        get_metadata::encoding(file, fname, &mut diagram)?;
+    }
+
+    let pages = match archive.by_name(PAGES_PART) {
+        Ok(mut file) => {
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf)?;
+            get_metadata::encoding(Cursor::new(buf), "pages.xml", &mut diagram)?
+        }
+        Err(zip::result::ZipError::FileNotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for page in build_pages(&diagram, &pages) {
+        visitor.page(&page);
+        if let Some(part) = &page.part {
+            let file = archive.by_name(part)?;
+            for item in PageReader::new(file) {
+                match item? {
+                    PageItem::Shape(mut shape) => {
+                        resolve_foreign(&diagram.rels, part, std::slice::from_mut(&mut *shape));
+                        visitor.shape(&page, *shape);
+                    }
+                    PageItem::Connect(connect) => visitor.connect(&page, connect),
+                }
+            }
+        }
+        visitor.end_page(&page);
+    }
+    Ok(())
+}
+
+pub fn visit_file<V: Visitor>(fname: &Path, visitor: &mut V) -> Result<(), VsdxError> {
+    let file = fs::File::open(fname)?;
+    visit(BufReader::new(file), visitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx;
+    use crate::parser_vsdx::write_vsdx::tests::sample_page;
+
+    const PAGE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<PageContents xmlns="http://schemas.microsoft.com/office/visio/2012/main">
+  <Shapes>
+    <Shape ID="1" NameU="Group" Type="Group" Extra="x">
+      <Cell N="PinX" V="1" U="IN"/>
+      <Section N="Geometry" IX="0">
+        <Cell N="NoFill" V="0"/>
+        <Row T="MoveTo" IX="1"><Cell N="X" V="0"/><Cell N="Y" V="0"/></Row>
+        <Row T="LineTo" IX="2" Del="1"/>
+      </Section>
+      <Section N="Field">
+        <Row IX="0"><Cell N="Value" V="42"/></Row>
+        <Row IX="1"><Cell N="Value" V="45356" U="DATE"/><Cell N="Format" V="esc(M/d/yyyy)" F="FIELDPICTURE(20)"/></Row>
+      </Section>
+      <Text>Hello <cp IX="0"/><fld IX="0">42</fld> world <fld IX="1"/></Text>
+      <Shapes>
+        <Shape ID="2" Master="5"><Cell N="Width" V="2" F="Inh"/></Shape>
+      </Shapes>
+    </Shape>
+    <Shape ID="3" Type="Foreign">
+      <ForeignData ForeignType="Bitmap"><Rel r:id="rId1" xmlns:r="urn:r"/></ForeignData>
+    </Shape>
+  </Shapes>
+  <Connects><Connect FromSheet="2" FromCell="BeginX" ToSheet="3"/></Connects>
+</PageContents>"#;
+
+    #[test]
+    fn test_streamed_shapes_match_full_parse() {
+        let mut diagram = Diagram::default();
+        let elements = get_metadata::encoding(PAGE.as_bytes(), "page1.xml", &mut diagram).unwrap();
+        let expected = crate::parser_vsdx::shapes::build_shapes(&elements);
+
+        let items: Vec<PageItem> = PageReader::new(PAGE.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let shapes: Vec<Shape> = items
+            .iter()
+            .filter_map(|item| match item {
+                PageItem::Shape(shape) => Some(*shape.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(shapes, expected);
+        assert_eq!(shapes[0].text.as_deref(), Some("Hello 42 world 3/5/2024"));
+        assert_eq!(shapes[0].shapes[0].id, "2");
+        assert!(matches!(&items[2], PageItem::Connect(c) if c.from_sheet == "2"));
+    }
+
+    struct Collect {
+        pages: Vec<String>,
+        shapes: Vec<Shape>,
+    }
+
+    impl Visitor for Collect {
+        fn page(&mut self, page: &Page) {
+            self.pages.push(page.name.clone());
+        }
+
+        fn shape(&mut self, _page: &Page, shape: Shape) {
+            self.shapes.push(shape);
+        }
+    }
+
+    #[test]
+    fn test_visit_package() {
+        let diagram = Diagram {
+            pages: vec![sample_page(&["shape 1", "shape 2", "shape 3"])],
+            ..Default::default()
+        };
+        let bytes = crate::parser_vsdx::write_vsdx::to_bytes(&diagram).unwrap();
+
+        let mut collect = Collect {
+            pages: vec![],
+            shapes: vec![],
+        };
+        visit(Cursor::new(bytes.clone()), &mut collect).unwrap();
+
+        let parsed = read_vsdx::parse(Cursor::new(bytes)).unwrap();
+        assert_eq!(collect.pages, vec!["Page-1".to_string()]);
+        assert_eq!(collect.shapes, parsed.pages[0].shapes);
+    }
+}
@@ -0,0 +1,124 @@
+//! Проект VBA документов с макросами.
+//!
+//! Макросы лежат в части `visio/vbaProject.bin` — это OLE Compound Document,
+//! поэтому содержимое перечисляется через `cfb`. Код модулей не разбирается.
+
+use std::io::Cursor;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::read_vsdx::Diagram;
+
+const VBA_PART_NAME: &str = "vbaProject.bin";
+
+/// Часть `vbaProject.bin` и список её потоков
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+pub struct VbaProject {
+    /// Путь части внутри пакета
+    pub part: String,
+    pub streams: Vec<VbaStream>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+pub struct VbaStream {
+    /// Путь потока внутри хранилища, например `VBA/dir`
+    pub path: String,
+    pub size: u64,
+}
+
+/// Находит проект VBA среди частей пакета
+pub fn read_project(diagram: &Diagram) -> Option<VbaProject> {
+    let (part, data) = diagram
+        .package
+        .iter()
+        .find(|(name, _)| name.rsplit('/').next() == Some(VBA_PART_NAME))?;
+
+    let mut project = VbaProject {
+        part: part.to_owned(),
+        streams: vec![],
+    };
+    match cfb::CompoundFile::open(Cursor::new(data.as_slice())) {
+        Ok(cf) => {
+            project.streams = cf
+                .walk()
+                .filter(|entry| entry.is_stream())
+                .map(|entry| VbaStream {
+                    path: entry
+                        .path()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                        .trim_start_matches('/')
+                        .to_string(),
+                    size: entry.len(),
+                })
+                .collect();
+        }
+        Err(e) => log::warn!("Unable to read {}: {}", part, e),
+    }
+    Some(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{FileFormat, detect_format};
+    use crate::parser_vsdx::read_vsdx;
+    use crate::parser_vsdx::write_vsdx::{
+        self,
+        tests::{round_trip, sample_page},
+    };
+    use std::io::Write;
+
+    fn vba_bin() -> Vec<u8> {
+        let mut cf = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        cf.create_storage("VBA").unwrap();
+        cf.create_stream("VBA/dir")
+            .unwrap()
+            .write_all(b"module")
+            .unwrap();
+        cf.create_stream("PROJECT")
+            .unwrap()
+            .write_all(b"ID")
+            .unwrap();
+        cf.flush().unwrap();
+        cf.into_inner().into_inner()
+    }
+
+    #[test]
+    fn test_macro_enabled_package() {
+        let mut diagram = round_trip(&Diagram {
+            pages: vec![sample_page(&[])],
+            ..Default::default()
+        });
+        assert_eq!(diagram.vba_project, None);
+
+        // Пакет становится VSDM: другой тип главной части и проект VBA
+        let content_types = String::from_utf8(diagram.package["[Content_Types].xml"].clone())
+            .unwrap()
+            .replace(
+                "vnd.ms-visio.drawing.main+xml",
+                "vnd.ms-visio.drawing.macroEnabled.main+xml",
+            );
+        diagram.package.insert(
+            "[Content_Types].xml".to_string(),
+            content_types.into_bytes(),
+        );
+        diagram
+            .package
+            .insert("visio/vbaProject.bin".to_string(), vba_bin());
+
+        let bytes = write_vsdx::to_bytes(&diagram).unwrap();
+        assert_eq!(detect_format(&bytes), Some(FileFormat::Vsdm));
+
+        // Проект читается и без сохранения частей пакета
+        let read = read_vsdx::parse(Cursor::new(bytes)).unwrap();
+        assert!(read.package.is_empty());
+        let project = read.vba_project.unwrap();
+        assert_eq!(project.part, "visio/vbaProject.bin");
+        let mut paths: Vec<&str> = project.streams.iter().map(|s| s.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["PROJECT", "VBA/dir"]);
+        assert_eq!(project.streams.iter().map(|s| s.size).sum::<u64>(), 8);
+    }
+}
@@ -0,0 +1,1376 @@
+//! Запись модели [`Diagram`] обратно в пакет VSDX (OPC).
+//!
+//! Части, которые модель не описывает (тема, свойства документа, VBA и т.п.), переносятся
+//! из исходного пакета байт в байт, если он разобран с `ParseOptions::keep_package`. Страницы и мастера, которые не менялись после чтения,
+//! тоже копируются без изменений; изменённые и новые генерируются из модели.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{Cursor, Seek, Write},
+    path::Path,
+};
+
+use xml::{
+    EventReader,
+    reader::XmlEvent as ReadEvent,
+    writer::{EmitterConfig, EventWriter, XmlEvent},
+};
+
+use super::get_metadata::{self, Element};
+use super::lossless::{XmlDocument, XmlElement, XmlNode};
+use super::read_vsdx::{
+    Cell, CellName, Connect, Diagram, Master, Page, Row, Section, Shape, VsdxError,
+};
+use super::shapes::{
+    DOCUMENT_PART, MASTERS_PART, PAGES_PART, build_connects, build_masters, build_pages,
+    build_shapes, is_embedding, resolve_foreign,
+};
+
+const CONTENT_TYPES_PART: &str = "[Content_Types].xml";
+const ROOT_RELS_PART: &str = "_rels/.rels";
+const WINDOWS_PART: &str = "visio/windows.xml";
+
+const VISIO_NS: &str = "http://schemas.microsoft.com/office/visio/2012/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const CONTENT_TYPES_NS: &str = "http://schemas.openxmlformats.org/package/2006/content-types";
+
+const REL_DOCUMENT: &str = "http://schemas.microsoft.com/visio/2010/relationships/document";
+const REL_PAGES: &str = "http://schemas.microsoft.com/visio/2010/relationships/pages";
+const REL_PAGE: &str = "http://schemas.microsoft.com/visio/2010/relationships/page";
+const REL_MASTERS: &str = "http://schemas.microsoft.com/visio/2010/relationships/masters";
+const REL_MASTER: &str = "http://schemas.microsoft.com/visio/2010/relationships/master";
+const REL_WINDOWS: &str = "http://schemas.microsoft.com/visio/2010/relationships/windows";
+const REL_IMAGE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+const REL_OLE_OBJECT: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/oleObject";
+const REL_PACKAGE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/package";
+
+/// Связь из части `_rels/*.rels`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relationship {
+    pub id: String,
+    pub rel_type: String,
+    pub target: String,
+    pub target_mode: Option<String>,
+}
+
+/// Сохраняет диаграмму в файл VSDX
+pub fn save(diagram: &Diagram, path: &Path) -> Result<(), VsdxError> {
+    let file = fs::File::create(path)?;
+    write(diagram, file)?;
+    Ok(())
+}
+
+/// Сериализует диаграмму в байты пакета VSDX
+pub fn to_bytes(diagram: &Diagram) -> Result<Vec<u8>, VsdxError> {
+    Ok(write(diagram, Cursor::new(Vec::new()))?.into_inner())
+}
+
+/// Записывает пакет VSDX в `writer` и возвращает его обратно
+pub fn write<W: Write + Seek>(diagram: &Diagram, writer: W) -> Result<W, VsdxError> {
+    let parts = package_parts(diagram)?;
+
+    let mut zip = zip::ZipWriter::new(writer);
+    let options = zip::write::SimpleFileOptions::default();
+    // По соглашению OPC описание типов идёт первым
+    if let Some(content_types) = parts.get(CONTENT_TYPES_PART) {
+        zip.start_file(CONTENT_TYPES_PART, options)?;
+        zip.write_all(content_types)?;
+    }
+    for (name, data) in &parts {
+        if name == CONTENT_TYPES_PART {
+            continue;
+        }
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?)
+}
+
+/// Собирает все части пакета: путь -> содержимое
+pub fn package_parts(diagram: &Diagram) -> Result<BTreeMap<String, Vec<u8>>, VsdxError> {
+    let mut parts: BTreeMap<String, Vec<u8>> = diagram
+        .package
+        .iter()
+        .filter(|(name, _)| {
+            name.as_str() != CONTENT_TYPES_PART
+                && !name.starts_with("visio/pages/")
+                && !name.starts_with("visio/masters/")
+        })
+        .map(|(name, data)| (name.to_owned(), data.to_owned()))
+        .collect();
+
+    for (name, data) in &diagram.media {
+        parts.insert(name.to_owned(), data.to_owned());
+    }
+
+    let master_parts = assign_parts(&diagram.masters, "visio/masters/master", |m| &m.part);
+    let page_parts = assign_parts(&diagram.pages, "visio/pages/page", |p| &p.part);
+
+    if !diagram.masters.is_empty() || diagram.package.contains_key(MASTERS_PART) {
+        write_masters(diagram, &master_parts, &mut parts)?;
+    }
+    write_pages(diagram, &page_parts, &mut parts)?;
+
+    if !parts.contains_key(DOCUMENT_PART) {
+        parts.insert(DOCUMENT_PART.to_string(), document_xml(diagram)?);
+    }
+    if !parts.contains_key(WINDOWS_PART) {
+        parts.insert(WINDOWS_PART.to_string(), windows_xml(diagram)?);
+    }
+
+    let mut document_rels = vec![(REL_PAGES, "pages/pages.xml"), (REL_WINDOWS, "windows.xml")];
+    if parts.contains_key(MASTERS_PART) {
+        document_rels.push((REL_MASTERS, "masters/masters.xml"));
+    }
+    ensure_rels(&mut parts, DOCUMENT_PART, &document_rels)?;
+    ensure_rels(&mut parts, "", &[(REL_DOCUMENT, DOCUMENT_PART)])?;
+
+    let content_types = content_types_xml(diagram, &parts)?;
+    parts.insert(CONTENT_TYPES_PART.to_string(), content_types);
+
+    Ok(parts)
+}
+
+/// Путь к части со связями: `dir/_rels/name.rels`
+pub fn rels_part(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/_rels/{}.rels", dir, name),
+        None => format!("_rels/{}.rels", part),
+    }
+}
+
+/// Разбирает часть `.rels` с сохранением порядка
+pub fn parse_rels(data: &[u8]) -> Vec<Relationship> {
+    let mut rels = Vec::new();
+    for event in EventReader::new(data) {
+        match event {
+            Ok(ReadEvent::StartElement {
+                name, attributes, ..
+            }) if name.local_name == "Relationship" => {
+                let attr = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|a| a.name.local_name == key)
+                        .map(|a| a.value.clone())
+                };
+                rels.push(Relationship {
+                    id: attr("Id").unwrap_or_default(),
+                    rel_type: attr("Type").unwrap_or_default(),
+                    target: attr("Target").unwrap_or_default(),
+                    target_mode: attr("TargetMode"),
+                });
+            }
+            Err(e) => {
+                log::warn!("Unable to parse relationships: {}", e);
+                break;
+            }
+            _ => {}
+        }
+    }
+    rels
+}
+
+/// Путь цели относительно каталога части-источника (обратное к `resolve_target`)
+fn relative_target(source_part: &str, target_part: &str) -> String {
+    let source_dir: Vec<&str> = source_part
+        .rsplit_once('/')
+        .map(|(dir, _)| dir.split('/').collect())
+        .unwrap_or_default();
+    let target: Vec<&str> = target_part.split('/').collect();
+
+    let common = source_dir
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut result: Vec<&str> = vec![".."; source_dir.len() - common];
+    result.extend(&target[common..]);
+    result.join("/")
+}
+
+fn next_rel_id(rels: &[Relationship]) -> String {
+    let max = rels
+        .iter()
+        .filter_map(|r| r.id.strip_prefix("rId").and_then(|n| n.parse::<u32>().ok()))
+        .max()
+        .unwrap_or(0);
+    format!("rId{}", max + 1)
+}
+
+/// Назначает пути частям без `part`, не пересекаясь с уже занятыми
+fn assign_parts<T>(items: &[T], prefix: &str, part: impl Fn(&T) -> &Option<String>) -> Vec<String> {
+    let mut used: Vec<String> = items.iter().filter_map(|i| part(i).clone()).collect();
+    let mut n = 1;
+    items
+        .iter()
+        .map(|item| match part(item) {
+            Some(existing) => existing.to_owned(),
+            None => loop {
+                let candidate = format!("{}{}.xml", prefix, n);
+                n += 1;
+                if !used.contains(&candidate) {
+                    used.push(candidate.clone());
+                    break candidate;
+                }
+            },
+        })
+        .collect()
+}
+
+fn parse_part(diagram: &Diagram, part: &str) -> Option<HashMap<i64, Element>> {
+    let data = diagram.package.get(part)?;
+    let fname = part.rsplit('/').next().unwrap_or(part);
+    let mut scratch = Diagram::default();
+    get_metadata::encoding(Cursor::new(data), fname, &mut scratch).ok()
+}
+
+/// Исходная часть не изменилась: фигуры и связи, прочитанные из неё заново, совпадают с моделью
+fn unchanged_contents(
+    diagram: &Diagram,
+    part: &str,
+    shapes: &[Shape],
+    connects: &[Connect],
+) -> bool {
+    let Some(hash_elements) = parse_part(diagram, part) else {
+        return false;
+    };
+    let mut original = build_shapes(&hash_elements);
+    resolve_foreign(&diagram.rels, part, &mut original);
+    original == shapes && build_connects(&hash_elements) == connects
+}
+
+/// Исходные элементы `Text` фигур части по ID вместе с текстом, который из них прочитан.
+/// Форматирование (`cp`, `pp`, `tp`) и поля `fld` есть только в них, а не в модели
+fn original_texts(diagram: &Diagram, part: &str) -> HashMap<String, (Option<String>, XmlElement)> {
+    fn walk(
+        el: &XmlElement,
+        read: &HashMap<String, Option<String>>,
+        out: &mut HashMap<String, (Option<String>, XmlElement)>,
+    ) {
+        if el.name.local_name == "Shape"
+            && let Some(id) = el.attr("ID")
+            && let Some(text) = el.elements().find(|c| c.name.local_name == "Text")
+        {
+            let read = read.get(id).cloned().flatten();
+            out.insert(id.to_string(), (read, text.clone()));
+        }
+        for child in el.elements() {
+            walk(child, read, out);
+        }
+    }
+    fn read_texts(shapes: &[Shape], out: &mut HashMap<String, Option<String>>) {
+        for shape in shapes {
+            out.insert(shape.id.clone(), shape.text.clone());
+            read_texts(&shape.shapes, out);
+        }
+    }
+
+    let mut result = HashMap::new();
+    let (Some(data), Some(hash_elements)) = (diagram.package.get(part), parse_part(diagram, part))
+    else {
+        return result;
+    };
+    let Ok(document) = XmlDocument::parse(data) else {
+        return result;
+    };
+    let mut read = HashMap::new();
+    read_texts(&build_shapes(&hash_elements), &mut read);
+    walk(&document.root, &read, &mut result);
+    result
+}
+
+fn copy_part(diagram: &Diagram, part: &str, parts: &mut BTreeMap<String, Vec<u8>>) {
+    for name in [part.to_string(), rels_part(part)] {
+        if let Some(data) = diagram.package.get(&name) {
+            parts.insert(name, data.to_owned());
+        }
+    }
+}
+
+fn write_masters(
+    diagram: &Diagram,
+    master_parts: &[String],
+    parts: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), VsdxError> {
+    for (master, part) in diagram.masters.iter().zip(master_parts) {
+        if master.part.as_deref() == Some(part.as_str())
+            && unchanged_contents(diagram, part, &master.shapes, &[])
+        {
+            copy_part(diagram, part, parts);
+            continue;
+        }
+        write_contents(diagram, "MasterContents", part, &master.shapes, &[], parts)?;
+    }
+
+    let unchanged = parse_part(diagram, MASTERS_PART).is_some_and(|original| {
+        let mut model: Vec<Master> = diagram.masters.iter().map(master_meta).collect();
+        for (master, part) in model.iter_mut().zip(master_parts) {
+            master.part = Some(part.to_owned());
+        }
+        build_masters(diagram, &original) == model
+    });
+    if unchanged {
+        copy_part(diagram, MASTERS_PART, parts);
+        return Ok(());
+    }
+
+    let mut rels = Vec::new();
+    let mut w = XmlWriter::new();
+    w.start_root("Masters")?;
+    for (master, part) in diagram.masters.iter().zip(master_parts) {
+        let rel_id = format!("rId{}", rels.len() + 1);
+        rels.push(Relationship {
+            id: rel_id.clone(),
+            rel_type: REL_MASTER.to_string(),
+            target: relative_target(MASTERS_PART, part),
+            target_mode: None,
+        });
+
+        let mut attrs = vec![("ID", master.id.as_str())];
+        if !master.name.is_empty() {
+            attrs.push(("NameU", &master.name));
+            attrs.push(("Name", &master.name));
+        }
+        if let Some(prompt) = &master.prompt {
+            attrs.push(("Prompt", prompt));
+        }
+        attrs.extend(master.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        w.start("Master", &attrs)?;
+        w.rel(&rel_id)?;
+        if let Some(icon) = &master.icon {
+            w.start("Icon", &[])?;
+            w.text(icon)?;
+            w.end()?;
+        }
+        w.end()?;
+    }
+    w.end()?;
+    parts.insert(MASTERS_PART.to_string(), w.finish());
+    parts.insert(rels_part(MASTERS_PART), rels_xml(&rels)?);
+    Ok(())
+}
+
+fn master_meta(master: &Master) -> Master {
+    Master {
+        shapes: vec![],
+        ..master.clone()
+    }
+}
+
+fn page_meta(page: &Page) -> Page {
+    Page {
+        shapes: vec![],
+        connects: vec![],
+        ..page.clone()
+    }
+}
+
+fn write_pages(
+    diagram: &Diagram,
+    page_parts: &[String],
+    parts: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), VsdxError> {
+    for (page, part) in diagram.pages.iter().zip(page_parts) {
+        if page.part.as_deref() == Some(part.as_str())
+            && unchanged_contents(diagram, part, &page.shapes, &page.connects)
+        {
+            copy_part(diagram, part, parts);
+            continue;
+        }
+        write_contents(
+            diagram,
+            "PageContents",
+            part,
+            &page.shapes,
+            &page.connects,
+            parts,
+        )?;
+    }
+
+    let unchanged = parse_part(diagram, PAGES_PART).is_some_and(|original| {
+        let mut model: Vec<Page> = diagram.pages.iter().map(page_meta).collect();
+        for (page, part) in model.iter_mut().zip(page_parts) {
+            page.part = Some(part.to_owned());
+        }
+        build_pages(diagram, &original) == model
+    });
+    if unchanged {
+        copy_part(diagram, PAGES_PART, parts);
+        return Ok(());
+    }
+
+    let mut rels = Vec::new();
+    let mut w = XmlWriter::new();
+    w.start_root("Pages")?;
+    for (page, part) in diagram.pages.iter().zip(page_parts) {
+        let rel_id = format!("rId{}", rels.len() + 1);
+        rels.push(Relationship {
+            id: rel_id.clone(),
+            rel_type: REL_PAGE.to_string(),
+            target: relative_target(PAGES_PART, part),
+            target_mode: None,
+        });
+
+        let mut attrs = vec![("ID", page.id.as_str())];
+        if !page.name.is_empty() {
+            attrs.push(("NameU", &page.name));
+            attrs.push(("Name", page.local_name.as_deref().unwrap_or(&page.name)));
+        }
+        if page.background {
+            attrs.push(("Background", "1"));
+        }
+        if let Some(back) = &page.background_page {
+            attrs.push(("BackPage", back));
+        }
+        attrs.extend(page.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        w.start("Page", &attrs)?;
+
+        let mut cells = page.cells.clone();
+        for (name, value) in [("PageWidth", page.width), ("PageHeight", page.height)] {
+            let cell = cells.entry(name.into()).or_default();
+            if cell.number() != Some(value) {
+                cell.value = value.to_string();
+            }
+        }
+        w.start("PageSheet", &[])?;
+        w.cells(&cells)?;
+        for section in &page.sections {
+            w.section(section)?;
+        }
+        w.end()?;
+        w.rel(&rel_id)?;
+        w.end()?;
+    }
+    w.end()?;
+    parts.insert(PAGES_PART.to_string(), w.finish());
+    parts.insert(rels_part(PAGES_PART), rels_xml(&rels)?);
+    Ok(())
+}
+
+/// Генерирует часть `pageN.xml` или `masterN.xml` и её связи
+fn write_contents(
+    diagram: &Diagram,
+    root: &str,
+    part: &str,
+    shapes: &[Shape],
+    connects: &[Connect],
+    parts: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), VsdxError> {
+    let rels_name = rels_part(part);
+    let mut rels = diagram
+        .package
+        .get(&rels_name)
+        .map(|data| parse_rels(data))
+        .unwrap_or_default();
+
+    let mut w = XmlWriter::new();
+    w.original_texts = original_texts(diagram, part);
+    w.start_root(root)?;
+    if !shapes.is_empty() {
+        w.start("Shapes", &[])?;
+        for shape in shapes {
+            w.shape("Shape", shape, &mut |target| {
+                foreign_rel(&mut rels, part, target)
+            })?;
+        }
+        w.end()?;
+    }
+    if !connects.is_empty() {
+        w.start("Connects", &[])?;
+        for connect in connects {
+            let mut attrs = vec![("FromSheet", connect.from_sheet.as_str())];
+            if let Some(v) = &connect.from_cell {
+                attrs.push(("FromCell", v));
+            }
+            if let Some(v) = &connect.from_part {
+                attrs.push(("FromPart", v));
+            }
+            attrs.push(("ToSheet", &connect.to_sheet));
+            if let Some(v) = &connect.to_cell {
+                attrs.push(("ToCell", v));
+            }
+            if let Some(v) = &connect.to_part {
+                attrs.push(("ToPart", v));
+            }
+            w.start("Connect", &attrs)?;
+            w.end()?;
+        }
+        w.end()?;
+    }
+    w.end()?;
+    parts.insert(part.to_string(), w.finish());
+
+    // Visio ожидает связь страницы с каждым используемым мастером
+    if root == "PageContents" {
+        let mut used = Vec::new();
+        collect_masters(shapes, &mut used);
+        for master in diagram.masters.iter().filter(|m| used.contains(&&m.id)) {
+            let Some(master_part) = master_part_of(diagram, master) else {
+                continue;
+            };
+            let target = relative_target(part, &master_part);
+            if !rels
+                .iter()
+                .any(|r| r.rel_type == REL_MASTER && r.target == target)
+            {
+                rels.push(Relationship {
+                    id: next_rel_id(&rels),
+                    rel_type: REL_MASTER.to_string(),
+                    target,
+                    target_mode: None,
+                });
+            }
+        }
+    }
+
+    if !rels.is_empty() {
+        parts.insert(rels_name, rels_xml(&rels)?);
+    }
+    Ok(())
+}
+
+fn master_part_of(diagram: &Diagram, master: &Master) -> Option<String> {
+    let index = diagram.masters.iter().position(|m| m.id == master.id)?;
+    assign_parts(&diagram.masters, "visio/masters/master", |m| &m.part)
+        .into_iter()
+        .nth(index)
+}
+
+fn collect_masters<'a>(shapes: &'a [Shape], used: &mut Vec<&'a String>) {
+    for shape in shapes {
+        if let Some(master) = &shape.master
+            && !used.contains(&master)
+        {
+            used.push(master);
+        }
+        collect_masters(&shape.shapes, used);
+    }
+}
+
+/// Возвращает id связи с изображением или встроенным объектом, добавляя её при необходимости;
+/// существующая связь переиспользуется
+fn foreign_rel(rels: &mut Vec<Relationship>, part: &str, target: &str) -> String {
+    let relative = relative_target(part, target);
+    if let Some(rel) = rels.iter().find(|r| r.target == relative) {
+        return rel.id.clone();
+    }
+    // Объект OLE хранится в `.bin`, документы Office - пакетами как есть
+    let rel_type = match target.rsplit_once('.') {
+        _ if !is_embedding(target) => REL_IMAGE,
+        Some((_, "bin")) => REL_OLE_OBJECT,
+        _ => REL_PACKAGE,
+    };
+    let id = next_rel_id(rels);
+    rels.push(Relationship {
+        id: id.clone(),
+        rel_type: rel_type.to_string(),
+        target: relative,
+        target_mode: None,
+    });
+    id
+}
+
+/// Добавляет недостающие связи нужных типов в `.rels` части `source`
+/// (пустая строка — корневые связи пакета)
+fn ensure_rels(
+    parts: &mut BTreeMap<String, Vec<u8>>,
+    source: &str,
+    required: &[(&str, &str)],
+) -> Result<(), VsdxError> {
+    let rels_name = if source.is_empty() {
+        ROOT_RELS_PART.to_string()
+    } else {
+        rels_part(source)
+    };
+    let mut rels = parts
+        .get(&rels_name)
+        .map(|data| parse_rels(data))
+        .unwrap_or_default();
+    let before = rels.len();
+
+    for (rel_type, target) in required {
+        if !rels.iter().any(|r| r.rel_type == *rel_type) {
+            rels.push(Relationship {
+                id: next_rel_id(&rels),
+                rel_type: rel_type.to_string(),
+                target: target.to_string(),
+                target_mode: None,
+            });
+        }
+    }
+
+    if rels.len() != before || !parts.contains_key(&rels_name) {
+        parts.insert(rels_name, rels_xml(&rels)?);
+    }
+    Ok(())
+}
+
+fn rels_xml(rels: &[Relationship]) -> Result<Vec<u8>, VsdxError> {
+    let mut w = XmlWriter::new();
+    w.write(XmlEvent::start_element("Relationships").default_ns(PACKAGE_REL_NS))?;
+    for rel in rels {
+        let mut attrs = vec![
+            ("Id", rel.id.as_str()),
+            ("Type", &rel.rel_type),
+            ("Target", &rel.target),
+        ];
+        if let Some(mode) = &rel.target_mode {
+            attrs.push(("TargetMode", mode));
+        }
+        w.start("Relationship", &attrs)?;
+        w.end()?;
+    }
+    w.end()?;
+    Ok(w.finish())
+}
+
+fn document_xml(diagram: &Diagram) -> Result<Vec<u8>, VsdxError> {
+    let mut w = XmlWriter::new();
+    w.start_root("VisioDocument")?;
+    w.start("StyleSheets", &[])?;
+    if diagram.style_sheets.is_empty() {
+        let no_style = Shape {
+            id: "0".to_string(),
+            name: "No Style".to_string(),
+            cells: [
+                ("LineWeight", "0.01"),
+                ("LineColor", "#000000"),
+                ("LinePattern", "1"),
+                ("FillForegnd", "#ffffff"),
+                ("FillPattern", "1"),
+            ]
+            .iter()
+            .map(|(n, v)| {
+                (
+                    (*n).into(),
+                    Cell {
+                        value: v.to_string(),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect(),
+            ..Default::default()
+        };
+        w.shape("StyleSheet", &no_style, &mut |_| String::new())?;
+    }
+    for style in &diagram.style_sheets {
+        w.shape("StyleSheet", style, &mut |_| String::new())?;
+    }
+    w.end()?;
+    w.end()?;
+    Ok(w.finish())
+}
+
+fn windows_xml(diagram: &Diagram) -> Result<Vec<u8>, VsdxError> {
+    let mut w = XmlWriter::new();
+    w.start_root("Windows")?;
+    if let Some(page) = diagram.pages.iter().find(|p| !p.background) {
+        w.start(
+            "Window",
+            &[("ID", "0"), ("WindowType", "Drawing"), ("Page", &page.id)],
+        )?;
+        w.end()?;
+    }
+    w.end()?;
+    Ok(w.finish())
+}
+
+/// Тип содержимого известных частей пакета Visio
+fn known_content_type(part: &str) -> Option<&'static str> {
+    let dir = part.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+    let content_type = match part {
+        DOCUMENT_PART => "application/vnd.ms-visio.drawing.main+xml",
+        PAGES_PART => "application/vnd.ms-visio.pages+xml",
+        MASTERS_PART => "application/vnd.ms-visio.masters+xml",
+        WINDOWS_PART => "application/vnd.ms-visio.windows+xml",
+        "docProps/core.xml" => "application/vnd.openxmlformats-package.core-properties+xml",
+        "docProps/app.xml" => {
+            "application/vnd.openxmlformats-officedocument.extended-properties+xml"
+        }
+        "docProps/custom.xml" => {
+            "application/vnd.openxmlformats-officedocument.custom-properties+xml"
+        }
+        _ if dir == "visio/pages" => "application/vnd.ms-visio.page+xml",
+        _ if dir == "visio/masters" => "application/vnd.ms-visio.master+xml",
+        _ if dir == "visio/theme" => "application/vnd.openxmlformats-officedocument.theme+xml",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+fn default_content_type(extension: &str) -> Option<&'static str> {
+    let content_type = match extension {
+        "rels" => "application/vnd.openxmlformats-package.relationships+xml",
+        "xml" => "application/xml",
+        "png" => "image/png",
+        "jpeg" | "jpg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "emf" => "image/x-emf",
+        "wmf" => "image/x-wmf",
+        "svg" => "image/svg+xml",
+        "bin" => "application/vnd.openxmlformats-officedocument.oleObject",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "vsdx" => "application/vnd.ms-visio.drawing",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+fn content_types_xml(
+    diagram: &Diagram,
+    parts: &BTreeMap<String, Vec<u8>>,
+) -> Result<Vec<u8>, VsdxError> {
+    let mut defaults: BTreeMap<String, String> = BTreeMap::new();
+    let mut overrides: BTreeMap<String, String> = BTreeMap::new();
+
+    if let Some(original) = diagram.package.get(CONTENT_TYPES_PART) {
+        for event in EventReader::new(original.as_slice()) {
+            let Ok(ReadEvent::StartElement {
+                name, attributes, ..
+            }) = event
+            else {
+                continue;
+            };
+            let attr = |key: &str| {
+                attributes
+                    .iter()
+                    .find(|a| a.name.local_name == key)
+                    .map(|a| a.value.clone())
+            };
+            match (name.local_name.as_str(), attr("ContentType")) {
+                ("Default", Some(ct)) => {
+                    if let Some(ext) = attr("Extension") {
+                        defaults.insert(ext.to_lowercase(), ct);
+                    }
+                }
+                ("Override", Some(ct)) => {
+                    if let Some(part) = attr("PartName") {
+                        overrides.insert(part.trim_start_matches('/').to_string(), ct);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for name in parts.keys() {
+        let extension = name.rsplit_once('.').map(|(_, e)| e.to_lowercase());
+        if let Some(ext) = &extension
+            && !defaults.contains_key(ext)
+            && let Some(ct) = default_content_type(ext)
+        {
+            defaults.insert(ext.to_owned(), ct.to_string());
+        }
+        // Главная часть сохраняет исходный тип: набор элементов, шаблон или документ с макросами
+        if let Some(ct) = known_content_type(name)
+            && !(name == DOCUMENT_PART && overrides.contains_key(name))
+        {
+            overrides.insert(name.to_owned(), ct.to_string());
+        }
+    }
+    overrides.retain(|name, _| parts.contains_key(name));
+
+    let mut w = XmlWriter::new();
+    w.write(XmlEvent::start_element("Types").default_ns(CONTENT_TYPES_NS))?;
+    for (ext, ct) in &defaults {
+        w.start("Default", &[("Extension", ext), ("ContentType", ct)])?;
+        w.end()?;
+    }
+    for (part, ct) in &overrides {
+        let part_name = format!("/{}", part);
+        w.start("Override", &[("PartName", &part_name), ("ContentType", ct)])?;
+        w.end()?;
+    }
+    w.end()?;
+    Ok(w.finish())
+}
+
+/// Тонкая обёртка над `EventWriter` для частей VSDX
+struct XmlWriter {
+    inner: EventWriter<Vec<u8>>,
+    /// Исходные `Text` фигур по ID и прочитанный из них текст, см. [`original_texts`]
+    original_texts: HashMap<String, (Option<String>, XmlElement)>,
+}
+
+impl XmlWriter {
+    fn new() -> Self {
+        let config = EmitterConfig::new()
+            .perform_indent(false)
+            .write_document_declaration(true);
+        Self {
+            inner: config.create_writer(Vec::new()),
+            original_texts: HashMap::new(),
+        }
+    }
+
+    fn write<'a>(&mut self, event: impl Into<XmlEvent<'a>>) -> Result<(), VsdxError> {
+        Ok(self.inner.write(event)?)
+    }
+
+    /// Корневой элемент с пространствами имён Visio
+    fn start_root(&mut self, name: &str) -> Result<(), VsdxError> {
+        self.write(
+            XmlEvent::start_element(name)
+                .default_ns(VISIO_NS)
+                .ns("r", REL_NS),
+        )
+    }
+
+    fn start(&mut self, name: &str, attrs: &[(&str, &str)]) -> Result<(), VsdxError> {
+        let mut event = XmlEvent::start_element(name);
+        for (key, value) in attrs {
+            event = event.attr(*key, value);
+        }
+        self.write(event)
+    }
+
+    fn end(&mut self) -> Result<(), VsdxError> {
+        self.write(XmlEvent::end_element())
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), VsdxError> {
+        self.write(XmlEvent::characters(text))
+    }
+
+    /// Элемент из исходной части со всеми дочерними узлами
+    fn element(&mut self, el: &XmlElement) -> Result<(), VsdxError> {
+        let name = el.name.qualified();
+        let attrs: Vec<(String, &str)> = el
+            .attributes
+            .iter()
+            .map(|a| (a.name.qualified(), a.value.as_str()))
+            .collect();
+        let mut event = XmlEvent::start_element(name.as_str());
+        for (prefix, uri) in &el.namespaces {
+            event = if prefix.is_empty() {
+                event.default_ns(uri.as_str())
+            } else {
+                event.ns(prefix.as_str(), uri.as_str())
+            };
+        }
+        for (key, value) in &attrs {
+            event = event.attr(key.as_str(), value);
+        }
+        self.write(event)?;
+        for node in &el.children {
+            match node {
+                XmlNode::Element(child) => self.element(child)?,
+                XmlNode::Text(text) => self.text(text)?,
+                XmlNode::CData(text) => self.write(XmlEvent::cdata(text))?,
+                XmlNode::Comment(text) => self.write(XmlEvent::comment(text))?,
+                XmlNode::ProcessingInstruction { name, data } => {
+                    self.write(XmlEvent::processing_instruction(name, data.as_deref()))?
+                }
+            }
+        }
+        self.end()
+    }
+
+    fn rel(&mut self, rel_id: &str) -> Result<(), VsdxError> {
+        self.write(XmlEvent::start_element("Rel").attr("r:id", rel_id))?;
+        self.end()
+    }
+
+    /// Ячейки в порядке имён, чтобы вывод не зависел от порядка `HashMap`
+    fn cells(&mut self, cells: &HashMap<CellName, Cell>) -> Result<(), VsdxError> {
+        let mut names: Vec<&CellName> = cells.keys().collect();
+        names.sort();
+        for name in names {
+            let cell = &cells[name.as_str()];
+            let mut attrs = vec![("N", name.as_str()), ("V", cell.value.as_str())];
+            if let Some(unit) = &cell.unit {
+                attrs.push(("U", unit));
+            }
+            if let Some(formula) = &cell.formula {
+                attrs.push(("F", formula));
+            }
+            self.start("Cell", &attrs)?;
+            self.end()?;
+        }
+        Ok(())
+    }
+
+    fn section(&mut self, section: &Section) -> Result<(), VsdxError> {
+        let mut attrs = vec![("N", section.name.as_str())];
+        if let Some(ix) = &section.index {
+            attrs.push(("IX", ix));
+        }
+        self.start("Section", &attrs)?;
+        self.cells(&section.cells)?;
+        for row in &section.rows {
+            self.row(row)?;
+        }
+        self.end()
+    }
+
+    fn row(&mut self, row: &Row) -> Result<(), VsdxError> {
+        let mut attrs = vec![];
+        if let Some(ix) = &row.index {
+            attrs.push(("IX", ix.as_str()));
+        }
+        if let Some(name) = &row.name {
+            attrs.push(("N", name));
+        }
+        if let Some(row_type) = &row.row_type {
+            attrs.push(("T", row_type));
+        }
+        if row.deleted {
+            attrs.push(("Del", "1"));
+        }
+        self.start("Row", &attrs)?;
+        self.cells(&row.cells)?;
+        self.end()
+    }
+
+    /// Фигура или лист стиля; `foreign_rel` выдаёт id связи для внешних данных
+    fn shape(
+        &mut self,
+        element: &str,
+        shape: &Shape,
+        foreign_rel: &mut dyn FnMut(&str) -> String,
+    ) -> Result<(), VsdxError> {
+        let mut attrs = vec![("ID", shape.id.as_str())];
+        if !shape.name.is_empty() {
+            attrs.push(("NameU", &shape.name));
+            attrs.push(("Name", &shape.name));
+        }
+        if element == "Shape" && !shape.shape_type.is_empty() {
+            attrs.push(("Type", &shape.shape_type));
+        }
+        let optional = [
+            ("Master", &shape.master),
+            ("MasterShape", &shape.master_shape),
+            ("LineStyle", &shape.line_style),
+            ("FillStyle", &shape.fill_style),
+            ("TextStyle", &shape.text_style),
+        ];
+        for (key, value) in &optional {
+            if let Some(value) = value {
+                attrs.push((key, value));
+            }
+        }
+        attrs.extend(shape.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.start(element, &attrs)?;
+
+        self.cells(&shape.cells)?;
+        for section in &shape.sections {
+            self.section(section)?;
+        }
+        // Текст, не изменённый через `set_text`, пишется исходным элементом с форматированием
+        let original = self
+            .original_texts
+            .get(&shape.id)
+            .filter(|(read, _)| *read == shape.text)
+            .map(|(_, el)| el.clone());
+        match (&shape.text, original) {
+            (Some(_), Some(el)) => self.element(&el)?,
+            (Some(text), None) => {
+                self.start("Text", &[])?;
+                self.text(text)?;
+                self.end()?;
+            }
+            (None, _) => {}
+        }
+        if let Some(foreign) = &shape.foreign {
+            let mut attrs = vec![("ForeignType", foreign.foreign_type.as_str())];
+            if let Some(compression) = &foreign.compression {
+                attrs.push(("CompressionType", compression));
+            }
+            if foreign.show_as_icon {
+                attrs.push(("ShowAsIcon", "1"));
+            }
+            self.start("ForeignData", &attrs)?;
+            let rels = [
+                (&foreign.target, &foreign.rel_id),
+                (&foreign.object_target, &foreign.object_rel_id),
+            ];
+            for (target, rel_id) in rels {
+                let rel_id = match target {
+                    Some(target) => Some(foreign_rel(target)),
+                    None => rel_id.clone(),
+                };
+                if let Some(rel_id) = rel_id {
+                    self.rel(&rel_id)?;
+                }
+            }
+            self.end()?;
+        }
+        if !shape.shapes.is_empty() {
+            self.start("Shapes", &[])?;
+            for child in &shape.shapes {
+                self.shape("Shape", child, foreign_rel)?;
+            }
+            self.end()?;
+        }
+        self.end()
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.inner.into_inner()
+    }
+}
+
+impl Shape {
+    /// Задаёт значение ячейки; формула сбрасывается, иначе Visio пересчитает значение
+    pub fn set_cell(&mut self, name: &str, value: impl ToString) {
+        self.cells.insert(
+            name.into(),
+            Cell {
+                value: value.to_string(),
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = Some(text.to_string());
+    }
+
+    /// Задаёт значение поля Shape Data (строка `name` секции `Property`)
+    pub fn set_property(&mut self, name: &str, value: &str) {
+        let section = match self.sections.iter().position(|s| s.name == "Property") {
+            Some(pos) => &mut self.sections[pos],
+            None => {
+                self.sections.push(Section {
+                    name: "Property".to_string(),
+                    ..Default::default()
+                });
+                self.sections.last_mut().unwrap()
+            }
+        };
+        let row = match section
+            .rows
+            .iter()
+            .position(|r| r.name.as_deref() == Some(name))
+        {
+            Some(pos) => &mut section.rows[pos],
+            None => {
+                section.rows.push(Row {
+                    name: Some(name.to_string()),
+                    ..Default::default()
+                });
+                section.rows.last_mut().unwrap()
+            }
+        };
+        row.cells.insert(
+            "Value".into(),
+            Cell {
+                value: value.to_string(),
+                unit: Some("STR".to_string()),
+                formula: None,
+            },
+        );
+    }
+}
+
+impl Page {
+    /// Следующий свободный ID фигуры на странице (с учётом вложенных)
+    pub fn next_shape_id(&self) -> u32 {
+        fn max_id(shapes: &[Shape]) -> u32 {
+            shapes
+                .iter()
+                .map(|s| s.id.parse::<u32>().unwrap_or(0).max(max_id(&s.shapes)))
+                .max()
+                .unwrap_or(0)
+        }
+        max_id(&self.shapes) + 1
+    }
+
+    /// Добавляет фигуру верхнего уровня; пустой ID назначается автоматически
+    pub fn add_shape(&mut self, mut shape: Shape) -> String {
+        if shape.id.is_empty() {
+            shape.id = self.next_shape_id().to_string();
+        }
+        if shape.shape_type.is_empty() {
+            shape.shape_type = "Shape".to_string();
+        }
+        let id = shape.id.clone();
+        self.shapes.push(shape);
+        id
+    }
+
+    /// Экземпляр мастера с центром в точке `(pin_x, pin_y)` (дюймы)
+    pub fn add_master_instance(&mut self, master: &Master, pin_x: f64, pin_y: f64) -> String {
+        let mut shape = Shape {
+            name: master.name.clone(),
+            shape_type: master
+                .shapes
+                .first()
+                .map(|s| s.shape_type.clone())
+                .unwrap_or_else(|| "Shape".to_string()),
+            master: Some(master.id.clone()),
+            ..Default::default()
+        };
+        shape.set_cell("PinX", pin_x);
+        shape.set_cell("PinY", pin_y);
+        self.add_shape(shape)
+    }
+
+    /// Прямая соединительная линия между центрами фигур с приклеиванием концов
+    pub fn connect(&mut self, from_id: &str, to_id: &str) -> Option<String> {
+        let pin = |id: &str| {
+            let shape = self.shapes.iter().find(|s| s.id == id)?;
+            Some((shape.number("PinX")?, shape.number("PinY")?))
+        };
+        let (begin, end) = (pin(from_id)?, pin(to_id)?);
+        let (dx, dy) = (end.0 - begin.0, end.1 - begin.1);
+        let length = (dx * dx + dy * dy).sqrt();
+
+        let mut shape = Shape {
+            name: "Dynamic connector".to_string(),
+            shape_type: "Shape".to_string(),
+            ..Default::default()
+        };
+        for (name, value) in [
+            ("PinX", (begin.0 + end.0) / 2.0),
+            ("PinY", (begin.1 + end.1) / 2.0),
+            ("Width", length),
+            ("Height", 0.0),
+            ("LocPinX", length / 2.0),
+            ("LocPinY", 0.0),
+            ("Angle", dy.atan2(dx)),
+            ("BeginX", begin.0),
+            ("BeginY", begin.1),
+            ("EndX", end.0),
+            ("EndY", end.1),
+            ("ObjType", 2.0),
+        ] {
+            shape.set_cell(name, value);
+        }
+
+        let point = |ix: &str, row_type: &str, x: f64| {
+            let mut row = Row {
+                index: Some(ix.to_string()),
+                row_type: Some(row_type.to_string()),
+                ..Default::default()
+            };
+            for (name, value) in [("X", x), ("Y", 0.0)] {
+                row.cells.insert(
+                    name.into(),
+                    Cell {
+                        value: value.to_string(),
+                        ..Default::default()
+                    },
+                );
+            }
+            row
+        };
+        shape.sections.push(Section {
+            name: "Geometry".to_string(),
+            index: Some("0".to_string()),
+            cells: HashMap::from([(
+                "NoFill".into(),
+                Cell {
+                    value: "1".to_string(),
+                    ..Default::default()
+                },
+            )]),
+            rows: vec![point("1", "MoveTo", 0.0), point("2", "LineTo", length)],
+        });
+
+        let id = self.add_shape(shape);
+        for (cell, part, target) in [("BeginX", "9", from_id), ("EndX", "12", to_id)] {
+            self.connects.push(Connect {
+                from_sheet: id.clone(),
+                from_cell: Some(cell.to_string()),
+                from_part: Some(part.to_string()),
+                to_sheet: target.to_string(),
+                to_cell: Some("PinX".to_string()),
+                to_part: Some("3".to_string()),
+            });
+        }
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx;
+
+    fn sample_diagram() -> Diagram {
+        let mut diagram = Diagram::default();
+        let mut box_shape = Shape {
+            name: "Box".to_string(),
+            ..Default::default()
+        };
+        box_shape.set_cell("Width", 1);
+        box_shape.set_cell("Height", 0.5);
+
+        diagram.masters.push(Master {
+            id: "2".to_string(),
+            name: "Box".to_string(),
+            prompt: Some("Drag onto the page".to_string()),
+            icon: Some("iVBORw0KGgo=".to_string()),
+            shapes: vec![Shape {
+                id: "5".to_string(),
+                shape_type: "Shape".to_string(),
+                ..box_shape
+            }],
+            ..Default::default()
+        });
+
+        let mut page = Page {
+            id: "0".to_string(),
+            name: "Page-1".to_string(),
+            local_name: Some("Страница-1".to_string()),
+            width: 8.5,
+            height: 11.0,
+            ..Default::default()
+        };
+        let master = diagram.masters[0].clone();
+        let a = page.add_master_instance(&master, 1.0, 1.0);
+        let b = page.add_master_instance(&master, 4.0, 3.0);
+        page.shapes[0].set_text("Hello & <world>");
+        page.shapes[1].set_property("Cost", "42");
+        page.connect(&a, &b).unwrap();
+        diagram.pages.push(page);
+        diagram
+    }
+
+    /// Страница `Page-1` с фигурой на каждый текст; `PinX` фигуры - её номер с единицы
+    pub(crate) fn sample_page(texts: &[&str]) -> Page {
+        let mut page = Page {
+            id: "0".to_string(),
+            name: "Page-1".to_string(),
+            width: 8.5,
+            height: 11.0,
+            ..Default::default()
+        };
+        for (i, text) in texts.iter().enumerate() {
+            let mut shape = Shape::default();
+            shape.set_cell("PinX", i + 1);
+            shape.set_text(text);
+            page.add_shape(shape);
+        }
+        page
+    }
+
+    /// Пакет, прочитанный с сохранением частей для записи обратно
+    pub(crate) fn read_editable(bytes: Vec<u8>) -> Diagram {
+        read_vsdx::parse_with_options(Cursor::new(bytes), &read_vsdx::ParseOptions::editable())
+            .unwrap()
+    }
+
+    /// Диаграмма после записи в пакет и чтения обратно
+    pub(crate) fn round_trip(diagram: &Diagram) -> Diagram {
+        read_editable(to_bytes(diagram).unwrap())
+    }
+
+    #[test]
+    fn test_relative_target() {
+        assert_eq!(
+            relative_target("visio/pages/pages.xml", "visio/pages/page1.xml"),
+            "page1.xml"
+        );
+        assert_eq!(
+            relative_target("visio/pages/page1.xml", "visio/masters/master1.xml"),
+            "../masters/master1.xml"
+        );
+        assert_eq!(
+            relative_target("visio/document.xml", "visio/windows.xml"),
+            "windows.xml"
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let diagram = sample_diagram();
+        let bytes = to_bytes(&diagram).unwrap();
+        let read = read_editable(bytes);
+
+        assert_eq!(read.masters.len(), 1);
+        assert_eq!(read.masters[0].shapes, diagram.masters[0].shapes);
+        assert_eq!(read.masters[0].icon, diagram.masters[0].icon);
+        assert_eq!(read.masters[0].prompt, diagram.masters[0].prompt);
+        assert_eq!(read.pages.len(), 1);
+        assert_eq!(read.pages[0].name, "Page-1");
+        assert_eq!(read.pages[0].local_name.as_deref(), Some("Страница-1"));
+        assert_eq!(read.pages[0].shapes, diagram.pages[0].shapes);
+        assert_eq!(read.pages[0].connects, diagram.pages[0].connects);
+        assert_eq!(
+            read.pages[0].shapes[0].text.as_deref(),
+            Some("Hello & <world>")
+        );
+
+        let parts = package_parts(&read).unwrap();
+        let page_rels = parse_rels(&parts["visio/pages/_rels/page1.xml.rels"]);
+        assert!(page_rels.iter().any(|r| r.rel_type == REL_MASTER));
+        assert!(parts.contains_key(WINDOWS_PART));
+    }
+
+    #[test]
+    fn test_unchanged_parts_are_copied() {
+        let bytes = to_bytes(&sample_diagram()).unwrap();
+        let read = read_editable(bytes);
+        let first = package_parts(&read).unwrap();
+
+        let mut edited = read.clone();
+        edited
+            .package
+            .insert("customXml/item1.xml".to_string(), b"<a/>".to_vec());
+        let second = package_parts(&edited).unwrap();
+        for (name, data) in &first {
+            assert_eq!(&second[name], data, "part {} changed", name);
+        }
+        assert_eq!(second["customXml/item1.xml"], b"<a/>".to_vec());
+
+        edited.pages[0].shapes[1].set_property("Cost", "43");
+        let third = package_parts(&edited).unwrap();
+        assert_ne!(
+            third["visio/pages/page1.xml"],
+            first["visio/pages/page1.xml"]
+        );
+        assert_eq!(third[PAGES_PART], first[PAGES_PART]);
+    }
+
+    #[test]
+    fn test_formatted_text_survives_page_edit() {
+        let bytes = to_bytes(&sample_diagram()).unwrap();
+        let read = read_editable(bytes);
+
+        // Текст первой фигуры с форматированием и полем, как его сохраняет Visio
+        let mut parts = package_parts(&read).unwrap();
+        let page = String::from_utf8(parts["visio/pages/page1.xml"].clone()).unwrap();
+        let formatted = page.replace(
+            "<Text>Hello &amp; &lt;world&gt;</Text>",
+            r#"<Text><cp IX="0"/><pp IX="0"/>Cost: <cp IX="1"/><fld IX="0">42</fld></Text>"#,
+        );
+        assert_ne!(formatted, page);
+        parts.insert("visio/pages/page1.xml".to_string(), formatted.into_bytes());
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in &parts {
+            zip.start_file(name.as_str(), zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        let read = read_editable(zip.finish().unwrap().into_inner());
+        assert_eq!(read.pages[0].shapes[0].text.as_deref(), Some("Cost: 42"));
+
+        // Правка другой фигуры на странице не трогает разметку текста первой
+        let mut edited = read.clone();
+        edited.pages[0].shapes[1].set_cell("PinX", 5);
+        let page =
+            String::from_utf8(package_parts(&edited).unwrap()["visio/pages/page1.xml"].clone())
+                .unwrap();
+        assert!(page.contains(r#"<cp IX="1" />"#), "{}", page);
+        assert!(page.contains(r#"<fld IX="0">42</fld>"#), "{}", page);
+
+        // Новый текст через `set_text` пишется без прежнего форматирования
+        edited.pages[0].shapes[0].set_text("Plain");
+        let page =
+            String::from_utf8(package_parts(&edited).unwrap()["visio/pages/page1.xml"].clone())
+                .unwrap();
+        assert!(page.contains("<Text>Plain</Text>"));
+        assert!(!page.contains("<fld"));
+    }
+}
@@ -0,0 +1,976 @@
+use std::collections::HashMap;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::render::RenderError;
+use crate::render::scene::{
+    Affine, Color, FillRule, HAlign, ImageData, Item, LineCap, Path, Scene, Stroke, TextBlock,
+    VAlign,
+};
+
+const EMR_HEADER: u32 = 0x01;
+const EMR_POLYBEZIER: u32 = 0x02;
+const EMR_POLYGON: u32 = 0x03;
+const EMR_POLYLINE: u32 = 0x04;
+const EMR_POLYBEZIERTO: u32 = 0x05;
+const EMR_POLYLINETO: u32 = 0x06;
+const EMR_POLYPOLYLINE: u32 = 0x07;
+const EMR_POLYPOLYGON: u32 = 0x08;
+const EMR_SETWINDOWEXTEX: u32 = 0x09;
+const EMR_SETWINDOWORGEX: u32 = 0x0A;
+const EMR_SETVIEWPORTEXTEX: u32 = 0x0B;
+const EMR_SETVIEWPORTORGEX: u32 = 0x0C;
+const EMR_EOF: u32 = 0x0E;
+const EMR_SETMAPMODE: u32 = 0x11;
+const EMR_SETPOLYFILLMODE: u32 = 0x13;
+const EMR_SETTEXTALIGN: u32 = 0x16;
+const EMR_SETTEXTCOLOR: u32 = 0x18;
+const EMR_MOVETOEX: u32 = 0x1B;
+const EMR_SAVEDC: u32 = 0x21;
+const EMR_RESTOREDC: u32 = 0x22;
+const EMR_SETWORLDTRANSFORM: u32 = 0x23;
+const EMR_MODIFYWORLDTRANSFORM: u32 = 0x24;
+const EMR_SELECTOBJECT: u32 = 0x25;
+const EMR_CREATEPEN: u32 = 0x26;
+const EMR_CREATEBRUSHINDIRECT: u32 = 0x27;
+const EMR_DELETEOBJECT: u32 = 0x28;
+const EMR_ELLIPSE: u32 = 0x2A;
+const EMR_RECTANGLE: u32 = 0x2B;
+const EMR_ROUNDRECT: u32 = 0x2C;
+const EMR_LINETO: u32 = 0x36;
+const EMR_BEGINPATH: u32 = 0x3B;
+const EMR_ENDPATH: u32 = 0x3C;
+const EMR_CLOSEFIGURE: u32 = 0x3D;
+const EMR_FILLPATH: u32 = 0x3E;
+const EMR_STROKEANDFILLPATH: u32 = 0x3F;
+const EMR_STROKEPATH: u32 = 0x40;
+const EMR_STRETCHDIBITS: u32 = 0x51;
+const EMR_EXTCREATEFONTINDIRECTW: u32 = 0x52;
+const EMR_EXTTEXTOUTW: u32 = 0x54;
+const EMR_POLYBEZIER16: u32 = 0x55;
+const EMR_POLYGON16: u32 = 0x56;
+const EMR_POLYLINE16: u32 = 0x57;
+const EMR_POLYBEZIERTO16: u32 = 0x58;
+const EMR_POLYLINETO16: u32 = 0x59;
+const EMR_POLYPOLYLINE16: u32 = 0x5A;
+const EMR_POLYPOLYGON16: u32 = 0x5B;
+const EMR_EXTCREATEPEN: u32 = 0x5F;
+
+/// Сигнатура " EMF" в заголовке
+const ENHMETA_SIGNATURE: u32 = 0x464D_4520;
+const STOCK_OBJECT: u32 = 0x8000_0000;
+
+/// Проверяет заголовок EMF
+pub fn is_emf(data: &[u8]) -> bool {
+    data.len() >= 44
+        && LittleEndian::read_u32(&data[0..4]) == EMR_HEADER
+        && LittleEndian::read_u32(&data[40..44]) == ENHMETA_SIGNATURE
+}
+
+#[derive(Debug, Clone)]
+struct Pen {
+    color: Color,
+    width: f64,
+    style: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Font {
+    height: f64,
+    weight: i32,
+    italic: bool,
+    face: String,
+}
+
+#[derive(Debug, Clone)]
+enum Object {
+    Pen(Pen),
+    Brush(Option<Color>),
+    Font(Font),
+}
+
+#[derive(Debug, Clone)]
+struct DcState {
+    pen: Option<Pen>,
+    brush: Option<Color>,
+    font: Font,
+    text_color: Color,
+    text_align: u32,
+    fill_rule: FillRule,
+    map_mode: u32,
+    window_org: (f64, f64),
+    window_ext: (f64, f64),
+    viewport_org: (f64, f64),
+    viewport_ext: (f64, f64),
+    world: Affine,
+}
+
+impl Default for DcState {
+    fn default() -> Self {
+        Self {
+            pen: Some(Pen {
+                color: Color::BLACK,
+                width: 0.0,
+                style: 0,
+            }),
+            brush: Some(Color::WHITE),
+            font: Font {
+                height: 12.0,
+                weight: 400,
+                italic: false,
+                face: String::new(),
+            },
+            text_color: Color::BLACK,
+            text_align: 0,
+            fill_rule: FillRule::EvenOdd,
+            map_mode: 1,
+            window_org: (0.0, 0.0),
+            window_ext: (1.0, 1.0),
+            viewport_org: (0.0, 0.0),
+            viewport_ext: (1.0, 1.0),
+            world: Affine::identity(),
+        }
+    }
+}
+
+/// Проигрывает записи EMF в список отрисовки
+struct Player<'a> {
+    data: &'a [u8],
+    /// Пиксели устройства -> пункты сцены
+    device: Affine,
+    /// Миллиметров в пикселе устройства
+    mm_per_px: (f64, f64),
+    state: DcState,
+    saved: Vec<DcState>,
+    objects: HashMap<u32, Object>,
+    position: (f64, f64),
+    path: Option<Path>,
+    items: Vec<Item>,
+}
+
+/// Разбирает EMF в сцену; размер сцены берётся из рамки заголовка
+pub fn scene(data: &[u8]) -> Result<Scene, RenderError> {
+    if !is_emf(data) || data.len() < 88 {
+        return Err(RenderError::Emf("not an EMF file".to_string()));
+    }
+
+    let int = |off: usize| LittleEndian::read_i32(&data[off..off + 4]) as f64;
+    let frame = (int(24), int(28), int(32), int(36));
+    let device = (int(72), int(76));
+    let millimeters = (int(80), int(84));
+
+    let mm_per_px = if device.0 > 0.0 && device.1 > 0.0 && millimeters.0 > 0.0 {
+        (millimeters.0 / device.0, millimeters.1 / device.1)
+    } else {
+        (25.4 / 96.0, 25.4 / 96.0)
+    };
+
+    // Рамка задана в сотых долях миллиметра
+    let width = (frame.2 - frame.0) / 100.0 / 25.4 * 72.0;
+    let height = (frame.3 - frame.1) / 100.0 / 25.4 * 72.0;
+    let left_px = frame.0 / 100.0 / mm_per_px.0;
+    let top_px = frame.1 / 100.0 / mm_per_px.1;
+
+    let mut player = Player {
+        data,
+        device: Affine::scale(mm_per_px.0 / 25.4 * 72.0, mm_per_px.1 / 25.4 * 72.0)
+            .then(&Affine::translate(-left_px, -top_px)),
+        mm_per_px,
+        state: DcState::default(),
+        saved: Vec::new(),
+        objects: HashMap::new(),
+        position: (0.0, 0.0),
+        path: None,
+        items: Vec::new(),
+    };
+    player.play();
+
+    let mut scene = Scene::new(width.max(0.0), height.max(0.0));
+    scene.items = player.items;
+    Ok(scene)
+}
+
+fn color_ref(data: &[u8]) -> Color {
+    Color::rgb(data[0], data[1], data[2])
+}
+
+impl Player<'_> {
+    fn play(&mut self) {
+        let mut offset = 0;
+        while offset + 8 <= self.data.len() {
+            let record_type = LittleEndian::read_u32(&self.data[offset..]);
+            let size = LittleEndian::read_u32(&self.data[offset + 4..]) as usize;
+            if size < 8 || offset + size > self.data.len() {
+                log::warn!("EMF: bad record size {} at {}", size, offset);
+                break;
+            }
+            if record_type == EMR_EOF {
+                break;
+            }
+            let record = &self.data[offset..offset + size];
+            self.record(record_type, record);
+            offset += size;
+        }
+    }
+
+    fn i32_at(record: &[u8], off: usize) -> Option<f64> {
+        record
+            .get(off..off + 4)
+            .map(|b| LittleEndian::read_i32(b) as f64)
+    }
+
+    fn u32_at(record: &[u8], off: usize) -> Option<u32> {
+        record.get(off..off + 4).map(LittleEndian::read_u32)
+    }
+
+    fn f32_at(record: &[u8], off: usize) -> Option<f64> {
+        record
+            .get(off..off + 4)
+            .map(|b| LittleEndian::read_f32(b) as f64)
+    }
+
+    fn point(record: &[u8], off: usize) -> Option<(f64, f64)> {
+        Some((Self::i32_at(record, off)?, Self::i32_at(record, off + 4)?))
+    }
+
+    fn rect(record: &[u8], off: usize) -> Option<(f64, f64, f64, f64)> {
+        Some((
+            Self::i32_at(record, off)?,
+            Self::i32_at(record, off + 4)?,
+            Self::i32_at(record, off + 8)?,
+            Self::i32_at(record, off + 12)?,
+        ))
+    }
+
+    fn xform(record: &[u8], off: usize) -> Option<Affine> {
+        Some(Affine {
+            a: Self::f32_at(record, off)?,
+            b: Self::f32_at(record, off + 4)?,
+            c: Self::f32_at(record, off + 8)?,
+            d: Self::f32_at(record, off + 12)?,
+            e: Self::f32_at(record, off + 16)?,
+            f: Self::f32_at(record, off + 20)?,
+        })
+    }
+
+    /// Точки POINTL или POINTS начиная с `off`
+    fn points(record: &[u8], off: usize, count: usize, short: bool) -> Vec<(f64, f64)> {
+        let step = if short { 4 } else { 8 };
+        (0..count)
+            .map_while(|i| {
+                let p = off + i * step;
+                if short {
+                    let b = record.get(p..p + 4)?;
+                    Some((
+                        LittleEndian::read_i16(&b[0..2]) as f64,
+                        LittleEndian::read_i16(&b[2..4]) as f64,
+                    ))
+                } else {
+                    Self::point(record, p)
+                }
+            })
+            .collect()
+    }
+
+    /// Логические координаты -> пиксели устройства
+    fn page_transform(&self) -> Affine {
+        let s = &self.state;
+        let (wx, wy) = s.window_org;
+        let (vx, vy) = s.viewport_org;
+        let mapping = match s.map_mode {
+            7 | 8 => {
+                let sx = if s.window_ext.0 != 0.0 {
+                    s.viewport_ext.0 / s.window_ext.0
+                } else {
+                    1.0
+                };
+                let mut sy = if s.window_ext.1 != 0.0 {
+                    s.viewport_ext.1 / s.window_ext.1
+                } else {
+                    1.0
+                };
+                if s.map_mode == 7 {
+                    sy = sx.abs() * sy.signum();
+                }
+                Affine::scale(sx, sy)
+            }
+            2..=6 => {
+                let mm = match s.map_mode {
+                    2 => 0.1,
+                    3 => 0.01,
+                    4 => 0.254,
+                    5 => 0.0254,
+                    _ => 25.4 / 1440.0,
+                };
+                Affine::scale(mm / self.mm_per_px.0, -mm / self.mm_per_px.1)
+            }
+            _ => Affine::identity(),
+        };
+        Affine::translate(vx, vy)
+            .then(&mapping)
+            .then(&Affine::translate(-wx, -wy))
+    }
+
+    fn transform(&self) -> Affine {
+        self.device
+            .then(&self.page_transform())
+            .then(&self.state.world)
+    }
+
+    fn stroke(&self) -> Option<Stroke> {
+        let pen = self.state.pen.as_ref()?;
+        let t = self.transform();
+        let width = if pen.width <= 0.0 {
+            self.device.scale_factor()
+        } else {
+            pen.width * t.scale_factor()
+        };
+        let unit = width.max(1.0);
+        let dash: &[f64] = match pen.style & 0x0F {
+            1 => &[3.0, 1.0],
+            2 => &[1.0, 1.0],
+            3 => &[3.0, 1.0, 1.0, 1.0],
+            4 => &[3.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+            _ => &[],
+        };
+        let cap = match pen.style & 0x0F00 {
+            0x0100 => LineCap::Square,
+            0x0200 => LineCap::Butt,
+            _ => LineCap::Round,
+        };
+        Some(Stroke {
+            color: pen.color,
+            width,
+            dash: dash.iter().map(|d| d * unit).collect(),
+            cap,
+        })
+    }
+
+    fn emit(&mut self, path: Path, fill: bool, stroke: bool) {
+        let fill = if fill { self.state.brush } else { None };
+        let stroke = if stroke { self.stroke() } else { None };
+        if path.is_empty() || (fill.is_none() && stroke.is_none()) {
+            return;
+        }
+        self.items.push(Item::Path {
+            path,
+            fill,
+            fill_rule: self.state.fill_rule,
+            stroke,
+        });
+    }
+
+    /// Фигура либо рисуется сразу, либо добавляется в открытый контур
+    fn shape(&mut self, path: Path, fill: bool) {
+        match self.path.as_mut() {
+            Some(open) => open.cmds.extend(path.cmds),
+            None => self.emit(path, fill, true),
+        }
+    }
+
+    fn poly(&mut self, points: &[(f64, f64)], bezier: bool, to: bool, closed: bool) {
+        let t = self.transform();
+        let mut path = Path::default();
+        let mut iter = points.iter().map(|&(x, y)| t.apply(x, y));
+
+        if to {
+            let (x, y) = t.apply(self.position.0, self.position.1);
+            if self.path.as_ref().is_none_or(|p| p.cmds.is_empty()) {
+                path.move_to(x, y);
+            }
+        } else if let Some((x, y)) = iter.next() {
+            path.move_to(x, y);
+        }
+
+        let rest: Vec<_> = iter.collect();
+        if bezier {
+            for c in rest.chunks_exact(3) {
+                path.cubic_to(c[0].0, c[0].1, c[1].0, c[1].1, c[2].0, c[2].1);
+            }
+        } else {
+            for &(x, y) in &rest {
+                path.line_to(x, y);
+            }
+        }
+        if closed {
+            path.close();
+        }
+        if let Some(&last) = points.last() {
+            self.position = last;
+        }
+        self.shape(path, closed);
+    }
+
+    fn rectangle(&mut self, r: (f64, f64, f64, f64)) {
+        let t = self.transform();
+        let mut path = Path::default();
+        for (i, &(x, y)) in [(r.0, r.1), (r.2, r.1), (r.2, r.3), (r.0, r.3)]
+            .iter()
+            .enumerate()
+        {
+            let (x, y) = t.apply(x, y);
+            if i == 0 {
+                path.move_to(x, y);
+            } else {
+                path.line_to(x, y);
+            }
+        }
+        path.close();
+        self.shape(path, true);
+    }
+
+    fn ellipse(&mut self, r: (f64, f64, f64, f64)) {
+        const K: f64 = 0.552_284_749_830_793_6;
+        let (cx, cy) = ((r.0 + r.2) / 2.0, (r.1 + r.3) / 2.0);
+        let (rx, ry) = ((r.2 - r.0) / 2.0, (r.3 - r.1) / 2.0);
+        let mut path = Path::default();
+        path.move_to(cx + rx, cy);
+        path.cubic_to(cx + rx, cy + K * ry, cx + K * rx, cy + ry, cx, cy + ry);
+        path.cubic_to(cx - K * rx, cy + ry, cx - rx, cy + K * ry, cx - rx, cy);
+        path.cubic_to(cx - rx, cy - K * ry, cx - K * rx, cy - ry, cx, cy - ry);
+        path.cubic_to(cx + K * rx, cy - ry, cx + rx, cy - K * ry, cx + rx, cy);
+        path.close();
+        let path = path.transform(&self.transform());
+        self.shape(path, true);
+    }
+
+    fn record(&mut self, record_type: u32, r: &[u8]) {
+        match record_type {
+            EMR_POLYBEZIER | EMR_POLYGON | EMR_POLYLINE | EMR_POLYBEZIERTO | EMR_POLYLINETO
+            | EMR_POLYBEZIER16 | EMR_POLYGON16 | EMR_POLYLINE16 | EMR_POLYBEZIERTO16
+            | EMR_POLYLINETO16 => {
+                let short = record_type >= EMR_POLYBEZIER16;
+                let Some(count) = Self::u32_at(r, 24) else {
+                    return;
+                };
+                let points = Self::points(r, 28, count as usize, short);
+                let kind = if short {
+                    record_type - EMR_POLYBEZIER16 + EMR_POLYBEZIER
+                } else {
+                    record_type
+                };
+                match kind {
+                    EMR_POLYBEZIER => self.poly(&points, true, false, false),
+                    EMR_POLYGON => self.poly(&points, false, false, true),
+                    EMR_POLYLINE => self.poly(&points, false, false, false),
+                    EMR_POLYBEZIERTO => self.poly(&points, true, true, false),
+                    _ => self.poly(&points, false, true, false),
+                }
+            }
+            EMR_POLYPOLYLINE | EMR_POLYPOLYGON | EMR_POLYPOLYLINE16 | EMR_POLYPOLYGON16 => {
+                let short = record_type >= EMR_POLYPOLYLINE16;
+                let closed = matches!(record_type, EMR_POLYPOLYGON | EMR_POLYPOLYGON16);
+                let (Some(polys), Some(total)) = (Self::u32_at(r, 24), Self::u32_at(r, 28)) else {
+                    return;
+                };
+                let counts: Vec<usize> = (0..polys as usize)
+                    .filter_map(|i| Self::u32_at(r, 32 + i * 4).map(|c| c as usize))
+                    .collect();
+                let points = Self::points(r, 32 + polys as usize * 4, total as usize, short);
+                let t = self.transform();
+                let mut path = Path::default();
+                let mut start = 0;
+                for count in counts {
+                    let Some(poly) = points.get(start..start + count) else {
+                        break;
+                    };
+                    for (i, &(x, y)) in poly.iter().enumerate() {
+                        let (x, y) = t.apply(x, y);
+                        if i == 0 {
+                            path.move_to(x, y);
+                        } else {
+                            path.line_to(x, y);
+                        }
+                    }
+                    if closed {
+                        path.close();
+                    }
+                    start += count;
+                }
+                self.shape(path, closed);
+            }
+            EMR_SETWINDOWEXTEX => {
+                if let Some(p) = Self::point(r, 8) {
+                    self.state.window_ext = p;
+                }
+            }
+            EMR_SETWINDOWORGEX => {
+                if let Some(p) = Self::point(r, 8) {
+                    self.state.window_org = p;
+                }
+            }
+            EMR_SETVIEWPORTEXTEX => {
+                if let Some(p) = Self::point(r, 8) {
+                    self.state.viewport_ext = p;
+                }
+            }
+            EMR_SETVIEWPORTORGEX => {
+                if let Some(p) = Self::point(r, 8) {
+                    self.state.viewport_org = p;
+                }
+            }
+            EMR_SETMAPMODE => {
+                if let Some(mode) = Self::u32_at(r, 8) {
+                    self.state.map_mode = mode;
+                }
+            }
+            EMR_SETPOLYFILLMODE => {
+                self.state.fill_rule = match Self::u32_at(r, 8) {
+                    Some(2) => FillRule::NonZero,
+                    _ => FillRule::EvenOdd,
+                };
+            }
+            EMR_SETTEXTALIGN => {
+                if let Some(align) = Self::u32_at(r, 8) {
+                    self.state.text_align = align;
+                }
+            }
+            EMR_SETTEXTCOLOR => {
+                if let Some(c) = r.get(8..12) {
+                    self.state.text_color = color_ref(c);
+                }
+            }
+            EMR_MOVETOEX => {
+                if let Some(p) = Self::point(r, 8) {
+                    self.position = p;
+                    let (x, y) = self.transform().apply(p.0, p.1);
+                    if let Some(path) = self.path.as_mut() {
+                        path.move_to(x, y);
+                    }
+                }
+            }
+            EMR_LINETO => {
+                if let Some(p) = Self::point(r, 8) {
+                    self.poly(&[p], false, true, false);
+                }
+            }
+            EMR_SAVEDC => self.saved.push(self.state.clone()),
+            EMR_RESTOREDC => {
+                let back = Self::i32_at(r, 8).unwrap_or(-1.0).abs().max(1.0) as usize;
+                for _ in 1..back {
+                    self.saved.pop();
+                }
+                if let Some(state) = self.saved.pop() {
+                    self.state = state;
+                }
+            }
+            EMR_SETWORLDTRANSFORM => {
+                if let Some(x) = Self::xform(r, 8) {
+                    self.state.world = x;
+                }
+            }
+            EMR_MODIFYWORLDTRANSFORM => {
+                let (Some(x), Some(mode)) = (Self::xform(r, 8), Self::u32_at(r, 32)) else {
+                    return;
+                };
+                self.state.world = match mode {
+                    1 => Affine::identity(),
+                    2 => self.state.world.then(&x),
+                    3 => x.then(&self.state.world),
+                    _ => x,
+                };
+            }
+            EMR_CREATEPEN => {
+                let (Some(ih), Some(style), Some(width), Some(color)) = (
+                    Self::u32_at(r, 8),
+                    Self::u32_at(r, 12),
+                    Self::i32_at(r, 16),
+                    r.get(24..28),
+                ) else {
+                    return;
+                };
+                self.objects.insert(
+                    ih,
+                    Object::Pen(Pen {
+                        color: color_ref(color),
+                        width,
+                        style,
+                    }),
+                );
+            }
+            EMR_EXTCREATEPEN => {
+                let (Some(ih), Some(style), Some(width), Some(color)) = (
+                    Self::u32_at(r, 8),
+                    Self::u32_at(r, 28),
+                    Self::u32_at(r, 32),
+                    r.get(40..44),
+                ) else {
+                    return;
+                };
+                self.objects.insert(
+                    ih,
+                    Object::Pen(Pen {
+                        color: color_ref(color),
+                        width: width as f64,
+                        style,
+                    }),
+                );
+            }
+            EMR_CREATEBRUSHINDIRECT => {
+                let (Some(ih), Some(style), Some(color)) =
+                    (Self::u32_at(r, 8), Self::u32_at(r, 12), r.get(16..20))
+                else {
+                    return;
+                };
+                let brush = if style == 1 {
+                    None
+                } else {
+                    Some(color_ref(color))
+                };
+                self.objects.insert(ih, Object::Brush(brush));
+            }
+            EMR_EXTCREATEFONTINDIRECTW => {
+                let (Some(ih), Some(height), Some(weight)) =
+                    (Self::u32_at(r, 8), Self::i32_at(r, 12), Self::i32_at(r, 28))
+                else {
+                    return;
+                };
+                let face: Vec<u16> = r
+                    .get(40..104)
+                    .unwrap_or(&[])
+                    .chunks_exact(2)
+                    .map(LittleEndian::read_u16)
+                    .take_while(|&c| c != 0)
+                    .collect();
+                self.objects.insert(
+                    ih,
+                    Object::Font(Font {
+                        height: height.abs(),
+                        weight: weight as i32,
+                        italic: r.get(32).is_some_and(|&i| i != 0),
+                        face: String::from_utf16_lossy(&face),
+                    }),
+                );
+            }
+            EMR_SELECTOBJECT => {
+                if let Some(ih) = Self::u32_at(r, 8) {
+                    self.select(ih);
+                }
+            }
+            EMR_DELETEOBJECT => {
+                if let Some(ih) = Self::u32_at(r, 8) {
+                    self.objects.remove(&ih);
+                }
+            }
+            EMR_RECTANGLE | EMR_ROUNDRECT => {
+                if let Some(rect) = Self::rect(r, 8) {
+                    self.rectangle(rect);
+                }
+            }
+            EMR_ELLIPSE => {
+                if let Some(rect) = Self::rect(r, 8) {
+                    self.ellipse(rect);
+                }
+            }
+            EMR_BEGINPATH => self.path = Some(Path::default()),
+            EMR_ENDPATH => {}
+            EMR_CLOSEFIGURE => {
+                if let Some(path) = self.path.as_mut() {
+                    path.close();
+                }
+            }
+            EMR_FILLPATH | EMR_STROKEANDFILLPATH | EMR_STROKEPATH => {
+                if let Some(path) = self.path.take() {
+                    self.emit(
+                        path,
+                        record_type != EMR_STROKEPATH,
+                        record_type != EMR_FILLPATH,
+                    );
+                }
+            }
+            EMR_STRETCHDIBITS => self.stretch_dib(r),
+            EMR_EXTTEXTOUTW => self.text(r),
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, ih: u32) {
+        if ih & STOCK_OBJECT != 0 {
+            match ih & !STOCK_OBJECT {
+                0 => self.state.brush = Some(Color::WHITE),
+                1 => self.state.brush = Some(Color::rgb(192, 192, 192)),
+                2 => self.state.brush = Some(Color::rgb(128, 128, 128)),
+                3 => self.state.brush = Some(Color::rgb(64, 64, 64)),
+                4 => self.state.brush = Some(Color::BLACK),
+                5 => self.state.brush = None,
+                6 | 7 => {
+                    self.state.pen = Some(Pen {
+                        color: if ih & !STOCK_OBJECT == 6 {
+                            Color::WHITE
+                        } else {
+                            Color::BLACK
+                        },
+                        width: 0.0,
+                        style: 0,
+                    })
+                }
+                8 => self.state.pen = None,
+                _ => {}
+            }
+            return;
+        }
+        match self.objects.get(&ih) {
+            Some(Object::Pen(pen)) => {
+                self.state.pen = if pen.style & 0x0F == 5 {
+                    None
+                } else {
+                    Some(pen.clone())
+                }
+            }
+            Some(Object::Brush(brush)) => self.state.brush = *brush,
+            Some(Object::Font(font)) => self.state.font = font.clone(),
+            None => log::debug!("EMF: select of unknown object {}", ih),
+        }
+    }
+
+    fn text(&mut self, r: &[u8]) {
+        let (Some(reference), Some(chars), Some(off_string)) =
+            (Self::point(r, 36), Self::u32_at(r, 44), Self::u32_at(r, 48))
+        else {
+            return;
+        };
+        let start = off_string as usize;
+        let Some(raw) = r.get(start..start + chars as usize * 2) else {
+            return;
+        };
+        let units: Vec<u16> = raw.chunks_exact(2).map(LittleEndian::read_u16).collect();
+        let text = String::from_utf16_lossy(&units);
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let align = self.state.text_align;
+        let h_align = match align & 0x06 {
+            0x06 => HAlign::Center,
+            0x02 => HAlign::Right,
+            _ => HAlign::Left,
+        };
+        let size = if self.state.font.height > 0.0 {
+            self.state.font.height
+        } else {
+            12.0
+        };
+        let (v_align, shift) = match align & 0x18 {
+            0x18 => (VAlign::Bottom, size * 0.2),
+            0x08 => (VAlign::Bottom, 0.0),
+            _ => (VAlign::Top, 0.0),
+        };
+
+        let t = self.transform();
+        // Текст всегда рисуется сверху вниз, даже если ось Y логических координат смотрит вверх
+        let flip = if t.d < 0.0 { -1.0 } else { 1.0 };
+        let transform = t
+            .then(&Affine::translate(reference.0, reference.1))
+            .then(&Affine::scale(1.0, flip))
+            .then(&Affine::translate(0.0, shift));
+
+        self.items.push(Item::Text(TextBlock {
+            transform,
+            width: 0.0,
+            height: 0.0,
+            text,
+            font: Some(self.state.font.face.clone()).filter(|f| !f.is_empty()),
+            size,
+            color: self.state.text_color,
+            bold: self.state.font.weight >= 700,
+            italic: self.state.font.italic,
+            h_align,
+            v_align,
+            wrap: false,
+        }));
+    }
+
+    fn stretch_dib(&mut self, r: &[u8]) {
+        let (Some(x), Some(y), Some(off_bmi), Some(off_bits), Some(cx), Some(cy)) = (
+            Self::i32_at(r, 24),
+            Self::i32_at(r, 28),
+            Self::u32_at(r, 48),
+            Self::u32_at(r, 56),
+            Self::i32_at(r, 72),
+            Self::i32_at(r, 76),
+        ) else {
+            return;
+        };
+        let Some(bmi) = r.get(off_bmi as usize..) else {
+            return;
+        };
+        let Some(bits) = r.get(off_bits as usize..) else {
+            return;
+        };
+        let Some(image) = decode_dib(bmi, bits) else {
+            log::debug!("EMF: unsupported DIB");
+            return;
+        };
+        let transform = self
+            .transform()
+            .then(&Affine::translate(x, y))
+            .then(&Affine::scale(cx, cy));
+        self.items.push(Item::Image {
+            transform,
+            data: image,
+        });
+    }
+}
+
+/// Декодирует файл BMP: заголовок `BITMAPFILEHEADER` и DIB за ним
+pub fn decode_bmp(bytes: &[u8]) -> Option<ImageData> {
+    if !bytes.starts_with(b"BM") || bytes.len() < 14 {
+        return None;
+    }
+    let off_bits = LittleEndian::read_u32(&bytes[10..14]) as usize;
+    decode_dib(&bytes[14..], bytes.get(off_bits..)?)
+}
+
+/// Декодирует несжатый DIB (1, 4, 8, 24 и 32 бита на пиксель) в RGBA
+fn decode_dib(bmi: &[u8], bits: &[u8]) -> Option<ImageData> {
+    if bmi.len() < 40 {
+        return None;
+    }
+    let header_size = LittleEndian::read_u32(&bmi[0..4]) as usize;
+    let width = LittleEndian::read_i32(&bmi[4..8]);
+    let height = LittleEndian::read_i32(&bmi[8..12]);
+    let bit_count = LittleEndian::read_u16(&bmi[14..16]) as usize;
+    let compression = LittleEndian::read_u32(&bmi[16..20]);
+    let clr_used = LittleEndian::read_u32(&bmi[32..36]) as usize;
+    if width <= 0 || height == 0 || !(compression == 0 || compression == 3) {
+        return None;
+    }
+    if !matches!(bit_count, 1 | 4 | 8 | 24 | 32) {
+        return None;
+    }
+
+    let (w, h) = (width as usize, height.unsigned_abs() as usize);
+    let top_down = height < 0;
+    let palette_len = if bit_count <= 8 {
+        if clr_used > 0 {
+            clr_used
+        } else {
+            1 << bit_count
+        }
+    } else {
+        0
+    };
+    let palette: Vec<[u8; 3]> = bmi
+        .get(header_size..header_size + palette_len * 4)
+        .unwrap_or(&[])
+        .chunks_exact(4)
+        .map(|c| [c[2], c[1], c[0]])
+        .collect();
+
+    let stride = (w * bit_count).div_ceil(32) * 4;
+    if bits.len() < stride * h {
+        return None;
+    }
+
+    let mut pixels = Vec::with_capacity(w * h * 4);
+    for row in 0..h {
+        let src_row = if top_down { row } else { h - 1 - row };
+        let line = &bits[src_row * stride..(src_row + 1) * stride];
+        for col in 0..w {
+            let rgb = match bit_count {
+                1 | 4 | 8 => {
+                    let bit = col * bit_count;
+                    let byte = line[bit / 8];
+                    let shift = 8 - bit_count - bit % 8;
+                    let index = (byte >> shift) as usize & ((1 << bit_count) - 1);
+                    palette.get(index).copied().unwrap_or([0, 0, 0])
+                }
+                24 => [line[col * 3 + 2], line[col * 3 + 1], line[col * 3]],
+                _ => [line[col * 4 + 2], line[col * 4 + 1], line[col * 4]],
+            };
+            pixels.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+        }
+    }
+
+    Some(ImageData::Rgba {
+        width: w as u32,
+        height: h as u32,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(record_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&record_type.to_le_bytes());
+        out.extend_from_slice(&((body.len() + 8) as u32).to_le_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Минимальный EMF: 100x100 пикселей при 96 DPI
+    fn sample_emf() -> Vec<u8> {
+        let mut header = ints(&[0, 0, 99, 99, 0, 0, 2646, 2646]);
+        header.extend_from_slice(&ENHMETA_SIGNATURE.to_le_bytes());
+        // version, bytes, records, handles, nDescription, offDescription, nPalEntries,
+        // szlDevice 1000x1000 пикселей на 265x265 мм (96 DPI)
+        header.extend(ints(&[0x10000, 0, 0, 0, 0, 0, 0, 1000, 1000, 265, 265]));
+
+        let mut data = record(EMR_HEADER, &header);
+        data.extend(record(EMR_CREATEBRUSHINDIRECT, &ints(&[1, 0, 0x0000FF])));
+        data.extend(record(EMR_SELECTOBJECT, &ints(&[1])));
+        data.extend(record(EMR_RECTANGLE, &ints(&[10, 10, 90, 90])));
+        data.extend(record(EMR_EOF, &ints(&[0, 0, 0])));
+        data
+    }
+
+    #[test]
+    fn test_emf_rectangle() {
+        let data = sample_emf();
+        assert!(is_emf(&data));
+
+        let scene = scene(&data).unwrap();
+        assert!((scene.width - 75.0).abs() < 0.1);
+        assert_eq!(scene.items.len(), 1);
+        match &scene.items[0] {
+            Item::Path { fill, .. } => assert_eq!(*fill, Some(Color::rgb(255, 0, 0))),
+            other => panic!("unexpected item {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_dib_24bit() {
+        let mut bmi = ints(&[40, 1, 1]);
+        bmi.extend_from_slice(&1u16.to_le_bytes());
+        bmi.extend_from_slice(&24u16.to_le_bytes());
+        bmi.extend(ints(&[0, 0, 0, 0, 0, 0]));
+        let bits = [0x10, 0x20, 0x30, 0];
+        match decode_dib(&bmi, &bits) {
+            Some(ImageData::Rgba { pixels, .. }) => assert_eq!(pixels, vec![0x30, 0x20, 0x10, 255]),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_bmp() {
+        let mut bmp = b"BM".to_vec();
+        bmp.extend(ints(&[58, 0, 54, 40, 1, 1]));
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&24u16.to_le_bytes());
+        bmp.extend(ints(&[0, 0, 0, 0, 0, 0]));
+        bmp.extend_from_slice(&[0x10, 0x20, 0x30, 0]);
+        match decode_bmp(&bmp) {
+            Some(ImageData::Rgba { pixels, .. }) => assert_eq!(pixels, vec![0x30, 0x20, 0x10, 255]),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(decode_bmp(&bmp[..20]).is_none());
+    }
+
+    #[test]
+    fn test_decode_dib_bad_bit_count() {
+        for bit_count in [0u16, 2, 3, 5, 6, 7, 16] {
+            let mut bmi = ints(&[40, 1, 1]);
+            bmi.extend_from_slice(&1u16.to_le_bytes());
+            bmi.extend_from_slice(&bit_count.to_le_bytes());
+            bmi.extend(ints(&[0, 0, 0, 0, 0, 0]));
+            assert!(decode_dib(&bmi, &[0xFF; 8]).is_none(), "{bit_count}");
+        }
+    }
+}
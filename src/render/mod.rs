@@ -0,0 +1,165 @@
+//! Растровый вывод страниц в PNG без GPU.
+//!
+//! Страница (VSDX, VSD) или EMF сначала переводится в список отрисовки [`scene::Scene`],
+//! затем растеризуется на CPU через tiny-skia.
+
+pub mod emf;
+pub mod raster;
+pub mod scene;
+pub mod svg;
+pub mod visio;
+
+use std::{
+    fs,
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::format::{FileFormat, detect_format};
+use crate::parser_vdx::{self, VdxError};
+use crate::parser_vsd;
+use crate::parser_vsdx::read_vsdx::{self, Diagram, VsdxError};
+use raster::Rasterizer;
+use scene::{Color, Scene};
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("VSDX error: {0}")]
+    Vsdx(#[from] VsdxError),
+    #[error("VSD error: {0}")]
+    Vsd(String),
+    #[error("VDX error: {0}")]
+    Vdx(#[from] VdxError),
+    #[error("EMF error: {0}")]
+    Emf(String),
+    #[error("Unsupported file format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Page {0} not found")]
+    PageNotFound(usize),
+    #[error("Invalid image size {0}x{1}")]
+    InvalidSize(f64, f64),
+    #[error("Unable to load font {0}")]
+    Font(String),
+    #[error("PNG error: {0}")]
+    Png(String),
+}
+
+/// Настройки растрового вывода
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Разрешение в точках на дюйм
+    pub dpi: f32,
+    pub anti_alias: bool,
+    /// Цвет фона; `None` — прозрачный
+    pub background: Option<Color>,
+    /// Файл шрифта TrueType/OpenType; по умолчанию ищется системный DejaVu Sans
+    pub font_path: Option<PathBuf>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            dpi: 96.0,
+            anti_alias: true,
+            background: Some(Color::WHITE),
+            font_path: None,
+        }
+    }
+}
+
+/// Растеризует сцену и кодирует в PNG
+pub fn render_scene(scene: &Scene, options: &RenderOptions) -> Result<Vec<u8>, RenderError> {
+    let pixmap = Rasterizer::new(options)?.rasterize(scene, options)?;
+    pixmap
+        .encode_png()
+        .map_err(|e| RenderError::Png(e.to_string()))
+}
+
+/// Рисует страницу `page_index` (с нуля) уже разобранной диаграммы
+pub fn render_page(
+    diagram: &Diagram,
+    page_index: usize,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, RenderError> {
+    let page = diagram
+        .pages
+        .get(page_index)
+        .ok_or(RenderError::PageNotFound(page_index))?;
+    render_scene(&visio::page_scene(diagram, page), options)
+}
+
+/// Страница `page_index` (с нуля) в виде документа SVG
+pub fn render_page_svg(diagram: &Diagram, page_index: usize) -> Result<String, RenderError> {
+    let page = diagram
+        .pages
+        .get(page_index)
+        .ok_or(RenderError::PageNotFound(page_index))?;
+    Ok(svg::scene_to_svg(&visio::page_scene(diagram, page)))
+}
+
+/// То же, что [`render_page_svg`]; ссылки на страницы документа получают адрес от `page_href`,
+/// например имя файла SVG этой страницы
+pub fn render_page_svg_linked(
+    diagram: &Diagram,
+    page_index: usize,
+    page_href: &dyn Fn(usize) -> String,
+) -> Result<String, RenderError> {
+    let page = diagram
+        .pages
+        .get(page_index)
+        .ok_or(RenderError::PageNotFound(page_index))?;
+    Ok(svg::scene_to_svg(&visio::page_scene_linked(
+        diagram, page, page_href,
+    )))
+}
+
+/// EMF целиком в виде документа SVG
+pub fn render_emf_svg(data: &[u8]) -> Result<String, RenderError> {
+    Ok(svg::scene_to_svg(&emf::scene(data)?))
+}
+
+/// Рисует EMF целиком
+pub fn render_emf(data: &[u8], options: &RenderOptions) -> Result<Vec<u8>, RenderError> {
+    render_scene(&emf::scene(data)?, options)
+}
+
+/// Рисует файл VSDX, VSD, VDX или EMF; формат определяется по содержимому файла
+pub fn render_file(
+    file_path: &Path,
+    page_index: usize,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, RenderError> {
+    let data = fs::read(file_path)?;
+    match detect_format(&data) {
+        Some(format) if format.is_package() => {
+            render_page(&read_vsdx::parse(Cursor::new(data))?, page_index, options)
+        }
+        Some(FileFormat::Vsd) => {
+            let diagram =
+                parser_vsd::parse_data(&data).map_err(|e| RenderError::Vsd(e.to_string()))?;
+            render_page(&diagram, page_index, options)
+        }
+        Some(FileFormat::Vdx) => render_page(&parser_vdx::parse_data(&data)?, page_index, options),
+        Some(FileFormat::Emf) => render_emf(&data, options),
+        Some(format) => Err(RenderError::UnsupportedFormat(format.to_string())),
+        None => Err(RenderError::UnsupportedFormat(
+            file_path.display().to_string(),
+        )),
+    }
+}
+
+/// Рисует файл и сохраняет PNG
+pub fn render_file_to_png(
+    file_path: &Path,
+    page_index: usize,
+    out_path: &Path,
+    options: &RenderOptions,
+) -> Result<(), RenderError> {
+    let png = render_file(file_path, page_index, options)?;
+    fs::write(out_path, png)?;
+    Ok(())
+}
@@ -0,0 +1,508 @@
+use std::path::Path as FsPath;
+
+use ab_glyph::{Font, FontVec, GlyphId, OutlineCurve};
+use tiny_skia::{
+    FillRule as SkFillRule, LineCap as SkLineCap, Paint, PathBuilder, Pixmap, PixmapPaint,
+    PremultipliedColorU8, StrokeDash, Transform,
+};
+
+use crate::render::scene::{
+    Affine, Color, FillRule, HAlign, ImageData, Item, LineCap, Path, Scene, Stroke, TextBlock,
+    VAlign,
+};
+use crate::render::{RenderError, RenderOptions};
+
+/// Шрифты, которые ищутся, если в настройках путь не задан
+const FALLBACK_FONTS: [&str; 5] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/Library/Fonts/Arial.ttf",
+];
+
+/// Растеризует сцену средствами CPU
+pub struct Rasterizer {
+    font: Option<FontVec>,
+    anti_alias: bool,
+}
+
+impl Rasterizer {
+    pub fn new(options: &RenderOptions) -> Result<Self, RenderError> {
+        let font = match &options.font_path {
+            Some(path) => Some(load_font(path)?),
+            None => FALLBACK_FONTS
+                .iter()
+                .find_map(|path| load_font(FsPath::new(path)).ok()),
+        };
+        if font.is_none() {
+            log::warn!("No font found, text will not be rendered");
+        }
+        Ok(Self {
+            font,
+            anti_alias: options.anti_alias,
+        })
+    }
+
+    pub fn rasterize(&self, scene: &Scene, options: &RenderOptions) -> Result<Pixmap, RenderError> {
+        let scale = options.dpi as f64 / 72.0;
+        let width = (scene.width * scale).ceil();
+        let height = (scene.height * scale).ceil();
+        if !(1.0..=u32::MAX as f64).contains(&width) || !(1.0..=u32::MAX as f64).contains(&height) {
+            return Err(RenderError::InvalidSize(width, height));
+        }
+        let mut pixmap = Pixmap::new(width as u32, height as u32)
+            .ok_or(RenderError::InvalidSize(width, height))?;
+
+        if let Some(bg) = options.background {
+            pixmap.fill(tiny_skia::Color::from_rgba8(bg.r, bg.g, bg.b, bg.a));
+        }
+
+        self.draw_items(&mut pixmap, &scene.items, &Affine::scale(scale, scale));
+        Ok(pixmap)
+    }
+
+    fn draw_items(&self, pixmap: &mut Pixmap, items: &[Item], transform: &Affine) {
+        for item in items {
+            match item {
+                Item::Path {
+                    path,
+                    fill,
+                    fill_rule,
+                    stroke,
+                } => {
+                    let Some(sk_path) = to_sk_path(&path.transform(transform)) else {
+                        continue;
+                    };
+                    if let Some(color) = fill {
+                        let rule = match fill_rule {
+                            FillRule::NonZero => SkFillRule::Winding,
+                            FillRule::EvenOdd => SkFillRule::EvenOdd,
+                        };
+                        pixmap.fill_path(
+                            &sk_path,
+                            &self.paint(*color),
+                            rule,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                    if let Some(stroke) = stroke {
+                        let sk_stroke = to_sk_stroke(stroke, transform.scale_factor());
+                        pixmap.stroke_path(
+                            &sk_path,
+                            &self.paint(stroke.color),
+                            &sk_stroke,
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+                Item::Image {
+                    transform: image_transform,
+                    data,
+                } => self.draw_image(pixmap, data, &transform.then(image_transform)),
+                Item::Text(block) => self.draw_text(pixmap, block, transform),
+                Item::Group {
+                    transform: group_transform,
+                    items,
+                } => self.draw_items(pixmap, items, &transform.then(group_transform)),
+            }
+        }
+    }
+
+    /// Помечает место неподдерживаемого изображения: серый прямоугольник с диагоналями
+    fn draw_missing_image(&self, pixmap: &mut Pixmap, transform: &Affine) {
+        let mut frame = Path::default();
+        frame.move_to(0.0, 0.0);
+        frame.line_to(1.0, 0.0);
+        frame.line_to(1.0, 1.0);
+        frame.line_to(0.0, 1.0);
+        frame.close();
+        frame.move_to(0.0, 0.0);
+        frame.line_to(1.0, 1.0);
+        frame.move_to(1.0, 0.0);
+        frame.line_to(0.0, 1.0);
+        let stroke = Stroke {
+            width: 1.0 / transform.scale_factor().max(f64::EPSILON),
+            color: Color::rgb(128, 128, 128),
+            dash: Vec::new(),
+            cap: LineCap::Butt,
+        };
+        let item = Item::Path {
+            path: frame,
+            fill: Some(Color::rgb(224, 224, 224)),
+            fill_rule: FillRule::NonZero,
+            stroke: Some(stroke),
+        };
+        self.draw_items(pixmap, std::slice::from_ref(&item), transform);
+    }
+
+    fn paint(&self, color: Color) -> Paint<'static> {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(color.r, color.g, color.b, color.a);
+        paint.anti_alias = self.anti_alias;
+        paint
+    }
+
+    fn draw_image(&self, pixmap: &mut Pixmap, data: &ImageData, transform: &Affine) {
+        let image = match data {
+            ImageData::Encoded(bytes) => match Pixmap::decode_png(bytes) {
+                Ok(image) => image,
+                Err(e) => {
+                    log::warn!("Unsupported image data: {}", e);
+                    self.draw_missing_image(pixmap, transform);
+                    return;
+                }
+            },
+            ImageData::Rgba {
+                width,
+                height,
+                pixels,
+            } => {
+                let Some(mut image) = Pixmap::new(*width, *height) else {
+                    return;
+                };
+                for (dst, src) in image.pixels_mut().iter_mut().zip(pixels.chunks_exact(4)) {
+                    let a = src[3] as u16;
+                    let pm = |c: u8| ((c as u16 * a + 127) / 255) as u8;
+                    if let Some(color) =
+                        PremultipliedColorU8::from_rgba(pm(src[0]), pm(src[1]), pm(src[2]), src[3])
+                    {
+                        *dst = color;
+                    }
+                }
+                image
+            }
+        };
+
+        let t = transform.then(&Affine::scale(
+            1.0 / image.width() as f64,
+            1.0 / image.height() as f64,
+        ));
+        let paint = PixmapPaint {
+            quality: tiny_skia::FilterQuality::Bilinear,
+            ..Default::default()
+        };
+        pixmap.draw_pixmap(0, 0, image.as_ref(), &paint, to_sk_transform(&t), None);
+    }
+
+    fn draw_text(&self, pixmap: &mut Pixmap, block: &TextBlock, transform: &Affine) {
+        let Some(font) = &self.font else {
+            return;
+        };
+        let Some(glyphs) = layout_text(font, block) else {
+            return;
+        };
+
+        let t = transform.then(&block.transform);
+        let mut builder = PathBuilder::new();
+        for (glyph, x, baseline) in glyphs {
+            let Some(outline) = font.outline(glyph) else {
+                continue;
+            };
+            let factor = block.size / font.height_unscaled() as f64;
+            let slant = if block.italic { 0.2 } else { 0.0 };
+            let map = |p: ab_glyph::Point| {
+                let (ux, uy) = (p.x as f64 * factor, p.y as f64 * factor);
+                let (px, py) = t.apply(x + ux + uy * slant, baseline - uy);
+                (px as f32, py as f32)
+            };
+
+            let mut last: Option<(f32, f32)> = None;
+            for curve in &outline.curves {
+                let (start, end) = match curve {
+                    OutlineCurve::Line(p0, p1) => (map(*p0), map(*p1)),
+                    OutlineCurve::Quad(p0, _, p2) => (map(*p0), map(*p2)),
+                    OutlineCurve::Cubic(p0, _, _, p3) => (map(*p0), map(*p3)),
+                };
+                if last != Some(start) {
+                    builder.move_to(start.0, start.1);
+                }
+                match curve {
+                    OutlineCurve::Line(..) => builder.line_to(end.0, end.1),
+                    OutlineCurve::Quad(_, p1, _) => {
+                        let c = map(*p1);
+                        builder.quad_to(c.0, c.1, end.0, end.1)
+                    }
+                    OutlineCurve::Cubic(_, p1, p2, _) => {
+                        let (c1, c2) = (map(*p1), map(*p2));
+                        builder.cubic_to(c1.0, c1.1, c2.0, c2.1, end.0, end.1)
+                    }
+                }
+                last = Some(end);
+            }
+        }
+
+        let Some(path) = builder.finish() else {
+            return;
+        };
+        let paint = self.paint(block.color);
+        pixmap.fill_path(
+            &path,
+            &paint,
+            SkFillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+        if block.bold {
+            let stroke = tiny_skia::Stroke {
+                width: (block.size * 0.04 * t.scale_factor()) as f32,
+                ..Default::default()
+            };
+            pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+}
+
+fn load_font(path: &FsPath) -> Result<FontVec, RenderError> {
+    let data = std::fs::read(path)?;
+    FontVec::try_from_vec(data).map_err(|_| RenderError::Font(path.display().to_string()))
+}
+
+/// Раскладывает текст блока: глиф, смещение по X и базовая линия в координатах блока
+fn layout_text(font: &FontVec, block: &TextBlock) -> Option<Vec<(GlyphId, f64, f64)>> {
+    let units = font.height_unscaled() as f64;
+    if units <= 0.0 || block.size <= 0.0 {
+        return None;
+    }
+    let factor = block.size / units;
+    let advance = |a: GlyphId, prev: Option<GlyphId>| {
+        let kern = prev.map_or(0.0, |p| font.kern_unscaled(p, a) as f64);
+        (font.h_advance_unscaled(a) as f64 + kern) * factor
+    };
+    let measure = |s: &str| {
+        let mut prev = None;
+        s.chars()
+            .map(|c| {
+                let id = font.glyph_id(c);
+                let w = advance(id, prev);
+                prev = Some(id);
+                w
+            })
+            .sum::<f64>()
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    for paragraph in block.text.split('\n') {
+        let paragraph = paragraph.trim_end_matches('\r');
+        if !block.wrap || block.width <= 0.0 {
+            lines.push(paragraph.to_string());
+            continue;
+        }
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && measure(&candidate) > block.width {
+                lines.push(std::mem::take(&mut line));
+                line = word.to_string();
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+
+    let ascent = font.ascent_unscaled() as f64 * factor;
+    let line_height = (font.height_unscaled() + font.line_gap_unscaled()) as f64 * factor;
+    let total = line_height * lines.len() as f64;
+    let top = match block.v_align {
+        VAlign::Top => 0.0,
+        VAlign::Middle => (block.height - total) / 2.0,
+        VAlign::Bottom => block.height - total,
+    };
+
+    let mut glyphs = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let width = measure(line);
+        let mut x = match block.h_align {
+            HAlign::Left => 0.0,
+            HAlign::Center => (block.width - width) / 2.0,
+            HAlign::Right => block.width - width,
+        };
+        let baseline = top + i as f64 * line_height + ascent;
+        let mut prev = None;
+        for c in line.chars() {
+            let id = font.glyph_id(c);
+            if let Some(p) = prev {
+                x += font.kern_unscaled(p, id) as f64 * factor;
+            }
+            glyphs.push((id, x, baseline));
+            x += font.h_advance_unscaled(id) as f64 * factor;
+            prev = Some(id);
+        }
+    }
+    Some(glyphs)
+}
+
+fn to_sk_transform(t: &Affine) -> Transform {
+    Transform::from_row(
+        t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32,
+    )
+}
+
+fn to_sk_path(path: &Path) -> Option<tiny_skia::Path> {
+    use crate::render::scene::PathCmd;
+
+    let mut builder = PathBuilder::new();
+    for cmd in &path.cmds {
+        match *cmd {
+            PathCmd::MoveTo(x, y) => builder.move_to(x as f32, y as f32),
+            PathCmd::LineTo(x, y) => builder.line_to(x as f32, y as f32),
+            PathCmd::QuadTo(x1, y1, x, y) => {
+                builder.quad_to(x1 as f32, y1 as f32, x as f32, y as f32)
+            }
+            PathCmd::CubicTo(x1, y1, x2, y2, x, y) => builder.cubic_to(
+                x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32,
+            ),
+            PathCmd::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+fn to_sk_stroke(stroke: &Stroke, scale: f64) -> tiny_skia::Stroke {
+    // Линия тоньше пикселя всё равно должна быть видна
+    let width = (stroke.width * scale).max(1.0) as f32;
+    let dash = if stroke.dash.len() >= 2 && stroke.dash.len().is_multiple_of(2) {
+        StrokeDash::new(
+            stroke.dash.iter().map(|d| (d * scale) as f32).collect(),
+            0.0,
+        )
+    } else {
+        None
+    };
+    tiny_skia::Stroke {
+        width,
+        line_cap: match stroke.cap {
+            LineCap::Butt => SkLineCap::Butt,
+            LineCap::Round => SkLineCap::Round,
+            LineCap::Square => SkLineCap::Square,
+        },
+        dash,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            dpi: 72.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rasterize_filled_rectangle() {
+        let mut path = Path::default();
+        path.move_to(10.0, 10.0);
+        path.line_to(30.0, 10.0);
+        path.line_to(30.0, 30.0);
+        path.line_to(10.0, 30.0);
+        path.close();
+
+        let mut scene = Scene::new(40.0, 40.0);
+        scene.items.push(Item::Path {
+            path,
+            fill: Some(Color::rgb(255, 0, 0)),
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+        });
+
+        let options = options();
+        let pixmap = Rasterizer::new(&options)
+            .unwrap()
+            .rasterize(&scene, &options)
+            .unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (40, 40));
+
+        let inside = pixmap.pixel(20, 20).unwrap();
+        assert_eq!((inside.red(), inside.green(), inside.blue()), (255, 0, 0));
+        let outside = pixmap.pixel(2, 2).unwrap();
+        assert_eq!(
+            (outside.red(), outside.green(), outside.blue()),
+            (255, 255, 255)
+        );
+    }
+
+    #[test]
+    fn test_jpeg_foreign_shape_marked_missing() {
+        use crate::parser_vsdx::read_vsdx::{Cell, Diagram, ForeignData, Page, Shape};
+
+        let cells = [
+            ("PinX", 0.5),
+            ("PinY", 0.5),
+            ("Width", 1.0),
+            ("Height", 1.0),
+            ("LocPinX", 0.5),
+            ("LocPinY", 0.5),
+        ]
+        .iter()
+        .map(|(n, v)| {
+            (
+                (*n).into(),
+                Cell {
+                    value: v.to_string(),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+        let page = Page {
+            width: 1.0,
+            height: 1.0,
+            shapes: vec![Shape {
+                cells,
+                foreign: Some(ForeignData {
+                    foreign_type: "Bitmap".to_string(),
+                    target: Some("visio/media/image1.jpeg".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut diagram = Diagram {
+            pages: vec![page],
+            ..Default::default()
+        };
+        diagram.media.insert(
+            "visio/media/image1.jpeg".to_string(),
+            vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10, b'J', b'F', b'I', b'F'],
+        );
+
+        let scene = crate::render::visio::page_scene(&diagram, &diagram.pages[0]);
+        let options = options();
+        let pixmap = Rasterizer::new(&options)
+            .unwrap()
+            .rasterize(&scene, &options)
+            .unwrap();
+        let (w, h) = (pixmap.width(), pixmap.height());
+        let inside = pixmap.pixel(w / 4, h / 2).unwrap();
+        assert_eq!(
+            (inside.red(), inside.green(), inside.blue()),
+            (224, 224, 224)
+        );
+    }
+
+    #[test]
+    fn test_dpi_scales_output() {
+        let scene = Scene::new(72.0, 36.0);
+        let options = RenderOptions {
+            dpi: 144.0,
+            ..Default::default()
+        };
+        let pixmap = Rasterizer::new(&options)
+            .unwrap()
+            .rasterize(&scene, &options)
+            .unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (144, 72));
+    }
+}
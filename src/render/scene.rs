@@ -0,0 +1,378 @@
+/// Аффинное преобразование `(x, y) -> (a*x + c*y + e, b*x + d*y + f)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Affine {
+    pub fn identity() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self {
+            e: x,
+            f: y,
+            ..Self::identity()
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self {
+            a: sx,
+            d: sy,
+            ..Self::identity()
+        }
+    }
+
+    pub fn rotate(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    /// Сначала применяется `other`, затем `self`
+    pub fn then(&self, other: &Affine) -> Affine {
+        Affine {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// Средний коэффициент масштабирования, нужен для толщины линий
+    pub fn scale_factor(&self) -> f64 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    pub fn invert(&self) -> Option<Affine> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        Some(Affine {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    /// Разбирает `#RRGGBB`; индексы палитры Visio 0..23 тоже поддерживаются
+    pub fn parse(value: &str) -> Option<Color> {
+        let value = value.trim();
+        if let Some(hex) = value.strip_prefix('#') {
+            if hex.len() != 6 {
+                return None;
+            }
+            let v = u32::from_str_radix(hex, 16).ok()?;
+            return Some(Color::rgb((v >> 16) as u8, (v >> 8) as u8, v as u8));
+        }
+        let index = value.parse::<f64>().ok()? as usize;
+        VISIO_PALETTE
+            .get(index)
+            .map(|&(r, g, b)| Color::rgb(r, g, b))
+    }
+
+    pub fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+}
+
+/// Стандартная палитра документа Visio для индексированных цветов
+const VISIO_PALETTE: [(u8, u8, u8); 24] = [
+    (0, 0, 0),
+    (255, 255, 255),
+    (255, 0, 0),
+    (0, 255, 0),
+    (0, 0, 255),
+    (255, 255, 0),
+    (255, 0, 255),
+    (0, 255, 255),
+    (128, 0, 0),
+    (0, 128, 0),
+    (0, 0, 128),
+    (128, 128, 0),
+    (128, 0, 128),
+    (0, 128, 128),
+    (192, 192, 192),
+    (230, 230, 230),
+    (205, 205, 205),
+    (179, 179, 179),
+    (154, 154, 154),
+    (128, 128, 128),
+    (102, 102, 102),
+    (77, 77, 77),
+    (51, 51, 51),
+    (26, 26, 26),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCmd {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    QuadTo(f64, f64, f64, f64),
+    CubicTo(f64, f64, f64, f64, f64, f64),
+    Close,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    pub cmds: Vec<PathCmd>,
+}
+
+impl Path {
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        self.cmds.push(PathCmd::MoveTo(x, y));
+    }
+
+    pub fn line_to(&mut self, x: f64, y: f64) {
+        self.cmds.push(PathCmd::LineTo(x, y));
+    }
+
+    pub fn quad_to(&mut self, x1: f64, y1: f64, x: f64, y: f64) {
+        self.cmds.push(PathCmd::QuadTo(x1, y1, x, y));
+    }
+
+    pub fn cubic_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, x: f64, y: f64) {
+        self.cmds.push(PathCmd::CubicTo(x1, y1, x2, y2, x, y));
+    }
+
+    pub fn close(&mut self) {
+        self.cmds.push(PathCmd::Close);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self
+            .cmds
+            .iter()
+            .any(|c| !matches!(c, PathCmd::MoveTo(..) | PathCmd::Close))
+    }
+
+    pub fn transform(&self, t: &Affine) -> Path {
+        let cmds = self
+            .cmds
+            .iter()
+            .map(|cmd| match *cmd {
+                PathCmd::MoveTo(x, y) => {
+                    let (x, y) = t.apply(x, y);
+                    PathCmd::MoveTo(x, y)
+                }
+                PathCmd::LineTo(x, y) => {
+                    let (x, y) = t.apply(x, y);
+                    PathCmd::LineTo(x, y)
+                }
+                PathCmd::QuadTo(x1, y1, x, y) => {
+                    let (x1, y1) = t.apply(x1, y1);
+                    let (x, y) = t.apply(x, y);
+                    PathCmd::QuadTo(x1, y1, x, y)
+                }
+                PathCmd::CubicTo(x1, y1, x2, y2, x, y) => {
+                    let (x1, y1) = t.apply(x1, y1);
+                    let (x2, y2) = t.apply(x2, y2);
+                    let (x, y) = t.apply(x, y);
+                    PathCmd::CubicTo(x1, y1, x2, y2, x, y)
+                }
+                PathCmd::Close => PathCmd::Close,
+            })
+            .collect();
+        Path { cmds }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub color: Color,
+    /// Толщина в пунктах
+    pub width: f64,
+    /// Чередование штрихов и пробелов в пунктах; пустой массив — сплошная линия
+    pub dash: Vec<f64>,
+    pub cap: LineCap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextBlock {
+    /// Переводит систему координат блока (пункты, ось Y вниз, начало в левом верхнем углу)
+    /// в координаты сцены
+    pub transform: Affine,
+    pub width: f64,
+    pub height: f64,
+    pub text: String,
+    pub font: Option<String>,
+    /// Размер шрифта в пунктах
+    pub size: f64,
+    pub color: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub h_align: HAlign,
+    pub v_align: VAlign,
+    /// Переносить строки по ширине блока
+    pub wrap: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageData {
+    /// Закодированное изображение (PNG, JPEG, GIF и т. п.);
+    /// растеризатор декодирует только PNG
+    Encoded(Vec<u8>),
+    /// Несжатые пиксели RGBA без предумножения альфы
+    Rgba {
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Path {
+        path: Path,
+        fill: Option<Color>,
+        fill_rule: FillRule,
+        stroke: Option<Stroke>,
+    },
+    /// Изображение, растянутое на единичный квадрат `(0,0)-(1,1)` после `transform`
+    Image {
+        transform: Affine,
+        data: ImageData,
+    },
+    Text(TextBlock),
+    /// Вложенная сцена (например, EMF внутри фигуры)
+    Group {
+        transform: Affine,
+        items: Vec<Item>,
+    },
+}
+
+/// Область фигуры, по щелчку на которой открывается гиперссылка
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkArea {
+    /// Контур в координатах сцены
+    pub path: Path,
+    pub href: String,
+    pub title: Option<String>,
+    pub new_window: bool,
+}
+
+/// Список отрисовки страницы в пунктах (1/72 дюйма), ось Y направлена вниз
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scene {
+    pub width: f64,
+    pub height: f64,
+    pub items: Vec<Item>,
+    /// Гиперссылки фигур поверх рисунка; растровый вывод их не использует
+    pub links: Vec<LinkArea>,
+}
+
+impl Scene {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            items: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affine_then_and_invert() {
+        let t = Affine::translate(10.0, 5.0).then(&Affine::scale(2.0, 3.0));
+        assert_eq!(t.apply(1.0, 1.0), (12.0, 8.0));
+
+        let inv = t.invert().unwrap();
+        let (x, y) = inv.apply(12.0, 8.0);
+        assert!((x - 1.0).abs() < 1e-9 && (y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_color_parse() {
+        assert_eq!(Color::parse("#FF8000"), Some(Color::rgb(255, 128, 0)));
+        assert_eq!(Color::parse("2"), Some(Color::rgb(255, 0, 0)));
+        assert_eq!(Color::parse("Themed"), None);
+    }
+}
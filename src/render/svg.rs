@@ -0,0 +1,343 @@
+//! Векторный вывод сцены в SVG.
+//!
+//! Координаты сцены уже в пунктах с осью Y вниз, поэтому они переносятся в SVG как есть.
+
+use std::fmt::Write;
+
+use crate::render::scene::{
+    Affine, Color, FillRule, HAlign, ImageData, Item, LineCap, LinkArea, Path, PathCmd, Scene,
+    Stroke, TextBlock, VAlign,
+};
+
+/// Сериализует сцену в документ SVG
+pub fn scene_to_svg(scene: &Scene) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{w}pt" height="{h}pt" viewBox="0 0 {w} {h}">"#,
+        w = num(scene.width),
+        h = num(scene.height)
+    );
+    write_items(&mut out, &scene.items);
+    write_links(&mut out, &scene.links);
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Прозрачные области ссылок поверх рисунка: щелчок по фигуре открывает ссылку
+fn write_links(out: &mut String, links: &[LinkArea]) {
+    for link in links {
+        let target = if link.new_window {
+            r#" target="_blank""#
+        } else {
+            ""
+        };
+        let _ = writeln!(out, r#"<a href="{}"{}>"#, escape(&link.href), target);
+        if let Some(title) = &link.title {
+            let _ = writeln!(out, "<title>{}</title>", escape(title));
+        }
+        let _ = writeln!(
+            out,
+            r#"<path d="{}" fill="transparent" pointer-events="all"/>"#,
+            path_data(&link.path)
+        );
+        out.push_str("</a>\n");
+    }
+}
+
+fn write_items(out: &mut String, items: &[Item]) {
+    for item in items {
+        match item {
+            Item::Path {
+                path,
+                fill,
+                fill_rule,
+                stroke,
+            } => write_path(out, path, *fill, *fill_rule, stroke.as_ref()),
+            Item::Image { transform, data } => write_image(out, transform, data),
+            Item::Text(block) => write_text(out, block),
+            Item::Group { transform, items } => {
+                let _ = writeln!(out, r#"<g transform="{}">"#, matrix(transform));
+                write_items(out, items);
+                out.push_str("</g>\n");
+            }
+        }
+    }
+}
+
+/// Число без лишних нулей: SVG не нуждается в 15 знаках после запятой
+fn num(v: f64) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn matrix(t: &Affine) -> String {
+    format!(
+        "matrix({} {} {} {} {} {})",
+        num(t.a),
+        num(t.b),
+        num(t.c),
+        num(t.d),
+        num(t.e),
+        num(t.f)
+    )
+}
+
+fn color(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+fn path_data(path: &Path) -> String {
+    let mut d = String::new();
+    for cmd in &path.cmds {
+        match *cmd {
+            PathCmd::MoveTo(x, y) => {
+                let _ = write!(d, "M{} {}", num(x), num(y));
+            }
+            PathCmd::LineTo(x, y) => {
+                let _ = write!(d, "L{} {}", num(x), num(y));
+            }
+            PathCmd::QuadTo(x1, y1, x, y) => {
+                let _ = write!(d, "Q{} {} {} {}", num(x1), num(y1), num(x), num(y));
+            }
+            PathCmd::CubicTo(x1, y1, x2, y2, x, y) => {
+                let _ = write!(
+                    d,
+                    "C{} {} {} {} {} {}",
+                    num(x1),
+                    num(y1),
+                    num(x2),
+                    num(y2),
+                    num(x),
+                    num(y)
+                );
+            }
+            PathCmd::Close => d.push('Z'),
+        }
+    }
+    d
+}
+
+fn write_path(
+    out: &mut String,
+    path: &Path,
+    fill: Option<Color>,
+    fill_rule: FillRule,
+    stroke: Option<&Stroke>,
+) {
+    if path.cmds.is_empty() {
+        return;
+    }
+    let _ = write!(out, r#"<path d="{}""#, path_data(path));
+    match fill {
+        Some(c) => {
+            let _ = write!(out, r#" fill="{}""#, color(c));
+            if c.a < 255 {
+                let _ = write!(out, r#" fill-opacity="{}""#, num(c.a as f64 / 255.0));
+            }
+            if fill_rule == FillRule::EvenOdd {
+                out.push_str(r#" fill-rule="evenodd""#);
+            }
+        }
+        None => out.push_str(r#" fill="none""#),
+    }
+    if let Some(stroke) = stroke {
+        let _ = write!(
+            out,
+            r#" stroke="{}" stroke-width="{}""#,
+            color(stroke.color),
+            num(stroke.width)
+        );
+        if stroke.color.a < 255 {
+            let _ = write!(
+                out,
+                r#" stroke-opacity="{}""#,
+                num(stroke.color.a as f64 / 255.0)
+            );
+        }
+        if !stroke.dash.is_empty() {
+            let dash: Vec<String> = stroke.dash.iter().map(|d| num(*d)).collect();
+            let _ = write!(out, r#" stroke-dasharray="{}""#, dash.join(" "));
+        }
+        match stroke.cap {
+            LineCap::Butt => {}
+            LineCap::Round => out.push_str(r#" stroke-linecap="round""#),
+            LineCap::Square => out.push_str(r#" stroke-linecap="square""#),
+        }
+    }
+    out.push_str("/>\n");
+}
+
+fn write_image(out: &mut String, transform: &Affine, data: &ImageData) {
+    let (mime, bytes) = match data {
+        ImageData::Encoded(bytes) => (image_mime(bytes), bytes.clone()),
+        ImageData::Rgba {
+            width,
+            height,
+            pixels,
+        } => match rgba_to_png(*width, *height, pixels) {
+            Some(png) => ("image/png", png),
+            None => return,
+        },
+    };
+    let _ = writeln!(
+        out,
+        r#"<image transform="{}" width="1" height="1" preserveAspectRatio="none" href="data:{};base64,{}"/>"#,
+        matrix(transform),
+        mime,
+        base64(&bytes)
+    );
+}
+
+fn image_mime(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"GIF8") {
+        "image/gif"
+    } else if bytes.starts_with(b"BM") {
+        "image/bmp"
+    } else {
+        "image/png"
+    }
+}
+
+fn rgba_to_png(width: u32, height: u32, pixels: &[u8]) -> Option<Vec<u8>> {
+    let mut premultiplied = pixels.to_vec();
+    for px in premultiplied.chunks_exact_mut(4) {
+        let a = px[3] as u32;
+        for c in &mut px[..3] {
+            *c = ((*c as u32 * a + 127) / 255) as u8;
+        }
+    }
+    let size = tiny_skia::IntSize::from_wh(width, height)?;
+    tiny_skia::Pixmap::from_vec(premultiplied, size)?
+        .encode_png()
+        .ok()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_text(out: &mut String, block: &TextBlock) {
+    let lines: Vec<&str> = block.text.lines().collect();
+    if lines.is_empty() {
+        return;
+    }
+    let line_height = block.size * 1.2;
+    let total = line_height * lines.len() as f64;
+    let top = match block.v_align {
+        VAlign::Top => 0.0,
+        VAlign::Middle => (block.height - total) / 2.0,
+        VAlign::Bottom => block.height - total,
+    };
+    let (x, anchor) = match block.h_align {
+        HAlign::Left => (0.0, "start"),
+        HAlign::Center => (block.width / 2.0, "middle"),
+        HAlign::Right => (block.width, "end"),
+    };
+
+    let _ = write!(
+        out,
+        r#"<text transform="{}" font-size="{}" fill="{}" text-anchor="{}""#,
+        matrix(&block.transform),
+        num(block.size),
+        color(block.color),
+        anchor
+    );
+    if let Some(font) = &block.font {
+        let _ = write!(out, r#" font-family="{}""#, escape(font));
+    }
+    if block.bold {
+        out.push_str(r#" font-weight="bold""#);
+    }
+    if block.italic {
+        out.push_str(r#" font-style="italic""#);
+    }
+    out.push('>');
+    for (i, line) in lines.iter().enumerate() {
+        // Базовая линия примерно на 0.8 кегля ниже верха строки
+        let y = top + line_height * i as f64 + block.size * 0.8;
+        let _ = write!(
+            out,
+            r#"<tspan x="{}" y="{}">{}</tspan>"#,
+            num(x),
+            num(y),
+            escape(line)
+        );
+    }
+    out.push_str("</text>\n");
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(BASE64[(n >> 18) as usize & 63] as char);
+        out.push(BASE64[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            BASE64[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            BASE64[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_scene_to_svg() {
+        let mut scene = Scene::new(100.0, 50.0);
+        let mut path = Path::default();
+        path.move_to(0.0, 0.0);
+        path.line_to(10.5, 20.0);
+        path.close();
+        scene.items.push(Item::Path {
+            path,
+            fill: Some(Color::rgb(255, 0, 0)),
+            fill_rule: FillRule::NonZero,
+            stroke: Some(Stroke {
+                color: Color::BLACK,
+                width: 1.0,
+                dash: vec![],
+                cap: LineCap::Round,
+            }),
+        });
+        let svg = scene_to_svg(&scene);
+        assert!(svg.contains(r#"viewBox="0 0 100 50""#));
+        assert!(svg.contains(r##"<path d="M0 0L10.5 20Z" fill="#ff0000""##));
+        assert!(svg.contains(r#"stroke-linecap="round""#));
+    }
+}
@@ -0,0 +1,746 @@
+use std::f64::consts::PI;
+
+use crate::export::hyperlinks::{page_anchor, resolve};
+use crate::parser_vsdx::read_vsdx::{Cell, Diagram, Page, Section, Shape};
+use crate::parser_vsdx::shapes::apply_masters;
+use crate::render::emf;
+use crate::render::scene::{
+    Affine, Color, FillRule, HAlign, ImageData, Item, LineCap, LinkArea, Path, Scene, Stroke,
+    TextBlock, VAlign,
+};
+
+/// Пунктов в дюйме: ShapeSheet хранит размеры в дюймах, сцена — в пунктах
+const PT: f64 = 72.0;
+
+/// Строит сцену страницы, включая её фоновую страницу. Ссылки на другие страницы
+/// ведут на якоря `#page-N`
+pub fn page_scene(diagram: &Diagram, page: &Page) -> Scene {
+    page_scene_linked(diagram, page, &|index| format!("#{}", page_anchor(index)))
+}
+
+/// То же, что [`page_scene`]; `page_href` даёт адрес страницы документа по индексу
+pub fn page_scene_linked(
+    diagram: &Diagram,
+    page: &Page,
+    page_href: &dyn Fn(usize) -> String,
+) -> Scene {
+    let mut scene = Scene::new(page.width * PT, page.height * PT);
+    let to_scene = Affine {
+        a: PT,
+        b: 0.0,
+        c: 0.0,
+        d: -PT,
+        e: 0.0,
+        f: page.height * PT,
+    };
+
+    let mut backgrounds = Vec::new();
+    let mut current = page.background_page.as_ref();
+    while let Some(id) = current {
+        match diagram.pages.iter().find(|p| &p.id == id) {
+            Some(bg) if !backgrounds.iter().any(|b: &&Page| b.id == bg.id) && bg.id != page.id => {
+                backgrounds.push(bg);
+                current = bg.background_page.as_ref();
+            }
+            _ => break,
+        }
+    }
+
+    for layer in backgrounds.iter().rev().chain([&page]) {
+        // Модель хранит только локальные значения, унаследованные берутся из мастеров
+        let mut shapes = layer.shapes.clone();
+        apply_masters(&mut shapes, &diagram.masters);
+        render_shapes(diagram, &shapes, &to_scene, &mut scene.items);
+        link_areas(diagram, &shapes, &to_scene, page_href, &mut scene.links);
+    }
+
+    scene
+}
+
+/// Прямоугольники фигур с гиперссылкой по умолчанию, вложенные фигуры - поверх группы
+fn link_areas(
+    diagram: &Diagram,
+    shapes: &[Shape],
+    parent: &Affine,
+    page_href: &dyn Fn(usize) -> String,
+    links: &mut Vec<LinkArea>,
+) {
+    for shape in shapes {
+        let transform = parent.then(&shape_transform(shape));
+        if let Some(link) = shape.default_hyperlink().filter(|l| !l.invisible)
+            && let Some(target) = resolve(diagram, &link)
+        {
+            let width = shape.number("Width").unwrap_or(0.0);
+            let height = shape.number("Height").unwrap_or(0.0);
+            let mut path = Path::default();
+            path.move_to(0.0, 0.0);
+            path.line_to(width, 0.0);
+            path.line_to(width, height);
+            path.line_to(0.0, height);
+            path.close();
+            let title = [&link.description, &link.address, &link.sub_address]
+                .into_iter()
+                .find(|t| !t.is_empty())
+                .cloned();
+            links.push(LinkArea {
+                path: path.transform(&transform),
+                href: target.href(page_href),
+                title,
+                new_window: link.new_window,
+            });
+        }
+        link_areas(diagram, &shape.shapes, &transform, page_href, links);
+    }
+}
+
+fn render_shapes(diagram: &Diagram, shapes: &[Shape], parent: &Affine, items: &mut Vec<Item>) {
+    for shape in shapes {
+        let transform = parent.then(&shape_transform(shape));
+        render_shape(diagram, shape, &transform, items);
+        render_shapes(diagram, &shape.shapes, &transform, items);
+    }
+}
+
+/// Перевод из локальных координат фигуры в координаты родителя
+pub fn shape_transform(shape: &Shape) -> Affine {
+    let pin_x = shape.number("PinX").unwrap_or(0.0);
+    let pin_y = shape.number("PinY").unwrap_or(0.0);
+    let loc_x = shape.number("LocPinX").unwrap_or(0.0);
+    let loc_y = shape.number("LocPinY").unwrap_or(0.0);
+    let angle = shape.number("Angle").unwrap_or(0.0);
+    let flip_x = shape.number("FlipX").unwrap_or(0.0) != 0.0;
+    let flip_y = shape.number("FlipY").unwrap_or(0.0) != 0.0;
+
+    Affine::translate(pin_x, pin_y)
+        .then(&Affine::rotate(angle))
+        .then(&Affine::scale(
+            if flip_x { -1.0 } else { 1.0 },
+            if flip_y { -1.0 } else { 1.0 },
+        ))
+        .then(&Affine::translate(-loc_x, -loc_y))
+}
+
+fn render_shape(diagram: &Diagram, shape: &Shape, transform: &Affine, items: &mut Vec<Item>) {
+    let width = shape.number("Width").unwrap_or(0.0);
+    let height = shape.number("Height").unwrap_or(0.0);
+    let scale = transform.scale_factor() / PT;
+
+    if let Some(image) = foreign_item(diagram, shape, transform, width, height) {
+        items.push(image);
+    }
+
+    let stroke = shape_stroke(diagram, shape, scale);
+    let fill = shape_fill(diagram, shape);
+
+    for section in shape.sections_named("Geometry") {
+        if flag(section.cells.get("NoShow")) {
+            continue;
+        }
+        let (path, closed) = geometry_path(section, width, height);
+        if path.is_empty() {
+            continue;
+        }
+        let fill = if closed && !flag(section.cells.get("NoFill")) {
+            fill
+        } else {
+            None
+        };
+        let stroke = if flag(section.cells.get("NoLine")) {
+            None
+        } else {
+            stroke.clone()
+        };
+        if fill.is_none() && stroke.is_none() {
+            continue;
+        }
+        items.push(Item::Path {
+            path: path.transform(transform),
+            fill,
+            fill_rule: FillRule::EvenOdd,
+            stroke,
+        });
+    }
+
+    if let Some(text) = text_item(diagram, shape, transform, width, height) {
+        items.push(text);
+    }
+}
+
+fn flag(cell: Option<&Cell>) -> bool {
+    cell.and_then(Cell::number).unwrap_or(0.0) != 0.0
+}
+
+/// Ищет ячейку в фигуре, затем по цепочке стилей соответствующей категории
+pub fn style_cell<'a>(diagram: &'a Diagram, shape: &'a Shape, name: &str) -> Option<&'a Cell> {
+    if let Some(cell) = shape.cells.get(name) {
+        return Some(cell);
+    }
+
+    let pick = |s: &'a Shape| -> Option<&'a String> {
+        if name.starts_with("Line") || name.ends_with("Arrow") || name == "Rounding" {
+            s.line_style.as_ref()
+        } else if name.starts_with("Fill") || name.starts_with("Shdw") {
+            s.fill_style.as_ref()
+        } else {
+            s.text_style.as_ref()
+        }
+    };
+
+    let mut style_id = pick(shape);
+    for _ in 0..16 {
+        let style = diagram
+            .style_sheets
+            .iter()
+            .find(|s| Some(&s.id) == style_id)?;
+        if let Some(cell) = style.cells.get(name) {
+            return Some(cell);
+        }
+        style_id = pick(style);
+    }
+    None
+}
+
+/// Первая строка секции (Character, Paragraph) с учётом стиля текста
+fn style_row_cell<'a>(
+    diagram: &'a Diagram,
+    shape: &'a Shape,
+    section: &str,
+    name: &str,
+) -> Option<&'a Cell> {
+    let from = |s: &'a Shape| {
+        s.sections_named(section)
+            .first()
+            .and_then(|sec| sec.rows.first())
+            .and_then(|row| row.cells.get(name))
+    };
+    if let Some(cell) = from(shape) {
+        return Some(cell);
+    }
+
+    let mut style_id = shape.text_style.as_ref();
+    for _ in 0..16 {
+        let style = diagram
+            .style_sheets
+            .iter()
+            .find(|s| Some(&s.id) == style_id)?;
+        if let Some(cell) = from(style) {
+            return Some(cell);
+        }
+        style_id = style.text_style.as_ref();
+    }
+    None
+}
+
+fn style_number(diagram: &Diagram, shape: &Shape, name: &str) -> Option<f64> {
+    style_cell(diagram, shape, name).and_then(Cell::number)
+}
+
+fn alpha(transparency: Option<f64>) -> u8 {
+    ((1.0 - transparency.unwrap_or(0.0).clamp(0.0, 1.0)) * 255.0).round() as u8
+}
+
+fn shape_fill(diagram: &Diagram, shape: &Shape) -> Option<Color> {
+    let pattern = style_number(diagram, shape, "FillPattern").unwrap_or(1.0);
+    if pattern == 0.0 {
+        return None;
+    }
+    let color = style_cell(diagram, shape, "FillForegnd")
+        .and_then(|c| Color::parse(&c.value))
+        .unwrap_or(Color::WHITE);
+    Some(color.with_alpha(alpha(style_number(diagram, shape, "FillForegndTrans"))))
+}
+
+fn shape_stroke(diagram: &Diagram, shape: &Shape, scale: f64) -> Option<Stroke> {
+    let pattern = style_number(diagram, shape, "LinePattern").unwrap_or(1.0) as u32;
+    if pattern == 0 {
+        return None;
+    }
+    let color = style_cell(diagram, shape, "LineColor")
+        .and_then(|c| Color::parse(&c.value))
+        .unwrap_or(Color::BLACK)
+        .with_alpha(alpha(style_number(diagram, shape, "LineColorTrans")));
+    let width = style_number(diagram, shape, "LineWeight").unwrap_or(0.01) * PT * scale;
+    let cap = match style_number(diagram, shape, "LineCap").unwrap_or(0.0) as u32 {
+        1 => LineCap::Square,
+        2 => LineCap::Butt,
+        _ => LineCap::Round,
+    };
+
+    Some(Stroke {
+        color,
+        width,
+        dash: dash_pattern(pattern, width),
+        cap,
+    })
+}
+
+/// Упрощённое соответствие `LinePattern` штриховкам
+pub fn dash_pattern(pattern: u32, width: f64) -> Vec<f64> {
+    let unit = width.max(1.0);
+    let dashes: &[f64] = match pattern {
+        0 | 1 => &[],
+        2 => &[4.0, 3.0],
+        3 => &[1.0, 3.0],
+        4 => &[4.0, 3.0, 1.0, 3.0],
+        5 => &[4.0, 3.0, 1.0, 3.0, 1.0, 3.0],
+        6 => &[8.0, 3.0, 1.0, 3.0],
+        7 => &[8.0, 3.0],
+        8 => &[8.0, 3.0, 4.0, 3.0],
+        _ => &[3.0, 2.0],
+    };
+    dashes.iter().map(|d| d * unit).collect()
+}
+
+/// Строит контур секции Geometry в локальных координатах фигуры (дюймы, ось Y вверх)
+pub fn geometry_path(section: &Section, width: f64, height: f64) -> (Path, bool) {
+    let mut path = Path::default();
+    let mut start = (0.0, 0.0);
+    let mut cur = (0.0, 0.0);
+    let mut has_start = false;
+    let mut closed = true;
+    let mut segments = 0;
+
+    let mut rows: Vec<_> = section.rows.iter().filter(|r| !r.deleted).collect();
+    rows.sort_by_key(|r| r.index.as_deref().and_then(|i| i.parse::<u32>().ok()));
+
+    let mut finish = |path: &mut Path, start: (f64, f64), cur: (f64, f64), segments: usize| {
+        if segments > 0 {
+            if close_enough(start, cur) {
+                path.close();
+            } else {
+                closed = false;
+            }
+        }
+    };
+
+    for row in rows {
+        let x = row.number("X").unwrap_or(0.0);
+        let y = row.number("Y").unwrap_or(0.0);
+        let a = row.number("A").unwrap_or(0.0);
+        let b = row.number("B").unwrap_or(0.0);
+        let c = row.number("C").unwrap_or(0.0);
+        let d = row.number("D").unwrap_or(0.0);
+
+        let row_type = row.row_type.as_deref().unwrap_or("");
+        let (x, y) = if row_type.starts_with("Rel") {
+            (x * width, y * height)
+        } else {
+            (x, y)
+        };
+
+        if !has_start
+            && !matches!(
+                row_type,
+                "MoveTo" | "RelMoveTo" | "Ellipse" | "InfiniteLine"
+            )
+        {
+            path.move_to(0.0, 0.0);
+            has_start = true;
+        }
+
+        match row_type {
+            "MoveTo" | "RelMoveTo" => {
+                finish(&mut path, start, cur, segments);
+                path.move_to(x, y);
+                start = (x, y);
+                cur = (x, y);
+                segments = 0;
+                has_start = true;
+            }
+            "LineTo" | "RelLineTo" | "SplineStart" | "SplineKnot" | "NURBSTo" => {
+                path.line_to(x, y);
+                cur = (x, y);
+                segments += 1;
+            }
+            "PolylineTo" => {
+                for (px, py) in polyline_points(row.cells.get("A"), width, height) {
+                    path.line_to(px, py);
+                }
+                path.line_to(x, y);
+                cur = (x, y);
+                segments += 1;
+            }
+            "ArcTo" => {
+                arc_to(&mut path, cur, (x, y), a);
+                cur = (x, y);
+                segments += 1;
+            }
+            "EllipticalArcTo" | "RelEllipticalArcTo" => {
+                let (a, b) = if row_type.starts_with("Rel") {
+                    (a * width, b * height)
+                } else {
+                    (a, b)
+                };
+                elliptical_arc_to(&mut path, cur, (a, b), (x, y), c, d);
+                cur = (x, y);
+                segments += 1;
+            }
+            "RelCubBezTo" => {
+                path.cubic_to(a * width, b * height, c * width, d * height, x, y);
+                cur = (x, y);
+                segments += 1;
+            }
+            "RelQuadBezTo" => {
+                path.quad_to(a * width, b * height, x, y);
+                cur = (x, y);
+                segments += 1;
+            }
+            "Ellipse" => {
+                finish(&mut path, start, cur, segments);
+                ellipse(&mut path, (x, y), (a, b), (c, d));
+                segments = 0;
+                has_start = false;
+            }
+            _ => {}
+        }
+    }
+    finish(&mut path, start, cur, segments);
+
+    (path, closed)
+}
+
+fn close_enough(a: (f64, f64), b: (f64, f64)) -> bool {
+    (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6
+}
+
+/// Точки из формулы `POLYLINE(xType, yType, x1, y1, ...)`
+fn polyline_points(cell: Option<&Cell>, width: f64, height: f64) -> Vec<(f64, f64)> {
+    let Some(formula) = cell.and_then(|c| c.formula.as_ref().or(Some(&c.value))) else {
+        return vec![];
+    };
+    let Some(args) = formula
+        .trim()
+        .strip_prefix("POLYLINE(")
+        .and_then(|s| s.strip_suffix(')'))
+    else {
+        return vec![];
+    };
+    let values: Vec<f64> = args
+        .split(',')
+        .filter_map(|v| v.trim().parse::<f64>().ok())
+        .collect();
+    if values.len() < 2 {
+        return vec![];
+    }
+    let x_rel = values[0] == 0.0;
+    let y_rel = values[1] == 0.0;
+    values[2..]
+        .chunks_exact(2)
+        .map(|p| {
+            (
+                if x_rel { p[0] * width } else { p[0] },
+                if y_rel { p[1] * height } else { p[1] },
+            )
+        })
+        .collect()
+}
+
+fn arc_to(path: &mut Path, from: (f64, f64), to: (f64, f64), bow: f64) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if bow.abs() < 1e-9 || len < 1e-9 {
+        path.line_to(to.0, to.1);
+        return;
+    }
+    // Положительная стрелка прогиба — дуга слева по направлению движения
+    let control = (
+        (from.0 + to.0) / 2.0 - bow * dy / len,
+        (from.1 + to.1) / 2.0 + bow * dx / len,
+    );
+    for (x, y) in arc_through(from, control, to) {
+        path.line_to(x, y);
+    }
+}
+
+fn elliptical_arc_to(
+    path: &mut Path,
+    from: (f64, f64),
+    control: (f64, f64),
+    to: (f64, f64),
+    angle: f64,
+    ratio: f64,
+) {
+    let ratio = if ratio.abs() < 1e-9 { 1.0 } else { ratio };
+    // Поворачиваем большую ось к горизонтали и сжимаем до окружности
+    let squash = Affine::scale(1.0, ratio).then(&Affine::rotate(-angle));
+    let Some(unsquash) = squash.invert() else {
+        path.line_to(to.0, to.1);
+        return;
+    };
+    let p0 = squash.apply(from.0, from.1);
+    let p1 = squash.apply(control.0, control.1);
+    let p2 = squash.apply(to.0, to.1);
+    for (x, y) in arc_through(p0, p1, p2) {
+        let (x, y) = unsquash.apply(x, y);
+        path.line_to(x, y);
+    }
+}
+
+/// Аппроксимирует отрезками дугу окружности через три точки (без начальной)
+pub fn arc_through(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64)) -> Vec<(f64, f64)> {
+    let d = 2.0 * (p0.0 * (p1.1 - p2.1) + p1.0 * (p2.1 - p0.1) + p2.0 * (p0.1 - p1.1));
+    if d.abs() < 1e-12 {
+        return vec![p2];
+    }
+    let sq = |p: (f64, f64)| p.0 * p.0 + p.1 * p.1;
+    let cx = (sq(p0) * (p1.1 - p2.1) + sq(p1) * (p2.1 - p0.1) + sq(p2) * (p0.1 - p1.1)) / d;
+    let cy = (sq(p0) * (p2.0 - p1.0) + sq(p1) * (p0.0 - p2.0) + sq(p2) * (p1.0 - p0.0)) / d;
+    let r = ((p0.0 - cx).powi(2) + (p0.1 - cy).powi(2)).sqrt();
+
+    let ang = |p: (f64, f64)| (p.1 - cy).atan2(p.0 - cx);
+    let a0 = ang(p0);
+    let norm = |a: f64| a.rem_euclid(2.0 * PI);
+    let sweep_ccw = norm(ang(p2) - a0);
+    let mid_ccw = norm(ang(p1) - a0);
+    let sweep = if mid_ccw <= sweep_ccw {
+        sweep_ccw
+    } else {
+        sweep_ccw - 2.0 * PI
+    };
+
+    let steps = ((sweep.abs() / (PI / 32.0)).ceil() as usize).max(4);
+    let mut points: Vec<(f64, f64)> = (1..steps)
+        .map(|i| {
+            let a = a0 + sweep * i as f64 / steps as f64;
+            (cx + r * a.cos(), cy + r * a.sin())
+        })
+        .collect();
+    points.push(p2);
+    points
+}
+
+/// Эллипс с центром и двумя точками на концах полуосей
+fn ellipse(path: &mut Path, center: (f64, f64), a: (f64, f64), b: (f64, f64)) {
+    const K: f64 = 0.552_284_749_830_793_6;
+    let u = (a.0 - center.0, a.1 - center.1);
+    let v = (b.0 - center.0, b.1 - center.1);
+    let at = |cu: f64, cv: f64| {
+        (
+            center.0 + u.0 * cu + v.0 * cv,
+            center.1 + u.1 * cu + v.1 * cv,
+        )
+    };
+
+    let quadrants = [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0), (1.0, 0.0)];
+    let p = at(1.0, 0.0);
+    path.move_to(p.0, p.1);
+    for w in quadrants.windows(2) {
+        let (s, e) = (w[0], w[1]);
+        let c1 = at(s.0 + K * e.0, s.1 + K * e.1);
+        let c2 = at(e.0 + K * s.0, e.1 + K * s.1);
+        let p = at(e.0, e.1);
+        path.cubic_to(c1.0, c1.1, c2.0, c2.1, p.0, p.1);
+    }
+    path.close();
+}
+
+fn foreign_item(
+    diagram: &Diagram,
+    shape: &Shape,
+    transform: &Affine,
+    width: f64,
+    height: f64,
+) -> Option<Item> {
+    let foreign = shape.foreign.as_ref()?;
+    let data = diagram.media.get(foreign.target.as_ref()?)?;
+
+    let img_w = shape.number("ImgWidth").unwrap_or(width);
+    let img_h = shape.number("ImgHeight").unwrap_or(height);
+    let img_x = shape.number("ImgOffsetX").unwrap_or(0.0);
+    let img_y = shape.number("ImgOffsetY").unwrap_or(0.0);
+
+    // Единичный квадрат изображения (ось Y вниз) в локальные координаты фигуры
+    let unit = transform.then(&Affine {
+        a: img_w,
+        b: 0.0,
+        c: 0.0,
+        d: -img_h,
+        e: img_x,
+        f: img_y + img_h,
+    });
+
+    if foreign.foreign_type == "EnhMetaFile" || emf::is_emf(data) {
+        let scene = emf::scene(data).ok()?;
+        if scene.width <= 0.0 || scene.height <= 0.0 {
+            return None;
+        }
+        return Some(Item::Group {
+            transform: unit.then(&Affine::scale(1.0 / scene.width, 1.0 / scene.height)),
+            items: scene.items,
+        });
+    }
+
+    // BMP не понимают ни tiny-skia, ни большинство просмотрщиков SVG
+    let data = emf::decode_bmp(data).unwrap_or_else(|| ImageData::Encoded(data.to_owned()));
+    Some(Item::Image {
+        transform: unit,
+        data,
+    })
+}
+
+fn text_item(
+    diagram: &Diagram,
+    shape: &Shape,
+    transform: &Affine,
+    width: f64,
+    height: f64,
+) -> Option<Item> {
+    let text = shape.text.as_ref()?.trim_end_matches(['\n', '\r']);
+    if text.trim().is_empty() || flag(shape.cells.get("HideText")) {
+        return None;
+    }
+
+    let txt_w = shape.number("TxtWidth").unwrap_or(width);
+    let txt_h = shape.number("TxtHeight").unwrap_or(height);
+    let txt_pin_x = shape.number("TxtPinX").unwrap_or(width / 2.0);
+    let txt_pin_y = shape.number("TxtPinY").unwrap_or(height / 2.0);
+    let txt_loc_x = shape.number("TxtLocPinX").unwrap_or(txt_w / 2.0);
+    let txt_loc_y = shape.number("TxtLocPinY").unwrap_or(txt_h / 2.0);
+    let txt_angle = shape.number("TxtAngle").unwrap_or(0.0);
+
+    let margin = |name: &str| style_number(diagram, shape, name).unwrap_or(4.0 / PT) * PT;
+    let (left, right) = (margin("LeftMargin"), margin("RightMargin"));
+    let (top, bottom) = (margin("TopMargin"), margin("BottomMargin"));
+
+    // Блок текста: пункты, ось Y вниз, начало в левом верхнем углу с учётом полей
+    let block = transform
+        .then(&Affine::translate(txt_pin_x, txt_pin_y))
+        .then(&Affine::rotate(txt_angle))
+        .then(&Affine::translate(-txt_loc_x, -txt_loc_y))
+        .then(&Affine {
+            a: 1.0 / PT,
+            b: 0.0,
+            c: 0.0,
+            d: -1.0 / PT,
+            e: 0.0,
+            f: txt_h,
+        })
+        .then(&Affine::translate(left, top));
+
+    let char_cell = |name: &str| style_row_cell(diagram, shape, "Character", name);
+    let style = char_cell("Style").and_then(Cell::number).unwrap_or(0.0) as u32;
+
+    Some(Item::Text(TextBlock {
+        transform: block,
+        width: (txt_w * PT - left - right).max(0.0),
+        height: (txt_h * PT - top - bottom).max(0.0),
+        text: text.replace('\u{2028}', "\n"),
+        font: char_cell("Font")
+            .map(|c| c.value.clone())
+            .filter(|f| f.parse::<f64>().is_err() && f != "Themed"),
+        size: char_cell("Size")
+            .and_then(Cell::number)
+            .map(|s| s * PT)
+            .unwrap_or(12.0),
+        color: char_cell("Color")
+            .and_then(|c| Color::parse(&c.value))
+            .unwrap_or(Color::BLACK),
+        bold: style & 1 != 0,
+        italic: style & 2 != 0,
+        h_align: match style_row_cell(diagram, shape, "Paragraph", "HorzAlign")
+            .and_then(Cell::number)
+            .unwrap_or(1.0) as u32
+        {
+            0 => HAlign::Left,
+            2 => HAlign::Right,
+            _ => HAlign::Center,
+        },
+        v_align: match style_number(diagram, shape, "VerticalAlign").unwrap_or(1.0) as u32 {
+            0 => VAlign::Top,
+            2 => VAlign::Bottom,
+            _ => VAlign::Middle,
+        },
+        wrap: true,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_vsdx::read_vsdx::Row;
+    use std::collections::HashMap;
+
+    fn row(row_type: &str, ix: u32, cells: &[(&str, f64)]) -> Row {
+        Row {
+            index: Some(ix.to_string()),
+            row_type: Some(row_type.to_string()),
+            cells: cells
+                .iter()
+                .map(|(n, v)| {
+                    (
+                        (*n).into(),
+                        Cell {
+                            value: v.to_string(),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rectangle_geometry_is_closed() {
+        let section = Section {
+            name: "Geometry".to_string(),
+            index: Some("0".to_string()),
+            cells: HashMap::new(),
+            rows: vec![
+                row("RelMoveTo", 1, &[("X", 0.0), ("Y", 0.0)]),
+                row("RelLineTo", 2, &[("X", 1.0), ("Y", 0.0)]),
+                row("RelLineTo", 3, &[("X", 1.0), ("Y", 1.0)]),
+                row("RelLineTo", 4, &[("X", 0.0), ("Y", 1.0)]),
+                row("RelLineTo", 5, &[("X", 0.0), ("Y", 0.0)]),
+            ],
+        };
+        let (path, closed) = geometry_path(&section, 2.0, 1.0);
+        assert!(closed);
+        assert_eq!(
+            path.cmds[2],
+            crate::render::scene::PathCmd::LineTo(2.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_arc_through_passes_control_side() {
+        let points = arc_through((0.0, 0.0), (1.0, 1.0), (2.0, 0.0));
+        assert_eq!(*points.last().unwrap(), (2.0, 0.0));
+        assert!(points.iter().all(|p| p.1 >= -1e-9));
+        let top = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+        assert!((top - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_shape_transform_rotation() {
+        let shape = Shape {
+            cells: [
+                ("PinX", 2.0),
+                ("PinY", 1.0),
+                ("LocPinX", 0.5),
+                ("LocPinY", 0.5),
+            ]
+            .iter()
+            .map(|(n, v)| {
+                (
+                    (*n).into(),
+                    Cell {
+                        value: v.to_string(),
+                        ..Default::default()
+                    },
+                )
+            })
+            .chain([(
+                "Angle".into(),
+                Cell {
+                    value: (PI / 2.0).to_string(),
+                    ..Default::default()
+                },
+            )])
+            .collect(),
+            ..Default::default()
+        };
+        let (x, y) = shape_transform(&shape).apply(1.0, 0.5);
+        assert!((x - 2.0).abs() < 1e-9 && (y - 1.5).abs() < 1e-9);
+    }
+}